@@ -0,0 +1,469 @@
+/**
+Source formatter for Cem (`cem fmt`)
+
+Prints a parsed `Program` back as source in one canonical layout:
+
+```text
+type Option(T)
+  | Some(T)
+  | None
+
+: describe ( Option(Int) -- String )
+  match
+    Some(n) => [ int-to-string ]
+    None => [ "nothing" ]
+  end ;
+```
+
+Imports come first, one per line, then type definitions, then words, each
+separated by a blank line.
+Runs of words and literals fill lines up to `MAX_WIDTH`. A quotation, `if` or
+`while` stays on the line when everything inside it does and it's short;
+otherwise its bodies are indented on lines of their own. `match` always
+takes one line per branch.
+
+Number and character literals are printed as they were written (`0x1F`,
+`1_000`, `'a'`), from the program's `literals`. Comments aren't part of the
+syntax tree, so they're not preserved.
+*/
+use super::types::{Effect, StackType, Type};
+use super::{Expr, MatchBranch, Pattern, Program, SourceLoc, TypeDef, WordDef};
+use std::collections::HashMap;
+
+/// Width that runs of words are wrapped at
+const MAX_WIDTH: usize = 80;
+
+/// Longest quotation, `if` or `while` that's kept on one line
+const MAX_INLINE_WIDTH: usize = 60;
+
+const INDENT: &str = "  ";
+
+/// How literals were written, by location (`Program::literals`)
+type Literals = HashMap<SourceLoc, String>;
+
+/// Format a whole program as canonical source
+pub fn format_program(program: &Program) -> String {
+    let imports: String = program
+        .imports
+        .iter()
+        .map(|import| format!("import {}\n", format_string(&import.path)))
+        .collect();
+    let definitions: Vec<String> = (!imports.is_empty())
+        .then_some(imports)
+        .into_iter()
+        .chain(program.type_defs.iter().map(format_type_def))
+        .chain(
+            program
+                .word_defs
+                .iter()
+                .map(|word| format_word_def(word, &program.literals)),
+        )
+        .collect();
+    definitions.join("\n")
+}
+
+fn format_type_def(typedef: &TypeDef) -> String {
+    let mut out = format!("type {}", typedef.name);
+    if !typedef.type_params.is_empty() {
+        out.push_str(&format!("({})", typedef.type_params.join(", ")));
+    }
+    out.push('\n');
+
+    for variant in &typedef.variants {
+        out.push_str(&format!("{}| {}", INDENT, variant.name));
+        if !variant.fields.is_empty() {
+            let fields: Vec<String> = variant.fields.iter().map(format_type).collect();
+            out.push_str(&format!("({})", fields.join(", ")));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn format_word_def(word: &WordDef, literals: &Literals) -> String {
+    let header = format!(": {} {}", word.name, format_effect(&word.effect));
+    let mut lines = format_block(&word.body, 1, literals);
+    match lines.last_mut() {
+        Some(last) => last.push_str(" ;"),
+        None => return format!("{} ;\n", header),
+    }
+
+    let mut out = header + "\n";
+    for line in lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+/// An effect as written in a signature: `( ..R Int -- ..R Bool )`
+pub fn format_effect(effect: &Effect) -> String {
+    let side = |stack: &StackType| {
+        let types = format_stack(stack);
+        if types.is_empty() {
+            types
+        } else {
+            format!("{} ", types)
+        }
+    };
+    format!("( {}-- {})", side(&effect.inputs), side(&effect.outputs))
+}
+
+/// The types on a stack, bottom first, starting with its row variable if any
+fn format_stack(stack: &StackType) -> String {
+    match stack {
+        StackType::Empty => String::new(),
+        StackType::RowVar(name) => format!("..{}", name),
+        StackType::Cons { rest, top } => match format_stack(rest) {
+            rest if rest.is_empty() => format_type(top),
+            rest => format!("{} {}", rest, format_type(top)),
+        },
+    }
+}
+
+/// A type as written in source: arguments in parentheses, separated by spaces
+fn format_type(ty: &Type) -> String {
+    match ty {
+        Type::Named { name, args } if !args.is_empty() => {
+            let args: Vec<String> = args.iter().map(format_type).collect();
+            format!("{}({})", name, args.join(" "))
+        }
+        Type::Quotation(effect) => {
+            // `[ -- ]`, the same sides as a signature between brackets
+            let effect = format_effect(effect);
+            format!("[{}]", &effect[1..effect.len() - 1])
+        }
+        ty => ty.to_string(),
+    }
+}
+
+/// Lines for a sequence of expressions, indented `depth` levels
+fn format_block(exprs: &[Expr], depth: usize, literals: &Literals) -> Vec<String> {
+    let indent = INDENT.repeat(depth);
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for expr in exprs {
+        match format_inline(expr, literals) {
+            Some(text) => {
+                if !current.is_empty() && indent.len() + current.len() + 1 + text.len() > MAX_WIDTH
+                {
+                    lines.push(format!("{}{}", indent, current));
+                    current.clear();
+                }
+                if !current.is_empty() {
+                    current.push(' ');
+                }
+                current.push_str(&text);
+            }
+            None => {
+                if !current.is_empty() {
+                    lines.push(format!("{}{}", indent, current));
+                    current.clear();
+                }
+                lines.extend(format_multiline(expr, depth, literals));
+            }
+        }
+    }
+
+    if !current.is_empty() {
+        lines.push(format!("{}{}", indent, current));
+    }
+    lines
+}
+
+/// An expression on one line, if it can be written on one
+fn format_inline(expr: &Expr, literals: &Literals) -> Option<String> {
+    let text = match expr {
+        Expr::IntLit(n, loc) => return Some(written(literals, loc, || n.to_string())),
+        Expr::FloatLit(x, loc) => return Some(written(literals, loc, || format!("{:?}", x))),
+        Expr::BoolLit(b, _) => return Some(b.to_string()),
+        Expr::StringLit(s, _) => return Some(format_string(s)),
+        Expr::WordCall(name, _) => return Some(name.clone()),
+        Expr::Quotation(body, _) => format_inline_quotation(body, literals)?,
+        Expr::Match { .. } => return None,
+        Expr::If {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            let then_branch = format_inline(then_branch, literals)?;
+            match quotation_body(else_branch) {
+                [] => format!("if {}", then_branch),
+                _ => format!(
+                    "if {} {}",
+                    then_branch,
+                    format_inline(else_branch, literals)?
+                ),
+            }
+        }
+        Expr::While { cond, body, .. } => format!(
+            "while {} {}",
+            format_inline(cond, literals)?,
+            format_inline(body, literals)?
+        ),
+    };
+
+    (text.len() <= MAX_INLINE_WIDTH).then_some(text)
+}
+
+/// `[ a b ]`, or `[ ]` when empty, if everything inside fits on one line
+fn format_inline_quotation(body: &[Expr], literals: &Literals) -> Option<String> {
+    let mut text = String::from("[");
+    for expr in body {
+        text.push(' ');
+        text.push_str(&format_inline(expr, literals)?);
+    }
+    text.push_str(" ]");
+    Some(text)
+}
+
+/// An expression spread over lines, starting at indentation `depth`
+fn format_multiline(expr: &Expr, depth: usize, literals: &Literals) -> Vec<String> {
+    let indent = INDENT.repeat(depth);
+    let mut lines = Vec::new();
+
+    match expr {
+        Expr::Quotation(body, _) => {
+            lines.push(format!("{}[", indent));
+            lines.extend(format_block(body, depth + 1, literals));
+            lines.push(format!("{}]", indent));
+        }
+        Expr::Match { branches, .. } => {
+            lines.push(format!("{}match", indent));
+            for branch in branches {
+                lines.extend(format_branch(branch, depth + 1, literals));
+            }
+            lines.push(format!("{}end", indent));
+        }
+        Expr::If {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            lines.push(format!("{}if [", indent));
+            lines.extend(format_block(
+                quotation_body(then_branch),
+                depth + 1,
+                literals,
+            ));
+            if quotation_body(else_branch).is_empty() {
+                lines.push(format!("{}]", indent));
+            } else {
+                lines.push(format!("{}] [", indent));
+                lines.extend(format_block(
+                    quotation_body(else_branch),
+                    depth + 1,
+                    literals,
+                ));
+                lines.push(format!("{}]", indent));
+            }
+        }
+        Expr::While { cond, body, .. } => {
+            lines.push(format!("{}while [", indent));
+            lines.extend(format_block(quotation_body(cond), depth + 1, literals));
+            lines.push(format!("{}] [", indent));
+            lines.extend(format_block(quotation_body(body), depth + 1, literals));
+            lines.push(format!("{}]", indent));
+        }
+        // Everything else fits on a line
+        expr => {
+            lines.extend(format_inline(expr, literals).map(|text| format!("{}{}", indent, text)))
+        }
+    }
+    lines
+}
+
+/// `Pattern => [ body ]`, with the body on lines of its own if it doesn't fit
+fn format_branch(branch: &MatchBranch, depth: usize, literals: &Literals) -> Vec<String> {
+    let indent = INDENT.repeat(depth);
+    let pattern = match branch.pattern {
+        Pattern::IntLit(_) => written(literals, &branch.loc, || branch.pattern.to_string()),
+        _ => branch.pattern.to_string(),
+    };
+    let head = format!("{}{} => ", indent, pattern);
+
+    if let Some(body) = format_inline_quotation(&branch.body, literals)
+        && head.len() + body.len() <= MAX_WIDTH
+    {
+        return vec![head + &body];
+    }
+
+    let mut lines = vec![head + "["];
+    lines.extend(format_block(&branch.body, depth + 1, literals));
+    lines.push(format!("{}]", indent));
+    lines
+}
+
+/// The literal at `loc` as it was written, or `printed` if that's not known
+fn written(literals: &Literals, loc: &SourceLoc, printed: impl FnOnce() -> String) -> String {
+    literals.get(loc).cloned().unwrap_or_else(printed)
+}
+
+/// The expressions of an `if` or `while` branch, which the parser always
+/// builds as a quotation
+fn quotation_body(branch: &Expr) -> &[Expr] {
+    match branch {
+        Expr::Quotation(body, _) => body,
+        other => std::slice::from_ref(other),
+    }
+}
+
+/// A string literal with the escapes the lexer understands
+fn format_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        push_escaped(&mut out, c, '"');
+    }
+    out.push('"');
+    out
+}
+
+/// A character literal, `'a'`, with the same escapes as a string
+pub(crate) fn format_char(c: char) -> String {
+    let mut out = String::from("'");
+    push_escaped(&mut out, c, '\'');
+    out.push('\'');
+    out
+}
+
+/// Push `c` onto a literal delimited by `quote`, escaped if it must be
+fn push_escaped(out: &mut String, c: char, quote: char) {
+    match c {
+        c if c == quote => {
+            out.push('\\');
+            out.push(c);
+        }
+        '\\' => out.push_str("\\\\"),
+        '\n' => out.push_str("\\n"),
+        '\t' => out.push_str("\\t"),
+        '\r' => out.push_str("\\r"),
+        c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+        c => out.push(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn format_source(source: &str) -> String {
+        format_program(&Parser::new(source).parse().unwrap())
+    }
+
+    #[test]
+    fn test_format_golden() {
+        let source = r#"
+            import "shapes/base.cem" import "util.cem"
+            type Shape(T) | Circle(T) | Rect(T, Option(T)) | Empty
+            : area ( Shape(Int) -- Int ) match Circle(r) => [ r r * 3 * ]
+              Rect(w, h) => [ w h match Some(n) => [ nip n * ] None => [ drop 0 ] end ]
+              Empty => [ 0 ] end ;
+            : apply ( ..R [ ..R -- ..R Int ] -- ..R Int ) call ;
+            : greet ( -- ) "say \"hi\"\n\u{7}" write 2.5 -3 drop drop ;
+            : count ( Int -- Int ) while [ dup 0 > ] [ 1 - ] dup 0 < if [ neg ] ;
+            : noop ( -- ) ;
+        "#;
+
+        let expected = r#"import "shapes/base.cem"
+import "util.cem"
+
+type Shape(T)
+  | Circle(T)
+  | Rect(T, Option(T))
+  | Empty
+
+: area ( Shape(Int) -- Int )
+  match
+    Circle(r) => [ r r * 3 * ]
+    Rect(w, h) => [
+      w h
+      match
+        Some(n) => [ nip n * ]
+        None => [ drop 0 ]
+      end
+    ]
+    Empty => [ 0 ]
+  end ;
+
+: apply ( ..R [ ..R -- ..R Int ] -- ..R Int )
+  call ;
+
+: greet ( -- )
+  "say \"hi\"\n\u{7}" write 2.5 -3 drop drop ;
+
+: count ( Int -- Int )
+  while [ dup 0 > ] [ 1 - ] dup 0 < if [ neg ] ;
+
+: noop ( -- ) ;
+"#;
+        assert_eq!(format_source(source), expected);
+    }
+
+    #[test]
+    fn test_literals_keep_their_spelling() {
+        let source = r#"
+            : chars ( -- Int Int Int ) 'a' '\'' '\n' ;
+            : ints ( -- Int Int Int ) 0x1F 1_000_000 -2_500 ;
+            : floats ( -- Float Float Float ) 1e3 2.5E-3 1_0.5 ;
+            : plain ( -- Int Float ) -7 3.0 ;
+        "#;
+
+        let expected = r#": chars ( -- Int Int Int )
+  'a' '\'' '\n' ;
+
+: ints ( -- Int Int Int )
+  0x1F 1_000_000 -2_500 ;
+
+: floats ( -- Float Float Float )
+  1e3 2.5E-3 1_0.5 ;
+
+: plain ( -- Int Float )
+  -7 3.0 ;
+"#;
+        assert_eq!(format_source(source), expected);
+    }
+
+    #[test]
+    fn test_radix_literals_round_trip() {
+        let source = r#"
+            : mask ( Int -- Int ) 0xFF bitand 0o755 bitor 0b1010_0101 bitxor -0x10 + ;
+            : name ( Int -- String ) match 0x0 => [ "zero" ] 0b1 => [ "one" ] _ => [ drop "many" ] end ;
+        "#;
+
+        let expected = r#": mask ( Int -- Int )
+  0xFF bitand 0o755 bitor 0b1010_0101 bitxor -0x10 + ;
+
+: name ( Int -- String )
+  match
+    0x0 => [ "zero" ]
+    0b1 => [ "one" ]
+    _ => [ drop "many" ]
+  end ;
+"#;
+        let formatted = format_source(source);
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source(&formatted), formatted);
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let prelude = include_str!("../../../stdlib/prelude.cem");
+        let long_if = ": f ( Int -- Int ) dup 0 < if [ \"a long message for a negative number\" write_line neg ] [ \"fine\" write_line ] ;";
+
+        for source in [prelude, long_if] {
+            let once = format_source(source);
+            assert_eq!(format_source(&once), once, "{}", once);
+        }
+    }
+
+    #[test]
+    fn test_long_lines_wrap() {
+        let words = vec!["dup drop"; 30].join(" ");
+        let formatted = format_source(&format!(": f ( Int -- Int ) {} ;", words));
+
+        assert!(formatted.lines().count() > 2, "{}", formatted);
+        assert!(formatted.lines().all(|line| line.len() <= MAX_WIDTH));
+    }
+}
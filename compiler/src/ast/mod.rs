@@ -3,8 +3,10 @@ Abstract Syntax Tree definitions for Cem
 
 This module defines the core AST types representing Cem programs.
 */
+pub mod format;
 pub mod types;
 
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
 
@@ -55,10 +57,113 @@ impl fmt::Display for SourceLoc {
 }
 
 /// A complete Cem program
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Program {
+    pub imports: Vec<Import>,
     pub type_defs: Vec<TypeDef>,
     pub word_defs: Vec<WordDef>,
+    /// How each number and character literal was written, by location
+    ///
+    /// The tree only keeps their values, so without this `0x1F`, `1_000` and
+    /// `'a'` would all be formatted as decimals.
+    pub literals: HashMap<SourceLoc, String>,
+}
+
+impl Program {
+    /// Render the whole tree, one node per line with its location
+    ///
+    /// Unlike `Display` for `Expr`, nothing is elided, so this shows exactly
+    /// what the parser built (used by `cem compile --emit ast`).
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        for import in &self.imports {
+            out.push_str(&format!("import {:?} @ {}\n", import.path, import.loc));
+        }
+        for typedef in &self.type_defs {
+            let params = if typedef.type_params.is_empty() {
+                String::new()
+            } else {
+                format!("<{}>", typedef.type_params.join(", "))
+            };
+            out.push_str(&format!(
+                "type {}{} @ {}\n",
+                typedef.name, params, typedef.loc
+            ));
+            for variant in &typedef.variants {
+                let fields: Vec<String> = variant.fields.iter().map(|t| t.to_string()).collect();
+                out.push_str(&format!(
+                    "  {}({}) @ {}\n",
+                    variant.name,
+                    fields.join(", "),
+                    variant.loc
+                ));
+            }
+        }
+        for word in &self.word_defs {
+            out.push_str(&format!(
+                "word {} {} @ {}\n",
+                word.name, word.effect, word.loc
+            ));
+            dump_exprs(&mut out, &word.body, 1);
+        }
+        out
+    }
+}
+
+/// Append `exprs` to `out`, indented `depth` levels
+fn dump_exprs(out: &mut String, exprs: &[Expr], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for expr in exprs {
+        let line = match expr {
+            Expr::IntLit(n, _) => format!("Int {}", n),
+            Expr::FloatLit(x, _) => format!("Float {:?}", x),
+            Expr::BoolLit(b, _) => format!("Bool {}", b),
+            Expr::StringLit(s, _) => format!("String {:?}", s),
+            Expr::WordCall(name, _) => format!("Call {}", name),
+            Expr::Quotation(..) => "Quotation".to_string(),
+            Expr::Match { .. } => "Match".to_string(),
+            Expr::If { .. } => "If".to_string(),
+            Expr::While { .. } => "While".to_string(),
+        };
+        out.push_str(&format!("{}{} @ {}\n", indent, line, expr.loc()));
+
+        match expr {
+            Expr::Quotation(body, _) => dump_exprs(out, body, depth + 1),
+            Expr::Match { branches, .. } => {
+                for branch in branches {
+                    out.push_str(&format!("{}  {} =>\n", indent, branch.pattern));
+                    dump_exprs(out, &branch.body, depth + 2);
+                }
+            }
+            Expr::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                out.push_str(&format!("{}  then\n", indent));
+                dump_exprs(out, std::slice::from_ref(then_branch.as_ref()), depth + 2);
+                out.push_str(&format!("{}  else\n", indent));
+                dump_exprs(out, std::slice::from_ref(else_branch.as_ref()), depth + 2);
+            }
+            Expr::While { cond, body, .. } => {
+                out.push_str(&format!("{}  cond\n", indent));
+                dump_exprs(out, std::slice::from_ref(cond.as_ref()), depth + 2);
+                out.push_str(&format!("{}  body\n", indent));
+                dump_exprs(out, std::slice::from_ref(body.as_ref()), depth + 2);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `import "path.cem"`: another file whose definitions this one uses
+///
+/// The path is relative to the importing file. Imports are textual: the
+/// imported definitions join the program under their own names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub path: String,
+    pub loc: SourceLoc,
 }
 
 /// Type definition (Algebraic Data Type / Sum Type)
@@ -67,6 +172,7 @@ pub struct TypeDef {
     pub name: String,
     pub type_params: Vec<String>,
     pub variants: Vec<Variant>,
+    pub loc: SourceLoc, // Location of the `type` keyword
 }
 
 /// A variant of a sum type
@@ -74,6 +180,7 @@ pub struct TypeDef {
 pub struct Variant {
     pub name: String,
     pub fields: Vec<types::Type>,
+    pub loc: SourceLoc, // Location of the variant name
 }
 
 /// Word (function) definition
@@ -91,6 +198,9 @@ pub enum Expr {
     /// Literal integer
     IntLit(i64, SourceLoc),
 
+    /// Literal floating-point number
+    FloatLit(f64, SourceLoc),
+
     /// Literal boolean
     BoolLit(bool, SourceLoc),
 
@@ -115,6 +225,13 @@ pub enum Expr {
         else_branch: Box<Expr>,
         loc: SourceLoc,
     },
+
+    /// While loop: runs `body` for as long as `cond` leaves true on top
+    While {
+        cond: Box<Expr>,
+        body: Box<Expr>,
+        loc: SourceLoc,
+    },
 }
 
 impl Expr {
@@ -122,12 +239,14 @@ impl Expr {
     pub fn loc(&self) -> &SourceLoc {
         match self {
             Expr::IntLit(_, loc) => loc,
+            Expr::FloatLit(_, loc) => loc,
             Expr::BoolLit(_, loc) => loc,
             Expr::StringLit(_, loc) => loc,
             Expr::WordCall(_, loc) => loc,
             Expr::Quotation(_, loc) => loc,
             Expr::Match { loc, .. } => loc,
             Expr::If { loc, .. } => loc,
+            Expr::While { loc, .. } => loc,
         }
     }
 }
@@ -137,6 +256,8 @@ impl Expr {
 pub struct MatchBranch {
     pub pattern: Pattern,
     pub body: Vec<Expr>,
+    /// Where the pattern starts
+    pub loc: SourceLoc,
 }
 
 /// Pattern for matching on sum types
@@ -145,14 +266,73 @@ pub enum Pattern {
     /// Match a specific variant, binding its fields
     Variant {
         name: String,
-        // Field patterns could be added later for nested matching
+        /// Patterns for the variant's fields, e.g. `Some(x)` or `Cons(Some(x), _)`.
+        /// When empty, the fields are unwrapped onto the stack instead.
+        fields: Vec<Pattern>,
     },
+
+    /// Name a variant's field, e.g. the `x` of `Some(x)`
+    Binding(String),
+
+    /// Match an `Int` scrutinee equal to this value
+    IntLit(i64),
+
+    /// Match a `Bool` scrutinee equal to this value
+    BoolLit(bool),
+
+    /// Catch-all pattern `_`, matching anything not covered by earlier branches.
+    /// The scrutinee is consumed, like a unit variant.
+    Wildcard,
+}
+
+impl Pattern {
+    /// Whether the pattern matches any value: a binding or the wildcard
+    pub fn is_irrefutable(&self) -> bool {
+        matches!(self, Pattern::Binding(_) | Pattern::Wildcard)
+    }
+
+    /// Whether a variant pattern also tests what its fields hold, as
+    /// `Cons(Nil, _)` does
+    pub fn tests_fields(&self) -> bool {
+        match self {
+            Pattern::Variant { fields, .. } => fields.iter().any(|f| !f.is_irrefutable()),
+            _ => false,
+        }
+    }
+
+    /// The names the pattern binds, at any depth
+    pub fn binding_names(&self) -> Vec<&str> {
+        match self {
+            Pattern::Binding(name) => vec![name.as_str()],
+            Pattern::Variant { fields, .. } => {
+                fields.iter().flat_map(Pattern::binding_names).collect()
+            }
+            Pattern::IntLit(_) | Pattern::BoolLit(_) | Pattern::Wildcard => Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Variant { name, fields } if fields.is_empty() => write!(f, "{}", name),
+            Pattern::Variant { name, fields } => {
+                let fields: Vec<String> = fields.iter().map(Pattern::to_string).collect();
+                write!(f, "{}({})", name, fields.join(", "))
+            }
+            Pattern::Binding(name) => write!(f, "{}", name),
+            Pattern::IntLit(n) => write!(f, "{}", n),
+            Pattern::BoolLit(b) => write!(f, "{}", b),
+            Pattern::Wildcard => write!(f, "_"),
+        }
+    }
 }
 
 impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Expr::IntLit(n, _) => write!(f, "{}", n),
+            Expr::FloatLit(x, _) => write!(f, "{:?}", x),
             Expr::BoolLit(b, _) => write!(f, "{}", b),
             Expr::StringLit(s, _) => write!(f, "\"{}\"", s),
             Expr::WordCall(name, _) => write!(f, "{}", name),
@@ -171,6 +351,7 @@ impl fmt::Display for Expr {
                 write!(f, "end")
             }
             Expr::If { .. } => write!(f, "if"),
+            Expr::While { .. } => write!(f, "while"),
         }
     }
 }
@@ -14,6 +14,9 @@ pub enum Type {
     /// Boolean type (Copy)
     Bool,
 
+    /// 64-bit floating-point type (Copy)
+    Float,
+
     /// String type (Linear - not Copy)
     String,
 
@@ -94,6 +97,22 @@ impl StackType {
         }
     }
 
+    /// Number of concrete types above the stack's base (`Empty` or a row variable)
+    pub fn known_depth(&self) -> usize {
+        match self {
+            StackType::Cons { rest, .. } => rest.known_depth() + 1,
+            StackType::Empty | StackType::RowVar(_) => 0,
+        }
+    }
+
+    /// The stack beneath all known types: `Empty` or a row variable
+    pub fn base(&self) -> &StackType {
+        match self {
+            StackType::Cons { rest, .. } => rest.base(),
+            base => base,
+        }
+    }
+
     /// Check if this is a row variable
     pub fn is_row_var(&self) -> bool {
         matches!(self, StackType::RowVar(_))
@@ -135,9 +154,11 @@ impl Type {
     /// Check if this type is Copy (can be duplicated without clone)
     pub fn is_copy(&self) -> bool {
         match self {
-            Type::Int | Type::Bool => true,
+            Type::Int | Type::Bool | Type::Float => true,
             Type::String => false,
-            Type::Var(_) => false,       // Conservative: assume not Copy
+            Type::Var(_) => false, // Conservative: assume not Copy
+            // Channel handles are shared, so copying one is just another handle
+            Type::Named { name, .. } if name == "Chan" => true,
             Type::Named { .. } => false, // Conservative: requires trait analysis
             Type::Quotation(_) => true,  // Quotations are Copy (just code pointers for now)
         }
@@ -154,6 +175,7 @@ impl fmt::Display for Type {
         match self {
             Type::Int => write!(f, "Int"),
             Type::Bool => write!(f, "Bool"),
+            Type::Float => write!(f, "Float"),
             Type::String => write!(f, "String"),
             Type::Var(name) => write!(f, "{}", name),
             Type::Named { name, args } => {
@@ -185,14 +207,19 @@ impl fmt::Display for StackType {
                 }
                 write!(f, "{}", top)
             }
-            StackType::RowVar(name) => write!(f, "{}", name),
+            StackType::RowVar(name) => write!(f, "..{}", name),
         }
     }
 }
 
 impl fmt::Display for Effect {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "( {} -- {} )", self.inputs, self.outputs)
+        // Each side as in source: `( -- )`, not `(  --  )`, when it's empty
+        let side = |stack: &StackType| match stack {
+            StackType::Empty => String::new(),
+            stack => format!("{} ", stack),
+        };
+        write!(f, "( {}-- {})", side(&self.inputs), side(&self.outputs))
     }
 }
 
@@ -200,6 +227,34 @@ impl fmt::Display for Effect {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_known_depth() {
+        let closed = StackType::from_vec(vec![Type::Int, Type::Bool]);
+        assert_eq!(closed.known_depth(), 2);
+        assert_eq!(closed.depth(), Some(2));
+
+        let open = StackType::RowVar("R".to_string()).push(Type::Int);
+        assert_eq!(open.known_depth(), 1);
+        assert_eq!(open.depth(), None);
+    }
+
+    #[test]
+    fn test_row_variable_base_and_display() {
+        let open = StackType::RowVar("R".to_string())
+            .push(Type::Int)
+            .push(Type::Var("A".to_string()));
+        assert_eq!(open.base(), &StackType::RowVar("R".to_string()));
+        assert_eq!(StackType::empty().push(Type::Int).base(), &StackType::Empty);
+
+        // Written as in source, so row and type variables can't be confused
+        let effect = Effect::new(open.clone(), open);
+        assert_eq!(effect.to_string(), "( ..R Int A -- ..R Int A )");
+        assert_eq!(
+            Effect::from_vecs(vec![Type::Int], vec![]).to_string(),
+            "( Int -- )"
+        );
+    }
+
     #[test]
     fn test_stack_operations() {
         let stack = StackType::empty().push(Type::Int).push(Type::Bool);
@@ -239,7 +294,15 @@ mod tests {
     fn test_copy_types() {
         assert!(Type::Int.is_copy());
         assert!(Type::Bool.is_copy());
+        assert!(Type::Float.is_copy());
         assert!(!Type::String.is_copy());
         assert!(Type::String.is_linear());
+
+        let named = |name: &str| Type::Named {
+            name: name.to_string(),
+            args: vec![Type::String],
+        };
+        assert!(named("Chan").is_copy());
+        assert!(named("List").is_linear());
     }
 }
@@ -5,9 +5,12 @@ This module handles:
 - Writing .ll files to disk
 - Invoking clang with appropriate flags
 - Linking with C runtime
+- Assembling bitcode with llvm-as
 */
-use super::{CodegenError, CodegenResult};
+use super::{CodeGen, CodegenError, CodegenResult};
+use crate::ast::Program;
 use std::fs;
+use std::io::{BufWriter, Write};
 use std::path::Path;
 use std::process::Command;
 
@@ -41,18 +44,27 @@ fn validate_path(path: &str) -> CodegenResult<()> {
 /// * `ir_code` - The LLVM IR as a string
 /// * `runtime_lib` - Path to libcem_runtime.a
 /// * `output` - Output executable path
+/// * `target` - Target triple to pass to clang, or None for clang's default
 ///
 /// # Example
 /// ```no_run
 /// use cemc::codegen::link_program;
 ///
 /// let ir = "define ptr @main(ptr %stack) { ... }";
-/// link_program(ir, "runtime/libcem_runtime.a", "program").unwrap();
+/// link_program(ir, "runtime/libcem_runtime.a", "program", None).unwrap();
 /// ```
-pub fn link_program(ir_code: &str, runtime_lib: &str, output: &str) -> CodegenResult<()> {
+pub fn link_program(
+    ir_code: &str,
+    runtime_lib: &str,
+    output: &str,
+    target: Option<&str>,
+) -> CodegenResult<()> {
     // Validate paths to prevent command injection
     validate_path(runtime_lib)?;
     validate_path(output)?;
+    if let Some(target) = target {
+        validate_target(target)?;
+    }
 
     // Write IR to temporary .ll file
     let ll_file = format!("{}.ll", output);
@@ -60,14 +72,126 @@ pub fn link_program(ir_code: &str, runtime_lib: &str, output: &str) -> CodegenRe
         message: format!("Failed to write {}: {}", ll_file, e),
     })?;
 
+    link_ir_file(&ll_file, runtime_lib, output, target)?;
+
+    // Keep .ll file for inspection but report success
+    println!("Generated: {}", ll_file);
+    println!("Executable: {}", output);
+
+    Ok(())
+}
+
+/// Link an LLVM IR file already on disk with C runtime to produce executable
+///
+/// # Arguments
+/// * `ll_file` - Path to the `.ll` file
+/// * `runtime_lib` - Path to libcem_runtime.a
+/// * `output` - Output executable path
+/// * `target` - Target triple to pass to clang, or None for clang's default
+pub fn link_ir_file(
+    ll_file: &str,
+    runtime_lib: &str,
+    output: &str,
+    target: Option<&str>,
+) -> CodegenResult<()> {
+    // Validate paths to prevent command injection
+    validate_path(ll_file)?;
+    validate_path(runtime_lib)?;
+    validate_path(output)?;
+
     // Call clang to compile and link
-    let status = Command::new("clang")
-        .arg(&ll_file)
-        .arg(runtime_lib)
-        .arg("-o")
-        .arg(output)
+    run_clang(&[ll_file, runtime_lib, "-o", output], target)
+}
+
+/// Compile an LLVM IR file to an object file, without linking
+pub fn compile_ir_file_to_object(
+    ll_file: &str,
+    output: &str,
+    target: Option<&str>,
+) -> CodegenResult<()> {
+    validate_path(ll_file)?;
+    validate_path(output)?;
+
+    run_clang(&["-c", ll_file, "-o", output], target)
+}
+
+/// Compile an LLVM IR file to native assembly
+pub fn compile_ir_file_to_assembly(
+    ll_file: &str,
+    output: &str,
+    target: Option<&str>,
+) -> CodegenResult<()> {
+    validate_path(ll_file)?;
+    validate_path(output)?;
+
+    run_clang(&["-S", ll_file, "-o", output], target)
+}
+
+/// Assemble an LLVM IR file into LLVM bitcode (.bc) with `llvm-as`
+///
+/// The bitcode targets whatever triple the IR names; there's nothing to
+/// cross-compile here.
+pub fn compile_ir_file_to_bitcode(ll_file: &str, output: &str) -> CodegenResult<()> {
+    validate_path(ll_file)?;
+    validate_path(output)?;
+
+    let status = Command::new("llvm-as")
+        .args([ll_file, "-o", output])
+        .status()
+        .map_err(|e| CodegenError::LinkerError {
+            message: if e.kind() == std::io::ErrorKind::NotFound {
+                "llvm-as not found. Install LLVM and put llvm-as on your PATH \
+                 (some packages only install a versioned name like llvm-as-18), \
+                 or use --emit ir for text IR"
+                    .to_string()
+            } else {
+                format!("Failed to execute llvm-as: {}", e)
+            },
+        })?;
+
+    if !status.success() {
+        return Err(CodegenError::LinkerError {
+            message: format!("llvm-as exited with status: {}", status),
+        });
+    }
+
+    Ok(())
+}
+
+/// Validate a target triple, so it can't be mistaken for a clang flag
+fn validate_target(target: &str) -> CodegenResult<()> {
+    let valid = !target.is_empty()
+        && !target.starts_with('-')
+        && target
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(CodegenError::LinkerError {
+            message: format!("Invalid target triple '{}'", target),
+        });
+    }
+
+    Ok(())
+}
+
+/// Build the clang command for `args`, cross-compiling if `target` is given
+fn clang_command(args: &[&str], target: Option<&str>) -> CodegenResult<Command> {
+    let mut command = Command::new("clang");
+    if let Some(target) = target {
+        validate_target(target)?;
+        command.arg("-target").arg(target);
+    }
+    command
+        .args(args)
         .arg("-O2") // Enable optimizations for musttail
-        .arg("-Wno-override-module") // Suppress target triple override warning
+        .arg("-Wno-override-module"); // Suppress target triple override warning
+
+    Ok(command)
+}
+
+/// Run clang with `args` plus the flags every build uses
+fn run_clang(args: &[&str], target: Option<&str>) -> CodegenResult<()> {
+    let status = clang_command(args, target)?
         .status()
         .map_err(|e| CodegenError::LinkerError {
             message: format!("Failed to execute clang: {}", e),
@@ -79,16 +203,62 @@ pub fn link_program(ir_code: &str, runtime_lib: &str, output: &str) -> CodegenRe
         });
     }
 
-    // Keep .ll file for inspection but report success
-    println!("Generated: {}", ll_file);
-    println!("Executable: {}", output);
-
     Ok(())
 }
 
+/// Compile a parsed program to an executable, linked with a prebuilt runtime
+///
+/// Unlike the `cem` CLI, this never builds the runtime: `runtime_lib` must be
+/// an existing `libcem_runtime.a` for the same target. The IR is written to
+/// `<output>.ll` and removed once linked.
+///
+/// # Arguments
+/// * `program` - The parsed (and normally type-checked) program
+/// * `entry_word` - Word that `main()` calls
+/// * `runtime_lib` - Path to libcem_runtime.a
+/// * `output` - Output executable path
+/// * `target` - Target triple to cross-compile for, or None for clang's default
+///
+/// # Example
+/// ```no_run
+/// use cemc::codegen::compile_executable;
+/// use cemc::parser::Parser;
+///
+/// let program = Parser::new(": main ( -- ) \"hi\" write_line ;").parse().unwrap();
+/// compile_executable(&program, "main", "target/release/libcem_runtime.a", "hi", None).unwrap();
+/// ```
+pub fn compile_executable(
+    program: &Program,
+    entry_word: &str,
+    runtime_lib: &str,
+    output: &str,
+    target: Option<&str>,
+) -> CodegenResult<()> {
+    validate_path(output)?;
+
+    let mut codegen = CodeGen::new();
+    if let Some(target) = target {
+        validate_target(target)?;
+        codegen.set_target_triple(target);
+    }
+
+    let ll_file = format!("{}.ll", output);
+    let write_error = |e: std::io::Error| CodegenError::LinkerError {
+        message: format!("Failed to write {}: {}", ll_file, e),
+    };
+    let mut writer = BufWriter::new(fs::File::create(&ll_file).map_err(write_error)?);
+    codegen.compile_program_to_writer(program, Some(entry_word), &mut writer)?;
+    writer.flush().map_err(write_error)?;
+    drop(writer);
+
+    let linked = link_ir_file(&ll_file, runtime_lib, output, target);
+    fs::remove_file(&ll_file).ok();
+    linked
+}
+
 /// Link program with default runtime location
 pub fn link_program_default(ir_code: &str, output: &str) -> CodegenResult<()> {
-    link_program(ir_code, "target/release/libcem_runtime.a", output)
+    link_program(ir_code, "target/release/libcem_runtime.a", output, None)
 }
 
 /// Compile LLVM IR to object file without linking
@@ -105,23 +275,7 @@ pub fn compile_to_object(ir_code: &str, output: &str) -> CodegenResult<()> {
     })?;
 
     // Call clang to compile to object file
-    let status = Command::new("clang")
-        .arg("-c")
-        .arg(&ll_file)
-        .arg("-o")
-        .arg(format!("{}.o", output))
-        .arg("-O2") // Enable optimizations
-        .arg("-Wno-override-module") // Suppress target triple override warning
-        .status()
-        .map_err(|e| CodegenError::LinkerError {
-            message: format!("Failed to execute clang: {}", e),
-        })?;
-
-    if !status.success() {
-        return Err(CodegenError::LinkerError {
-            message: format!("clang exited with status: {}", status),
-        });
-    }
+    run_clang(&["-c", &ll_file, "-o", &format!("{}.o", output)], None)?;
 
     println!("Generated: {}", ll_file);
     println!("Object file: {}.o", output);
@@ -146,6 +300,58 @@ pub fn check_clang() -> CodegenResult<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_clang_command_forwards_target() {
+        let command = clang_command(&["prog.ll"], Some("aarch64-unknown-linux-gnu")).unwrap();
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            &args[..3],
+            ["-target", "aarch64-unknown-linux-gnu", "prog.ll"]
+        );
+
+        // Without a target, clang picks its default
+        let command = clang_command(&["prog.ll"], None).unwrap();
+        assert!(command.get_args().all(|arg| arg != "-target"));
+    }
+
+    #[test]
+    fn test_link_program_rejects_flag_as_target() {
+        let result = link_program("", "libcem_runtime.a", "prog", Some("-fplugin=evil.so"));
+        assert!(matches!(result, Err(CodegenError::LinkerError { .. })));
+    }
+
+    #[test]
+    fn test_compile_executable_links_prebuilt_runtime() {
+        // Built by `cargo build --workspace`; the library API never builds it
+        let runtime_lib = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../target/debug/libcem_runtime.a"
+        );
+        let runtime_lib = fs::canonicalize(runtime_lib).expect("build the runtime first");
+        let dir = std::env::temp_dir().join(format!("cem-compile-api-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("hi");
+
+        let program = crate::parser::Parser::new(": main ( -- ) \"hi\" write_line ;")
+            .parse()
+            .unwrap();
+        let result = compile_executable(
+            &program,
+            "main",
+            runtime_lib.to_str().unwrap(),
+            output.to_str().unwrap(),
+            None,
+        );
+        let run = result.map(|()| Command::new(&output).output().unwrap());
+        let leftover_ir = dir.join("hi.ll").exists();
+        fs::remove_dir_all(&dir).ok();
+
+        let run = run.unwrap();
+        assert!(run.status.success());
+        assert_eq!(String::from_utf8_lossy(&run.stdout), "hi\n");
+        assert!(!leftover_ir);
+    }
+
     #[test]
     fn test_check_clang() {
         let version = check_clang().unwrap();
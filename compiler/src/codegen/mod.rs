@@ -11,7 +11,7 @@ See docs/LLVM_TEXT_IR.md for design rationale.
 
 The code generator walks the AST and emits LLVM IR text:
 - Words → Functions
-- Literals → push_int/push_bool/push_string calls
+- Literals → push_int/push_float/push_bool/push_string calls
 - Word calls → Function calls
 - Primitives → Runtime function calls
 
@@ -32,11 +32,13 @@ pub mod linker;
 
 pub use error::{CodegenError, CodegenResult};
 pub use ir::IRGenerator;
-pub use linker::{compile_to_object, link_program};
+pub use linker::{
+    compile_executable, compile_ir_file_to_assembly, compile_ir_file_to_bitcode,
+    compile_ir_file_to_object, compile_to_object, link_ir_file, link_program,
+};
 
-#[cfg(test)]
-use crate::ast::SourceLoc;
-use crate::ast::{Expr, Pattern, Program, WordDef};
+use crate::ast::format::format_effect;
+use crate::ast::{Expr, MatchBranch, Pattern, Program, SourceLoc, WordDef};
 use std::fmt::Write as _;
 use std::process::Command;
 
@@ -46,17 +48,24 @@ pub struct CodeGen {
     string_globals: String, // Separate area for string constant declarations
     quotation_functions: String, // Separate area for quotation function definitions
     temp_counter: usize,
-    string_counter: usize, // Separate counter for string constants (never reset)
+    string_counter: usize, // Separate counter for string constants (not reset between words, only by `reset`)
     current_block: String, // Track the current basic block label we're emitting into
     metadata_counter: usize, // Counter for debug metadata IDs
     file_metadata: std::collections::HashMap<String, usize>, // filename -> metadata ID
     compile_unit_id: Option<usize>, // ID of the DICompileUnit metadata node
-    word_subprograms: Vec<(String, usize, usize, usize)>, // (word_name, file_id, line, subprogram_id)
+    word_subprograms: Vec<(String, usize, usize, usize, usize)>, // (word_name, file_id, line, subprogram_id, stack_variable_id)
     current_subprogram_id: Option<usize>, // ID of the current function's DISubprogram
     debug_locations: std::collections::HashMap<(usize, usize, usize, usize), usize>, // (file_id, line, col, scope) -> DILocation ID
     string_constants: std::collections::HashMap<String, String>, // string content -> global name (@.str.N)
-    variant_tags: std::collections::HashMap<String, u32>, // variant_name -> tag (index in type definition)
-    variant_field_counts: std::collections::HashMap<String, usize>, // variant_name -> number of fields
+    quotation_counter: usize, // Separate counter for quotation functions (not reset between words, only by `reset`)
+    quotation_names: std::collections::HashMap<String, String>, // quotation body key -> function name (quot_N)
+    variant_tags: std::collections::HashMap<(String, String), u32>, // (type_name, variant_name) -> tag (index in type definition)
+    variant_field_counts: std::collections::HashMap<(String, String), usize>, // (type_name, variant_name) -> number of fields
+    variant_types: std::collections::HashMap<String, Vec<String>>, // variant_name -> types declaring it
+    match_bindings: Vec<(String, String)>, // (binding name, field cell temp) for enclosing match branches
+    binding_barrier: usize, // match_bindings below this index belong to an enclosing function
+    target_triple: Option<String>, // Emitted in the module header when cross-compiling
+    print_final_stack: bool, // main() prints the entry word's final stack instead of exiting with it
 }
 
 impl CodeGen {
@@ -76,11 +85,88 @@ impl CodeGen {
             current_subprogram_id: None,
             debug_locations: std::collections::HashMap::new(),
             string_constants: std::collections::HashMap::new(),
+            quotation_counter: 0,
+            quotation_names: std::collections::HashMap::new(),
             variant_tags: std::collections::HashMap::new(),
             variant_field_counts: std::collections::HashMap::new(),
+            variant_types: std::collections::HashMap::new(),
+            match_bindings: Vec::new(),
+            binding_barrier: 0,
+            target_triple: None,
+            print_final_stack: false,
         }
     }
 
+    /// Return to the state of a new code generator, so the instance can
+    /// compile another program
+    ///
+    /// Buffers and maps are cleared rather than reallocated, and every
+    /// counter, including the string and quotation counters that otherwise
+    /// run for a whole program, starts over. Settings made with `set_*` are
+    /// kept.
+    pub fn reset(&mut self) {
+        // Destructured so a new field can't be left out
+        let CodeGen {
+            output,
+            string_globals,
+            quotation_functions,
+            temp_counter,
+            string_counter,
+            current_block,
+            metadata_counter,
+            file_metadata,
+            compile_unit_id,
+            word_subprograms,
+            current_subprogram_id,
+            debug_locations,
+            string_constants,
+            quotation_counter,
+            quotation_names,
+            variant_tags,
+            variant_field_counts,
+            variant_types,
+            match_bindings,
+            binding_barrier,
+            target_triple: _,
+            print_final_stack: _,
+        } = self;
+
+        output.clear();
+        string_globals.clear();
+        quotation_functions.clear();
+        *temp_counter = 0;
+        *string_counter = 0;
+        current_block.clear();
+        current_block.push_str("entry");
+        *metadata_counter = 0;
+        file_metadata.clear();
+        *compile_unit_id = None;
+        word_subprograms.clear();
+        *current_subprogram_id = None;
+        debug_locations.clear();
+        string_constants.clear();
+        *quotation_counter = 0;
+        quotation_names.clear();
+        variant_tags.clear();
+        variant_field_counts.clear();
+        variant_types.clear();
+        match_bindings.clear();
+        *binding_barrier = 0;
+    }
+
+    /// Make main() print the entry word's final stack and exit with 0, rather
+    /// than exiting with an Int left on top
+    pub fn set_print_final_stack(&mut self, print: bool) {
+        self.print_final_stack = print;
+    }
+
+    /// Emit `target triple` for cross-compiling
+    ///
+    /// Without one, the module has no triple and clang uses its default.
+    pub fn set_target_triple(&mut self, triple: &str) {
+        self.target_triple = Some(triple.to_string());
+    }
+
     /// Generate a fresh temporary variable name (without % prefix)
     fn fresh_temp(&mut self) -> String {
         let name = format!("{}", self.temp_counter);
@@ -90,6 +176,10 @@ impl CodeGen {
 
     /// Escape a string for LLVM IR string literals
     /// LLVM IR requires hex escaping for non-printable characters
+    ///
+    /// Control bytes, NUL included, come out as `\XX`. An escaped NUL is
+    /// still valid IR, but the runtime would see the string end there, so
+    /// string literals are checked for NUL before they get here.
     fn escape_llvm_string(s: &str) -> String {
         let mut result = String::new();
         for ch in s.chars() {
@@ -100,7 +190,7 @@ impl CodeGen {
                 '\\' => result.push_str(r"\\"),
                 // Escape quote
                 '"' => result.push_str(r#"\""#),
-                // All other characters as hex escapes
+                // Control bytes and everything outside ASCII as hex escapes
                 _ => {
                     for byte in ch.to_string().as_bytes() {
                         result.push_str(&format!(r"\{:02X}", byte));
@@ -121,6 +211,7 @@ impl CodeGen {
             "-" => "subtract".to_string(),
             "*" => "multiply".to_string(),
             "/" => "divide".to_string(),
+            "%" => "modulo".to_string(),
             // Comparison operators (match runtime function names)
             "<" => "lt".to_string(),
             ">" => "gt".to_string(),
@@ -128,33 +219,311 @@ impl CodeGen {
             ">=" => "ge".to_string(),
             "=" => "eq".to_string(),
             "!=" => "ne".to_string(),
+            "equal" => "cell_equal".to_string(),
+            // Boolean operators, named for the type they take like the bitwise ones
+            "and" => "bool_and".to_string(),
+            "or" => "bool_or".to_string(),
             // Special functions
             "exit" => "exit_op".to_string(), // Avoid conflict with stdlib exit()
+            "write" => "write_op".to_string(), // Avoid conflict with libc write()
+            "getenv" => "getenv_op".to_string(), // Avoid conflict with libc getenv()
+            "random" => "random_op".to_string(), // Avoid conflict with libc random()
+            "call" => "call_quotation".to_string(),
+            "spawn" => "spawn_quotation".to_string(),
+            "yield" => "yield_strand".to_string(),
+            "sleep" => "sleep_strand".to_string(), // Avoid conflict with libc sleep()
+            "join" => "join_strand".to_string(),
+            // Channels (send and recv would clash with libc)
+            "channel" => "channel_new".to_string(),
+            "send" => "channel_send".to_string(),
+            "recv" => "channel_recv".to_string(),
+            // Stack words whose names aren't valid symbols
+            "-rot" => "unrot".to_string(),
+            "2dup" => "two_dup".to_string(),
+            "2drop" => "two_drop".to_string(),
             // For hyphenated names, replace hyphens with underscores
             _ => name.replace('-', "_"),
         }
     }
 
+    /// Look up a match binding visible in the function being compiled,
+    /// returning the temp holding its field cell
+    fn match_binding(&self, name: &str) -> Option<&str> {
+        self.match_bindings[self.binding_barrier..]
+            .iter()
+            .rev()
+            .find(|(binding, _)| binding == name)
+            .map(|(_, cell)| cell.as_str())
+    }
+
+    /// Check if a word call compiles to a real function call that can be tail-called,
+    /// i.e. it is not a variant constructor, runtime built-in, or match binding
+    fn is_tail_callable(&self, name: &str) -> bool {
+        !self.variant_types.contains_key(name)
+            && !Self::is_runtime_builtin(name)
+            && !self
+                .match_bindings
+                .iter()
+                .any(|(binding, _)| binding == name)
+    }
+
+    /// The type and name of the variant a constructor call builds, if `name` is a constructor
+    ///
+    /// Codegen has no types to go by, so a variant name two types declare is ambiguous.
+    fn constructor_key(&self, name: &str) -> CodegenResult<Option<(String, String)>> {
+        match self.variant_types.get(name).map(Vec::as_slice) {
+            None => Ok(None),
+            Some([type_name]) => Ok(Some((type_name.clone(), name.to_string()))),
+            Some(types) => Err(CodegenError::InternalError(format!(
+                "Constructor '{}' is ambiguous: declared by types {}",
+                name,
+                types.join(", ")
+            ))),
+        }
+    }
+
+    /// Whether a match's branches name every value of what it matches on:
+    /// both Bools, or every variant of `matched_type`
+    ///
+    /// Recomputed here rather than trusted from the checker, since the checker
+    /// can be skipped.
+    fn covers_every_value(&self, branches: &[MatchBranch], matched_type: Option<&str>) -> bool {
+        let covers = |pattern: &Pattern| branches.iter().any(|b| b.pattern == *pattern);
+        if branches
+            .iter()
+            .any(|b| matches!(b.pattern, Pattern::BoolLit(_)))
+        {
+            return covers(&Pattern::BoolLit(true)) && covers(&Pattern::BoolLit(false));
+        }
+
+        let Some(matched_type) = matched_type else {
+            return false;
+        };
+        self.variant_tags
+            .keys()
+            .filter(|(type_name, _)| type_name == matched_type)
+            .all(|(_, variant)| {
+                branches.iter().any(|b| {
+                    matches!(&b.pattern, Pattern::Variant { name, .. } if name == variant)
+                        && !b.pattern.tests_fields()
+                })
+            })
+    }
+
+    /// The cells of a variant's fields, in field order, given its data chain
+    ///
+    /// The chain holds the last field first, since it was on top of the stack
+    /// when the variant was built.
+    fn field_cells(&mut self, data: &str, count: usize) -> CodegenResult<Vec<String>> {
+        let mut cells = vec![data.to_string()];
+        while cells.len() < count {
+            let next_ptr = self.fresh_temp();
+            writeln!(
+                &mut self.output,
+                "  %{} = getelementptr inbounds {{ i32, [4 x i8], [16 x i8], ptr }}, ptr %{}, i32 0, i32 3",
+                next_ptr,
+                cells.last().unwrap()
+            )
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+            let next_field = self.fresh_temp();
+            writeln!(
+                &mut self.output,
+                "  %{} = load ptr, ptr %{}",
+                next_field, next_ptr
+            )
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+            cells.push(next_field);
+        }
+        cells.truncate(count);
+        cells.reverse();
+        Ok(cells)
+    }
+
+    /// The data chain of the variant held in `cell` (union offset 8)
+    fn variant_data_of(&mut self, cell: &str) -> CodegenResult<String> {
+        let data_ptr = self.fresh_temp();
+        writeln!(
+            &mut self.output,
+            "  %{} = getelementptr inbounds {{ i32, [4 x i8], [16 x i8], ptr }}, ptr %{}, i32 0, i32 2, i32 8",
+            data_ptr, cell
+        )
+        .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+        let data = self.fresh_temp();
+        writeln!(
+            &mut self.output,
+            "  %{} = load ptr, ptr %{}",
+            data, data_ptr
+        )
+        .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        Ok(data)
+    }
+
+    /// Branch to `fail_label` unless the fields in a variant's data chain hold
+    /// the variants their patterns name, at any depth
+    ///
+    /// Ends in a fresh block, reached when every field matches.
+    fn emit_field_tests(
+        &mut self,
+        fields: &[Pattern],
+        data: &str,
+        fail_label: &str,
+    ) -> CodegenResult<()> {
+        let cells = self.field_cells(data, fields.len())?;
+        for (field, cell) in fields.iter().zip(cells) {
+            let Pattern::Variant { name, fields } = field else {
+                continue;
+            };
+            let key = self
+                .constructor_key(name)?
+                .ok_or_else(|| CodegenError::InternalError(format!("Unknown variant: {}", name)))?;
+
+            let tag_ptr = self.fresh_temp();
+            writeln!(
+                &mut self.output,
+                "  %{} = getelementptr inbounds {{ i32, [4 x i8], [16 x i8], ptr }}, ptr %{}, i32 0, i32 2, i32 0",
+                tag_ptr, cell
+            )
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+            let tag = self.fresh_temp();
+            writeln!(&mut self.output, "  %{} = load i32, ptr %{}", tag, tag_ptr)
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+            let matches = self.fresh_temp();
+            writeln!(
+                &mut self.output,
+                "  %{} = icmp eq i32 %{}, {}",
+                matches, tag, self.variant_tags[&key]
+            )
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+            let matched_label = format!("match_field_{}", self.fresh_temp());
+            writeln!(
+                &mut self.output,
+                "  br i1 %{}, label %{}, label %{}",
+                matches, matched_label, fail_label
+            )
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+            writeln!(&mut self.output, "{}:", matched_label)
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+            if field.tests_fields() {
+                let inner_data = self.variant_data_of(&cell)?;
+                self.emit_field_tests(fields, &inner_data, fail_label)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bring the names a variant's field patterns bind into scope, each
+    /// referring to its field cell in the data chain, at any depth
+    fn bind_fields(&mut self, fields: &[Pattern], data: &str) -> CodegenResult<()> {
+        let cells = self.field_cells(data, fields.len())?;
+        for (field, cell) in fields.iter().zip(cells) {
+            match field {
+                Pattern::Binding(name) => self.match_bindings.push((name.clone(), cell)),
+                Pattern::Variant { fields, .. } if !field.binding_names().is_empty() => {
+                    let inner_data = self.variant_data_of(&cell)?;
+                    self.bind_fields(fields, &inner_data)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The type whose variants a match's patterns name, or None if it has no variant patterns
+    ///
+    /// Every variant pattern must belong to the type, so patterns that share a
+    /// variant name with another type are told apart by the rest of the match.
+    fn matched_type(&self, branches: &[MatchBranch]) -> CodegenResult<Option<String>> {
+        let mut candidates: Option<Vec<&String>> = None;
+        for branch in branches {
+            let Pattern::Variant { name, .. } = &branch.pattern else {
+                continue;
+            };
+            let declaring = self
+                .variant_types
+                .get(name)
+                .ok_or_else(|| CodegenError::InternalError(format!("Unknown variant: {}", name)))?;
+            let remaining: Vec<&String> = match candidates {
+                None => declaring.iter().collect(),
+                Some(types) => types
+                    .into_iter()
+                    .filter(|t| declaring.contains(t))
+                    .collect(),
+            };
+            if remaining.is_empty() {
+                return Err(CodegenError::InternalError(format!(
+                    "Variant '{}' doesn't belong to the same type as the match's other patterns",
+                    name
+                )));
+            }
+            candidates = Some(remaining);
+        }
+
+        match candidates.as_deref() {
+            None => Ok(None),
+            Some([type_name]) => Ok(Some((*type_name).clone())),
+            Some(types) => Err(CodegenError::InternalError(format!(
+                "Match patterns are ambiguous: they fit types {}",
+                types
+                    .iter()
+                    .map(|t| t.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// Extend `bound` with the names a match pattern introduces
+    fn pattern_scope<'a>(bound: &[&'a str], pattern: &'a Pattern) -> Vec<&'a str> {
+        let mut scope = bound.to_vec();
+        scope.extend(pattern.binding_names());
+        scope
+    }
+
     /// Check if a word is a runtime built-in (not user-defined)
     /// Runtime built-ins should NOT use musttail in match branches
     fn is_runtime_builtin(name: &str) -> bool {
         matches!(
             name,
             // Stack operations
-            "dup" | "drop" | "swap" | "over" | "rot" | "nip" | "tuck" | "pick" | "dip" |
+            "dup" | "drop" | "swap" | "over" | "rot" | "-rot" | "nip" | "tuck" | "2dup" | "2drop" |
+            "pick" | "depth" | "ndrop" | "ndup" | "dip" |
             // Arithmetic
-            "+" | "-" | "*" | "/" |
+            "+" | "-" | "*" | "/" | "%" | "neg" | "abs" | "min" | "max" |
+            // Bitwise
+            "bitand" | "bitor" | "bitxor" | "shl" | "shr" | "invert" |
+            // Boolean
+            "not" | "and" | "or" |
             // Comparisons
-            "<" | ">" | "<=" | ">=" | "=" | "!=" |
+            "<" | ">" | "<=" | ">=" | "=" | "!=" | "equal" |
             // String operations
             "string-length" | "string-concat" | "string-equal" |
             "string_length" | "string_concat" | "string_equal" |  // underscore variants
+            "string_lt" | "string_gt" | "string_compare" | "substring" | "string_to_int" |
+            "string_char_length" | "string_contains" | "string_starts_with" | "string_ends_with" |
+            "string_split" | "string_char_at" | "string_repeat" | "string_reverse" |
+            "string_trim" | "string_to_upper" | "string_to_lower" | "string_index_of" |
+            "string_replace" |
             // Conversions
             "int-to-string" | "bool-to-string" |
             "int_to_string" | "bool_to_string" |  // underscore variants
             // I/O (these are async but don't need musttail)
-            "write-line" | "read-line" |
-            "write_line" | "read_line" // underscore variants
+            "write-line" | "read-line" | "write" |
+            "write_line" | "read_line" | // underscore variants
+            "read-file" | "read_file" | "write-file" | "write_file" |
+            // Process environment
+            "args_count" | "arg_at" | "getenv" |
+            // Pseudo-random numbers
+            "random" | "random-seed" |
+            // Strands
+            "spawn" | "spawn-with" | "spawn-result" | "join" | "yield" | "sleep" |
+            // Channels
+            "channel" | "send" | "recv"
         )
     }
 
@@ -174,14 +543,36 @@ impl CodeGen {
         program: &Program,
         entry_word: Option<&str>,
     ) -> CodegenResult<String> {
+        let mut buffer = Vec::new();
+        self.compile_program_to_writer(program, entry_word, &mut buffer)?;
+        String::from_utf8(buffer).map_err(|e| CodegenError::InternalError(e.to_string()))
+    }
+
+    /// Compile a complete program, writing the LLVM IR to `writer`
+    ///
+    /// Same as `compile_program_with_main`, but the IR goes straight to the
+    /// writer (such as a `.ll` file) instead of being assembled into one
+    /// `String` first.
+    pub fn compile_program_to_writer(
+        &mut self,
+        program: &Program,
+        entry_word: Option<&str>,
+        writer: &mut dyn std::io::Write,
+    ) -> CodegenResult<()> {
         // Emit module header
         writeln!(&mut self.output, "; Cem Compiler - Generated LLVM IR")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         writeln!(&mut self.output).map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
-        // Note: We intentionally omit the target triple to let clang use its default.
-        // This avoids "overriding the module target triple" warnings that occur when
-        // the IR triple doesn't exactly match clang's compilation target.
+        // Note: Unless cross-compiling, we intentionally omit the target triple to let
+        // clang use its default. This avoids "overriding the module target triple"
+        // warnings that occur when the IR triple doesn't exactly match clang's
+        // compilation target. A requested triple is also passed to clang as -target.
+        if let Some(triple) = &self.target_triple {
+            writeln!(&mut self.output, "target triple = \"{}\"", triple)
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+            writeln!(&mut self.output).map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        }
 
         // Declare runtime functions
         self.emit_runtime_declarations()?;
@@ -190,9 +581,13 @@ impl CodeGen {
         // Each variant gets a u32 tag corresponding to its index in the type's variant list
         for typedef in &program.type_defs {
             for (idx, variant) in typedef.variants.iter().enumerate() {
-                self.variant_tags.insert(variant.name.clone(), idx as u32);
-                self.variant_field_counts
-                    .insert(variant.name.clone(), variant.fields.len());
+                let key = (typedef.name.clone(), variant.name.clone());
+                self.variant_tags.insert(key.clone(), idx as u32);
+                self.variant_field_counts.insert(key, variant.fields.len());
+                self.variant_types
+                    .entry(variant.name.clone())
+                    .or_default()
+                    .push(typedef.name.clone());
             }
         }
 
@@ -218,13 +613,21 @@ impl CodeGen {
         // Emit debug metadata footer (compile unit and module flags)
         self.emit_debug_info_footer()?;
 
-        // Assemble final output:
+        // Write the final output in order:
         // 1. String constants (global declarations)
         // 2. Quotation functions (must come before word definitions that use them)
         // 3. Word definitions and main function
-        let final_output = self.string_globals.clone() + &self.quotation_functions + &self.output;
+        for section in [
+            &self.string_globals,
+            &self.quotation_functions,
+            &self.output,
+        ] {
+            writer
+                .write_all(section.as_bytes())
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        }
 
-        Ok(final_output)
+        Ok(())
     }
 
     /// Get the target triple by querying clang
@@ -255,22 +658,44 @@ impl CodeGen {
         writeln!(&mut self.output, "; Runtime function declarations")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
+        // Debug info intrinsic for the stack pointer variable
+        writeln!(
+            &mut self.output,
+            "declare void @llvm.dbg.value(metadata, metadata, metadata)"
+        )
+        .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
         // Stack operations (ptr -> ptr)
         for func in &[
-            "dup", "drop", "swap", "over", "rot", "nip", "tuck", "pick", "dip",
+            "dup", "drop", "swap", "over", "rot", "unrot", "nip", "tuck", "two_dup", "two_drop",
+            "pick", "depth", "ndrop", "ndup", "dip",
         ] {
             writeln!(&mut self.output, "declare ptr @{}(ptr)", func)
                 .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         }
 
         // Arithmetic (ptr -> ptr)
-        for func in &["add", "subtract", "multiply", "divide"] {
+        for func in &[
+            "add", "subtract", "multiply", "divide", "modulo", "neg", "abs", "min", "max",
+        ] {
+            writeln!(&mut self.output, "declare ptr @{}(ptr)", func)
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        }
+
+        // Bitwise (ptr -> ptr)
+        for func in &["bitand", "bitor", "bitxor", "shl", "shr", "invert"] {
+            writeln!(&mut self.output, "declare ptr @{}(ptr)", func)
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        }
+
+        // Boolean (ptr -> ptr)
+        for func in &["not", "bool_and", "bool_or"] {
             writeln!(&mut self.output, "declare ptr @{}(ptr)", func)
                 .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         }
 
         // Comparisons (ptr -> ptr)
-        for func in &["lt", "gt", "le", "ge", "eq", "ne"] {
+        for func in &["lt", "gt", "le", "ge", "eq", "ne", "cell_equal"] {
             writeln!(&mut self.output, "declare ptr @{}(ptr)", func)
                 .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         }
@@ -278,6 +703,8 @@ impl CodeGen {
         // Push operations
         writeln!(&mut self.output, "declare ptr @push_int(ptr, i64)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @push_float(ptr, double)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         writeln!(&mut self.output, "declare ptr @push_bool(ptr, i1)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         writeln!(&mut self.output, "declare ptr @push_string(ptr, ptr)")
@@ -290,14 +717,58 @@ impl CodeGen {
         // Control flow operations
         writeln!(&mut self.output, "declare ptr @call_quotation(ptr)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @spawn_quotation(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @yield_strand(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @sleep_strand(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @spawn_with(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @spawn_result(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @join_strand(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+        // Channel operations
+        writeln!(&mut self.output, "declare ptr @channel_new(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @channel_send(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @channel_recv(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
         // String operations
         writeln!(&mut self.output, "declare ptr @string_length(ptr)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @string_char_length(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         writeln!(&mut self.output, "declare ptr @string_concat(ptr)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         writeln!(&mut self.output, "declare ptr @string_equal(ptr)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        for func in &[
+            "string_lt",
+            "string_gt",
+            "string_compare",
+            "substring",
+            "string_to_int",
+            "string_contains",
+            "string_starts_with",
+            "string_ends_with",
+            "string_split",
+            "string_char_at",
+            "string_repeat",
+            "string_reverse",
+            "string_trim",
+            "string_to_upper",
+            "string_to_lower",
+            "string_index_of",
+            "string_replace",
+        ] {
+            writeln!(&mut self.output, "declare ptr @{}(ptr)", func)
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        }
 
         // Type conversions
         writeln!(&mut self.output, "declare ptr @int_to_string(ptr)")
@@ -306,7 +777,8 @@ impl CodeGen {
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
         // Exit operation
-        writeln!(&mut self.output, "declare void @exit_op(ptr)")
+        // Called like any other word, so declared to return the (never reached) stack
+        writeln!(&mut self.output, "declare ptr @exit_op(ptr)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
         // Scheduler operations (testing)
@@ -316,8 +788,28 @@ impl CodeGen {
         // I/O operations (async)
         writeln!(&mut self.output, "declare ptr @write_line(ptr)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @write_op(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         writeln!(&mut self.output, "declare ptr @read_line(ptr)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @read_file(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @write_file(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+        // Process environment
+        writeln!(&mut self.output, "declare ptr @args_count(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @arg_at(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @getenv_op(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+        // Pseudo-random numbers
+        writeln!(&mut self.output, "declare ptr @random_op(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare ptr @random_seed(ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
         // Scheduler operations
         writeln!(&mut self.output, "declare void @scheduler_init()")
@@ -328,6 +820,8 @@ impl CodeGen {
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         writeln!(&mut self.output, "declare i64 @strand_spawn(ptr, ptr)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare i64 @strand_spawn_main(ptr, ptr)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
         // Utility functions
         writeln!(&mut self.output, "declare void @print_stack(ptr)")
@@ -342,6 +836,8 @@ impl CodeGen {
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         writeln!(&mut self.output, "declare ptr @skip_n(ptr, i64)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        writeln!(&mut self.output, "declare i32 @stack_top_int_or(ptr, i32)")
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
         // LLVM intrinsics
         writeln!(
@@ -360,18 +856,22 @@ impl CodeGen {
     /// ```llvm
     /// define i32 @main() {
     /// entry:
-    ///   %stack = call ptr @entry_word(ptr null)
-    ///   call void @print_stack(ptr %stack)
+    ///   call void @scheduler_init()
+    ///   call i64 @strand_spawn_main(ptr @entry_word, ptr null)
+    ///   %stack = call ptr @scheduler_run()
+    ///   call void @scheduler_shutdown()
+    ///   %exit_code = call i32 @stack_top_int_or(ptr %stack, i32 0)
     ///   call void @free_stack(ptr %stack)
-    ///   ret i32 0
+    ///   ret i32 %exit_code
     /// }
     /// ```
     fn emit_main_function(&mut self, entry_word: &str) -> CodegenResult<()> {
-        // Avoid name collision - if entry word is "main", it was renamed to "cem_main"
+        // Avoid name collision - if entry word is "main", it was renamed to "cem_main".
+        // Any other entry is named like every word, e.g. hyphens become underscores.
         let function_name = if entry_word == "main" {
-            "cem_main"
+            "cem_main".to_string()
         } else {
-            entry_word
+            Self::map_operator_to_function(entry_word)
         };
 
         writeln!(&mut self.output, "; Main function")
@@ -385,10 +885,10 @@ impl CodeGen {
         writeln!(&mut self.output, "  call void @scheduler_init()")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
-        // Spawn entry word as a strand
+        // Spawn entry word as the main strand, whose final stack is kept
         writeln!(
             &mut self.output,
-            "  call i64 @strand_spawn(ptr @{}, ptr null)",
+            "  call i64 @strand_spawn_main(ptr @{}, ptr null)",
             function_name
         )
         .map_err(|e| CodegenError::InternalError(e.to_string()))?;
@@ -401,11 +901,25 @@ impl CodeGen {
         writeln!(&mut self.output, "  call void @scheduler_shutdown()")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
+        if self.print_final_stack {
+            writeln!(&mut self.output, "  call void @print_stack(ptr %stack)")
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+            writeln!(&mut self.output, "  %exit_code = add i32 0, 0")
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        } else {
+            // An Int left on top of the stack is the exit code
+            writeln!(
+                &mut self.output,
+                "  %exit_code = call i32 @stack_top_int_or(ptr %stack, i32 0)"
+            )
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        }
+
         // Clean up
         writeln!(&mut self.output, "  call void @free_stack(ptr %stack)")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
-        writeln!(&mut self.output, "  ret i32 0")
+        writeln!(&mut self.output, "  ret i32 %exit_code")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
         writeln!(&mut self.output, "}}").map_err(|e| CodegenError::InternalError(e.to_string()))?;
         writeln!(&mut self.output).map_err(|e| CodegenError::InternalError(e.to_string()))?;
@@ -495,7 +1009,7 @@ impl CodeGen {
             .map(|_| self.fresh_metadata_id())
             .collect();
 
-        for (i, (word_name, file_id, line, subprogram_id)) in
+        for (i, (word_name, file_id, line, subprogram_id, _)) in
             self.word_subprograms.iter().enumerate()
         {
             let type_id = type_ids[i];
@@ -505,6 +1019,28 @@ impl CodeGen {
             ).map_err(|e| CodegenError::InternalError(e.to_string()))?;
         }
 
+        // Emit the incoming %stack argument of each word as a variable, so a
+        // debugger can show the stack pointer
+        if !self.word_subprograms.is_empty() {
+            writeln!(&mut self.output).map_err(|e| CodegenError::InternalError(e.to_string()))?;
+            writeln!(&mut self.output, "; Stack pointer variable for each word")
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+            let pointer_type_id = self.fresh_metadata_id();
+            writeln!(
+                &mut self.output,
+                "!{} = !DIDerivedType(tag: DW_TAG_pointer_type, baseType: null, size: 64)",
+                pointer_type_id
+            )
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+            for (_, file_id, line, subprogram_id, variable_id) in &self.word_subprograms {
+                writeln!(&mut self.output,
+                    "!{} = !DILocalVariable(name: \"stack\", arg: 1, scope: !{}, file: !{}, line: {}, type: !{})",
+                    variable_id, subprogram_id, file_id, line, pointer_type_id
+                ).map_err(|e| CodegenError::InternalError(e.to_string()))?;
+            }
+        }
+
         // Emit stub type metadata for each function type
         writeln!(&mut self.output).map_err(|e| CodegenError::InternalError(e.to_string()))?;
         writeln!(&mut self.output, "; Type metadata (stubs)")
@@ -598,10 +1134,11 @@ impl CodeGen {
     }
 
     /// Register a word for debug metadata emission
-    /// Allocates a subprogram ID and stores info for later emission
-    /// Returns the subprogram ID to attach to the function
-    fn register_word_subprogram(&mut self, word: &WordDef) -> CodegenResult<usize> {
+    /// Allocates subprogram and stack variable IDs and stores info for later emission
+    /// Returns (subprogram ID to attach to the function, stack variable ID)
+    fn register_word_subprogram(&mut self, word: &WordDef) -> CodegenResult<(usize, usize)> {
         let subprogram_id = self.fresh_metadata_id();
+        let stack_variable_id = self.fresh_metadata_id();
 
         // Get the file metadata ID for this word's source location
         let file_id = self
@@ -610,10 +1147,15 @@ impl CodeGen {
             .copied()
             .unwrap_or(0);
 
-        self.word_subprograms
-            .push((word.name.clone(), file_id, word.loc.line, subprogram_id));
+        self.word_subprograms.push((
+            word.name.clone(),
+            file_id,
+            word.loc.line,
+            subprogram_id,
+            stack_variable_id,
+        ));
 
-        Ok(subprogram_id)
+        Ok((subprogram_id, stack_variable_id))
     }
 
     /// Compile a word definition to LLVM function
@@ -622,7 +1164,7 @@ impl CodeGen {
         self.current_block = "entry".to_string(); // Reset to entry block
 
         // Register this word for debug metadata (allocates ID for later emission)
-        let subprogram_id = self.register_word_subprogram(word)?;
+        let (subprogram_id, stack_variable_id) = self.register_word_subprogram(word)?;
 
         // Set current subprogram for debug location generation
         self.current_subprogram_id = Some(subprogram_id);
@@ -635,6 +1177,15 @@ impl CodeGen {
             Self::map_operator_to_function(&word.name)
         };
 
+        // The word's signature as written, to find it in the IR by
+        writeln!(
+            &mut self.output,
+            "; {} {}",
+            word.name,
+            format_effect(&word.effect)
+        )
+        .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
         // Emit function definition with debug metadata attachment
         writeln!(
             &mut self.output,
@@ -645,6 +1196,18 @@ impl CodeGen {
         writeln!(&mut self.output, "entry:")
             .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
+        // Describe the incoming stack pointer to the debugger. It's an SSA
+        // argument with no memory behind it, so this is a dbg.value, not a declare
+        let dbg = self.dbg_annotation(&word.loc);
+        if !dbg.is_empty() {
+            writeln!(
+                &mut self.output,
+                "  call void @llvm.dbg.value(metadata ptr %stack, metadata !{}, metadata !DIExpression()){}",
+                stack_variable_id, dbg
+            )
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        }
+
         // Compile all expressions in the word body
         // Function bodies are always in tail position (can use tail-call optimization)
         let (final_stack, _ends_with_musttail) =
@@ -677,20 +1240,35 @@ impl CodeGen {
     /// Check if an expression will have all code paths return (needs caller to emit ret)
     /// Returns true if the expression needs the caller to emit ret (WordCall)
     /// or if all branches end with expressions that need ret (Match/If with all branches returning)
-    fn check_all_paths_returned(&self, expr: &Expr) -> bool {
+    ///
+    /// `in_tail_position` is the position `expr` was compiled in: outside it,
+    /// calls are never emitted as musttail, so nothing returns.
+    fn check_all_paths_returned(&self, expr: &Expr, in_tail_position: bool) -> bool {
+        self.check_all_paths_returned_in(expr, &[], in_tail_position)
+    }
+
+    /// check_all_paths_returned, where `bound` holds match bindings in scope at `expr`
+    /// that are no longer registered (e.g. when re-examining a match after compiling it)
+    fn check_all_paths_returned_in(
+        &self,
+        expr: &Expr,
+        bound: &[&str],
+        in_tail_position: bool,
+    ) -> bool {
         match expr {
             // A user-defined word call (non-variant, non-builtin) in tail position will be compiled as musttail
             // The parent context (match branch or word body) will emit the ret statement
-            // Runtime built-ins use normal calls, so they don't count as "returned"
+            // Runtime built-ins and match bindings use normal calls, so they don't count as "returned"
             Expr::WordCall(name, _) => {
-                !self.variant_tags.contains_key(name) && !Self::is_runtime_builtin(name)
+                in_tail_position && self.is_tail_callable(name) && !bound.contains(&name.as_str())
             }
 
             // Match emits ret for each branch if all branches end with musttail
             Expr::Match { branches, .. } => branches.iter().all(|b| {
+                let scope = Self::pattern_scope(bound, &b.pattern);
                 b.body
                     .last()
-                    .is_some_and(|e| self.check_all_paths_returned(e))
+                    .is_some_and(|e| self.check_all_paths_returned_in(e, &scope, in_tail_position))
             }),
 
             // If emits ret for both branches if both end with musttail
@@ -700,16 +1278,16 @@ impl CodeGen {
                 ..
             } => {
                 let then_musttail = if let Expr::Quotation(exprs, _) = &**then_branch {
-                    exprs
-                        .last()
-                        .is_some_and(|e| self.check_all_paths_returned(e))
+                    exprs.last().is_some_and(|e| {
+                        self.check_all_paths_returned_in(e, bound, in_tail_position)
+                    })
                 } else {
                     false
                 };
                 let else_musttail = if let Expr::Quotation(exprs, _) = &**else_branch {
-                    exprs
-                        .last()
-                        .is_some_and(|e| self.check_all_paths_returned(e))
+                    exprs.last().is_some_and(|e| {
+                        self.check_all_paths_returned_in(e, bound, in_tail_position)
+                    })
                 } else {
                     false
                 };
@@ -731,9 +1309,10 @@ impl CodeGen {
 
             // Match has all branches returned if all end with expressions that return
             Expr::Match { branches, .. } => branches.iter().all(|b| {
+                let scope = Self::pattern_scope(&[], &b.pattern);
                 b.body
                     .last()
-                    .is_some_and(|e| self.check_all_paths_returned(e))
+                    .is_some_and(|e| self.check_all_paths_returned_in(e, &scope, true))
             }),
 
             // If has all branches returned if both end with expressions that return
@@ -745,14 +1324,14 @@ impl CodeGen {
                 let then_returned = if let Expr::Quotation(exprs, _) = &**then_branch {
                     exprs
                         .last()
-                        .is_some_and(|e| self.check_all_paths_returned(e))
+                        .is_some_and(|e| self.check_all_paths_returned(e, true))
                 } else {
                     false
                 };
                 let else_returned = if let Expr::Quotation(exprs, _) = &**else_branch {
                     exprs
                         .last()
-                        .is_some_and(|e| self.check_all_paths_returned(e))
+                        .is_some_and(|e| self.check_all_paths_returned(e, true))
                 } else {
                     false
                 };
@@ -763,7 +1342,57 @@ impl CodeGen {
         }
     }
 
-    /// Compile a branch quotation (quotation inside then/else)
+    /// Take the Bool on top of `stack` apart for a branch
+    /// Returns (i1 condition, rest of stack)
+    fn pop_condition(&mut self, stack: &str) -> CodegenResult<(String, String)> {
+        // Extract boolean value from stack top
+        // StackCell C layout (from runtime/stack.h):
+        //   - tag: i32 at offset 0 (4 bytes)
+        //   - padding: 4 bytes (for union alignment)
+        //   - value union at offset 8 (16 bytes total - largest member is variant struct)
+        //   - next: ptr at offset 24 (8 bytes)
+        // LLVM struct: { i32, [4 x i8], [16 x i8], ptr } = 32 bytes
+
+        // Get bool value from union at offset 8 (field index 2)
+        // Bool is stored as i8 in the first byte of the 16-byte union
+        let bool_ptr = self.fresh_temp();
+        writeln!(&mut self.output, "  %{} = getelementptr inbounds {{ i32, [4 x i8], [16 x i8], ptr }}, ptr %{}, i32 0, i32 2, i32 0", bool_ptr, stack)
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        let bool_val = self.fresh_temp();
+        writeln!(
+            &mut self.output,
+            "  %{} = load i8, ptr %{}",
+            bool_val, bool_ptr
+        )
+        .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+        // Use fresh temp for cond to avoid collisions in nested ifs
+        let cond_var = self.fresh_temp();
+        writeln!(
+            &mut self.output,
+            "  %{} = trunc i8 %{} to i1",
+            cond_var, bool_val
+        )
+        .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+        // Get rest of stack (next pointer at field index 3)
+        let rest_ptr = self.fresh_temp();
+        writeln!(&mut self.output, "  %{} = getelementptr inbounds {{ i32, [4 x i8], [16 x i8], ptr }}, ptr %{}, i32 0, i32 3", rest_ptr, stack)
+            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+        // Use fresh temp for rest to avoid collisions in nested ifs
+        let rest_var = self.fresh_temp();
+        writeln!(
+            &mut self.output,
+            "  %{} = load ptr, ptr %{}",
+            rest_var, rest_ptr
+        )
+        .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+        Ok((cond_var, rest_var))
+    }
+
+    /// Compile a branch quotation (quotation inside then/else, or a while's cond/body)
     /// Returns (result_var, ends_with_musttail)
     ///
     /// ends_with_musttail is true if the last expression in the quotation
@@ -803,6 +1432,8 @@ impl CodeGen {
         initial_stack: &str,
         in_tail_position: bool,
     ) -> CodegenResult<(String, bool)> {
+        let folded = self.fold_constants(exprs);
+        let exprs = folded.as_slice();
         let mut stack_var = initial_stack.to_string();
         let len = exprs.len();
 
@@ -823,8 +1454,7 @@ impl CodeGen {
             // Only set ends_with_musttail for user-defined words (not runtime built-ins)
             if is_tail
                 && let Expr::WordCall(name, _) = expr
-                && !self.variant_tags.contains_key(name)
-                && !Self::is_runtime_builtin(name)
+                && self.is_tail_callable(name)
             {
                 ends_with_musttail = true;
             }
@@ -832,6 +1462,112 @@ impl CodeGen {
         Ok((stack_var, ends_with_musttail))
     }
 
+    /// Push a pointer to the quotation function `quot_name`
+    fn push_quotation(&mut self, stack: &str, quot_name: &str) -> CodegenResult<String> {
+        let result = self.fresh_temp();
+        writeln!(
+            &mut self.output,
+            "  %{} = call ptr @push_quotation(ptr %{}, ptr @{})",
+            result, stack, quot_name
+        )
+        .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+        Ok(result)
+    }
+
+    /// Key identifying a quotation by its code, so identical ones can share a function
+    ///
+    /// Source locations are left out: the same code written twice is the same quotation.
+    fn quotation_key(exprs: &[Expr]) -> String {
+        fn without_locs(exprs: &[Expr]) -> Vec<Expr> {
+            let loc = SourceLoc::unknown;
+            exprs
+                .iter()
+                .map(|expr| match expr {
+                    Expr::IntLit(n, _) => Expr::IntLit(*n, loc()),
+                    Expr::FloatLit(x, _) => Expr::FloatLit(*x, loc()),
+                    Expr::BoolLit(b, _) => Expr::BoolLit(*b, loc()),
+                    Expr::StringLit(s, _) => Expr::StringLit(s.clone(), loc()),
+                    Expr::WordCall(name, _) => Expr::WordCall(name.clone(), loc()),
+                    Expr::Quotation(body, _) => Expr::Quotation(without_locs(body), loc()),
+                    Expr::Match { branches, .. } => Expr::Match {
+                        branches: branches
+                            .iter()
+                            .map(|branch| crate::ast::MatchBranch {
+                                pattern: branch.pattern.clone(),
+                                body: without_locs(&branch.body),
+                                loc: loc(),
+                            })
+                            .collect(),
+                        loc: loc(),
+                    },
+                    Expr::If {
+                        then_branch,
+                        else_branch,
+                        ..
+                    } => Expr::If {
+                        then_branch: Box::new(
+                            without_locs(std::slice::from_ref(then_branch)).remove(0),
+                        ),
+                        else_branch: Box::new(
+                            without_locs(std::slice::from_ref(else_branch)).remove(0),
+                        ),
+                        loc: loc(),
+                    },
+                    Expr::While { cond, body, .. } => Expr::While {
+                        cond: Box::new(without_locs(std::slice::from_ref(cond)).remove(0)),
+                        body: Box::new(without_locs(std::slice::from_ref(body)).remove(0)),
+                        loc: loc(),
+                    },
+                })
+                .collect()
+        }
+
+        format!("{:?}", without_locs(exprs))
+    }
+
+    /// Fold literal arithmetic and comparisons into a single literal
+    ///
+    /// `IntLit IntLit op` becomes one `IntLit` (or `BoolLit` for comparisons),
+    /// repeatedly, so `1 2 + 3 *` compiles to a single `push_int`. Division or
+    /// modulo by zero and overflow are left unfolded for the runtime to report.
+    fn fold_constants(&self, exprs: &[Expr]) -> Vec<Expr> {
+        let mut folded: Vec<Expr> = Vec::with_capacity(exprs.len());
+        for expr in exprs {
+            if let Expr::WordCall(op, _) = expr
+                && self.match_binding(op).is_none()
+                && let [.., Expr::IntLit(a, loc), Expr::IntLit(b, _)] = folded.as_slice()
+                && let Some(literal) = Self::fold_int_op(op, *a, *b, loc)
+            {
+                folded.truncate(folded.len() - 2);
+                folded.push(literal);
+            } else {
+                folded.push(expr.clone());
+            }
+        }
+        folded
+    }
+
+    /// The literal `a b op` evaluates to, if it can be computed without error
+    fn fold_int_op(op: &str, a: i64, b: i64, loc: &SourceLoc) -> Option<Expr> {
+        let int = |n: Option<i64>| n.map(|n| Expr::IntLit(n, loc.clone()));
+        let bool = |b: bool| Some(Expr::BoolLit(b, loc.clone()));
+        match op {
+            "+" => int(a.checked_add(b)),
+            "-" => int(a.checked_sub(b)),
+            "*" => int(a.checked_mul(b)),
+            // None for zero and for i64::MIN / -1
+            "/" => int(a.checked_div(b)),
+            "%" => int(a.checked_rem(b)),
+            "=" => bool(a == b),
+            "!=" => bool(a != b),
+            "<" => bool(a < b),
+            ">" => bool(a > b),
+            "<=" => bool(a <= b),
+            ">=" => bool(a >= b),
+            _ => None,
+        }
+    }
+
     /// Compile a single expression with tail-call context
     fn compile_expr_with_context(
         &mut self,
@@ -843,11 +1579,8 @@ impl CodeGen {
             // Tail-call optimization: if in tail position and calling a user-defined word, use musttail
             // BUT: variant constructors are not actual functions, so they can't be tail-called
             // AND: runtime built-ins should use normal calls to avoid musttail issues in match branches
-            Expr::WordCall(name, loc)
-                if in_tail_position
-                    && !self.variant_tags.contains_key(name)
-                    && !Self::is_runtime_builtin(name) =>
-            {
+            // AND: match bindings push a copy of their field rather than calling anything
+            Expr::WordCall(name, loc) if in_tail_position && self.is_tail_callable(name) => {
                 let result = self.fresh_temp();
                 let dbg = self.dbg_annotation(loc);
                 let func_name = Self::map_operator_to_function(name);
@@ -884,6 +1617,23 @@ impl CodeGen {
                 Ok(result)
             }
 
+            Expr::FloatLit(x, loc) => {
+                let result = self.fresh_temp();
+                let dbg = self.dbg_annotation(loc);
+                // LLVM only accepts decimal double constants that are exactly
+                // representable, so emit the IEEE-754 bit pattern in hex instead
+                writeln!(
+                    &mut self.output,
+                    "  %{} = call ptr @push_float(ptr %{}, double 0x{:016X}){}",
+                    result,
+                    stack,
+                    x.to_bits(),
+                    dbg
+                )
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                Ok(result)
+            }
+
             Expr::BoolLit(b, loc) => {
                 let result = self.fresh_temp();
                 let value = if *b { 1 } else { 0 };
@@ -898,6 +1648,15 @@ impl CodeGen {
             }
 
             Expr::StringLit(s, loc) => {
+                // The runtime gets a C string, which would end at the NUL. The
+                // lexer rejects such literals; this catches ASTs built directly.
+                if s.contains('\0') {
+                    return Err(CodegenError::InternalError(format!(
+                        "String literal {:?} contains NUL",
+                        s
+                    )));
+                }
+
                 // String deduplication: Check if we've already emitted this exact string content.
                 // Without this, identical strings like "hello" appearing multiple times in the
                 // source would create separate @.str.N globals for each occurrence, bloating
@@ -951,16 +1710,56 @@ impl CodeGen {
             }
 
             Expr::WordCall(name, loc) => {
-                // Check if this is a variant constructor
-                if let Some(&tag) = self.variant_tags.get(name) {
-                    // This is a variant constructor - emit push_variant call
-                    let field_count = self.variant_field_counts.get(name).copied().unwrap_or(0);
+                // Check if this is a binding introduced by an enclosing match pattern
+                if let Some(field_cell) = self.match_binding(name) {
+                    // Push a copy of the field; the variant keeps owning the original
+                    let field_cell = field_cell.to_string();
+                    let result = self.fresh_temp();
                     let dbg = self.dbg_annotation(loc);
+                    writeln!(
+                        &mut self.output,
+                        "  %{} = call ptr @copy_cell(ptr %{}){}",
+                        result, field_cell, dbg
+                    )
+                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
-                    match field_count {
-                        0 => {
-                            // Unit variant (no fields) - pass NULL as data
-                            let result = self.fresh_temp();
+                    let next_ptr = self.fresh_temp();
+                    writeln!(
+                        &mut self.output,
+                        "  %{} = getelementptr inbounds {{ i32, [4 x i8], [16 x i8], ptr }}, ptr %{}, i32 0, i32 3",
+                        next_ptr, result
+                    )
+                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                    writeln!(
+                        &mut self.output,
+                        "  store ptr %{}, ptr %{}",
+                        stack, next_ptr
+                    )
+                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                    return Ok(result);
+                }
+                if self.match_bindings[..self.binding_barrier]
+                    .iter()
+                    .any(|(binding, _)| binding == name)
+                {
+                    // Quotations compile to separate functions, out of reach of the field temps
+                    return Err(CodegenError::InternalError(format!(
+                        "Match binding '{}' cannot be used inside a quotation",
+                        name
+                    )));
+                }
+
+                // Check if this is a variant constructor
+                if let Some(key) = self.constructor_key(name)? {
+                    // This is a variant constructor - emit push_variant call
+                    let tag = self.variant_tags[&key];
+                    let field_count = self.variant_field_counts[&key];
+                    let dbg = self.dbg_annotation(loc);
+
+                    match field_count {
+                        0 => {
+                            // Unit variant (no fields) - pass NULL as data
+                            let result = self.fresh_temp();
                             writeln!(
                                 &mut self.output,
                                 "  %{} = call ptr @push_variant(ptr %{}, i32 {}, ptr null){}",
@@ -1151,15 +1950,26 @@ impl CodeGen {
             }
 
             Expr::Quotation(exprs, _loc) => {
+                // Identical quotations share one function (the first one's debug locations)
+                let key = Self::quotation_key(exprs);
+                if let Some(quot_name) = self.quotation_names.get(&key).cloned() {
+                    return self.push_quotation(stack, &quot_name);
+                }
+
                 // Generate an anonymous function for the quotation
-                let quot_name = format!("quot_{}", self.temp_counter);
+                let quot_name = format!("quot_{}", self.quotation_counter);
+                self.quotation_counter += 1;
                 let saved_counter = self.temp_counter;
-                self.temp_counter += 1;
+                self.temp_counter = 0;
 
                 // Save current output and generate quotation function separately
                 let saved_output = self.output.clone();
                 self.output.clear();
 
+                // Match bindings of the enclosing function are not visible in here
+                let saved_barrier = self.binding_barrier;
+                self.binding_barrier = self.match_bindings.len();
+
                 // Generate the quotation function
                 writeln!(&mut self.output, "define ptr @{}(ptr %stack) {{", quot_name)
                     .map_err(|e| CodegenError::InternalError(e.to_string()))?;
@@ -1199,17 +2009,11 @@ impl CodeGen {
                 self.output = saved_output;
 
                 // Restore temp counter for current function
-                self.temp_counter = saved_counter + 1;
+                self.temp_counter = saved_counter;
+                self.binding_barrier = saved_barrier;
 
-                // Now push the function pointer onto the stack
-                let result = self.fresh_temp();
-                writeln!(
-                    &mut self.output,
-                    "  %{} = call ptr @push_quotation(ptr %{}, ptr @{})",
-                    result, stack, quot_name
-                )
-                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
-                Ok(result)
+                self.quotation_names.insert(key, quot_name.clone());
+                self.push_quotation(stack, &quot_name)
             }
 
             Expr::Match { branches, loc: _ } => {
@@ -1232,12 +2036,86 @@ impl CodeGen {
                 // Generate labels for each branch and merge point
                 let match_id = self.temp_counter;
                 let merge_label = format!("match_merge_{}", match_id);
-                let default_label = format!("match_default_{}", match_id);
+
+                // Integer patterns switch on the Int value itself rather than a variant tag
+                let int_match = branches
+                    .iter()
+                    .any(|b| matches!(b.pattern, Pattern::IntLit(_)));
+                // Boolean patterns switch on the Bool's byte, as `if` reads it
+                let bool_match = branches
+                    .iter()
+                    .any(|b| matches!(b.pattern, Pattern::BoolLit(_)));
+                let variant_match = branches
+                    .iter()
+                    .any(|b| matches!(b.pattern, Pattern::Variant { .. }));
+                if [int_match, bool_match, variant_match]
+                    .iter()
+                    .filter(|kind| **kind)
+                    .count()
+                    > 1
+                {
+                    return Err(CodegenError::InternalError(
+                        "Cannot mix integer, boolean and variant patterns in one match".to_string(),
+                    ));
+                }
+                let switch_type = if int_match {
+                    "i64"
+                } else if bool_match {
+                    "i8"
+                } else {
+                    "i32"
+                };
+
+                // Tags are numbered per type, so the patterns pick which type's tags to use
+                let matched_type = self.matched_type(branches)?;
+                let variant_key =
+                    |name: &str| (matched_type.clone().unwrap_or_default(), name.to_string());
+
+                // A wildcard branch becomes the switch's default target. So does the
+                // last branch of a match that covers every value, in place of its
+                // case. Otherwise the default block reports a non-exhaustive match
+                // at runtime.
+                let default_idx = branches
+                    .iter()
+                    .position(|b| matches!(b.pattern, Pattern::Wildcard))
+                    .or_else(|| {
+                        self.covers_every_value(branches, matched_type.as_deref())
+                            .then(|| branches.len() - 1)
+                    });
+                // A branch whose pattern tests fields starts with those tests, and
+                // when they fail, moves on to the next branch for the same variant,
+                // then to the wildcard, then to the runtime error
+                let entry_label = |idx: usize| {
+                    let kind = if branches[idx].pattern.tests_fields() {
+                        "test"
+                    } else {
+                        "case"
+                    };
+                    format!("match_{}_{}_{}", kind, match_id, idx)
+                };
+                let variant_name = |idx: usize| match &branches[idx].pattern {
+                    Pattern::Variant { name, .. } => Some(name),
+                    _ => None,
+                };
+                let first_for_variant = |idx: usize| {
+                    (0..idx)
+                        .find(|&i| {
+                            variant_name(i).is_some() && variant_name(i) == variant_name(idx)
+                        })
+                        .unwrap_or(idx)
+                };
+                let error_label = format!("match_default_{}", match_id);
+                let default_label = match default_idx {
+                    Some(idx) => entry_label(first_for_variant(idx)),
+                    None => error_label.clone(),
+                };
+                let mut reaches_error = default_idx.is_none();
 
                 // Extract variant tag from stack top
                 // StackCell layout: { i32 tag, [4 x i8] padding, [16 x i8] union, ptr next }
                 // Variant is stored in union as: { i32 variant_tag, ptr variant_data }
                 // So variant_tag is at union offset 0 (field 2, index 0-3)
+                // An Int's i64 value also lives at union offset 0
 
                 // Get pointer to variant tag within the union
                 let variant_tag_ptr = self.fresh_temp();
@@ -1248,12 +2126,13 @@ impl CodeGen {
                 )
                 .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
-                // Load variant tag as i32 (first 4 bytes of union)
+                // Load variant tag as i32 (first 4 bytes of union), the Int value as i64, or
+                // the Bool as i8
                 let variant_tag = self.fresh_temp();
                 writeln!(
                     &mut self.output,
-                    "  %{} = load i32, ptr %{}",
-                    variant_tag, variant_tag_ptr
+                    "  %{} = load {}, ptr %{}",
+                    variant_tag, switch_type, variant_tag_ptr
                 )
                 .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
@@ -1277,42 +2156,59 @@ impl CodeGen {
                 // Extract variant data pointer (for single-field variants)
                 // Variant data is at union offset 8 (after the 4-byte tag + 4-byte padding)
                 // We need this to unwrap the variant in branches
-                let variant_data_ptr = self.fresh_temp();
-                writeln!(
-                    &mut self.output,
-                    "  %{} = getelementptr inbounds {{ i32, [4 x i8], [16 x i8], ptr }}, ptr %{}, i32 0, i32 2, i32 8",
-                    variant_data_ptr, stack
-                )
-                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                let variant_data = if int_match || bool_match {
+                    String::new() // Ints and Bools have no fields to unwrap
+                } else {
+                    let variant_data_ptr = self.fresh_temp();
+                    writeln!(
+                        &mut self.output,
+                        "  %{} = getelementptr inbounds {{ i32, [4 x i8], [16 x i8], ptr }}, ptr %{}, i32 0, i32 2, i32 8",
+                        variant_data_ptr, stack
+                    )
+                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
-                let variant_data = self.fresh_temp();
-                writeln!(
-                    &mut self.output,
-                    "  %{} = load ptr, ptr %{}",
-                    variant_data, variant_data_ptr
-                )
-                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                    let variant_data = self.fresh_temp();
+                    writeln!(
+                        &mut self.output,
+                        "  %{} = load ptr, ptr %{}",
+                        variant_data, variant_data_ptr
+                    )
+                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                    variant_data
+                };
 
                 // Generate switch statement
                 write!(
                     &mut self.output,
-                    "  switch i32 %{}, label %{} [",
-                    variant_tag, default_label
+                    "  switch {} %{}, label %{} [",
+                    switch_type, variant_tag, default_label
                 )
                 .map_err(|e| CodegenError::InternalError(e.to_string()))?;
 
                 // Add switch cases for each branch
                 for (idx, branch) in branches.iter().enumerate() {
-                    let Pattern::Variant { name } = &branch.pattern;
-                    // Look up variant tag from type environment
-                    let tag_value = self.variant_tags.get(name).copied().ok_or_else(|| {
-                        CodegenError::InternalError(format!("Unknown variant: {}", name))
-                    })?;
-                    let case_label = format!("match_case_{}_{}", match_id, idx);
+                    // Later branches for the same variant are reached from the first
+                    if Some(first_for_variant(idx)) == default_idx.map(first_for_variant)
+                        || first_for_variant(idx) != idx
+                    {
+                        continue;
+                    }
+                    let case_value = match &branch.pattern {
+                        // Look up variant tag from type environment
+                        Pattern::Variant { name, .. } => {
+                            i64::from(self.variant_tags[&variant_key(name)])
+                        }
+                        Pattern::IntLit(n) => *n,
+                        Pattern::BoolLit(b) => i64::from(*b),
+                        // Always the default target; bindings only appear in fields
+                        Pattern::Binding(_) | Pattern::Wildcard => continue,
+                    };
                     writeln!(
                         &mut self.output,
-                        "\n    i32 {}, label %{}",
-                        tag_value, case_label
+                        "\n    {} {}, label %{}",
+                        switch_type,
+                        case_value,
+                        entry_label(idx)
                     )
                     .map_err(|e| CodegenError::InternalError(e.to_string()))?;
                 }
@@ -1327,16 +2223,54 @@ impl CodeGen {
                 for (idx, branch) in branches.iter().enumerate() {
                     let case_label = format!("match_case_{}_{}", match_id, idx);
 
+                    if let Pattern::Variant { fields, .. } = &branch.pattern
+                        && branch.pattern.tests_fields()
+                    {
+                        let fail_label = match (idx + 1..branches.len())
+                            .find(|&i| variant_name(i) == variant_name(idx))
+                            .or_else(|| {
+                                branches
+                                    .iter()
+                                    .position(|b| matches!(b.pattern, Pattern::Wildcard))
+                            }) {
+                            Some(next) => entry_label(next),
+                            None => {
+                                reaches_error = true;
+                                error_label.clone()
+                            }
+                        };
+                        writeln!(&mut self.output, "{}:", entry_label(idx))
+                            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                        self.emit_field_tests(fields, &variant_data, &fail_label)?;
+                        writeln!(&mut self.output, "  br label %{}", case_label)
+                            .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                    }
+
                     writeln!(&mut self.output, "{}:", case_label)
                         .map_err(|e| CodegenError::InternalError(e.to_string()))?;
                     self.current_block = case_label.clone();
 
                     // Determine the initial stack for this branch
                     // For variants with data, we need to "unwrap" by linking data cell to rest
-                    let Pattern::Variant { name } = &branch.pattern;
-                    let field_count = self.variant_field_counts.get(name).copied().unwrap_or(0);
+                    let (field_count, fields) = match &branch.pattern {
+                        Pattern::Variant { name, fields } => (
+                            self.variant_field_counts[&variant_key(name)],
+                            fields.as_slice(),
+                        ),
+                        // Wildcard and literal patterns consume the scrutinee without unwrapping it
+                        Pattern::IntLit(_)
+                        | Pattern::BoolLit(_)
+                        | Pattern::Binding(_)
+                        | Pattern::Wildcard => (0, [].as_slice()),
+                    };
+                    let scope_len = self.match_bindings.len();
 
-                    let initial_stack = if field_count == 0 {
+                    let initial_stack = if !fields.is_empty() {
+                        // Named fields stay in the variant's data chain; each use of a
+                        // binding pushes a copy of its field cell
+                        self.bind_fields(fields, &variant_data)?;
+                        rest_var.clone()
+                    } else if field_count == 0 {
                         // Unit variant (e.g., None) - no data, just use rest
                         rest_var.clone()
                     } else if field_count == 1 {
@@ -1452,10 +2386,13 @@ impl CodeGen {
                     let predecessor = self.current_block.clone();
 
                     // Check if this branch terminates (either via musttail or nested match/if)
-                    let Pattern::Variant { name: _ } = &branch.pattern;
                     let branch_last_expr = branch.body.last();
                     let branch_terminates = ends_with_musttail
-                        || branch_last_expr.is_some_and(|e| self.check_all_paths_returned(e));
+                        || branch_last_expr
+                            .is_some_and(|e| self.check_all_paths_returned(e, in_tail_position));
+
+                    // The branch's bindings go out of scope
+                    self.match_bindings.truncate(scope_len);
 
                     if branch_terminates {
                         // Branch terminates - emit ret if needed
@@ -1475,18 +2412,20 @@ impl CodeGen {
                 }
 
                 // Default case (should never be reached if match is exhaustive)
-                writeln!(&mut self.output, "{}:", default_label)
-                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
-                writeln!(
-                    &mut self.output,
-                    "  call void @runtime_error(ptr @.str.match_error)"
-                )
-                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
-                writeln!(&mut self.output, "  unreachable")
+                if reaches_error {
+                    writeln!(&mut self.output, "{}:", default_label)
+                        .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                    writeln!(
+                        &mut self.output,
+                        "  call void @runtime_error(ptr @.str.match_error)"
+                    )
                     .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                    writeln!(&mut self.output, "  unreachable")
+                        .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                }
 
                 // Add error string to string globals if not already present
-                if !self.string_constants.contains_key("match_error") {
+                if reaches_error && !self.string_constants.contains_key("match_error") {
                     let error_msg = "match: non-exhaustive pattern (internal error)";
                     let escaped = Self::escape_llvm_string(error_msg);
                     let str_len = error_msg.len() + 1;
@@ -1552,49 +2491,7 @@ impl CodeGen {
                 let merge_label = format!("merge_{}", self.temp_counter);
                 self.temp_counter += 1;
 
-                // Extract boolean value from stack top
-                // StackCell C layout (from runtime/stack.h):
-                //   - tag: i32 at offset 0 (4 bytes)
-                //   - padding: 4 bytes (for union alignment)
-                //   - value union at offset 8 (16 bytes total - largest member is variant struct)
-                //   - next: ptr at offset 24 (8 bytes)
-                // LLVM struct: { i32, [4 x i8], [16 x i8], ptr } = 32 bytes
-
-                // Get bool value from union at offset 8 (field index 2)
-                // Bool is stored as i8 in the first byte of the 16-byte union
-                let bool_ptr = self.fresh_temp();
-                writeln!(&mut self.output, "  %{} = getelementptr inbounds {{ i32, [4 x i8], [16 x i8], ptr }}, ptr %{}, i32 0, i32 2, i32 0", bool_ptr, stack)
-                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
-                let bool_val = self.fresh_temp();
-                writeln!(
-                    &mut self.output,
-                    "  %{} = load i8, ptr %{}",
-                    bool_val, bool_ptr
-                )
-                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
-
-                // Use fresh temp for cond to avoid collisions in nested ifs
-                let cond_var = self.fresh_temp();
-                writeln!(
-                    &mut self.output,
-                    "  %{} = trunc i8 %{} to i1",
-                    cond_var, bool_val
-                )
-                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
-
-                // Get rest of stack (next pointer at field index 3)
-                let rest_ptr = self.fresh_temp();
-                writeln!(&mut self.output, "  %{} = getelementptr inbounds {{ i32, [4 x i8], [16 x i8], ptr }}, ptr %{}, i32 0, i32 3", rest_ptr, stack)
-                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
-
-                // Use fresh temp for rest to avoid collisions in nested ifs
-                let rest_var = self.fresh_temp();
-                writeln!(
-                    &mut self.output,
-                    "  %{} = load ptr, ptr %{}",
-                    rest_var, rest_ptr
-                )
-                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                let (cond_var, rest_var) = self.pop_condition(stack)?;
 
                 // Branch using the condition variable
                 writeln!(
@@ -1692,6 +2589,60 @@ impl CodeGen {
                     Ok(rest_var)
                 }
             }
+
+            Expr::While { cond, body, loc: _ } => {
+                // Loop: the header evaluates the condition, the body branches
+                // back to the header, and a false condition leaves the loop.
+                // Neither is in tail position, since the loop continues after them.
+                let cond_label = format!("while_cond_{}", self.temp_counter);
+                let body_label = format!("while_body_{}", self.temp_counter);
+                let end_label = format!("while_end_{}", self.temp_counter);
+                self.temp_counter += 1;
+
+                let entry_predecessor = self.current_block.clone();
+                writeln!(&mut self.output, "  br label %{}", cond_label)
+                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+                // Header: the stack arrives from before the loop or from the
+                // end of the body. The body isn't compiled yet, so the phi is
+                // inserted here once it is.
+                writeln!(&mut self.output, "{}:", cond_label)
+                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                self.current_block = cond_label.clone();
+                let phi_pos = self.output.len();
+                let loop_stack = self.fresh_temp();
+                let (cond_stack, _) = self.compile_branch_quotation(cond, &loop_stack, false)?;
+                let (cond_var, rest_var) = self.pop_condition(&cond_stack)?;
+                writeln!(
+                    &mut self.output,
+                    "  br i1 %{}, label %{}, label %{}",
+                    cond_var, body_label, end_label
+                )
+                .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+                // Body
+                writeln!(&mut self.output, "{}:", body_label)
+                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                self.current_block = body_label.clone();
+                let (body_stack, _) = self.compile_branch_quotation(body, &rest_var, false)?;
+                let body_predecessor = self.current_block.clone();
+                writeln!(&mut self.output, "  br label %{}", cond_label)
+                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+
+                self.output.insert_str(
+                    phi_pos,
+                    &format!(
+                        "  %{} = phi ptr [ %{}, %{} ], [ %{}, %{} ]\n",
+                        loop_stack, stack, entry_predecessor, body_stack, body_predecessor
+                    ),
+                );
+
+                // Exit
+                writeln!(&mut self.output, "{}:", end_label)
+                    .map_err(|e| CodegenError::InternalError(e.to_string()))?;
+                self.current_block = end_label;
+                Ok(rest_var)
+            }
         }
     }
 
@@ -1729,8 +2680,10 @@ mod tests {
         };
 
         let program = Program {
+            imports: Vec::new(),
             type_defs: vec![],
             word_defs: vec![word],
+            ..Program::default()
         };
 
         let ir = codegen.compile_program(&program).unwrap();
@@ -1741,6 +2694,54 @@ mod tests {
         assert!(ir.contains("ret ptr"));
     }
 
+    #[test]
+    fn test_string_literal_with_nul_is_rejected() {
+        // The lexer never produces one, but an AST built by hand can
+        let word = WordDef {
+            name: "nul".to_string(),
+            effect: Effect::from_vecs(vec![], vec![Type::String]),
+            body: vec![Expr::StringLit("a\0b".to_string(), SourceLoc::unknown())],
+            loc: SourceLoc::unknown(),
+        };
+        let program = Program {
+            imports: Vec::new(),
+            type_defs: vec![],
+            word_defs: vec![word],
+            ..Program::default()
+        };
+
+        let err = CodeGen::new().compile_program(&program).unwrap_err();
+        assert!(err.to_string().contains("contains NUL"), "{}", err);
+    }
+
+    #[test]
+    fn test_codegen_float_literal() {
+        let mut codegen = CodeGen::new();
+
+        // : half ( -- Float ) 0.5 ;
+        let word = WordDef {
+            name: "half".to_string(),
+            effect: Effect {
+                inputs: StackType::Empty,
+                outputs: StackType::Empty.push(Type::Float),
+            },
+            body: vec![Expr::FloatLit(0.5, SourceLoc::unknown())],
+            loc: SourceLoc::unknown(),
+        };
+
+        let program = Program {
+            imports: Vec::new(),
+            type_defs: vec![],
+            word_defs: vec![word],
+            ..Program::default()
+        };
+
+        let ir = codegen.compile_program(&program).unwrap();
+
+        assert!(ir.contains("declare ptr @push_float(ptr, double)"));
+        assert!(ir.contains("call ptr @push_float(ptr %stack, double 0x3FE0000000000000)"));
+    }
+
     #[test]
     fn test_codegen_word_call() {
         let mut codegen = CodeGen::new();
@@ -1760,8 +2761,10 @@ mod tests {
         };
 
         let program = Program {
+            imports: Vec::new(),
             type_defs: vec![],
             word_defs: vec![word],
+            ..Program::default()
         };
 
         let ir = codegen.compile_program(&program).unwrap();
@@ -1786,8 +2789,10 @@ mod tests {
         };
 
         let program = Program {
+            imports: Vec::new(),
             type_defs: vec![],
             word_defs: vec![word],
+            ..Program::default()
         };
 
         let ir = codegen.compile_program(&program).unwrap();
@@ -1826,8 +2831,10 @@ mod tests {
         };
 
         let program = Program {
+            imports: Vec::new(),
             type_defs: vec![],
             word_defs: vec![word],
+            ..Program::default()
         };
 
         let ir = codegen.compile_program(&program).unwrap();
@@ -1855,6 +2862,657 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_codegen_call_word() {
+        let program = crate::parser::Parser::new(": three ( -- Int ) [ 1 2 + ] call ;")
+            .parse()
+            .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // `call` is the runtime's call_quotation, not a word of its own
+        assert!(ir.contains("@call_quotation(ptr %"));
+        assert!(!ir.contains("@call("));
+    }
+
+    #[test]
+    fn test_codegen_write_word() {
+        let program = crate::parser::Parser::new(": hi ( -- ) \"hi\" write ;")
+            .parse()
+            .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // libc already has a `write`
+        assert!(ir.contains("@write_op(ptr %"));
+        assert!(!ir.contains("@write(ptr"));
+    }
+
+    #[test]
+    fn test_codegen_boolean_words() {
+        let program = crate::parser::Parser::new(": both ( Bool Bool -- Bool ) and not ;")
+            .parse()
+            .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        assert!(ir.contains("call ptr @bool_and(ptr %"));
+        assert!(ir.contains("call ptr @not(ptr %"));
+        assert!(ir.contains("declare ptr @bool_or(ptr)"));
+    }
+
+    #[test]
+    fn test_codegen_while_loop() {
+        let program =
+            crate::parser::Parser::new(": countdown ( Int -- Int ) while [ dup 0 > ] [ 1 - ] ;")
+                .parse()
+                .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // The header joins the stack from before the loop with the body's
+        assert!(ir.contains("br label %while_cond_"));
+        assert!(ir.contains("%entry ], [ %"), "{}", ir);
+        assert!(ir.contains(", %while_body_"), "{}", ir);
+        assert!(ir.contains(", label %while_body_"));
+        assert!(ir.contains("while_end_"));
+    }
+
+    #[test]
+    fn test_codegen_while_body_ending_in_match() {
+        let program = crate::parser::Parser::new(
+            ": dec ( Int -- Int ) 1 - ;
+             : countdown ( Int -- Int )
+               while [ dup 0 > ] [ dup 2 > match true => [ dec ] false => [ dec ] end ] ;",
+        )
+        .parse()
+        .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // The loop continues after the body, so its calls aren't tail calls and
+        // both branches rejoin before the back-edge
+        assert!(!ir.contains("musttail call ptr @dec"), "{}", ir);
+        assert_eq!(ir.matches("br label %match_merge_").count(), 2, "{}", ir);
+        assert!(ir.contains("], [ %"), "{}", ir);
+        let merge = ir.split("match_merge_").last().unwrap();
+        assert!(merge.contains("br label %while_cond_"), "{}", ir);
+    }
+
+    #[test]
+    fn test_main_can_print_the_final_stack() {
+        let program = crate::parser::Parser::new(": repl-line ( -- Int ) 42 ;")
+            .parse()
+            .unwrap();
+
+        let mut codegen = CodeGen::new();
+        codegen.set_print_final_stack(true);
+        let ir = codegen
+            .compile_program_with_main(&program, Some("repl-line"))
+            .unwrap();
+
+        // Hyphens in the entry's name are mapped as in its definition
+        assert!(
+            ir.contains("@strand_spawn_main(ptr @repl_line, ptr null)"),
+            "{}",
+            ir
+        );
+        // The 42 is printed rather than being the exit code
+        assert!(ir.contains("call void @print_stack(ptr %stack)"), "{}", ir);
+        assert!(!ir.contains("@stack_top_int_or(ptr %stack"), "{}", ir);
+    }
+
+    #[test]
+    fn test_codegen_getenv_word() {
+        let program = crate::parser::Parser::new(": home ( -- ) \"HOME\" getenv drop ;")
+            .parse()
+            .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // libc already has a `getenv`
+        assert!(ir.contains("declare ptr @getenv_op(ptr)"));
+        assert!(ir.contains("@getenv_op(ptr %"));
+        assert!(!ir.contains("@getenv(ptr"));
+    }
+
+    #[test]
+    fn test_codegen_random_words() {
+        let program = crate::parser::Parser::new(": roll ( -- Int ) 7 random-seed 6 random ;")
+            .parse()
+            .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // libc already has a `random`
+        assert!(ir.contains("@random_seed(ptr %"));
+        assert!(ir.contains("@random_op(ptr %"));
+        assert!(!ir.contains("@random(ptr"));
+    }
+
+    #[test]
+    fn test_compile_program_to_writer_matches_string_output() {
+        let source = ": greet ( -- ) \"hi\" write_line ;\n: main ( -- ) [ greet ] call ;";
+        let program = crate::parser::Parser::new(source).parse().unwrap();
+
+        let ir = CodeGen::new()
+            .compile_program_with_main(&program, Some("main"))
+            .unwrap();
+        let mut written = Vec::new();
+        CodeGen::new()
+            .compile_program_to_writer(&program, Some("main"), &mut written)
+            .unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), ir);
+
+        // String globals, then quotation functions, then definitions
+        let string_global = ir.find("@.str.").unwrap();
+        let quotation = ir.find("define ptr @quot_").unwrap();
+        let definition = ir.find("define ptr @greet(").unwrap();
+        assert!(string_global < quotation && quotation < definition);
+    }
+
+    #[test]
+    fn test_target_triple_only_when_requested() {
+        let program = crate::parser::Parser::new(": main ( -- ) ;")
+            .parse()
+            .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+        assert!(!ir.contains("target triple"));
+
+        let mut codegen = CodeGen::new();
+        codegen.set_target_triple("aarch64-apple-darwin");
+        let ir = codegen.compile_program(&program).unwrap();
+        assert!(ir.contains("target triple = \"aarch64-apple-darwin\"\n"));
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let compile = |body: &str| {
+            let source = format!(": f ( -- Int ) {} ;", body);
+            let program = crate::parser::Parser::new(&source).parse().unwrap();
+            CodeGen::new().compile_program(&program).unwrap()
+        };
+
+        let ir = compile("2 3 +");
+        assert_eq!(ir.matches("call ptr @push_int(").count(), 1);
+        assert!(ir.contains("@push_int(ptr %stack, i64 5)"));
+        assert!(!ir.contains("call ptr @add("));
+
+        // Chains fold all the way down
+        let ir = compile("1 2 + 3 * 10 -");
+        assert_eq!(ir.matches("call ptr @push_int(").count(), 1);
+        assert!(ir.contains("i64 -1)"));
+
+        // Comparisons fold to a Bool
+        let ir = compile("2 3 < drop 0");
+        assert!(ir.contains("@push_bool(ptr %stack, i1 1)"));
+        assert!(!ir.contains("call ptr @lt("));
+
+        // Errors are left for the runtime to report
+        for (body, op) in [
+            ("1 0 /", "@divide("),
+            ("1 0 %", "@modulo("),
+            ("9223372036854775807 1 +", "@add("),
+        ] {
+            let ir = compile(body);
+            assert!(ir.contains(&format!("call ptr {}", op)), "{}", body);
+        }
+
+        // Only literals next to each other fold
+        let ir = compile("2 dup +");
+        assert!(ir.contains("call ptr @add("));
+    }
+
+    #[test]
+    fn test_stack_pointer_debug_variable() {
+        let source = ": first ( -- ) ;\n: second ( -- ) first ;";
+        let program = crate::parser::Parser::new_with_filename(source, "vars.cem")
+            .parse()
+            .unwrap();
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // Each word gets its own variable, scoped to its own subprogram
+        for word in ["first", "second"] {
+            let subprogram = ir
+                .lines()
+                .find(|l| l.contains(&format!("DISubprogram(name: \"{}\"", word)))
+                .unwrap();
+            let subprogram_id = subprogram.split(' ').next().unwrap();
+            let variable = ir
+                .lines()
+                .find(|l| {
+                    l.contains("DILocalVariable(name: \"stack\", arg: 1")
+                        && l.contains(&format!("scope: {},", subprogram_id))
+                })
+                .unwrap_or_else(|| panic!("no stack variable for {}", word));
+            let variable_id = variable.split(' ').next().unwrap();
+
+            let define = format!("!dbg {} {{", subprogram_id);
+            let function = ir
+                .split("define ptr @")
+                .find(|f| f.contains(&define))
+                .unwrap();
+            assert!(
+                function.contains(&format!(
+                    "call void @llvm.dbg.value(metadata ptr %stack, metadata {}, metadata !DIExpression()), !dbg !",
+                    variable_id
+                )),
+                "{}",
+                function
+            );
+        }
+    }
+
+    #[test]
+    fn test_identical_quotations_share_a_function() {
+        let source = ": inc ( Int -- Int ) [ 1 + ] call ;
+: inc2 ( Int -- Int ) [ 1 + ] call
+  [ 1 + ] call ;
+: dec ( Int -- Int ) [ 1 - ] call ;";
+        let program = crate::parser::Parser::new(source).parse().unwrap();
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // One function for the three `[ 1 + ]`, one for `[ 1 - ]`, with distinct names
+        assert_eq!(ir.matches("define ptr @quot_").count(), 2);
+        assert!(ir.contains("define ptr @quot_0("));
+        assert!(ir.contains("define ptr @quot_1("));
+        assert_eq!(ir.matches("ptr @quot_0)").count(), 3);
+        assert_eq!(ir.matches("ptr @quot_1)").count(), 1);
+    }
+
+    #[test]
+    fn test_codegen_equal_word() {
+        let source = ": same ( String String -- Bool ) equal ;";
+        let program = crate::parser::Parser::new(source).parse().unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        assert!(ir.contains("declare ptr @cell_equal(ptr)"));
+        assert!(ir.contains("@cell_equal(ptr %"));
+    }
+
+    #[test]
+    fn test_codegen_exit_word() {
+        let program = crate::parser::Parser::new(": quit ( -- ) 3 exit ;")
+            .parse()
+            .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // The declaration matches how the call uses it
+        assert!(ir.contains("declare ptr @exit_op(ptr)"));
+        assert!(ir.contains("call ptr @exit_op(ptr %"));
+    }
+
+    #[test]
+    fn test_codegen_sleep_word() {
+        let program = crate::parser::Parser::new(": nap ( -- ) 10 sleep ;")
+            .parse()
+            .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // libc already has a `sleep`
+        assert!(ir.contains("@sleep_strand(ptr %"));
+        assert!(!ir.contains("@sleep(ptr"));
+    }
+
+    #[test]
+    fn test_codegen_channel_words() {
+        let source = ": go ( -- ) channel dup [ 1 send drop ] spawn-with recv drop drop ;";
+        let program = crate::parser::Parser::new(source).parse().unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // libc already has `send` and `recv`
+        assert!(ir.contains("@channel_new(ptr %"));
+        assert!(ir.contains("@channel_send(ptr %"));
+        assert!(ir.contains("@channel_recv(ptr %"));
+        assert!(ir.contains("@spawn_with(ptr %"));
+        assert!(!ir.contains("@send(ptr"));
+        assert!(!ir.contains("@recv(ptr"));
+    }
+
+    #[test]
+    fn test_codegen_join_word() {
+        let source = ": go ( -- Int ) [ 6 7 * ] spawn-result join ;";
+        let program = crate::parser::Parser::new(source).parse().unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        assert!(ir.contains("@spawn_result(ptr %"));
+        assert!(ir.contains("@join_strand(ptr %"));
+    }
+
+    #[test]
+    fn test_codegen_wildcard_is_switch_default() {
+        let mut codegen = CodeGen::new();
+
+        let option_type = TypeDef {
+            name: "Option".to_string(),
+            type_params: vec!["T".to_string()],
+            variants: vec![
+                Variant {
+                    name: "Some".to_string(),
+                    fields: vec![Type::Var("T".to_string())],
+                    loc: SourceLoc::unknown(),
+                },
+                Variant {
+                    name: "None".to_string(),
+                    fields: vec![],
+                    loc: SourceLoc::unknown(),
+                },
+            ],
+            loc: SourceLoc::unknown(),
+        };
+
+        // : is-none ( Option(Int) -- Bool ) match None => [ true ] _ => [ false ] end ;
+        let word = WordDef {
+            name: "is-none".to_string(),
+            effect: Effect::from_vecs(
+                vec![Type::Named {
+                    name: "Option".to_string(),
+                    args: vec![Type::Int],
+                }],
+                vec![Type::Bool],
+            ),
+            body: vec![Expr::Match {
+                branches: vec![
+                    MatchBranch {
+                        pattern: Pattern::Variant {
+                            name: "None".to_string(),
+                            fields: vec![],
+                        },
+                        body: vec![Expr::BoolLit(true, SourceLoc::unknown())],
+                        loc: SourceLoc::unknown(),
+                    },
+                    MatchBranch {
+                        pattern: Pattern::Wildcard,
+                        body: vec![Expr::BoolLit(false, SourceLoc::unknown())],
+                        loc: SourceLoc::unknown(),
+                    },
+                ],
+                loc: SourceLoc::unknown(),
+            }],
+            loc: SourceLoc::unknown(),
+        };
+
+        let program = Program {
+            imports: Vec::new(),
+            type_defs: vec![option_type],
+            word_defs: vec![word],
+            ..Program::default()
+        };
+
+        let ir = codegen.compile_program(&program).unwrap();
+
+        // The switch defaults to the wildcard branch and only lists None explicitly
+        assert!(ir.contains("label %match_case_0_1 [\n    i32 1, label %match_case_0_0"));
+        assert!(ir.contains("match_case_0_1:"));
+        assert!(!ir.contains("match_default_"));
+        assert!(!ir.contains("@.str.match_error"));
+    }
+
+    #[test]
+    fn test_exhaustive_match_has_no_runtime_default() {
+        let types = "type Light\n  | Red\n  | Amber\n  | Green\n";
+        let compile = |words: &str| {
+            let program = crate::parser::Parser::new(&format!("{}{}", types, words))
+                .parse()
+                .unwrap();
+            CodeGen::new().compile_program(&program).unwrap()
+        };
+
+        let ir = compile(
+            ": next ( Light -- Light ) match Red => [ Green ] Amber => [ Red ] Green => [ Amber ] end ;",
+        );
+        assert!(
+            ir.contains("label %match_case_0_2 [\n    i32 0, label %match_case_0_0\n\n    i32 1, label %match_case_0_1\n  ]"),
+            "{}",
+            ir
+        );
+        assert!(!ir.contains("match_default_"));
+        assert!(!ir.contains("@.str.match_error"));
+
+        // Unchecked code can leave a variant out, which still fails at runtime
+        let ir = compile(": stop ( Light -- Bool ) match Red => [ true ] Amber => [ false ] end ;");
+        assert!(ir.contains("match_default_0:"));
+        assert!(ir.contains("@.str.match_error"));
+    }
+
+    #[test]
+    fn test_reset_compiles_like_a_new_instance() {
+        let sources = [
+            "type Shape | Circle(Int) | Dot\n: area ( Shape -- Int ) match Circle(r) => [ r r * ] Dot => [ 0 ] end ;\n: main ( -- ) \"hi\" write_line [ 1 ] call drop ;",
+            ": greet ( -- ) \"hello\" write_line \"hi\" write_line [ 2 ] call drop ;",
+        ];
+        let programs: Vec<Program> = sources
+            .iter()
+            .map(|source| crate::parser::Parser::new(source).parse().unwrap())
+            .collect();
+
+        let mut reused = CodeGen::new();
+        for program in &programs {
+            reused.reset();
+            let ir = reused.compile_program(program).unwrap();
+            assert_eq!(ir, CodeGen::new().compile_program(program).unwrap());
+        }
+
+        // Without a reset, the second program carries the first's state
+        let mut stale = CodeGen::new();
+        stale.compile_program(&programs[0]).unwrap();
+        let ir = stale.compile_program(&programs[1]).unwrap();
+        assert_ne!(ir, CodeGen::new().compile_program(&programs[1]).unwrap());
+    }
+
+    #[test]
+    fn test_codegen_nested_patterns() {
+        let program = crate::parser::Parser::new(
+            r#"
+            type List | Cons(Int, List) | Nil
+            : second ( List -- Int )
+              match Cons(_, Cons(x, _)) => [ x ] Cons(_, Nil) => [ 0 ] Nil => [ 0 ] end ;
+            "#,
+        )
+        .parse()
+        .unwrap();
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // Both Cons branches share the switch case, which starts with the first's tests
+        assert!(
+            ir.contains("i32 0, label %match_test_0_0\n\n    i32 1, label %match_case_0_2\n  ]"),
+            "{}",
+            ir
+        );
+        // A failed test moves on to the next Cons branch, then to the runtime error
+        assert!(ir.contains("label %match_test_0_1\n"), "{}", ir);
+        assert!(ir.contains("label %match_default_0\n"), "{}", ir);
+        assert!(ir.contains("br label %match_case_0_0"), "{}", ir);
+        assert!(ir.contains("br label %match_case_0_1"), "{}", ir);
+    }
+
+    #[test]
+    fn test_codegen_pattern_bindings() {
+        let mut codegen = CodeGen::new();
+
+        let program = crate::parser::Parser::new(
+            r#"
+            type Pair | Pair(Int, Int)
+            : first ( Pair -- Int ) match Pair(a, _) => [ a ] end ;
+            : quoted ( Pair -- Int ) match Pair(a, _) => [ [ a ] drop 0 ] end ;
+            "#,
+        )
+        .parse()
+        .unwrap();
+
+        // Bindings are compiled in the function that owns the match, not in quotations
+        let err = codegen.compile_program(&program).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("cannot be used inside a quotation")
+        );
+
+        let program = Program {
+            imports: Vec::new(),
+            type_defs: program.type_defs,
+            word_defs: program.word_defs[..1].to_vec(),
+            ..Program::default()
+        };
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // The data chain holds the last field first, so `a` is one link in:
+        // follow the chain once, then copy that cell
+        let first = ir.split("define ptr @first").nth(1).unwrap();
+        assert!(first.contains("= call ptr @copy_cell(ptr %7)"), "{}", first);
+        assert!(first.contains("%6 = getelementptr inbounds"));
+        assert!(first.contains("%7 = load ptr, ptr %6"));
+        // A binding in tail position is not a word call
+        assert!(!first.contains("musttail"));
+    }
+
+    #[test]
+    fn test_type_with_more_than_a_thousand_variants() {
+        let variants: String = (0..1200).map(|i| format!("  | V{}\n", i)).collect();
+        let source = format!(
+            "type Big\n{}: last ( -- Big ) V1199 ;\n: is-last ( Big -- Bool ) match V1199 => [ true ] _ => [ false ] end ;",
+            variants
+        );
+        let program = crate::parser::Parser::new(&source).parse().unwrap();
+        crate::typechecker::TypeChecker::new()
+            .check_program(&program)
+            .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+        assert!(ir.contains("i32 1199, ptr null)"), "{}", ir);
+        assert!(ir.contains("i32 1199, label %match_case_"), "{}", ir);
+    }
+
+    #[test]
+    fn test_variant_tags_are_per_type() {
+        let types =
+            "type Fruit\n  | Apple\n  | Cherry\n\ntype Tree\n  | Oak\n  | Elm\n  | Cherry\n";
+        let compile = |words: &str| {
+            let program = crate::parser::Parser::new(&format!("{}{}", types, words))
+                .parse()
+                .unwrap();
+            CodeGen::new().compile_program(&program)
+        };
+
+        // The other patterns say which type's Cherry a match means
+        // (the last branch of a match covering every variant is the default)
+        let ir =
+            compile(": fruit ( Fruit -- Int ) match Cherry => [ 2 ] Apple => [ 1 ] end ;").unwrap();
+        assert!(ir.contains("i32 1, label %match_case_"), "{}", ir);
+        let ir =
+            compile(": tree ( Tree -- Int ) match Cherry => [ 3 ] Oak => [ 4 ] end ;").unwrap();
+        assert!(ir.contains("i32 2, label %match_case_"), "{}", ir);
+        assert!(!ir.contains("i32 1, label %match_case_"), "{}", ir);
+
+        // A constructor declared by one type is fine; one declared by both isn't
+        let ir = compile(": elm ( -- Tree ) Elm ;").unwrap();
+        assert!(ir.contains("@push_variant(ptr %"), "{}", ir);
+        let err = compile(": cherry ( -- Tree ) Cherry ;").unwrap_err();
+        assert!(err.to_string().contains("'Cherry' is ambiguous"), "{}", err);
+        let err =
+            compile(": any ( Tree -- Int ) match Cherry => [ 1 ] _ => [ 0 ] end ;").unwrap_err();
+        assert!(err.to_string().contains("fit types Fruit, Tree"), "{}", err);
+    }
+
+    #[test]
+    fn test_prelude_variant_tags_match_runtime() {
+        // string_to_int and read-file build Options and Results in the runtime with fixed tags
+        let program = crate::parser::Parser::new(include_str!("../../../stdlib/prelude.cem"))
+            .parse()
+            .unwrap();
+        let mut codegen = CodeGen::new();
+        codegen.compile_program(&program).unwrap();
+
+        let tag = |type_name: &str, variant: &str| {
+            codegen.variant_tags[&(type_name.to_string(), variant.to_string())]
+        };
+        assert_eq!(tag("Option", "Some"), 0);
+        assert_eq!(tag("Option", "None"), 1);
+        assert_eq!(tag("Result", "Ok"), 0);
+        assert_eq!(tag("Result", "Err"), 1);
+    }
+
+    #[test]
+    fn test_main_returns_exit_code() {
+        let program = crate::parser::Parser::new(": main ( -- Int ) 3 ;")
+            .parse()
+            .unwrap();
+
+        let ir = CodeGen::new()
+            .compile_program_with_main(&program, Some("main"))
+            .unwrap();
+
+        // The entry strand's final stack comes back from the scheduler
+        assert!(ir.contains("call i64 @strand_spawn_main(ptr @cem_main, ptr null)"));
+        assert!(ir.contains("%exit_code = call i32 @stack_top_int_or(ptr %stack, i32 0)"));
+        assert!(ir.contains("ret i32 %exit_code"));
+    }
+
+    #[test]
+    fn test_codegen_int_patterns() {
+        let program = crate::parser::Parser::new(
+            ": sign ( Int -- Int ) match 0 => [ 0 ] -7 => [ 1 ] _ => [ 2 ] end ;",
+        )
+        .parse()
+        .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // Switch on the Int value itself, with the wildcard as the default
+        assert!(ir.contains("%1 = load i64, ptr %0"));
+        assert!(ir.contains(
+            "switch i64 %1, label %match_case_0_2 [\n    i64 0, label %match_case_0_0\n\n    i64 -7, label %match_case_0_1"
+        ));
+        // Ints have no variant data to unwrap
+        assert!(!ir.contains("i32 0, i32 2, i32 8"));
+    }
+
+    #[test]
+    fn test_words_have_signature_comments() {
+        let program = crate::parser::Parser::new(
+            ": square ( Int -- Int ) dup * ;\n: some-list ( ..R -- ..R Option(Int) ) 1 Some ;",
+        )
+        .parse()
+        .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        assert!(
+            ir.contains("; square ( Int -- Int )\ndefine ptr @square(ptr %stack)"),
+            "{}",
+            ir
+        );
+        assert!(
+            ir.contains(
+                "; some-list ( ..R -- ..R Option(Int) )\ndefine ptr @some_list(ptr %stack)"
+            )
+        );
+    }
+
+    #[test]
+    fn test_codegen_bool_patterns() {
+        let program = crate::parser::Parser::new(
+            ": flip ( Bool -- Int ) match true => [ 0 ] false => [ 1 ] end ;",
+        )
+        .parse()
+        .unwrap();
+
+        let ir = CodeGen::new().compile_program(&program).unwrap();
+
+        // Switch on the Bool's byte. Both values are covered, so the last
+        // branch is the default.
+        assert!(ir.contains("%1 = load i8, ptr %0"));
+        assert!(ir.contains(
+            "switch i8 %1, label %match_case_0_1 [\n    i8 1, label %match_case_0_0\n  ]"
+        ));
+        assert!(!ir.contains("i32 0, i32 2, i32 8"));
+    }
+
     #[test]
     fn test_continuation_code_after_match() {
         // Regression test for bug where code after match expressions
@@ -1876,12 +3534,15 @@ mod tests {
                             args: vec![Type::Var("T".to_string())],
                         },
                     ],
+                    loc: SourceLoc::unknown(),
                 },
                 Variant {
                     name: "Nil".to_string(),
                     fields: vec![],
+                    loc: SourceLoc::unknown(),
                 },
             ],
+            loc: SourceLoc::unknown(),
         };
 
         // Word that has code after a match expression:
@@ -1910,18 +3571,22 @@ mod tests {
                         MatchBranch {
                             pattern: Pattern::Variant {
                                 name: "Cons".to_string(),
+                                fields: vec![],
                             },
                             body: vec![
                                 Expr::WordCall("drop".to_string(), SourceLoc::unknown()),
                                 Expr::WordCall("drop".to_string(), SourceLoc::unknown()),
                                 Expr::IntLit(0, SourceLoc::unknown()),
                             ],
+                            loc: SourceLoc::unknown(),
                         },
                         MatchBranch {
                             pattern: Pattern::Variant {
                                 name: "Nil".to_string(),
+                                fields: vec![],
                             },
                             body: vec![Expr::IntLit(0, SourceLoc::unknown())],
+                            loc: SourceLoc::unknown(),
                         },
                     ],
                     loc: SourceLoc::unknown(),
@@ -1934,8 +3599,10 @@ mod tests {
         };
 
         let program = Program {
+            imports: Vec::new(),
             type_defs: vec![list_type],
             word_defs: vec![word],
+            ..Program::default()
         };
 
         let ir = codegen.compile_program(&program).unwrap();
@@ -9,6 +9,7 @@
 pub mod ast;
 pub mod codegen;
 pub mod parser;
+pub mod repl;
 pub mod typechecker;
 
 pub use ast::types::{Effect, StackType, Type};
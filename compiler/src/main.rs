@@ -1,10 +1,30 @@
-use cemc::codegen::{CodeGen, link_program};
-use cemc::parser::Parser;
-use clap::{CommandFactory, Parser as ClapParser, Subcommand};
+use cemc::ast::Program;
+use cemc::ast::format::format_program;
+use cemc::codegen::{
+    CodeGen, compile_ir_file_to_assembly, compile_ir_file_to_bitcode, compile_ir_file_to_object,
+    link_ir_file,
+};
+use cemc::parser::{Lexer, Parser};
+use cemc::repl::{Evaluation, LINE_WORD, Session};
+use cemc::typechecker::TypeChecker;
+use clap::{CommandFactory, Parser as ClapParser, Subcommand, ValueEnum};
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+// Embed stdlib prelude at compile time
+// This ensures the prelude is always available regardless of where the binary is installed
+// (e.g., cargo install moves binary to ~/.cargo/bin but stdlib stays in src tree)
+const PRELUDE: &str = include_str!("../../stdlib/prelude.cem");
+
+/// Input name that reads the program from stdin
+const STDIN: &str = "-";
+
+/// Runtime library that executables are linked with
+const RUNTIME_LIB: &str = "target/release/libcem_runtime.a";
+
 /// Cem2 Compiler - A concatenative language with green threads and linear types
 #[derive(ClapParser)]
 #[command(name = "cem")]
@@ -17,19 +37,61 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Compile a Cem source file to an executable
+    /// Compile Cem source files to an executable
     Compile {
-        /// Input Cem source file
-        #[arg(value_name = "INPUT")]
-        input: String,
+        /// Input Cem source files, compiled together as one program. `-`
+        /// reads one from stdin.
+        #[arg(value_name = "INPUT", required = true)]
+        inputs: Vec<String>,
 
-        /// Output executable name (default: input filename without extension)
+        /// Output file name (default: first input filename without extension,
+        /// plus the extension of the emitted artifact)
         #[arg(short, long, value_name = "OUTPUT")]
         output: Option<String>,
 
+        /// Artifact to produce
+        #[arg(long, value_enum, default_value_t = Emit::Exe)]
+        emit: Emit,
+
+        /// Target triple to cross-compile for (default: clang's own target).
+        /// Linking an executable needs a runtime built for the same target.
+        #[arg(long, value_name = "TRIPLE")]
+        target: Option<String>,
+
         /// Keep intermediate LLVM IR file
         #[arg(long)]
         keep_ir: bool,
+
+        /// Skip type checking (for debugging codegen)
+        #[arg(long)]
+        no_typecheck: bool,
+    },
+
+    /// Compile a Cem source file and run it
+    Run {
+        /// Input Cem source file
+        #[arg(value_name = "INPUT")]
+        input: String,
+
+        /// Arguments passed to the program
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
+    /// Evaluate lines interactively, printing the stack each expression leaves.
+    /// Definitions (`:` or `type` lines) are kept for later lines.
+    Repl,
+
+    /// Print a Cem source file in canonical layout. Comments are not kept.
+    Fmt {
+        /// Input Cem source file
+        #[arg(value_name = "INPUT")]
+        input: String,
+
+        /// Rewrite the file in place instead of printing it. Refused if the
+        /// file has comments, since formatting would drop them.
+        #[arg(short, long)]
+        write: bool,
     },
 
     /// Generate shell completions for bash, zsh, fish, or powershell
@@ -45,10 +107,23 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     match cli.command {
         Commands::Compile {
-            input,
+            inputs,
             output,
+            emit,
+            target,
             keep_ir,
-        } => compile_command(&input, output.as_deref(), keep_ir),
+            no_typecheck,
+        } => compile_command(
+            &inputs,
+            output.as_deref(),
+            emit,
+            target.as_deref(),
+            keep_ir,
+            no_typecheck,
+        ),
+        Commands::Run { input, args } => run_command(&input, &args),
+        Commands::Repl => repl_command(),
+        Commands::Fmt { input, write } => fmt_command(&input, write),
         Commands::Completions { shell } => {
             generate_completions(shell);
             Ok(())
@@ -56,87 +131,473 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 }
 
+/// Artifact produced by `compile`
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// Print the input files' tokens and stop
+    Tokens,
+    /// Print the input files' syntax trees and stop
+    Ast,
+    /// LLVM IR (.ll)
+    Ir,
+    /// LLVM bitcode (.bc), assembled from the IR by llvm-as
+    Bc,
+    /// Object file (.o), not linked with the runtime
+    Obj,
+    /// Native assembly (.s)
+    Asm,
+    /// Executable linked with the runtime
+    Exe,
+}
+
+impl Emit {
+    /// Extension added to the default output name
+    fn extension(self) -> &'static str {
+        match self {
+            Emit::Ir => ".ll",
+            Emit::Bc => ".bc",
+            Emit::Obj => ".o",
+            Emit::Asm => ".s",
+            Emit::Exe | Emit::Tokens | Emit::Ast => "",
+        }
+    }
+
+    /// Whether this only prints the frontend's view of the input
+    fn is_dump(self) -> bool {
+        matches!(self, Emit::Tokens | Emit::Ast)
+    }
+}
+
 fn compile_command(
-    input_file: &str,
+    inputs: &[String],
     output_name: Option<&str>,
+    emit: Emit,
+    target: Option<&str>,
     keep_ir: bool,
+    no_typecheck: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Determine output name
+    if output_name.is_none() && inputs[0] == STDIN && !emit.is_dump() {
+        return Err("Reading from stdin leaves no name for the output; give one with -o".into());
+    }
     let output_name = output_name.map(String::from).unwrap_or_else(|| {
-        // Default: strip .cem extension and use as output name
-        Path::new(input_file)
+        // Default: strip .cem extension and add the artifact's
+        let stem = Path::new(&inputs[0])
             .file_stem()
             .and_then(|s| s.to_str())
-            .unwrap_or("output")
-            .to_string()
+            .unwrap_or("output");
+        format!("{}{}", stem, emit.extension())
     });
 
-    // Read source file
+    build(
+        inputs,
+        &output_name,
+        emit,
+        target,
+        keep_ir,
+        no_typecheck,
+        true,
+    )?;
+    if emit.is_dump() {
+        return Ok(());
+    }
+
+    println!("\n✅ Successfully compiled to ./{}", output_name);
+    if emit == Emit::Exe {
+        println!("Run it with: ./{}", output_name);
+    }
+
+    Ok(())
+}
+
+fn run_command(input_file: &str, args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // Build into a temp file named after this process, so concurrent runs don't collide
+    let stem = Path::new(input_file)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let executable = std::env::temp_dir().join(format!("cem-run-{}-{}", stem, std::process::id()));
+    let executable = executable
+        .to_str()
+        .ok_or("Temp directory path is not valid UTF-8")?;
+
+    build(
+        &[input_file.to_string()],
+        executable,
+        Emit::Exe,
+        None,
+        false,
+        false,
+        false,
+    )?;
+
+    let status = Command::new(executable).args(args).status();
+    fs::remove_file(executable).ok();
+    let status = status.map_err(|e| format!("Failed to run {}: {}", input_file, e))?;
+
+    // A program killed by a signal has no exit code
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+fn repl_command() -> Result<(), Box<dyn std::error::Error>> {
+    build_runtime()?;
+    let mut session = Session::new(parse_or_exit(PRELUDE, "stdlib/prelude.cem"));
+
+    // Every expression is built into the same temp file, named after this process
+    let executable = std::env::temp_dir().join(format!("cem-repl-{}", std::process::id()));
+    let executable = executable
+        .to_str()
+        .ok_or("Temp directory path is not valid UTF-8")?;
+
+    // One code generator, reset for each expression
+    let mut codegen = CodeGen::new();
+    codegen.set_print_final_stack(true);
+
+    let stdin = io::stdin();
+    loop {
+        print!("cem> ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match session.eval(&line) {
+            Ok(Evaluation::Defined(names)) => println!("Defined {}", names.join(", ")),
+            Ok(Evaluation::Run { program, .. }) => {
+                if let Err(e) = run_repl_line(&mut codegen, &program, executable) {
+                    eprintln!("{}", e);
+                }
+            }
+            Err(message) => eprintln!("{}", message),
+        }
+    }
+}
+
+/// Build and run an expression line's program, which prints the stack it leaves
+fn run_repl_line(
+    codegen: &mut CodeGen,
+    program: &Program,
+    executable: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ir_file = format!("{}.ll", executable);
+    codegen.reset();
+    let mut writer = BufWriter::new(fs::File::create(&ir_file)?);
+    codegen.compile_program_to_writer(program, Some(LINE_WORD), &mut writer)?;
+    writer.flush()?;
+    drop(writer);
+
+    let linked = link_ir_file(&ir_file, RUNTIME_LIB, executable, None);
+    fs::remove_file(&ir_file).ok();
+    linked?;
+
+    // A runtime error has already been reported on stderr
+    let status = Command::new(executable).status();
+    fs::remove_file(executable).ok();
+    status?;
+    Ok(())
+}
+
+fn fmt_command(input_file: &str, write: bool) -> Result<(), Box<dyn std::error::Error>> {
     let source = fs::read_to_string(input_file)
         .map_err(|e| format!("Failed to read {}: {}", input_file, e))?;
+    let formatted = format_program(&parse_or_exit(&source, input_file));
 
-    // Embed stdlib prelude at compile time
-    // This ensures the prelude is always available regardless of where the binary is installed
-    // (e.g., cargo install moves binary to ~/.cargo/bin but stdlib stays in src tree)
-    const PRELUDE: &str = include_str!("../../stdlib/prelude.cem");
+    let mut lexer = Lexer::new(&source);
+    lexer.tokenize();
+    let has_comments = lexer.comment_count() > 0;
 
-    // Combine prelude + user source
-    let combined_source = format!("{}\n\n{}", PRELUDE, source);
+    if !write {
+        if has_comments {
+            eprintln!("Note: comments in {} are not kept", input_file);
+        }
+        print!("{}", formatted);
+        return Ok(());
+    }
 
-    // Parse
-    println!("Parsing {}...", input_file);
-    let mut parser = Parser::new_with_filename(&combined_source, input_file);
-    let program = parser.parse().map_err(|e| format!("Parse error: {}", e))?;
+    if has_comments {
+        return Err(format!(
+            "Not rewriting {}: it has comments, which formatting would drop",
+            input_file
+        )
+        .into());
+    }
+    fs::write(input_file, formatted)
+        .map_err(|e| format!("Failed to write {}: {}", input_file, e))?;
+    Ok(())
+}
 
-    // Build runtime first
-    println!("Building runtime...");
-    let status = Command::new("just").arg("build-runtime").status()?;
+/// Parse `source`, or print each error with a snippet of the line it's on and exit
+fn parse_or_exit(source: &str, filename: &str) -> Program {
+    let mut parser = Parser::new_with_filename(source, filename);
+    parser.parse_all().unwrap_or_else(|errors| {
+        for e in errors {
+            eprintln!("{}: {}\n", filename, e.render(source));
+        }
+        std::process::exit(1);
+    })
+}
 
-    if !status.success() {
-        return Err("Failed to build runtime".into());
+/// Read an input file, or stdin for `-`
+///
+/// Returns the source and the name locations in it refer to, `<stdin>` for stdin.
+fn read_source(input_file: &str) -> Result<(String, &str), Box<dyn std::error::Error>> {
+    if input_file == STDIN {
+        let mut source = String::new();
+        io::stdin()
+            .read_to_string(&mut source)
+            .map_err(|e| format!("Failed to read stdin: {}", e))?;
+        Ok((source, "<stdin>"))
+    } else {
+        let source = fs::read_to_string(input_file)
+            .map_err(|e| format!("Failed to read {}: {}", input_file, e))?;
+        Ok((source, input_file))
     }
+}
 
-    // Generate LLVM IR
-    println!("Generating LLVM IR...");
-    let mut codegen = CodeGen::new();
+/// Parses files and the files they import into one program
+#[derive(Default)]
+struct Loader {
+    /// Definitions of every file loaded so far, each file's after its imports'
+    program: Program,
+    /// Files already loaded, which later imports of them skip
+    loaded: HashSet<PathBuf>,
+    /// Files being loaded, outermost first, with the names they were given as
+    importing: Vec<(PathBuf, String)>,
+}
+
+impl Loader {
+    /// Load `source`, read from `filename`, and the files it imports
+    ///
+    /// `path` is the file's canonical path, `None` for stdin. Imports are
+    /// resolved relative to the directory of `filename`.
+    fn load(
+        &mut self,
+        source: &str,
+        filename: &str,
+        path: Option<PathBuf>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if path.as_ref().is_some_and(|path| self.loaded.contains(path)) {
+            return Ok(());
+        }
+
+        let program = parse_or_exit(source, filename);
+        if let Some(path) = &path {
+            self.importing.push((path.clone(), filename.to_string()));
+        }
+
+        let dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+        for import in &program.imports {
+            let import_file = dir.join(&import.path);
+            let import_name = import_file.to_string_lossy();
+            let import_path = fs::canonicalize(&import_file)
+                .map_err(|e| format!("{}: Cannot import {}: {}", import.loc, import_name, e))?;
+
+            if let Some(start) = self
+                .importing
+                .iter()
+                .position(|(path, _)| *path == import_path)
+            {
+                // Back round to the file as it was first named
+                let cycle: Vec<&str> = self.importing[start..]
+                    .iter()
+                    .chain([&self.importing[start]])
+                    .map(|(_, name)| name.as_str())
+                    .collect();
+                return Err(format!("{}: Import cycle: {}", import.loc, cycle.join(" -> ")).into());
+            }
+
+            let import_source = fs::read_to_string(&import_file)
+                .map_err(|e| format!("{}: Cannot import {}: {}", import.loc, import_name, e))?;
+            self.load(&import_source, &import_name, Some(import_path))?;
+        }
+
+        if let Some(path) = path {
+            self.importing.pop();
+            self.loaded.insert(path);
+        }
+        self.program.type_defs.extend(program.type_defs);
+        self.program.word_defs.extend(program.word_defs);
+        Ok(())
+    }
+}
+
+/// Compile `inputs` (with the prelude) as one program to the `emit` artifact at `output_name`
+///
+/// Each file is parsed on its own, so locations refer to the file they're in.
+/// Files the inputs import are compiled too, once each however often they're
+/// imported. Names defined in more than one file are reported by the type
+/// checker, with both locations.
+///
+/// Progress messages are printed only when `verbose` is set; errors are always reported.
+fn build(
+    inputs: &[String],
+    output_name: &str,
+    emit: Emit,
+    target: Option<&str>,
+    keep_ir: bool,
+    no_typecheck: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let progress = |message: &str| {
+        if verbose {
+            println!("{}", message);
+        }
+    };
+
+    if inputs.iter().filter(|input| *input == STDIN).count() > 1 {
+        return Err("stdin (-) can only be one of the inputs".into());
+    }
+    let sources = inputs
+        .iter()
+        .map(|input| read_source(input))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Dumps cover only the input files, without the prelude, so lines match them
+    match emit {
+        Emit::Tokens => {
+            for (source, _) in &sources {
+                for token in Lexer::new(source).tokenize() {
+                    println!(
+                        "{}:{}\t{}\t{:?}",
+                        token.line, token.column, token.kind, token.lexeme
+                    );
+                }
+            }
+            return Ok(());
+        }
+        Emit::Ast => {
+            for (source, filename) in &sources {
+                print!("{}", parse_or_exit(source, filename).dump());
+            }
+            return Ok(());
+        }
+        Emit::Ir | Emit::Bc | Emit::Obj | Emit::Asm | Emit::Exe => {}
+    }
+
+    // Parse the prelude and each input on its own, so locations in every file are its own
+    let filenames: Vec<&str> = sources.iter().map(|(_, filename)| *filename).collect();
+    progress(&format!("Parsing {}...", filenames.join(", ")));
+    let prelude = parse_or_exit(PRELUDE, "stdlib/prelude.cem");
+    let mut loader = Loader::default();
+    for (source, filename) in &sources {
+        let path = (*filename != "<stdin>")
+            .then(|| fs::canonicalize(filename))
+            .transpose()
+            .map_err(|e| format!("Failed to read {}: {}", filename, e))?;
+        loader.load(source, filename, path)?;
+    }
+    let user = loader.program;
 
-    // Find entry point (look for "main" word, or use first word if only one)
-    let has_main = program.word_defs.iter().any(|w| w.name == "main");
-    let entry_word = if has_main {
-        Some("main")
-    } else if program.word_defs.len() == 1 {
-        println!(
+    // Find entry point (look for "main" word, or use the input's word if it has only one)
+    let has_main = user.word_defs.iter().any(|w| w.name == "main");
+    let entry = if has_main {
+        user.word_defs
+            .iter()
+            .find(|w| w.name == "main")
+            .unwrap()
+            .clone()
+    } else if user.word_defs.len() == 1 {
+        progress(&format!(
             "Note: Using '{}' as entry point (no 'main' word found)",
-            program.word_defs[0].name
-        );
-        Some(program.word_defs[0].name.as_str())
+            user.word_defs[0].name
+        ));
+        user.word_defs[0].clone()
     } else {
         eprintln!("Error: No 'main' word found and multiple words defined");
-        eprintln!("Either define a 'main' word or compile a file with only one word");
+        eprintln!("Either define a 'main' word or compile a program with only one word");
         std::process::exit(1);
     };
 
-    let ir = codegen.compile_program_with_main(&program, entry_word)?;
+    let program = Program {
+        imports: Vec::new(),
+        type_defs: [prelude.type_defs, user.type_defs].concat(),
+        word_defs: [prelude.word_defs, user.word_defs].concat(),
+        ..Program::default()
+    };
+
+    // Type check
+    if no_typecheck {
+        progress("Skipping type check (--no-typecheck)");
+    } else {
+        progress("Type checking...");
+        let mut checker = TypeChecker::new();
+        let checked = checker
+            .check_program(&program)
+            .and_then(|()| TypeChecker::check_entry_effect(&entry));
+        if let Err(e) = checked {
+            eprintln!("Type error: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Build runtime first, if it will be linked in
+    if emit == Emit::Exe {
+        progress("Building runtime...");
+        build_runtime()?;
+    }
+
+    // Generate LLVM IR
+    progress("Generating LLVM IR...");
+    let mut codegen = CodeGen::new();
+    if let Some(target) = target {
+        codegen.set_target_triple(target);
+    }
 
-    // Write IR to file
-    let ir_file = format!("{}.ll", output_name);
-    fs::write(&ir_file, &ir)?;
-    if keep_ir {
-        println!("Wrote LLVM IR to {}", ir_file);
+    // Stream IR straight to the file clang will read
+    let ir_file = if emit == Emit::Ir {
+        output_name.to_string()
+    } else {
+        format!("{}.ll", output_name)
+    };
+    let mut writer = BufWriter::new(fs::File::create(&ir_file)?);
+    codegen.compile_program_to_writer(&program, Some(&entry.name), &mut writer)?;
+    writer.flush()?;
+    drop(writer);
+    if keep_ir || emit == Emit::Ir {
+        progress(&format!("Wrote LLVM IR to {}", ir_file));
     }
 
-    // Link with runtime
-    println!("Linking...");
-    link_program(&ir, "target/release/libcem_runtime.a", &output_name)?;
+    match emit {
+        Emit::Ir => {}
+        Emit::Tokens | Emit::Ast => unreachable!("dumps return before codegen"),
+        Emit::Bc => {
+            progress("Assembling bitcode...");
+            compile_ir_file_to_bitcode(&ir_file, output_name)?;
+        }
+        Emit::Obj => {
+            progress("Compiling object file...");
+            compile_ir_file_to_object(&ir_file, output_name, target)?;
+        }
+        Emit::Asm => {
+            progress("Compiling assembly...");
+            compile_ir_file_to_assembly(&ir_file, output_name, target)?;
+        }
+        Emit::Exe => {
+            // Link with runtime
+            progress("Linking...");
+            link_ir_file(&ir_file, RUNTIME_LIB, output_name, target)?;
+        }
+    }
 
-    // Clean up IR file unless --keep-ir was specified
-    if !keep_ir {
+    // Clean up IR file unless --keep-ir was specified or it's the output
+    if !keep_ir && emit != Emit::Ir {
         fs::remove_file(&ir_file).ok();
     }
 
-    println!("\n✅ Successfully compiled to ./{}", output_name);
-    println!("Run it with: ./{}", output_name);
+    Ok(())
+}
 
+/// Build the runtime library that executables link with
+fn build_runtime() -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new("just").arg("build-runtime").status()?;
+    if !status.success() {
+        return Err("Failed to build runtime".into());
+    }
     Ok(())
 }
 
@@ -15,17 +15,21 @@ pub struct Token {
 pub enum TokenKind {
     // Literals
     IntLiteral,
+    FloatLiteral,
+    CharLiteral,
     StringLiteral,
     BoolLiteral,
 
     // Keywords
-    Type,  // type
-    Colon, // :
-    Pipe,  // |
-    Match, // match
-    End,   // end
-    If,    // if
-    Arrow, // =>
+    Import, // import
+    Type,   // type
+    Colon,  // :
+    Pipe,   // |
+    Match,  // match
+    End,    // end
+    If,     // if
+    While,  // while
+    Arrow,  // =>
 
     // Delimiters
     LeftParen,    // (
@@ -38,6 +42,10 @@ pub enum TokenKind {
     // Identifier (word name, type name, variant name)
     Ident,
 
+    // Lexical error (unterminated literal, malformed number, ...).
+    // The lexeme holds the error message; the parser reports it as a ParseError.
+    Error,
+
     // End of file
     Eof,
 
@@ -50,20 +58,40 @@ pub struct Lexer {
     position: usize,
     line: usize,
     column: usize,
+    max_string_length: usize,
+    /// Number of comments skipped so far
+    comments: usize,
 }
 
+/// Longest string literal, in bytes, unless the lexer is given another limit
+pub const DEFAULT_MAX_STRING_LENGTH: usize = 1_000_000; // 1MB
+
 impl Lexer {
     pub fn new(input: &str) -> Self {
+        Self::with_max_string_length(input, DEFAULT_MAX_STRING_LENGTH)
+    }
+
+    /// Create a lexer that allows string literals up to `max` bytes long
+    pub fn with_max_string_length(input: &str, max: usize) -> Self {
         Lexer {
             input: input.chars().collect(),
             position: 0,
             line: 1,
             column: 1,
+            max_string_length: max,
+            comments: 0,
         }
     }
 
+    /// Number of comments skipped so far, which no token records
+    pub fn comment_count(&self) -> usize {
+        self.comments
+    }
+
     pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace_and_comments();
+        if let Some(error) = self.skip_whitespace_and_comments() {
+            return error;
+        }
 
         if self.is_at_end() {
             return self.make_token(TokenKind::Eof, "");
@@ -139,7 +167,10 @@ impl Lexer {
                 };
             }
             '-' => {
-                // Check for --, negative number, or dash in identifier
+                // A `-` directly followed by a digit is always a negative
+                // number: the lexer can't know whether an operand came before
+                // it, so `3 -5` pushes 3 and -5. Subtraction is the separate
+                // word `-`, as in `3 5 -`. Otherwise `-` starts `--` or a word.
                 if self.peek_next() == Some('-') {
                     // It's --
                     self.advance();
@@ -178,13 +209,30 @@ impl Lexer {
                 };
             }
             '"' => return self.string_literal(),
+            '\'' => return self.char_literal(),
             _ => {
-                if c.is_ascii_digit()
-                    || (c == '-' && self.peek_next().is_some_and(|n| n.is_ascii_digit()))
+                if c.is_ascii_digit() && self.digits_start_word() {
+                    // `2dup`, `2drop`: a word whose name starts with a digit
+                    return self.identifier_or_keyword();
+                } else if c.is_ascii_digit()
+                    || (c == '_' && self.peek_next().is_some_and(|n| n.is_ascii_digit()))
                 {
+                    // A leading `_5` is lexed as a (malformed) number so the
+                    // parser can report the misplaced separator
                     return self.number_literal();
                 } else if c.is_alphabetic() || c == '_' || is_operator_char(c) {
                     return self.identifier_or_keyword();
+                } else if c == '.' && self.peek_next() == Some('.') {
+                    // `..R`, a row variable in a stack effect
+                    self.advance();
+                    self.advance();
+                    let name = self.identifier_or_keyword();
+                    return Token {
+                        kind: TokenKind::Ident,
+                        lexeme: format!("..{}", name.lexeme),
+                        line: start_line,
+                        column: start_column,
+                    };
                 }
             }
         }
@@ -212,35 +260,80 @@ impl Lexer {
         tokens
     }
 
-    fn skip_whitespace_and_comments(&mut self) {
+    /// Skip whitespace, line comments, and block comments
+    ///
+    /// Returns an error token if a block comment is still open at EOF.
+    fn skip_whitespace_and_comments(&mut self) -> Option<Token> {
         loop {
             if self.is_at_end() {
-                return;
+                return None;
             }
 
             match self.peek() {
-                ' ' | '\t' | '\r' => {
+                ' ' | '\t' | '\r' | '\n' => {
                     self.advance();
                 }
-                '\n' => {
-                    self.advance();
-                    self.line += 1;
-                    self.column = 0; // Will be incremented to 1 by next advance
+                '#' if self.peek_next() == Some('|') => {
+                    self.comments += 1;
+                    if let Some(error) = self.skip_block_comment() {
+                        return Some(error);
+                    }
                 }
                 '#' => {
                     // Comment until end of line
+                    self.comments += 1;
                     while !self.is_at_end() && self.peek() != '\n' {
                         self.advance();
                     }
                 }
-                _ => return,
+                _ => return None,
             }
         }
     }
 
-    fn string_literal(&mut self) -> Token {
-        const MAX_STRING_LENGTH: usize = 1_000_000; // 1MB limit
+    /// Skip a `#| ... |#` block comment, which may nest
+    ///
+    /// Newlines inside the comment are counted so that locations after it stay
+    /// accurate. Returns an error token pointing at the outermost `#|` if the
+    /// comment is never closed.
+    fn skip_block_comment(&mut self) -> Option<Token> {
+        let start_line = self.line;
+        let start_column = self.column;
+        self.advance(); // consume #
+        self.advance(); // consume |
+
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                return Some(Token {
+                    kind: TokenKind::Error,
+                    lexeme: "Unterminated block comment".to_string(),
+                    line: start_line,
+                    column: start_column,
+                });
+            }
+
+            match (self.peek(), self.peek_next()) {
+                ('#', Some('|')) => {
+                    self.advance();
+                    self.advance();
+                    depth += 1;
+                }
+                ('|', Some('#')) => {
+                    self.advance();
+                    self.advance();
+                    depth -= 1;
+                }
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+
+        None
+    }
 
+    fn string_literal(&mut self) -> Token {
         let start_line = self.line;
         let start_column = self.column;
         self.advance(); // consume opening "
@@ -248,13 +341,13 @@ impl Lexer {
         let mut value = String::new();
         while !self.is_at_end() && self.peek() != '"' {
             // Check string length limit
-            if value.len() >= MAX_STRING_LENGTH {
+            if value.len() >= self.max_string_length {
                 // Return error token
                 return Token {
-                    kind: TokenKind::Ident, // Use Ident for errors
+                    kind: TokenKind::Error,
                     lexeme: format!(
-                        "ERROR: String exceeds maximum length of {} bytes",
-                        MAX_STRING_LENGTH
+                        "String exceeds maximum length of {} bytes",
+                        self.max_string_length
                     ),
                     line: start_line,
                     column: start_column,
@@ -264,27 +357,35 @@ impl Lexer {
             if self.peek() == '\n' {
                 // Unterminated string (newline before closing quote)
                 return Token {
-                    kind: TokenKind::Ident,
-                    lexeme: "ERROR: Unterminated string literal (newline)".to_string(),
+                    kind: TokenKind::Error,
+                    lexeme: "Unterminated string literal (newline)".to_string(),
                     line: start_line,
                     column: start_column,
                 };
             }
 
             if self.peek() == '\\' {
+                let (line, column) = (self.line, self.column);
                 self.advance();
-                if !self.is_at_end() {
-                    let escaped = match self.peek() {
-                        'n' => '\n',
-                        't' => '\t',
-                        'r' => '\r',
-                        '\\' => '\\',
-                        '"' => '"',
-                        c => c,
-                    };
-                    value.push(escaped);
-                    self.advance();
+                if self.peek() == 'u' {
+                    match self.unicode_escape() {
+                        Ok('\0') => {
+                            return self.escape_error(NUL_IN_STRING.to_string(), line, column, '"');
+                        }
+                        Ok(c) => value.push(c),
+                        Err(message) => return self.escape_error(message, line, column, '"'),
+                    }
+                } else if self.peek() == '0' {
+                    return self.escape_error(NUL_IN_STRING.to_string(), line, column, '"');
+                } else if !self.is_at_end() && self.peek() != '\n' {
+                    match self.simple_escape() {
+                        Ok(c) => value.push(c),
+                        Err(message) => return self.escape_error(message, line, column, '"'),
+                    }
                 }
+            } else if self.peek() == '\0' {
+                let (line, column) = (self.line, self.column);
+                return self.escape_error(NUL_IN_STRING.to_string(), line, column, '"');
             } else {
                 value.push(self.peek());
                 self.advance();
@@ -294,8 +395,8 @@ impl Lexer {
         if self.is_at_end() {
             // Unterminated string (EOF before closing quote)
             return Token {
-                kind: TokenKind::Ident,
-                lexeme: "ERROR: Unterminated string literal (EOF)".to_string(),
+                kind: TokenKind::Error,
+                lexeme: "Unterminated string literal (EOF)".to_string(),
                 line: start_line,
                 column: start_column,
             };
@@ -311,6 +412,141 @@ impl Lexer {
         }
     }
 
+    /// Decode `u{1F600}` after a backslash, from the `u` on
+    ///
+    /// Takes one to six hex digits, which must name a Unicode scalar value.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        self.advance(); // consume u
+        if self.peek() != '{' {
+            return Err("Expected '{' after \\u in unicode escape".to_string());
+        }
+        self.advance();
+
+        let mut digits = String::new();
+        while self.peek().is_ascii_hexdigit() {
+            digits.push(self.advance());
+        }
+        if self.peek() != '}' {
+            return Err(format!(
+                "Malformed unicode escape \\u{{{}: expected hex digits and a closing '}}'",
+                digits
+            ));
+        }
+        self.advance();
+
+        if digits.is_empty() || digits.len() > 6 {
+            return Err(format!(
+                "Unicode escape \\u{{{}}} must have 1 to 6 hex digits",
+                digits
+            ));
+        }
+        let code = u32::from_str_radix(&digits, 16).expect("hex digits");
+        char::from_u32(code)
+            .ok_or_else(|| format!("Unicode escape \\u{{{}}} is not a valid code point", digits))
+    }
+
+    /// Decode a one-character escape like `\n`, consuming the character after
+    /// the backslash
+    fn simple_escape(&mut self) -> Result<char, String> {
+        let c = self.advance();
+        unescape(c).ok_or_else(|| format!("Unknown escape sequence '\\{}'", c.escape_default()))
+    }
+
+    /// An error token for a bad escape at `line` and `column`, after skipping
+    /// to the end of the literal so the rest of it doesn't lex as code
+    fn escape_error(&mut self, message: String, line: usize, column: usize, quote: char) -> Token {
+        while !self.is_at_end() && self.peek() != '\n' {
+            match self.advance() {
+                c if c == quote => break,
+                // An escaped quote doesn't end the literal
+                '\\' if self.peek() == quote => {
+                    self.advance();
+                }
+                _ => {}
+            }
+        }
+        Token {
+            kind: TokenKind::Error,
+            lexeme: message,
+            line,
+            column,
+        }
+    }
+
+    /// Lex a character literal like 'A' or '\n'
+    ///
+    /// The token's lexeme holds the decoded contents between the quotes.
+    /// Checking that there is exactly one character is left to the parser so
+    /// that `''` and `'ab'` produce parse errors at the literal's position.
+    fn char_literal(&mut self) -> Token {
+        let start_line = self.line;
+        let start_column = self.column;
+        self.advance(); // consume opening '
+
+        let mut value = String::new();
+        while !self.is_at_end() && self.peek() != '\'' && self.peek() != '\n' {
+            if self.peek() == '\\' {
+                let (line, column) = (self.line, self.column);
+                self.advance();
+                if self.peek() == 'u' {
+                    match self.unicode_escape() {
+                        Ok(c) => value.push(c),
+                        Err(message) => return self.escape_error(message, line, column, '\''),
+                    }
+                } else if !self.is_at_end() && self.peek() != '\n' {
+                    match self.simple_escape() {
+                        Ok(c) => value.push(c),
+                        Err(message) => return self.escape_error(message, line, column, '\''),
+                    }
+                }
+            } else {
+                value.push(self.peek());
+                self.advance();
+            }
+        }
+
+        if self.peek() != '\'' {
+            // Newline or EOF before the closing quote
+            return Token {
+                kind: TokenKind::Error,
+                lexeme: "Unterminated character literal".to_string(),
+                line: start_line,
+                column: start_column,
+            };
+        }
+
+        self.advance(); // consume closing '
+
+        Token {
+            kind: TokenKind::CharLiteral,
+            lexeme: value,
+            line: start_line,
+            column: start_column,
+        }
+    }
+
+    /// Whether the digits at the current position are followed by a letter
+    /// that can't continue a number, making them the start of a word
+    fn digits_start_word(&self) -> bool {
+        let rest = &self.input[self.position..];
+        if let ['0', prefix, ..] = rest
+            && radix_of_prefix(*prefix).is_some()
+        {
+            return false;
+        }
+        let digits = rest
+            .iter()
+            .take_while(|c| c.is_ascii_digit() || **c == '_')
+            .count();
+        match rest.get(digits) {
+            // An exponent marker with digits after it continues the number;
+            // one without is left to number_literal to report
+            Some('e' | 'E') => false,
+            Some(c) => c.is_alphabetic(),
+            None => false,
+        }
+    }
+
     fn number_literal(&mut self) -> Token {
         let start_line = self.line;
         let start_column = self.column;
@@ -322,19 +558,126 @@ impl Lexer {
             self.advance();
         }
 
-        while !self.is_at_end() && self.peek().is_ascii_digit() {
+        // `0x`, `0o` or `0b` starts an integer in another radix
+        if self.peek() == '0'
+            && let Some((radix, name)) = self.peek_next().and_then(radix_of_prefix)
+        {
+            return self.scan_radix_literal(value, radix, name, start_line, start_column);
+        }
+
+        // Digits may contain `_` separators (1_000_000). Placement is validated
+        // by the parser so that malformed separators get a precise column.
+        while !self.is_at_end() && (self.peek().is_ascii_digit() || self.peek() == '_') {
             value.push(self.peek());
             self.advance();
         }
 
+        let mut kind = TokenKind::IntLiteral;
+
+        // Fractional part: a dot only counts as a decimal point when a digit
+        // follows it, so `1 .` and `1.foo` still lex as separate tokens
+        if self.peek() == '.' && self.peek_next().is_some_and(|c| c.is_ascii_digit()) {
+            kind = TokenKind::FloatLiteral;
+            value.push('.');
+            self.advance();
+            while !self.is_at_end() && self.peek().is_ascii_digit() {
+                value.push(self.peek());
+                self.advance();
+            }
+        }
+
+        // Exponent part: e10, E-3, e+7
+        if self.peek() == 'e' || self.peek() == 'E' {
+            let has_sign = matches!(self.peek_next(), Some('+') | Some('-'));
+            let digit_offset = if has_sign { 2 } else { 1 };
+            let has_digits = self
+                .input
+                .get(self.position + digit_offset)
+                .is_some_and(|c| c.is_ascii_digit());
+
+            if !has_digits {
+                // Consume the dangling exponent marker so it doesn't lex as a word
+                value.push(self.advance());
+                if has_sign {
+                    value.push(self.advance());
+                }
+                return Token {
+                    kind: TokenKind::Error,
+                    lexeme: format!("Malformed exponent in number literal '{}'", value),
+                    line: start_line,
+                    column: start_column,
+                };
+            }
+
+            kind = TokenKind::FloatLiteral;
+            value.push(self.advance());
+            if has_sign {
+                value.push(self.advance());
+            }
+            while !self.is_at_end() && self.peek().is_ascii_digit() {
+                value.push(self.peek());
+                self.advance();
+            }
+        }
+
         Token {
-            kind: TokenKind::IntLiteral,
+            kind,
             lexeme: value,
             line: start_line,
             column: start_column,
         }
     }
 
+    /// The rest of an integer literal after its sign, starting at its radix
+    /// prefix (`0x1F`)
+    ///
+    /// Letters and digits up to the end of the literal are all taken into it,
+    /// so `0o78` is one bad literal rather than `0o7` followed by `8`. The
+    /// parser converts the digits.
+    fn scan_radix_literal(
+        &mut self,
+        mut value: String,
+        radix: u32,
+        name: &str,
+        line: usize,
+        column: usize,
+    ) -> Token {
+        // The `0` and the prefix letter
+        value.push(self.advance());
+        value.push(self.advance());
+
+        let mut digits = 0;
+        let mut invalid = None;
+        while !self.is_at_end() && (self.peek().is_alphanumeric() || self.peek() == '_') {
+            let c = self.advance();
+            value.push(c);
+            if c.is_digit(radix) {
+                digits += 1;
+            } else if c != '_' && invalid.is_none() {
+                invalid = Some(c);
+            }
+        }
+
+        let error = match invalid {
+            Some(c) => Some(format!(
+                "Invalid digit '{}' in {} literal '{}'",
+                c, name, value
+            )),
+            None if digits == 0 => Some(format!("Missing digits in {} literal '{}'", name, value)),
+            None => None,
+        };
+        Token {
+            kind: if error.is_some() {
+                TokenKind::Error
+            } else {
+                TokenKind::IntLiteral
+            },
+            lexeme: error.unwrap_or(value),
+            line,
+            column,
+        }
+    }
+
     fn identifier_or_keyword(&mut self) -> Token {
         let start_line = self.line;
         let start_column = self.column;
@@ -351,10 +694,12 @@ impl Lexer {
         }
 
         let kind = match value.as_str() {
+            "import" => TokenKind::Import,
             "type" => TokenKind::Type,
             "match" => TokenKind::Match,
             "end" => TokenKind::End,
             "if" => TokenKind::If,
+            "while" => TokenKind::While,
             "true" | "false" => TokenKind::BoolLiteral,
             _ => TokenKind::Ident,
         };
@@ -392,10 +737,19 @@ impl Lexer {
         }
     }
 
+    /// Consume the current character, keeping line/column in sync
+    ///
+    /// All line bookkeeping happens here so that the first character on a new
+    /// line is always reported at column 1.
     fn advance(&mut self) -> char {
         let c = self.peek();
         self.position += 1;
-        self.column += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
         c
     }
 
@@ -404,22 +758,56 @@ impl Lexer {
     }
 }
 
+/// Strings are passed to the runtime as C strings, which end at a NUL
+const NUL_IN_STRING: &str = "String literals can't contain NUL (strings end at the first NUL byte)";
+
+/// Decode the character following a backslash in string and char literals,
+/// or `None` for an unknown escape
+///
+/// `\0` decodes to NUL, which is only allowed in char literals.
+fn unescape(c: char) -> Option<char> {
+    match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        '\\' => Some('\\'),
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        _ => None,
+    }
+}
+
+/// Radix and name of the integer literal with prefix `0` and then `letter`
+pub(crate) fn radix_of_prefix(letter: char) -> Option<(u32, &'static str)> {
+    match letter {
+        'x' => Some((16, "hex")),
+        'o' => Some((8, "octal")),
+        'b' => Some((2, "binary")),
+        _ => None,
+    }
+}
+
 fn is_operator_char(c: char) -> bool {
-    matches!(c, '+' | '-' | '*' | '/' | '<' | '>' | '=' | '!')
+    matches!(c, '+' | '-' | '*' | '/' | '%' | '<' | '>' | '=' | '!')
 }
 
 impl fmt::Display for TokenKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             TokenKind::IntLiteral => write!(f, "INT"),
+            TokenKind::FloatLiteral => write!(f, "FLOAT"),
+            TokenKind::CharLiteral => write!(f, "CHAR"),
             TokenKind::StringLiteral => write!(f, "STRING"),
             TokenKind::BoolLiteral => write!(f, "BOOL"),
+            TokenKind::Import => write!(f, "import"),
             TokenKind::Type => write!(f, "type"),
             TokenKind::Colon => write!(f, ":"),
             TokenKind::Pipe => write!(f, "|"),
             TokenKind::Match => write!(f, "match"),
             TokenKind::End => write!(f, "end"),
             TokenKind::If => write!(f, "if"),
+            TokenKind::While => write!(f, "while"),
             TokenKind::Arrow => write!(f, "=>"),
             TokenKind::LeftParen => write!(f, "("),
             TokenKind::RightParen => write!(f, ")"),
@@ -428,6 +816,7 @@ impl fmt::Display for TokenKind {
             TokenKind::Dash => write!(f, "--"),
             TokenKind::Comma => write!(f, ","),
             TokenKind::Ident => write!(f, "IDENT"),
+            TokenKind::Error => write!(f, "ERROR"),
             TokenKind::Eof => write!(f, "EOF"),
             TokenKind::Comment => write!(f, "COMMENT"),
         }
@@ -464,6 +853,152 @@ mod tests {
         assert_eq!(tokens[2].lexeme, "0");
     }
 
+    #[test]
+    fn test_minus_sign_or_subtraction() {
+        let lexemes = |input: &str| -> Vec<(TokenKind, String)> {
+            Lexer::new(input)
+                .tokenize()
+                .into_iter()
+                .filter(|t| t.kind != TokenKind::Eof)
+                .map(|t| (t.kind, t.lexeme))
+                .collect()
+        };
+        let int = |n: &str| (TokenKind::IntLiteral, n.to_string());
+        let word = |w: &str| (TokenKind::Ident, w.to_string());
+
+        assert_eq!(lexemes("-5"), vec![int("-5")]);
+        // Adjacent to a digit, `-` is a sign even after an operand
+        assert_eq!(lexemes("3 -5"), vec![int("3"), int("-5")]);
+        assert_eq!(lexemes("3 - 5"), vec![int("3"), word("-"), int("5")]);
+        assert_eq!(lexemes("3 5 -"), vec![int("3"), int("5"), word("-")]);
+        assert_eq!(lexemes("-x"), vec![word("-x")]);
+        assert_eq!(lexemes("-rot"), vec![word("-rot")]);
+    }
+
+    #[test]
+    fn test_words_starting_with_digits() {
+        let lexemes = |input: &str| -> Vec<(TokenKind, String)> {
+            Lexer::new(input)
+                .tokenize()
+                .into_iter()
+                .filter(|t| t.kind != TokenKind::Eof)
+                .map(|t| (t.kind, t.lexeme))
+                .collect()
+        };
+        let word = |w: &str| (TokenKind::Ident, w.to_string());
+
+        assert_eq!(lexemes("2dup 2drop"), vec![word("2dup"), word("2drop")]);
+        // An exponent still makes a number
+        assert_eq!(
+            lexemes("2e3"),
+            vec![(TokenKind::FloatLiteral, "2e3".to_string())]
+        );
+        assert_eq!(lexemes("2 dup")[0].0, TokenKind::IntLiteral);
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        let mut lexer = Lexer::new("1_000_000 -2_5 1__0 _5 7_");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].kind, TokenKind::IntLiteral);
+        assert_eq!(tokens[0].lexeme, "1_000_000");
+        assert_eq!(tokens[1].kind, TokenKind::IntLiteral);
+        assert_eq!(tokens[1].lexeme, "-2_5");
+        // Malformed separators are kept in the lexeme for the parser to reject
+        assert_eq!(tokens[2].kind, TokenKind::IntLiteral);
+        assert_eq!(tokens[2].lexeme, "1__0");
+        assert_eq!(tokens[3].kind, TokenKind::IntLiteral);
+        assert_eq!(tokens[3].lexeme, "_5");
+        assert_eq!(tokens[4].kind, TokenKind::IntLiteral);
+        assert_eq!(tokens[4].lexeme, "7_");
+    }
+
+    #[test]
+    fn test_radix_literals() {
+        let tokens = Lexer::new("0x1F -0o755 0b1010_0101 0 0x 0o78 0b102 0xfg").tokenize();
+        let kinds_and_lexemes: Vec<(TokenKind, &str)> = tokens
+            .iter()
+            .map(|t| (t.kind.clone(), t.lexeme.as_str()))
+            .collect();
+
+        assert_eq!(
+            kinds_and_lexemes[..4],
+            [
+                (TokenKind::IntLiteral, "0x1F"),
+                (TokenKind::IntLiteral, "-0o755"),
+                (TokenKind::IntLiteral, "0b1010_0101"),
+                (TokenKind::IntLiteral, "0"),
+            ]
+        );
+        // Bad digits are taken into the literal and reported with it
+        assert_eq!(
+            kinds_and_lexemes[4..8],
+            [
+                (TokenKind::Error, "Missing digits in hex literal '0x'"),
+                (
+                    TokenKind::Error,
+                    "Invalid digit '8' in octal literal '0o78'"
+                ),
+                (
+                    TokenKind::Error,
+                    "Invalid digit '2' in binary literal '0b102'"
+                ),
+                (TokenKind::Error, "Invalid digit 'g' in hex literal '0xfg'"),
+            ]
+        );
+        assert_eq!(tokens[8].kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn test_float_literals() {
+        let mut lexer = Lexer::new("3.14 -0.5 1e10 2.5E-3");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].kind, TokenKind::FloatLiteral);
+        assert_eq!(tokens[0].lexeme, "3.14");
+        assert_eq!(tokens[1].kind, TokenKind::FloatLiteral);
+        assert_eq!(tokens[1].lexeme, "-0.5");
+        assert_eq!(tokens[2].kind, TokenKind::FloatLiteral);
+        assert_eq!(tokens[2].lexeme, "1e10");
+        assert_eq!(tokens[3].kind, TokenKind::FloatLiteral);
+        assert_eq!(tokens[3].lexeme, "2.5E-3");
+    }
+
+    #[test]
+    fn test_dot_without_digit_is_not_decimal_point() {
+        let mut lexer = Lexer::new("1 . 2.x");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].kind, TokenKind::IntLiteral);
+        assert_eq!(tokens[0].lexeme, "1");
+        assert_eq!(tokens[1].kind, TokenKind::Ident);
+        assert_eq!(tokens[1].lexeme, ".");
+        assert_eq!(tokens[2].kind, TokenKind::IntLiteral);
+        assert_eq!(tokens[2].lexeme, "2");
+    }
+
+    #[test]
+    fn test_row_variable() {
+        let mut lexer = Lexer::new("( ..R Int -- ..R )");
+        let tokens = lexer.tokenize();
+        assert_eq!(tokens[1].kind, TokenKind::Ident);
+        assert_eq!(tokens[1].lexeme, "..R");
+        assert_eq!(tokens[1].column, 3);
+        assert_eq!(tokens[4].lexeme, "..R");
+    }
+
+    #[test]
+    fn test_malformed_exponent() {
+        let mut lexer = Lexer::new("1e 2.0e+");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].kind, TokenKind::Error);
+        assert!(tokens[0].lexeme.contains("exponent"));
+        assert_eq!(tokens[1].kind, TokenKind::Error);
+        assert!(tokens[1].lexeme.contains("2.0e+"));
+    }
+
     #[test]
     fn test_strings() {
         let mut lexer = Lexer::new(r#""hello" "world\n""#);
@@ -475,15 +1010,101 @@ mod tests {
         assert_eq!(tokens[1].lexeme, "world\n");
     }
 
+    #[test]
+    fn test_unicode_escapes() {
+        let tokens = Lexer::new(r#""smile \u{1F600}!" "\u{e9}\u{41}" '\u{3bb}'"#).tokenize();
+
+        assert_eq!(tokens[0].kind, TokenKind::StringLiteral);
+        assert_eq!(tokens[0].lexeme, "smile 😀!");
+        assert_eq!(tokens[1].lexeme, "éA");
+        assert_eq!(tokens[2].kind, TokenKind::CharLiteral);
+        assert_eq!(tokens[2].lexeme, "λ");
+    }
+
+    #[test]
+    fn test_invalid_unicode_escapes() {
+        let cases = [
+            (r#""\u0041""#, "Expected '{'"),
+            (r#""\u{41""#, "closing '}'"),
+            (r#""\u{4g}""#, "closing '}'"),
+            (r#""\u{}""#, "1 to 6 hex digits"),
+            (r#""\u{1234567}""#, "1 to 6 hex digits"),
+            (r#""\u{D800}""#, "not a valid code point"),
+            (r#""\u{110000}""#, "not a valid code point"),
+            (r"'\u{D800}'", "not a valid code point"),
+        ];
+
+        for (input, expected) in cases {
+            let source = format!("1 {} \"after \\\" quote\" 2", input);
+            let tokens = Lexer::new(&source).tokenize();
+            assert_eq!(tokens[1].kind, TokenKind::Error, "{}", input);
+            assert!(
+                tokens[1].lexeme.contains(expected),
+                "{}: {}",
+                input,
+                tokens[1].lexeme
+            );
+            // Reported at the backslash, and the rest of the literal is skipped
+            assert_eq!(tokens[1].column, 4, "{}", input);
+            assert_eq!(tokens[2].kind, TokenKind::StringLiteral, "{}", input);
+            assert_eq!(tokens[3].lexeme, "2", "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_unknown_escapes_are_rejected() {
+        for (input, escape) in [
+            (r#""a\qb""#, r"\q"),
+            (r"'\q'", r"\q"),
+            (r#""a\x41""#, r"\x"),
+        ] {
+            let source = format!("1 {} \"after\" 2", input);
+            let tokens = Lexer::new(&source).tokenize();
+            assert_eq!(tokens[1].kind, TokenKind::Error, "{}", input);
+            assert_eq!(
+                tokens[1].lexeme,
+                format!("Unknown escape sequence '{}'", escape)
+            );
+            assert_eq!(tokens[2].lexeme, "after", "{}", input);
+        }
+
+        // A backslash before a line break doesn't continue the string
+        let tokens = Lexer::new("\"a\\\nb\"").tokenize();
+        assert_eq!(tokens[0].kind, TokenKind::Error);
+        assert!(tokens[0].lexeme.contains("Unterminated"));
+    }
+
+    #[test]
+    fn test_nul_escape_in_char_literal() {
+        let tokens = Lexer::new(r"'\0' '\''").tokenize();
+        assert_eq!(tokens[0].kind, TokenKind::CharLiteral);
+        assert_eq!(tokens[0].lexeme, "\0");
+        assert_eq!(tokens[1].lexeme, "'");
+    }
+
+    #[test]
+    fn test_nul_in_string_literal_is_rejected() {
+        for input in [r#""a\0b""#, r#""a\u{0}b""#, "\"a\0b\""] {
+            let source = format!("1 {} \"after\" 2", input);
+            let tokens = Lexer::new(&source).tokenize();
+            assert_eq!(tokens[1].kind, TokenKind::Error, "{:?}", input);
+            assert_eq!(tokens[1].lexeme, NUL_IN_STRING);
+            assert_eq!(tokens[1].column, 5, "{:?}", input);
+            assert_eq!(tokens[2].lexeme, "after", "{:?}", input);
+        }
+    }
+
     #[test]
     fn test_operators() {
-        let mut lexer = Lexer::new("+ - * / < > = dup");
+        let mut lexer = Lexer::new("+ - * / % < > = dup");
         let tokens = lexer.tokenize();
 
         assert_eq!(tokens[0].kind, TokenKind::Ident);
         assert_eq!(tokens[0].lexeme, "+");
-        assert_eq!(tokens[7].kind, TokenKind::Ident);
-        assert_eq!(tokens[7].lexeme, "dup");
+        assert_eq!(tokens[4].kind, TokenKind::Ident);
+        assert_eq!(tokens[4].lexeme, "%");
+        assert_eq!(tokens[8].kind, TokenKind::Ident);
+        assert_eq!(tokens[8].lexeme, "dup");
     }
 
     #[test]
@@ -495,13 +1116,63 @@ mod tests {
         assert_eq!(tokens[0].lexeme, "42");
     }
 
+    #[test]
+    fn test_char_literals() {
+        let mut lexer = Lexer::new(r"'A' '\n' '\'' '\\' 'é'");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].kind, TokenKind::CharLiteral);
+        assert_eq!(tokens[0].lexeme, "A");
+        assert_eq!(tokens[1].lexeme, "\n");
+        assert_eq!(tokens[2].lexeme, "'");
+        assert_eq!(tokens[3].lexeme, "\\");
+        assert_eq!(tokens[4].lexeme, "é");
+        assert_eq!(tokens[4].column, 20);
+    }
+
+    #[test]
+    fn test_unterminated_char_literal() {
+        let mut lexer = Lexer::new("'a\n42");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].kind, TokenKind::Error);
+        assert!(tokens[0].lexeme.contains("Unterminated character"));
+        assert_eq!(tokens[1].kind, TokenKind::IntLiteral);
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let mut lexer = Lexer::new("1 #| one\n #| nested |# \n two |# 2\n3");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].lexeme, "1");
+        assert_eq!(tokens[0].line, 1);
+        assert_eq!(tokens[1].kind, TokenKind::IntLiteral);
+        assert_eq!(tokens[1].lexeme, "2");
+        assert_eq!(tokens[1].line, 3);
+        assert_eq!(tokens[2].lexeme, "3");
+        assert_eq!(tokens[2].line, 4);
+        assert_eq!(tokens[3].kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let mut lexer = Lexer::new("1\n  #| outer #| inner |# \n never closed");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0].lexeme, "1");
+        assert_eq!(tokens[1].kind, TokenKind::Error);
+        assert!(tokens[1].lexeme.contains("Unterminated block comment"));
+        assert_eq!(tokens[1].line, 2);
+        assert_eq!(tokens[2].kind, TokenKind::Eof);
+    }
+
     #[test]
     fn test_unterminated_string_newline() {
         let mut lexer = Lexer::new("\"hello\n");
         let tokens = lexer.tokenize();
 
-        // Should get an error token
-        assert!(tokens[0].lexeme.starts_with("ERROR"));
+        assert_eq!(tokens[0].kind, TokenKind::Error);
         assert!(tokens[0].lexeme.contains("Unterminated"));
     }
 
@@ -510,8 +1181,7 @@ mod tests {
         let mut lexer = Lexer::new("\"hello");
         let tokens = lexer.tokenize();
 
-        // Should get an error token
-        assert!(tokens[0].lexeme.starts_with("ERROR"));
+        assert_eq!(tokens[0].kind, TokenKind::Error);
         assert!(tokens[0].lexeme.contains("Unterminated"));
     }
 
@@ -537,9 +1207,20 @@ mod tests {
         assert_eq!(tokens[2].line, 3);
     }
 
+    #[test]
+    fn test_first_token_on_line_is_column_one() {
+        let mut lexer = Lexer::new("dup\nswap\n  drop #| a\n |#over");
+        let tokens = lexer.tokenize();
+
+        assert_eq!((tokens[0].line, tokens[0].column), (1, 1));
+        assert_eq!((tokens[1].line, tokens[1].column), (2, 1));
+        assert_eq!((tokens[2].line, tokens[2].column), (3, 3));
+        assert_eq!((tokens[3].line, tokens[3].column), (4, 4));
+    }
+
     #[test]
     fn test_max_string_length() {
-        // Create a string that exceeds MAX_STRING_LENGTH (1MB)
+        // Create a string that exceeds the default limit (1MB)
         let mut input = String::from("\"");
         // Add 1,000,001 characters (exceeds 1MB limit)
         for _ in 0..1_000_001 {
@@ -550,8 +1231,15 @@ mod tests {
         let mut lexer = Lexer::new(&input);
         let tokens = lexer.tokenize();
 
-        // Should get an error token
-        assert!(tokens[0].lexeme.starts_with("ERROR"));
+        assert_eq!(tokens[0].kind, TokenKind::Error);
         assert!(tokens[0].lexeme.contains("maximum length"));
+
+        // Within a raised limit
+        let tokens = Lexer::with_max_string_length(&input, 2_000_000).tokenize();
+        assert_eq!(tokens[0].kind, TokenKind::StringLiteral);
+
+        let tokens = Lexer::with_max_string_length("\"hello\"", 4).tokenize();
+        assert_eq!(tokens[0].kind, TokenKind::Error);
+        assert!(tokens[0].lexeme.contains("maximum length of 4 bytes"));
     }
 }
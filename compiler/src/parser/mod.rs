@@ -4,8 +4,8 @@
 mod lexer;
 mod parse;
 
-pub use lexer::{Lexer, Token, TokenKind};
-pub use parse::{ParseError, Parser};
+pub use lexer::{DEFAULT_MAX_STRING_LENGTH, Lexer, Token, TokenKind};
+pub use parse::{DEFAULT_MAX_NESTING_DEPTH, ParseError, ParseLimits, Parser};
 
 #[cfg(test)]
 mod tests;
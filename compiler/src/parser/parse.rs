@@ -1,8 +1,13 @@
 /// Recursive descent parser for Cem
-use crate::ast::types::{Effect, Type};
-use crate::ast::{Expr, MatchBranch, Pattern, Program, TypeDef, Variant, WordDef};
-use crate::parser::lexer::{Lexer, Token, TokenKind};
+use crate::ast::format::format_char;
+use crate::ast::types::{Effect, StackType, Type};
+use crate::ast::{
+    Expr, Import, MatchBranch, Pattern, Program, SourceLoc, TypeDef, Variant, WordDef,
+};
+use crate::parser::lexer::{DEFAULT_MAX_STRING_LENGTH, Lexer, Token, TokenKind, radix_of_prefix};
+use std::collections::HashMap;
 use std::fmt;
+use std::num::IntErrorKind;
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
@@ -22,16 +27,66 @@ impl fmt::Display for ParseError {
     }
 }
 
+impl ParseError {
+    /// Render the error with the offending line of `source` and a `^` under
+    /// its column, like rustc
+    ///
+    /// `source` must be the text that was parsed. If the error's line isn't
+    /// in it (an error at the end of input), only the message is rendered.
+    pub fn render(&self, source: &str) -> String {
+        let Some(text) = self.line.checked_sub(1).and_then(|i| source.lines().nth(i)) else {
+            return self.to_string();
+        };
+
+        // Keep tabs before the column, so the caret lines up however they're shown
+        let indent: String = text
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(self.line.to_string().len());
+
+        format!(
+            "{}\n{} |\n{} | {}\n{} | {}^",
+            self, gutter, self.line, text, gutter, indent
+        )
+    }
+}
+
 impl std::error::Error for ParseError {}
 
-const MAX_NESTING_DEPTH: usize = 100;
+/// How deeply quotations and other blocks may nest, unless the parser is given another limit
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 100;
+
+/// Limits that keep hostile or runaway input from exhausting the compiler
+///
+/// The defaults suit hand-written code; machine-generated code may need more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// How deeply quotations and other blocks may nest
+    pub max_nesting_depth: usize,
+    /// Longest string literal, in bytes
+    pub max_string_length: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits {
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            max_string_length: DEFAULT_MAX_STRING_LENGTH,
+        }
+    }
+}
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     nesting_depth: usize,
+    max_nesting_depth: usize,
     /// Arc-wrapped filename to avoid duplication across all SourceLocs
     filename: Arc<str>,
+    /// How the number and character literals parsed so far were written
+    literals: HashMap<SourceLoc, String>,
 }
 
 impl Parser {
@@ -40,13 +95,31 @@ impl Parser {
     }
 
     pub fn new_with_filename(input: &str, filename: &str) -> Self {
-        let mut lexer = Lexer::new(input);
+        Self::with_limits(input, filename, ParseLimits::default())
+    }
+
+    /// Create a parser that allows blocks to nest `depth` deep
+    ///
+    /// Machine-generated code may nest quotations deeper than the default allows.
+    pub fn with_max_depth(input: &str, filename: &str, depth: usize) -> Self {
+        let limits = ParseLimits {
+            max_nesting_depth: depth,
+            ..ParseLimits::default()
+        };
+        Self::with_limits(input, filename, limits)
+    }
+
+    /// Create a parser with every limit given
+    pub fn with_limits(input: &str, filename: &str, limits: ParseLimits) -> Self {
+        let mut lexer = Lexer::with_max_string_length(input, limits.max_string_length);
         let tokens = lexer.tokenize();
         Parser {
             tokens,
             current: 0,
             nesting_depth: 0,
+            max_nesting_depth: limits.max_nesting_depth,
             filename: Arc::from(filename),
+            literals: HashMap::new(),
         }
     }
 
@@ -56,32 +129,113 @@ impl Parser {
         crate::ast::SourceLoc::new(token.line, token.column, Arc::clone(&self.filename))
     }
 
+    /// Note how the literal at the current token was written, for the
+    /// formatter, and return its location
+    fn record_literal(&mut self, written: String) -> SourceLoc {
+        let loc = self.current_loc();
+        self.literals.insert(loc.clone(), written);
+        loc
+    }
+
     /// Helper: Create SourceLoc from a specific token
     fn loc_from_token(&self, token: &Token) -> crate::ast::SourceLoc {
         crate::ast::SourceLoc::new(token.line, token.column, Arc::clone(&self.filename))
     }
 
     pub fn parse(&mut self) -> Result<Program, ParseError> {
+        self.parse_all().map_err(|mut errors| errors.swap_remove(0))
+    }
+
+    /// Parse the whole input as expressions rather than definitions, such as
+    /// a line typed at the REPL
+    pub fn parse_expressions(&mut self) -> Result<Vec<Expr>, ParseError> {
+        let mut exprs = Vec::new();
+        while !self.is_at_end() {
+            exprs.push(self.parse_expr()?);
+        }
+        Ok(exprs)
+    }
+
+    /// Parse the whole input, reporting every definition that fails to parse
+    /// rather than only the first
+    ///
+    /// After an error the parser skips past the next `;`, or to the next `:`,
+    /// `type` or `import`, and carries on with the definition after it. A match's `end`
+    /// isn't a boundary: the word it's in carries on after it.
+    ///
+    /// Imports must come before any definition.
+    pub fn parse_all(&mut self) -> Result<Program, Vec<ParseError>> {
+        let mut imports = Vec::new();
         let mut type_defs = Vec::new();
         let mut word_defs = Vec::new();
+        let mut errors = Vec::new();
 
         while !self.is_at_end() {
-            if self.check(&TokenKind::Type) {
-                type_defs.push(self.parse_type_def()?);
+            let start = self.current;
+            let result = if self.check(&TokenKind::Import) {
+                if type_defs.is_empty() && word_defs.is_empty() {
+                    self.parse_import().map(|import| imports.push(import))
+                } else {
+                    Err(self.error("Imports must come before definitions"))
+                }
+            } else if self.check(&TokenKind::Type) {
+                self.parse_type_def().map(|def| type_defs.push(def))
             } else if self.check(&TokenKind::Colon) {
-                word_defs.push(self.parse_word_def()?);
+                self.parse_word_def().map(|def| word_defs.push(def))
             } else {
-                return Err(self.error("Expected 'type' or ':'"));
+                Err(self.error("Expected 'type' or ':'"))
+            };
+
+            if let Err(e) = result {
+                errors.push(e);
+                self.skip_definition(start);
             }
         }
 
-        Ok(Program {
-            type_defs,
-            word_defs,
-        })
+        if errors.is_empty() {
+            Ok(Program {
+                imports,
+                type_defs,
+                word_defs,
+                literals: std::mem::take(&mut self.literals),
+            })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Skip the rest of a definition that failed to parse, started at token `start`
+    fn skip_definition(&mut self, start: usize) {
+        self.nesting_depth = 0;
+        if self.current == start {
+            self.advance();
+        }
+
+        while !self.is_at_end()
+            && !self.check(&TokenKind::Colon)
+            && !self.check(&TokenKind::Type)
+            && !self.check(&TokenKind::Import)
+        {
+            let ends_word = self.check_ident(";");
+            self.advance();
+            if ends_word {
+                break;
+            }
+        }
+    }
+
+    fn parse_import(&mut self) -> Result<Import, ParseError> {
+        let loc = self.current_loc();
+        self.consume(&TokenKind::Import, "Expected 'import'")?;
+        let path = self
+            .consume(&TokenKind::StringLiteral, "Expected a file path in quotes")?
+            .lexeme
+            .clone();
+        Ok(Import { path, loc })
     }
 
     fn parse_type_def(&mut self) -> Result<TypeDef, ParseError> {
+        let loc = self.current_loc();
         self.consume(&TokenKind::Type, "Expected 'type'")?;
 
         let name = self.consume_ident("Expected type name")?;
@@ -92,7 +246,9 @@ impl Parser {
             self.advance();
             while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
                 type_params.push(self.consume_ident("Expected type parameter")?);
-                if self.check(&TokenKind::RightParen) {
+                if self.check(&TokenKind::Comma) {
+                    self.advance();
+                } else {
                     break;
                 }
             }
@@ -104,6 +260,7 @@ impl Parser {
         // Parse variants
         let mut variants = Vec::new();
         loop {
+            let variant_loc = self.current_loc();
             let variant_name = self.consume_ident("Expected variant name")?;
 
             // Parse variant fields (optional)
@@ -127,6 +284,7 @@ impl Parser {
             variants.push(Variant {
                 name: variant_name,
                 fields,
+                loc: variant_loc,
             });
 
             // Check for more variants
@@ -141,6 +299,7 @@ impl Parser {
             name,
             type_params,
             variants,
+            loc,
         })
     }
 
@@ -152,7 +311,7 @@ impl Parser {
 
         // Parse effect signature
         self.consume(&TokenKind::LeftParen, "Expected '(' for effect signature")?;
-        let effect = self.parse_effect()?;
+        let effect = self.parse_effect(&TokenKind::RightParen, "effect signature")?;
         self.consume(
             &TokenKind::RightParen,
             "Expected ')' after effect signature",
@@ -174,22 +333,101 @@ impl Parser {
         })
     }
 
-    fn parse_effect(&mut self) -> Result<Effect, ParseError> {
+    /// Parse `inputs -- outputs` up to (not including) `close`, which ends
+    /// a word's signature or a quotation type
+    ///
+    /// Either side may start with a row variable, `..R`, naming the rest of
+    /// the stack beneath its types. The `..` is what sets it apart from a type
+    /// variable: in `( ..R A -- ..R A A )`, `R` is any number of values and `A`
+    /// is exactly one. A side without one sits on an empty base.
+    fn parse_effect(&mut self, close: &TokenKind, context: &str) -> Result<Effect, ParseError> {
         // Parse input stack types
-        let mut inputs = Vec::new();
-        while !self.check(&TokenKind::Dash) && !self.is_at_end() {
-            inputs.push(self.parse_type()?);
+        let mut inputs = self.parse_row_variable()?;
+        while !self.check(&TokenKind::Dash) && !self.check(close) && !self.is_at_end() {
+            inputs = inputs.push(self.parse_type()?);
         }
 
-        self.consume(&TokenKind::Dash, "Expected '--' in effect signature")?;
+        self.consume(&TokenKind::Dash, &format!("Expected '--' in {}", context))?;
 
         // Parse output stack types
-        let mut outputs = Vec::new();
-        while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
-            outputs.push(self.parse_type()?);
+        let mut outputs = self.parse_row_variable()?;
+        while !self.check(close) && !self.is_at_end() {
+            outputs = outputs.push(self.parse_type()?);
+        }
+
+        Ok(Effect::new(inputs, outputs))
+    }
+
+    /// Parse the base of one side of an effect: a row variable if one is written
+    fn parse_row_variable(&mut self) -> Result<StackType, ParseError> {
+        let Some(name) = self.peek().lexeme.strip_prefix("..") else {
+            return Ok(StackType::Empty);
+        };
+        if !name.starts_with(|c: char| c.is_alphabetic()) {
+            return Err(self.error("Expected a row variable name after '..'"));
+        }
+
+        let row = StackType::RowVar(name.to_string());
+        self.advance();
+        Ok(row)
+    }
+
+    /// Parse optional field patterns after a variant name in a match pattern,
+    /// e.g. `(x)` in `Some(x) =>` or `(Some(x), _)` in `Cons(Some(x), _) =>`
+    ///
+    /// A capitalized name is a variant the field must hold, which may have
+    /// field patterns of its own; any other name binds the field. `bound`
+    /// collects the names bound so far in the whole pattern.
+    fn parse_pattern_fields(
+        &mut self,
+        bound: &mut Vec<String>,
+    ) -> Result<Vec<Pattern>, ParseError> {
+        let mut fields = Vec::new();
+        if !self.check(&TokenKind::LeftParen) {
+            return Ok(fields);
+        }
+        self.advance();
+
+        loop {
+            if self.check_ident("_") {
+                self.advance();
+                fields.push(Pattern::Wildcard);
+            } else if self.peek().kind == TokenKind::Ident
+                && self
+                    .peek()
+                    .lexeme
+                    .starts_with(|c: char| c.is_ascii_uppercase())
+            {
+                let name = self.consume_ident("Expected variant name")?;
+                self.enter_nesting()?;
+                let nested = self.parse_pattern_fields(bound);
+                self.exit_nesting();
+                fields.push(Pattern::Variant {
+                    name,
+                    fields: nested?,
+                });
+            } else {
+                if self.peek().kind == TokenKind::Ident && bound.contains(&self.peek().lexeme) {
+                    let message = format!("Duplicate binding '{}' in pattern", self.peek().lexeme);
+                    return Err(self.error(&message));
+                }
+                let name = self.consume_ident("Expected binding name or pattern")?;
+                bound.push(name.clone());
+                fields.push(Pattern::Binding(name));
+            }
+
+            if self.check(&TokenKind::Comma) {
+                self.advance();
+            } else {
+                break;
+            }
         }
+        self.consume(
+            &TokenKind::RightParen,
+            "Expected ')' after pattern bindings",
+        )?;
 
-        Ok(Effect::from_vecs(inputs, outputs))
+        Ok(fields)
     }
 
     fn parse_type(&mut self) -> Result<Type, ParseError> {
@@ -203,37 +441,26 @@ impl Parser {
         // Check for quotation type: [ inputs -- outputs ]
         if self.check(&TokenKind::LeftBracket) {
             self.advance(); // consume '['
-
-            // Parse input stack types
-            let mut inputs = Vec::new();
-            while !self.check(&TokenKind::Dash) && !self.is_at_end() {
-                inputs.push(self.parse_type()?);
-            }
-
-            // Consume '--'
-            self.consume(&TokenKind::Dash, "Expected '--' in quotation type")?;
-
-            // Parse output stack types
-            let mut outputs = Vec::new();
-            while !self.check(&TokenKind::RightBracket) && !self.is_at_end() {
-                outputs.push(self.parse_type()?);
-            }
-
-            // Consume ']'
+            let effect = self.parse_effect(&TokenKind::RightBracket, "quotation type")?;
             self.consume(
                 &TokenKind::RightBracket,
                 "Expected ']' after quotation type",
             )?;
 
-            Ok(Type::Quotation(Box::new(Effect::from_vecs(
-                inputs, outputs,
-            ))))
+            Ok(Type::Quotation(Box::new(effect)))
+        } else if self.peek().lexeme.starts_with("..") {
+            let message = format!(
+                "Row variable '{}' must come first, beneath every other type",
+                self.peek().lexeme
+            );
+            Err(self.error(&message))
         } else {
             let name = self.consume_ident("Expected type name")?;
 
             match name.as_str() {
                 "Int" => Ok(Type::Int),
                 "Bool" => Ok(Type::Bool),
+                "Float" => Ok(Type::Float),
                 "String" => Ok(Type::String),
                 _ => {
                     // Check if it's a generic type variable (single uppercase letter or starts with lowercase)
@@ -278,17 +505,44 @@ impl Parser {
     fn parse_expr_inner(&mut self) -> Result<Expr, ParseError> {
         match &self.peek().kind {
             TokenKind::IntLiteral => {
-                let value = self.peek().lexeme.parse::<i64>().map_err(|_| {
+                let value = self.int_literal_value()?;
+                let loc = self.record_literal(self.peek().lexeme.clone());
+                self.advance();
+                Ok(Expr::IntLit(value, loc))
+            }
+
+            TokenKind::FloatLiteral => {
+                let digits = self.strip_digit_separators(self.peek())?;
+                let value = digits.parse::<f64>().map_err(|_| {
                     let token = self.peek();
                     ParseError {
-                        message: format!("Invalid integer: {}", token.lexeme),
+                        message: format!("Invalid float: {}", token.lexeme),
                         line: token.line,
                         column: token.column,
                     }
                 })?;
-                let loc = self.current_loc();
+                let loc = self.record_literal(self.peek().lexeme.clone());
                 self.advance();
-                Ok(Expr::IntLit(value, loc))
+                Ok(Expr::FloatLit(value, loc))
+            }
+
+            TokenKind::CharLiteral => {
+                // Character literals lower to their Unicode scalar value
+                let token = self.peek();
+                let mut chars = token.lexeme.chars();
+                let c = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    (None, _) => return Err(self.error("Empty character literal")),
+                    (Some(_), Some(_)) => {
+                        return Err(self.error(&format!(
+                            "Character literal must contain exactly one character, found '{}'",
+                            token.lexeme
+                        )));
+                    }
+                };
+                let loc = self.record_literal(format_char(c));
+                self.advance();
+                Ok(Expr::IntLit(c as i64, loc))
             }
 
             TokenKind::BoolLiteral => {
@@ -322,7 +576,15 @@ impl Parser {
                 let mut branches = Vec::new();
 
                 while !self.check(&TokenKind::End) && !self.is_at_end() {
-                    let variant_name = self.consume_ident("Expected variant name")?;
+                    if branches
+                        .last()
+                        .is_some_and(|b: &MatchBranch| b.pattern == Pattern::Wildcard)
+                    {
+                        return Err(self.error("Unreachable pattern after wildcard '_'"));
+                    }
+
+                    let pattern_loc = self.current_loc();
+                    let pattern = self.parse_pattern(&branches)?;
                     self.consume(&TokenKind::Arrow, "Expected '=>'")?;
 
                     // Parse branch body (quotation)
@@ -334,8 +596,9 @@ impl Parser {
                     self.consume(&TokenKind::RightBracket, "Expected ']'")?;
 
                     branches.push(MatchBranch {
-                        pattern: Pattern::Variant { name: variant_name },
+                        pattern,
                         body,
+                        loc: pattern_loc,
                     });
                 }
 
@@ -347,7 +610,7 @@ impl Parser {
                 let loc = self.current_loc();
                 self.advance(); // consume 'if'
 
-                // Expect two quotations: then-branch and else-branch
+                // Expect a then-branch quotation and, optionally, an else-branch
                 let then_loc = self.current_loc();
                 self.consume(&TokenKind::LeftBracket, "Expected '[' for then branch")?;
                 let mut then_exprs = Vec::new();
@@ -356,13 +619,18 @@ impl Parser {
                 }
                 self.consume(&TokenKind::RightBracket, "Expected ']'")?;
 
+                // Without an else-branch, the else does nothing, so the
+                // then-branch must leave the stack as it found it. A `[`
+                // straight after the then-branch always starts the else.
                 let else_loc = self.current_loc();
-                self.consume(&TokenKind::LeftBracket, "Expected '[' for else branch")?;
                 let mut else_exprs = Vec::new();
-                while !self.check(&TokenKind::RightBracket) && !self.is_at_end() {
-                    else_exprs.push(self.parse_expr()?);
+                if self.check(&TokenKind::LeftBracket) {
+                    self.advance();
+                    while !self.check(&TokenKind::RightBracket) && !self.is_at_end() {
+                        else_exprs.push(self.parse_expr()?);
+                    }
+                    self.consume(&TokenKind::RightBracket, "Expected ']'")?;
                 }
-                self.consume(&TokenKind::RightBracket, "Expected ']'")?;
 
                 Ok(Expr::If {
                     then_branch: Box::new(Expr::Quotation(then_exprs, then_loc)),
@@ -371,6 +639,36 @@ impl Parser {
                 })
             }
 
+            TokenKind::While => {
+                let loc = self.current_loc();
+                self.advance(); // consume 'while'
+
+                // Both the condition and the body are required quotations
+                let cond_loc = self.current_loc();
+                self.consume(&TokenKind::LeftBracket, "Expected '[' for while condition")?;
+                let mut cond_exprs = Vec::new();
+                while !self.check(&TokenKind::RightBracket) && !self.is_at_end() {
+                    cond_exprs.push(self.parse_expr()?);
+                }
+                self.consume(&TokenKind::RightBracket, "Expected ']'")?;
+
+                let body_loc = self.current_loc();
+                self.consume(&TokenKind::LeftBracket, "Expected '[' for while body")?;
+                let mut body_exprs = Vec::new();
+                while !self.check(&TokenKind::RightBracket) && !self.is_at_end() {
+                    body_exprs.push(self.parse_expr()?);
+                }
+                self.consume(&TokenKind::RightBracket, "Expected ']'")?;
+
+                Ok(Expr::While {
+                    cond: Box::new(Expr::Quotation(cond_exprs, cond_loc)),
+                    body: Box::new(Expr::Quotation(body_exprs, body_loc)),
+                    loc,
+                })
+            }
+
+            TokenKind::Error => Err(self.error("Invalid token")),
+
             TokenKind::Ident => {
                 let name = self.peek().lexeme.clone();
                 let loc = self.current_loc();
@@ -391,6 +689,33 @@ impl Parser {
 
     // Helper methods
 
+    /// Remove `_` digit separators from a number literal
+    ///
+    /// A separator is only valid between two digits, so `1_000` is accepted
+    /// while `_5`, `5_`, and `1__0` are rejected with the separator's column.
+    fn strip_digit_separators(&self, token: &Token) -> Result<String, ParseError> {
+        let chars: Vec<char> = token.lexeme.chars().collect();
+        let (radix, digits_start) = radix_and_digits_start(&token.lexeme);
+        for (i, &c) in chars.iter().enumerate() {
+            if c != '_' {
+                continue;
+            }
+            let after_digit = i > digits_start && chars[i - 1].is_digit(radix);
+            let before_digit = chars.get(i + 1).is_some_and(|n| n.is_digit(radix));
+            if !after_digit || !before_digit {
+                return Err(ParseError {
+                    message: format!(
+                        "Invalid digit separator in number literal '{}': '_' must appear between digits",
+                        token.lexeme
+                    ),
+                    line: token.line,
+                    column: token.column + i,
+                });
+            }
+        }
+        Ok(token.lexeme.replace('_', ""))
+    }
+
     fn peek(&self) -> &Token {
         &self.tokens[self.current]
     }
@@ -429,6 +754,80 @@ impl Parser {
         }
     }
 
+    /// Parse a match branch's pattern
+    ///
+    /// A match's patterns are all literals of one type, or all variants, apart
+    /// from the wildcard; a literal can't repeat an earlier branch's.
+    fn parse_pattern(&mut self, branches: &[MatchBranch]) -> Result<Pattern, ParseError> {
+        if self.check_ident("_") {
+            self.advance();
+            return Ok(Pattern::Wildcard);
+        }
+
+        let literal = if self.check(&TokenKind::IntLiteral) {
+            Some(Pattern::IntLit(self.int_literal_value()?))
+        } else if self.check(&TokenKind::BoolLiteral) {
+            Some(Pattern::BoolLit(self.peek().lexeme == "true"))
+        } else {
+            None
+        };
+
+        let kind = literal.as_ref().and_then(pattern_kind).unwrap_or("variant");
+        if let Some(other) = branches
+            .iter()
+            .filter_map(|b| pattern_kind(&b.pattern))
+            .find(|other| *other != kind)
+        {
+            let mut kinds = [other, kind];
+            kinds.sort_by_key(|kind| PATTERN_KINDS.iter().position(|k| k == kind));
+            return Err(self.error(&format!(
+                "Cannot mix {} and {} patterns in one match",
+                kinds[0], kinds[1]
+            )));
+        }
+
+        match literal {
+            Some(literal) => {
+                if branches.iter().any(|b| b.pattern == literal) {
+                    return Err(self.error(&format!("Duplicate pattern '{}'", literal)));
+                }
+                if self.check(&TokenKind::IntLiteral) {
+                    self.record_literal(self.peek().lexeme.clone());
+                }
+                self.advance();
+                Ok(literal)
+            }
+            None => {
+                let name = self.consume_ident("Expected variant name")?;
+                let fields = self.parse_pattern_fields(&mut Vec::new())?;
+                Ok(Pattern::Variant { name, fields })
+            }
+        }
+    }
+
+    /// Value of the integer literal at the current token (does not advance)
+    fn int_literal_value(&self) -> Result<i64, ParseError> {
+        let literal = self.strip_digit_separators(self.peek())?;
+        let (radix, digits_start) = radix_and_digits_start(&literal);
+        // Keep the sign, so the most negative Int can be written
+        let sign = if literal.starts_with('-') { "-" } else { "" };
+        let digits = format!("{}{}", sign, &literal[digits_start..]);
+        i64::from_str_radix(&digits, radix).map_err(|e| {
+            let token = self.peek();
+            let message = match e.kind() {
+                IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                    format!("Integer literal '{}' does not fit in i64", token.lexeme)
+                }
+                _ => format!("Invalid integer: {}", token.lexeme),
+            };
+            ParseError {
+                message,
+                line: token.line,
+                column: token.column,
+            }
+        })
+    }
+
     fn consume_ident(&mut self, message: &str) -> Result<String, ParseError> {
         if self.peek().kind == TokenKind::Ident {
             let lexeme = self.peek().lexeme.clone();
@@ -448,10 +847,20 @@ impl Parser {
         }
     }
 
+    /// Build a ParseError at the current token
+    ///
+    /// If the lexer flagged the current token as an error (e.g. an unterminated
+    /// string), its message is more useful than whatever the parser expected
+    /// here, so it takes precedence.
     fn error(&self, message: &str) -> ParseError {
         let token = self.peek();
+        let message = if token.kind == TokenKind::Error {
+            token.lexeme.clone()
+        } else {
+            message.to_string()
+        };
         ParseError {
-            message: message.to_string(),
+            message,
             line: token.line,
             column: token.column,
         }
@@ -459,9 +868,12 @@ impl Parser {
 
     fn enter_nesting(&mut self) -> Result<(), ParseError> {
         self.nesting_depth += 1;
-        if self.nesting_depth > MAX_NESTING_DEPTH {
+        if self.nesting_depth > self.max_nesting_depth {
             Err(ParseError {
-                message: format!("Maximum nesting depth of {} exceeded", MAX_NESTING_DEPTH),
+                message: format!(
+                    "Maximum nesting depth of {} exceeded",
+                    self.max_nesting_depth
+                ),
                 line: self.peek().line,
                 column: self.peek().column,
             })
@@ -475,6 +887,30 @@ impl Parser {
     }
 }
 
+/// Kinds of pattern that can't share a match, in the order errors name them
+const PATTERN_KINDS: [&str; 3] = ["integer", "boolean", "variant"];
+
+/// Which of `PATTERN_KINDS` a pattern is, if it's not the wildcard
+fn pattern_kind(pattern: &Pattern) -> Option<&'static str> {
+    match pattern {
+        Pattern::IntLit(_) => Some(PATTERN_KINDS[0]),
+        Pattern::BoolLit(_) => Some(PATTERN_KINDS[1]),
+        Pattern::Variant { .. } => Some(PATTERN_KINDS[2]),
+        Pattern::Binding(_) | Pattern::Wildcard => None,
+    }
+}
+
+/// Radix of a number literal and where its digits start, after any sign and
+/// `0x`-style prefix
+fn radix_and_digits_start(lexeme: &str) -> (u32, usize) {
+    let sign = usize::from(lexeme.starts_with('-'));
+    let mut rest = lexeme[sign..].chars();
+    match (rest.next(), rest.next().and_then(radix_of_prefix)) {
+        (Some('0'), Some((radix, _))) => (radix, sign + 2),
+        _ => (10, sign),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -515,6 +951,194 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_digit_separators() {
+        let input = ": test ( -- Int Int ) 1_000_000 -2_500 ;";
+        let mut parser = Parser::new(input);
+        let program = parser.parse().unwrap();
+
+        match &program.word_defs[0].body[..] {
+            [Expr::IntLit(1_000_000, _), Expr::IntLit(-2_500, _)] => (),
+            other => panic!("Expected IntLit(1000000) IntLit(-2500), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_radix_literals() {
+        let input = ": test ( -- Int Int Int Int Int Int ) 0xff -0o755 0b1010_0101 0 0o0 -0x8000_0000_0000_0000 ;";
+        let program = Parser::new(input).parse().unwrap();
+
+        let values: Vec<i64> = program.word_defs[0]
+            .body
+            .iter()
+            .map(|expr| match expr {
+                Expr::IntLit(n, _) => *n,
+                other => panic!("Expected IntLit, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(values, vec![255, -493, 0b1010_0101, 0, 0, i64::MIN]);
+
+        let err = Parser::new(": test ( -- Int ) 0o19 ;").parse().unwrap_err();
+        assert_eq!(err.message, "Invalid digit '9' in octal literal '0o19'");
+        assert_eq!(err.column, 19);
+        let err = Parser::new(": test ( -- Int ) 0x1_0000_0000_0000_0000 ;")
+            .parse()
+            .unwrap_err();
+        assert_eq!(
+            err.message,
+            "Integer literal '0x1_0000_0000_0000_0000' does not fit in i64"
+        );
+    }
+
+    #[test]
+    fn test_parse_int_literal_overflow() {
+        let input = ": test ( -- Int Int ) 9223372036854775807 -9223372036854775808 ;";
+        let program = Parser::new(input).parse().unwrap();
+        match &program.word_defs[0].body[..] {
+            [Expr::IntLit(i64::MAX, _), Expr::IntLit(i64::MIN, _)] => (),
+            other => panic!("Expected i64::MAX and i64::MIN, got {:?}", other),
+        }
+
+        for literal in ["9223372036854775808", "-9223372036854775809"] {
+            let err = Parser::new(&format!(": test ( -- Int ) {} ;", literal))
+                .parse()
+                .unwrap_err();
+            assert_eq!(
+                err.message,
+                format!("Integer literal '{}' does not fit in i64", literal)
+            );
+            assert_eq!((err.line, err.column), (1, 19));
+        }
+    }
+
+    #[test]
+    fn test_parse_malformed_digit_separators() {
+        // (input, column of the offending '_')
+        let cases = [
+            (": test ( -- Int ) 1__0 ;", 20),
+            (": test ( -- Int ) _5 ;", 19),
+            (": test ( -- Int ) 5_ ;", 20),
+            (": test ( -- Int ) 0x_ff ;", 21),
+            (": test ( -- Int ) 0b1_ ;", 22),
+        ];
+
+        for (input, column) in cases {
+            let mut parser = Parser::new(input);
+            let err = parser.parse().unwrap_err();
+            assert!(
+                err.message.contains("digit separator"),
+                "Unexpected error for {}: {}",
+                input,
+                err
+            );
+            assert_eq!(err.line, 1);
+            assert_eq!(err.column, column, "Wrong column for {}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_char_literals() {
+        let input = r": test ( -- Int Int Int Int ) 'A' '\n' '😀' '\0' ;";
+        let mut parser = Parser::new(input);
+        let program = parser.parse().unwrap();
+
+        match &program.word_defs[0].body[..] {
+            [
+                Expr::IntLit(65, _),
+                Expr::IntLit(10, _),
+                Expr::IntLit(0x1F600, _),
+                Expr::IntLit(0, _),
+            ] => (),
+            other => panic!("Expected code points 65 10 0x1F600 0, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_malformed_char_literals() {
+        // (input, expected message fragment, column of the literal)
+        let cases = [
+            (": test ( -- Int ) '' ;", "Empty character literal", 19),
+            (": test ( -- Int ) 'ab' ;", "exactly one character", 19),
+            (
+                ": test ( -- Int ) 1 'a",
+                "Unterminated character literal",
+                21,
+            ),
+        ];
+
+        for (input, message, column) in cases {
+            let mut parser = Parser::new(input);
+            let err = parser.parse().unwrap_err();
+            assert!(
+                err.message.contains(message),
+                "Unexpected error for {}: {}",
+                input,
+                err
+            );
+            assert_eq!(err.line, 1);
+            assert_eq!(err.column, column, "Wrong column for {}", input);
+        }
+    }
+
+    #[test]
+    fn test_lexer_errors_become_parse_errors() {
+        // (input, expected message fragment, line, column)
+        let cases = [
+            (
+                ": test ( -- ) \"oops ;",
+                "Unterminated string literal",
+                1,
+                15,
+            ),
+            (
+                ": test ( -- )\n  1 \"oops\n ;",
+                "Unterminated string literal",
+                2,
+                5,
+            ),
+            (
+                ": test ( -- ) ;\n#| never closed",
+                "Unterminated block comment",
+                2,
+                1,
+            ),
+            (": test ( -- Float ) 1e ;", "Malformed exponent", 1, 21),
+        ];
+
+        for (input, message, line, column) in cases {
+            let mut parser = Parser::new(input);
+            let err = parser.parse().unwrap_err();
+            assert!(
+                err.message.contains(message),
+                "Unexpected error for {:?}: {}",
+                input,
+                err
+            );
+            assert_eq!((err.line, err.column), (line, column), "for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_float_literals() {
+        let input = ": test ( -- Float Float ) 2.75 -0.5 ;";
+        let mut parser = Parser::new(input);
+        let program = parser.parse().unwrap();
+
+        assert_eq!(program.word_defs[0].body.len(), 2);
+        match &program.word_defs[0].body[0] {
+            Expr::FloatLit(x, _) => assert_eq!(*x, 2.75),
+            _ => panic!("Expected FloatLit(2.75)"),
+        }
+        match &program.word_defs[0].body[1] {
+            Expr::FloatLit(x, _) => assert_eq!(*x, -0.5),
+            _ => panic!("Expected FloatLit(-0.5)"),
+        }
+        assert_eq!(
+            program.word_defs[0].effect.outputs,
+            crate::ast::types::StackType::from_vec(vec![Type::Float, Type::Float])
+        );
+    }
+
     #[test]
     fn test_parse_quotation() {
         let input = ": test ( -- ) [ 1 2 + ] ;";
@@ -528,18 +1152,19 @@ mod tests {
         }
     }
 
+    /// A word whose body is `42` nested in `depth` quotations
+    fn nested_quotations(depth: usize) -> String {
+        format!(
+            ": test ( -- ) {}42 {};",
+            "[ ".repeat(depth),
+            "] ".repeat(depth)
+        )
+    }
+
     #[test]
     fn test_recursion_depth_limit() {
-        // Create deeply nested quotations that exceed MAX_NESTING_DEPTH
-        let mut input = String::from(": test ( -- ) ");
-        for _ in 0..105 {
-            input.push_str("[ ");
-        }
-        input.push_str("42 ");
-        for _ in 0..105 {
-            input.push_str("] ");
-        }
-        input.push(';');
+        // Create deeply nested quotations that exceed the default limit
+        let input = nested_quotations(105);
 
         let mut parser = Parser::new(&input);
         let result = parser.parse();
@@ -550,6 +1175,26 @@ mod tests {
         assert!(err.message.contains("nesting depth"));
     }
 
+    #[test]
+    fn test_configurable_depth_limit() {
+        let input = nested_quotations(105);
+
+        let mut parser = Parser::with_max_depth(&input, "deep.cem", 200);
+        assert!(parser.parse().is_ok());
+
+        let mut parser = Parser::with_max_depth(&input, "deep.cem", 10);
+        let err = parser.parse().unwrap_err();
+        assert!(err.message.contains("nesting depth of 10"), "{}", err);
+
+        let limits = ParseLimits {
+            max_string_length: 4,
+            ..ParseLimits::default()
+        };
+        let mut parser = Parser::with_limits(": hi ( -- ) \"hello\" drop ;", "hi.cem", limits);
+        let err = parser.parse().unwrap_err();
+        assert!(err.message.contains("maximum length of 4 bytes"), "{}", err);
+    }
+
     #[test]
     fn test_source_location_tracking() {
         // Test that line/column numbers are captured correctly
@@ -563,12 +1208,12 @@ mod tests {
         assert_eq!(word_loc.column, 1);
         assert_eq!(word_loc.file.as_ref(), "test.cem");
 
-        // Check integer literal location (line 2, column 2 for '42')
-        // The lexer uses 0-based columns internally but reports 1-based
+        // Check integer literal location (line 2, column 3 for '42')
+        // Columns are 1-based, so two leading spaces put '4' at column 3
         match &program.word_defs[0].body[0] {
             Expr::IntLit(42, loc) => {
                 assert_eq!(loc.line, 2);
-                assert_eq!(loc.column, 2); // Column for '4' in '42' after two spaces
+                assert_eq!(loc.column, 3); // Column for '4' in '42' after two spaces
                 assert_eq!(loc.file.as_ref(), "test.cem");
             }
             _ => panic!("Expected IntLit"),
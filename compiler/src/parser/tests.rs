@@ -1,6 +1,7 @@
 /// Integration tests for the parser
 use super::*;
 use crate::ast::Expr;
+use crate::ast::types::{Effect, StackType, Type};
 
 #[test]
 fn test_parse_complete_program() {
@@ -33,6 +34,20 @@ fn test_parse_complete_program() {
     assert_eq!(program.word_defs[0].name, "unwrap");
 }
 
+#[test]
+fn test_parse_type_parameters() {
+    let input = "type Result(T, E)\n  | Ok(T)\n  | Err(E)";
+    let program = Parser::new(input).parse().unwrap();
+    assert_eq!(program.type_defs[0].type_params, vec!["T", "E"]);
+
+    // Parameters are separated by commas, like variant fields
+    assert!(
+        Parser::new("type Pair(A B)\n  | Pair(A, B)")
+            .parse()
+            .is_err()
+    );
+}
+
 #[test]
 fn test_parse_pattern_match() {
     let input = r#"
@@ -81,6 +96,64 @@ fn test_parse_if_expression() {
     assert_eq!(program.word_defs[0].body.len(), 4);
 }
 
+#[test]
+fn test_parse_if_without_else() {
+    let input = ": warn ( Bool -- ) if [ \"careful\" write_line ] ;";
+    let program = Parser::new(input).parse().unwrap();
+
+    match &program.word_defs[0].body[0] {
+        Expr::If {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            assert!(matches!(&**then_branch, Expr::Quotation(exprs, _) if exprs.len() == 2));
+            assert!(matches!(&**else_branch, Expr::Quotation(exprs, _) if exprs.is_empty()));
+        }
+        other => panic!("Expected If, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_while() {
+    let input = ": countdown ( Int -- Int ) while [ dup 0 > ] [ 1 - ] ;";
+    let program = Parser::new(input).parse().unwrap();
+
+    match &program.word_defs[0].body[0] {
+        Expr::While { cond, body, .. } => {
+            assert!(matches!(&**cond, Expr::Quotation(exprs, _) if exprs.len() == 3));
+            assert!(matches!(&**body, Expr::Quotation(exprs, _) if exprs.len() == 2));
+        }
+        other => panic!("Expected While, got {:?}", other),
+    }
+
+    // Unlike if's else, the body can't be left out
+    assert!(
+        Parser::new(": spin ( -- ) while [ true ] ;")
+            .parse()
+            .is_err()
+    );
+}
+
+#[test]
+fn test_parse_imports() {
+    let input = "import \"lib/list.cem\"\nimport \"util.cem\"\n: main ( -- ) ;";
+    let program = Parser::new(input).parse().unwrap();
+
+    let paths: Vec<&str> = program.imports.iter().map(|i| i.path.as_str()).collect();
+    assert_eq!(paths, vec!["lib/list.cem", "util.cem"]);
+    assert_eq!(program.imports[1].loc.line, 2);
+    assert_eq!(program.word_defs.len(), 1);
+
+    // Only at the top, and only of a quoted path
+    let err = Parser::new(": main ( -- ) ;\nimport \"util.cem\"")
+        .parse()
+        .unwrap_err();
+    assert_eq!((err.line, err.column), (2, 1));
+    assert!(err.message.contains("before definitions"), "{}", err);
+    assert!(Parser::new("import util").parse().is_err());
+}
+
 #[test]
 fn test_parse_comments() {
     let input = r#"
@@ -136,3 +209,357 @@ fn test_parse_polymorphic_effect() {
     assert_eq!(effect.inputs.depth(), Some(1));
     assert_eq!(effect.outputs.depth(), Some(2));
 }
+
+#[test]
+fn test_parse_quotation_types() {
+    let input = ": apply ( a [ a -- b ] -- b ) call ;
+: twice ( [ [ Int -- Int ] -- Int ] -- ) drop ;
+: run ( [ -- ] -- ) call ;";
+    let program = Parser::new(input).parse().unwrap();
+
+    let var = |name: &str| Type::Var(name.to_string());
+    let quotation = |inputs, outputs| Type::Quotation(Box::new(Effect::from_vecs(inputs, outputs)));
+
+    let apply = &program.word_defs[0].effect;
+    let expected = Effect::from_vecs(
+        vec![var("a"), quotation(vec![var("a")], vec![var("b")])],
+        vec![var("b")],
+    );
+    assert_eq!(apply, &expected);
+
+    // Quotation types nest
+    let twice = &program.word_defs[1].effect;
+    let inner = quotation(vec![Type::Int], vec![Type::Int]);
+    let expected = Effect::from_vecs(vec![quotation(vec![inner], vec![Type::Int])], vec![]);
+    assert_eq!(twice, &expected);
+
+    let run = &program.word_defs[2].effect;
+    let expected = Effect::from_vecs(vec![quotation(vec![], vec![])], vec![]);
+    assert_eq!(run, &expected);
+}
+
+#[test]
+fn test_parse_row_variables() {
+    let input = ": my-dup ( ..R Int -- ..R Int Int ) dup ;
+: keep ( ..R [ ..R -- ..R ] -- ..R ) call ;
+: id ( A -- A ) ;";
+    let program = Parser::new(input).parse().unwrap();
+
+    let row = StackType::RowVar("R".to_string());
+    let my_dup = &program.word_defs[0].effect;
+    assert_eq!(my_dup.inputs, row.clone().push(Type::Int));
+    assert_eq!(my_dup.outputs, row.clone().push(Type::Int).push(Type::Int));
+
+    // Row variables work in quotation types too
+    let same = Type::Quotation(Box::new(Effect::new(row.clone(), row.clone())));
+    let keep = &program.word_defs[1].effect;
+    assert_eq!(keep, &Effect::new(row.clone().push(same), row));
+
+    // Without `..`, a leading uppercase name is still a type variable
+    let id = &program.word_defs[2].effect;
+    assert_eq!(id.inputs, StackType::Empty.push(Type::Var("A".to_string())));
+}
+
+#[test]
+fn test_parse_misplaced_row_variable() {
+    let err = Parser::new(": f ( Int ..R -- Int ) ;").parse().unwrap_err();
+    assert!(
+        err.message.contains("Row variable '..R' must come first"),
+        "{}",
+        err.message
+    );
+
+    let err = Parser::new(": f ( .. -- ) ;").parse().unwrap_err();
+    assert!(
+        err.message.contains("Expected a row variable name"),
+        "{}",
+        err.message
+    );
+}
+
+#[test]
+fn test_parse_quotation_type_needs_dash() {
+    let err = Parser::new(": run ( [ Int ] -- ) call ;")
+        .parse()
+        .unwrap_err();
+    assert!(
+        err.message.contains("Expected '--' in quotation type"),
+        "{}",
+        err.message
+    );
+}
+
+#[test]
+fn test_parse_wildcard_pattern() {
+    let input = r#"
+        : is-some ( Option(Int) -- Bool )
+          match
+            Some => [ drop true ]
+            _ => [ false ]
+          end ;
+    "#;
+
+    let mut parser = Parser::new(input);
+    let program = parser.parse().unwrap();
+
+    match &program.word_defs[0].body[0] {
+        Expr::Match { branches, .. } => {
+            assert_eq!(branches.len(), 2);
+            assert_eq!(branches[1].pattern, crate::ast::Pattern::Wildcard);
+            assert_eq!(branches[1].body.len(), 1);
+        }
+        _ => panic!("Expected Match expression"),
+    }
+}
+
+#[test]
+fn test_parse_pattern_bindings() {
+    let input = r#"
+        : first-or-zero ( List(Int) -- Int )
+          match
+            Cons(head, _) => [ head ]
+            Nil => [ 0 ]
+          end ;
+    "#;
+
+    let mut parser = Parser::new(input);
+    let program = parser.parse().unwrap();
+
+    match &program.word_defs[0].body[0] {
+        Expr::Match { branches, .. } => {
+            assert_eq!(
+                branches[0].pattern,
+                crate::ast::Pattern::Variant {
+                    name: "Cons".to_string(),
+                    fields: vec![
+                        crate::ast::Pattern::Binding("head".to_string()),
+                        crate::ast::Pattern::Wildcard,
+                    ],
+                }
+            );
+            assert_eq!(
+                branches[1].pattern,
+                crate::ast::Pattern::Variant {
+                    name: "Nil".to_string(),
+                    fields: vec![],
+                }
+            );
+        }
+        _ => panic!("Expected Match expression"),
+    }
+}
+
+#[test]
+fn test_parse_nested_patterns() {
+    let input = r#"
+        : second ( List(Int) -- Int )
+          match
+            Cons(_, Cons(x, _)) => [ x ]
+            Cons(_, Nil) => [ 0 ]
+            Nil => [ 0 ]
+          end ;
+    "#;
+
+    let program = Parser::new(input).parse().unwrap();
+
+    match &program.word_defs[0].body[0] {
+        Expr::Match { branches, .. } => {
+            use crate::ast::Pattern;
+            assert_eq!(
+                branches[0].pattern,
+                Pattern::Variant {
+                    name: "Cons".to_string(),
+                    fields: vec![
+                        Pattern::Wildcard,
+                        Pattern::Variant {
+                            name: "Cons".to_string(),
+                            fields: vec![Pattern::Binding("x".to_string()), Pattern::Wildcard],
+                        },
+                    ],
+                }
+            );
+            assert_eq!(branches[1].pattern.to_string(), "Cons(_, Nil)");
+            assert!(branches[1].pattern.tests_fields());
+            assert!(!branches[2].pattern.tests_fields());
+        }
+        _ => panic!("Expected Match expression"),
+    }
+}
+
+#[test]
+fn test_parse_malformed_pattern_bindings() {
+    let cases = [
+        ("match Cons(x, x) => [ x ] end", "Duplicate binding 'x'"),
+        ("match Some() => [ 0 ] end", "Expected binding name"),
+        ("match Some(x => [ x ] end", "Expected ')'"),
+        (
+            "match Some(Pair(x, x)) => [ x ] end",
+            "Duplicate binding 'x'",
+        ),
+        (
+            "match Some(Some(0)) => [ 0 ] end",
+            "Expected binding name or pattern",
+        ),
+    ];
+
+    for (body, expected) in cases {
+        let input = format!(": f ( Option(Int) -- Int ) {} ;", body);
+        let err = Parser::new(&input).parse().unwrap_err();
+        assert!(err.message.contains(expected), "{}: {}", body, err);
+    }
+}
+
+#[test]
+fn test_parse_int_patterns() {
+    let input = r#"
+        : describe ( Int -- String )
+          match
+            0 => [ "zero" ]
+            -1 => [ "minus one" ]
+            1_000 => [ "thousand" ]
+            _ => [ "other" ]
+          end ;
+    "#;
+
+    let mut parser = Parser::new(input);
+    let program = parser.parse().unwrap();
+
+    match &program.word_defs[0].body[0] {
+        Expr::Match { branches, .. } => {
+            let patterns: Vec<_> = branches.iter().map(|b| b.pattern.clone()).collect();
+            assert_eq!(
+                patterns,
+                vec![
+                    crate::ast::Pattern::IntLit(0),
+                    crate::ast::Pattern::IntLit(-1),
+                    crate::ast::Pattern::IntLit(1000),
+                    crate::ast::Pattern::Wildcard,
+                ]
+            );
+        }
+        _ => panic!("Expected Match expression"),
+    }
+}
+
+#[test]
+fn test_parse_bool_patterns() {
+    let input = ": describe ( Bool -- String ) match true => [ \"yes\" ] false => [ \"no\" ] end ;";
+    let program = Parser::new(input).parse().unwrap();
+
+    match &program.word_defs[0].body[0] {
+        Expr::Match { branches, .. } => {
+            let patterns: Vec<_> = branches.iter().map(|b| b.pattern.clone()).collect();
+            assert_eq!(
+                patterns,
+                vec![
+                    crate::ast::Pattern::BoolLit(true),
+                    crate::ast::Pattern::BoolLit(false),
+                ]
+            );
+        }
+        _ => panic!("Expected Match expression"),
+    }
+}
+
+#[test]
+fn test_parse_malformed_int_patterns() {
+    let cases = [
+        (
+            "match 0 => [ 0 ] None => [ 1 ] end",
+            "Cannot mix integer and variant",
+        ),
+        (
+            "match None => [ 0 ] 0 => [ 1 ] end",
+            "Cannot mix integer and variant",
+        ),
+        (
+            "match 1 => [ 0 ] 1 => [ 1 ] _ => [ 2 ] end",
+            "Duplicate pattern '1'",
+        ),
+        (
+            "match true => [ 0 ] 0 => [ 1 ] end",
+            "Cannot mix integer and boolean",
+        ),
+        (
+            "match None => [ 0 ] false => [ 1 ] end",
+            "Cannot mix boolean and variant",
+        ),
+        (
+            "match true => [ 0 ] true => [ 1 ] end",
+            "Duplicate pattern 'true'",
+        ),
+    ];
+
+    for (body, expected) in cases {
+        let input = format!(": f ( Int -- Int ) {} ;", body);
+        let err = Parser::new(&input).parse().unwrap_err();
+        assert!(err.message.contains(expected), "{}: {}", body, err);
+    }
+}
+
+#[test]
+fn test_parse_branch_after_wildcard_rejected() {
+    let input = r#"
+        : f ( Option(Int) -- Int )
+          match
+            _ => [ 0 ]
+            None => [ 1 ]
+          end ;
+    "#;
+
+    let mut parser = Parser::new(input);
+    let err = parser.parse().unwrap_err();
+    assert!(err.message.contains("after wildcard"), "{}", err);
+    assert_eq!(err.line, 5);
+}
+
+#[test]
+fn test_parse_error_renders_source_snippet() {
+    let source = ": foo ( Int -- Int )\n  dup ] + ;\n";
+    let err = Parser::new(source).parse().unwrap_err();
+
+    let expected = [
+        "Parse error at 2:7: Unexpected token: RightBracket",
+        "  |",
+        "2 |   dup ] + ;",
+        "  |       ^",
+    ];
+    assert_eq!(err.render(source), expected.join("\n"));
+
+    // Tabs before the column are kept, so the caret still lines up
+    let source = "\t: foo ( -- ) ] ;";
+    let err = Parser::new(source).parse().unwrap_err();
+    assert!(
+        err.render(source).ends_with("\n  | \t             ^"),
+        "{}",
+        err.render(source)
+    );
+}
+
+#[test]
+fn test_parse_all_reports_every_broken_definition() {
+    let input = ": first ( Int -- Int )
+  1 ] + ;
+: fine ( Int -- Int ) 1 + ;
+: second ( Int -- ) match
+    Some(x => [ drop ]
+  end ;
+: also-fine ( -- ) ;";
+
+    let errors = Parser::new(input).parse_all().unwrap_err();
+    let positions: Vec<_> = errors.iter().map(|e| (e.line, e.column)).collect();
+    assert_eq!(positions, [(2, 5), (5, 12)], "{:?}", errors);
+
+    // `parse` stops at the first
+    let err = Parser::new(input).parse().unwrap_err();
+    assert_eq!((err.line, err.column), (2, 5));
+
+    assert_eq!(
+        Parser::new(": ok ( -- ) ;")
+            .parse_all()
+            .unwrap()
+            .word_defs
+            .len(),
+        1
+    );
+}
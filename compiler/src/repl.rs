@@ -0,0 +1,179 @@
+/**
+Interactive sessions for `cem repl`
+
+A line that starts with `:` or `type` defines words and types, which later
+lines can use. Any other line is an expression: it's type checked on an
+empty stack and wrapped in a word of its own, which the caller compiles and
+runs. Each expression starts from an empty stack; nothing carries over from
+one to the next except definitions.
+
+A line that fails to parse or type check changes nothing, so the session
+carries on as it was.
+*/
+use crate::ast::types::{Effect, StackType};
+use crate::ast::{Program, SourceLoc, WordDef};
+use crate::parser::{Lexer, Parser, TokenKind};
+use crate::typechecker::TypeChecker;
+
+/// Name of the word an expression line is wrapped in
+pub const LINE_WORD: &str = "repl-line";
+
+/// Filename that locations in REPL input refer to
+const FILENAME: &str = "<repl>";
+
+/// What a line did
+#[derive(Debug)]
+pub enum Evaluation {
+    /// Defined these words and types, kept for later lines
+    Defined(Vec<String>),
+    /// An expression, wrapped in `LINE_WORD` in this complete program. It
+    /// leaves a stack of the given types.
+    Run { program: Program, stack: StackType },
+}
+
+/// Definitions accumulated over a session
+pub struct Session {
+    prelude: Program,
+    definitions: Program,
+}
+
+impl Session {
+    /// Start a session with nothing defined beyond `prelude`
+    pub fn new(prelude: Program) -> Self {
+        Session {
+            prelude,
+            definitions: Program::default(),
+        }
+    }
+
+    /// Evaluate one line of input
+    ///
+    /// Errors are rendered for display, with the offending part of the line.
+    pub fn eval(&mut self, line: &str) -> Result<Evaluation, String> {
+        match Lexer::new(line).next_token().kind {
+            TokenKind::Colon | TokenKind::Type => self.define(line),
+            _ => self.expression(line),
+        }
+    }
+
+    fn define(&mut self, line: &str) -> Result<Evaluation, String> {
+        let new = Parser::new_with_filename(line, FILENAME)
+            .parse_all()
+            .map_err(|errors| {
+                let rendered: Vec<String> = errors.iter().map(|e| e.render(line)).collect();
+                rendered.join("\n")
+            })?;
+
+        let definitions = Program {
+            imports: Vec::new(),
+            type_defs: [self.definitions.type_defs.clone(), new.type_defs.clone()].concat(),
+            word_defs: [self.definitions.word_defs.clone(), new.word_defs.clone()].concat(),
+            ..Program::default()
+        };
+        TypeChecker::new()
+            .check_program(&self.with_prelude(&definitions))
+            .map_err(|e| format!("Type error: {}", e))?;
+        self.definitions = definitions;
+
+        let names = new
+            .type_defs
+            .iter()
+            .map(|typedef| typedef.name.clone())
+            .chain(new.word_defs.iter().map(|word| word.name.clone()))
+            .collect();
+        Ok(Evaluation::Defined(names))
+    }
+
+    fn expression(&self, line: &str) -> Result<Evaluation, String> {
+        let body = Parser::new_with_filename(line, FILENAME)
+            .parse_expressions()
+            .map_err(|e| e.render(line))?;
+
+        let mut program = self.with_prelude(&self.definitions);
+        let mut checker = TypeChecker::new();
+        let stack = checker
+            .check_program(&program)
+            .and_then(|()| checker.check_expressions(&body))
+            .map_err(|e| format!("Type error: {}", e))?;
+
+        program.word_defs.push(WordDef {
+            name: LINE_WORD.to_string(),
+            effect: Effect::new(StackType::Empty, stack.clone()),
+            body,
+            loc: SourceLoc::file_only(FILENAME),
+        });
+        Ok(Evaluation::Run { program, stack })
+    }
+
+    /// The prelude followed by `definitions`
+    fn with_prelude(&self, definitions: &Program) -> Program {
+        Program {
+            imports: Vec::new(),
+            type_defs: [
+                self.prelude.type_defs.clone(),
+                definitions.type_defs.clone(),
+            ]
+            .concat(),
+            word_defs: [
+                self.prelude.word_defs.clone(),
+                definitions.word_defs.clone(),
+            ]
+            .concat(),
+            ..Program::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::types::Type;
+
+    fn session() -> Session {
+        let prelude = include_str!("../../stdlib/prelude.cem");
+        Session::new(Parser::new(prelude).parse().unwrap())
+    }
+
+    #[test]
+    fn test_definitions_persist_across_lines() {
+        let mut session = session();
+
+        match session.eval(": double ( Int -- Int ) 2 * ;") {
+            Ok(Evaluation::Defined(names)) => assert_eq!(names, vec!["double"]),
+            other => panic!("Expected Defined, got {:?}", other),
+        }
+
+        match session.eval("21 double \"done\"") {
+            Ok(Evaluation::Run { program, stack }) => {
+                assert_eq!(stack, StackType::from_vec(vec![Type::Int, Type::String]));
+                let line = program.word_defs.last().unwrap();
+                assert_eq!(line.name, LINE_WORD);
+                assert_eq!(line.body.len(), 3);
+                assert!(program.word_defs.iter().any(|w| w.name == "double"));
+            }
+            other => panic!("Expected Run, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_errors_leave_the_session_unchanged() {
+        let mut session = session();
+
+        let err = session.eval("1 ]").unwrap_err();
+        assert!(err.contains("Parse error"), "{}", err);
+        let err = session.eval("1 \"a\" +").unwrap_err();
+        assert!(err.contains("Type error"), "{}", err);
+        // Each line starts from an empty stack
+        let err = session.eval("drop").unwrap_err();
+        assert!(err.contains("Type error"), "{}", err);
+
+        // A definition that doesn't check isn't kept
+        assert!(session.eval(": bad ( -- Int ) \"no\" ;").is_err());
+        assert!(session.eval("bad").is_err());
+        assert!(matches!(
+            session.eval(": bad ( -- Int ) 1 ;"),
+            Ok(Evaluation::Defined(_))
+        ));
+        assert!(matches!(session.eval("bad"), Ok(Evaluation::Run { .. })));
+    }
+}
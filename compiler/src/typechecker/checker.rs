@@ -1,19 +1,46 @@
-#[cfg(test)]
-use crate::ast::SourceLoc;
 /**
 Core type checker for Cem
 
 Implements bidirectional type checking with stack effect inference.
+
+Linear values are tracked where they have names: the field bindings of a
+match pattern. Referencing a linear binding moves its value onto the stack,
+so a second reference is a use after move. Values on the stack itself are
+anonymous; shuffling words like `swap` and `rot` only reorder them, and
+nothing records that a consumed stack value is gone. Copies made by `dup`,
+`over`, `tuck` and `2dup` are rejected instead (see `check_duplication`).
 */
 use crate::ast::types::{Effect, StackType, Type};
-use crate::ast::{Expr, MatchBranch, Pattern, Program, WordDef};
+use crate::ast::{Expr, MatchBranch, Pattern, Program, SourceLoc, Variant, WordDef};
 use crate::typechecker::environment::Environment;
 use crate::typechecker::errors::{TypeError, TypeResult};
-use crate::typechecker::unification::{unify_stack_types, unify_types};
+use crate::typechecker::unification::{
+    StackSubstitution, Substitution, substitute_stack, unify_stack_types, unify_types,
+};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+
+/// Upper bound on the inputs inferred for a single quotation
+const MAX_QUOTATION_INPUTS: usize = 64;
 
 /// The main type checker
 pub struct TypeChecker {
     env: Environment,
+    /// Counter for fresh type and row variable names
+    fresh_counter: Cell<usize>,
+    /// Match bindings in scope, whose types mention the enclosing word's
+    /// type variables rather than fresh ones
+    bindings: HashSet<String>,
+    /// Bindings of matches enclosing the current quotation, which it can't
+    /// reach: a quotation is compiled as a function of its own
+    hidden_bindings: HashSet<String>,
+    /// Match bindings in scope whose values are linear
+    linear_bindings: HashSet<String>,
+    /// Linear bindings already moved onto the stack on the current path
+    moved: RefCell<HashSet<String>>,
+    /// What unification has learned about type variables while checking the
+    /// current word
+    learned: RefCell<Substitution>,
 }
 
 impl TypeChecker {
@@ -21,46 +48,237 @@ impl TypeChecker {
     pub fn new() -> Self {
         TypeChecker {
             env: Environment::new(),
+            fresh_counter: Cell::new(0),
+            bindings: HashSet::new(),
+            hidden_bindings: HashSet::new(),
+            linear_bindings: HashSet::new(),
+            moved: RefCell::new(HashSet::new()),
+            learned: RefCell::new(Substitution::new()),
         }
     }
 
+    /// Generate a fresh variable name
+    ///
+    /// Names start with an underscore, which source type annotations can't
+    /// produce, so they never capture a user's type variables.
+    fn fresh_name(&self, prefix: &str) -> String {
+        let n = self.fresh_counter.get();
+        self.fresh_counter.set(n + 1);
+        format!("_{}{}", prefix, n)
+    }
+
     /// Type check a complete program
+    ///
+    /// Type and variant names must be unique across the program, since variant
+    /// constructors share one namespace (codegen keys variant tags on the name
+    /// alone). A program may still define a type that replaces a built-in one
+    /// such as `Option`; that's how the prelude provides them to codegen.
+    ///
+    /// Every word must have a unique name. Built-in words can't be redefined
+    /// either, since a user definition would collide with the runtime's
+    /// implementation of the same name.
+    ///
+    /// `main` must be ( -- ) or ( -- Int ); an Int it leaves is the exit code.
     pub fn check_program(&mut self, program: &Program) -> TypeResult<()> {
         // First pass: add all type definitions
+        Self::check_unique_type_names(program)?;
         for typedef in &program.type_defs {
             self.env.add_type(typedef.clone());
         }
 
-        // Second pass: check all word definitions
+        // Second pass: register every word's declared effect, its contract, so
+        // calls resolve regardless of definition order (including recursion)
+        let mut defined: HashMap<&str, &SourceLoc> = HashMap::new();
+        for word_def in &program.word_defs {
+            if self.env.is_builtin_word(&word_def.name) {
+                return Err(Box::new(TypeError::BuiltinRedefinition {
+                    name: word_def.name.clone(),
+                    loc: word_def.loc.clone(),
+                }));
+            }
+            if let Some(first_loc) = defined.insert(&word_def.name, &word_def.loc) {
+                return Err(Box::new(TypeError::DuplicateWord {
+                    name: word_def.name.clone(),
+                    first_loc: first_loc.clone(),
+                    second_loc: word_def.loc.clone(),
+                }));
+            }
+            if word_def.name == "main" {
+                Self::check_entry_effect(word_def)?;
+            }
+            self.env
+                .add_word(word_def.name.clone(), word_def.effect.clone());
+        }
+
+        // Third pass: check all word bodies
         for word_def in &program.word_defs {
-            self.check_word_def(word_def)?;
+            self.check_word_def(word_def)
+                .map_err(|error| TypeError::InWord {
+                    word: word_def.name.clone(),
+                    loc: word_def.loc.clone(),
+                    error,
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Reject type and variant names the program declares more than once
+    fn check_unique_type_names(program: &Program) -> TypeResult<()> {
+        let mut types: HashMap<&str, &SourceLoc> = HashMap::new();
+        let mut variants: HashMap<&str, (&str, &SourceLoc)> = HashMap::new();
+
+        for typedef in &program.type_defs {
+            if let Some(first_loc) = types.insert(&typedef.name, &typedef.loc) {
+                return Err(Box::new(TypeError::DuplicateType {
+                    name: typedef.name.clone(),
+                    first_loc: first_loc.clone(),
+                    second_loc: typedef.loc.clone(),
+                }));
+            }
+
+            for variant in &typedef.variants {
+                if let Some((first_type, first_loc)) =
+                    variants.insert(&variant.name, (&typedef.name, &variant.loc))
+                {
+                    return Err(Box::new(TypeError::DuplicateVariant {
+                        name: variant.name.clone(),
+                        first_type: first_type.to_string(),
+                        first_loc: first_loc.clone(),
+                        second_type: typedef.name.clone(),
+                        second_loc: variant.loc.clone(),
+                    }));
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Check that the entry word takes nothing and leaves nothing or an exit code
+    ///
+    /// `check_program` does this for `main`; a program's sole word can be its
+    /// entry too, which only the caller choosing the entry knows.
+    pub fn check_entry_effect(entry: &WordDef) -> TypeResult<()> {
+        let no_exit_code = Effect::from_vecs(vec![], vec![]);
+        let exit_code = Effect::from_vecs(vec![], vec![Type::Int]);
+        if entry.effect == no_exit_code || entry.effect == exit_code {
+            Ok(())
+        } else {
+            Err(Box::new(TypeError::InvalidMainEffect {
+                word: entry.name.clone(),
+                effect: entry.effect.clone(),
+                loc: entry.loc.clone(),
+            }))
+        }
+    }
+
+    /// Type check expressions run on an empty stack, returning the stack
+    /// they leave
+    ///
+    /// Words and types are those `check_program` registered, so this is for
+    /// code outside any definition, like a line typed at the REPL.
+    pub fn check_expressions(&self, exprs: &[Expr]) -> TypeResult<StackType> {
+        self.learned.take();
+        self.check_body(exprs, StackType::Empty)
+    }
+
     /// Type check a word definition
-    fn check_word_def(&mut self, word: &WordDef) -> TypeResult<()> {
-        // Start with the input stack from the declared effect
-        let mut current_stack = word.effect.inputs.clone();
+    ///
+    /// The declared effect's type variables stand for any type the caller
+    /// chooses, so the body may neither decide what one of them is nor
+    /// identify two of them.
+    fn check_word_def(&self, word: &WordDef) -> TypeResult<()> {
+        self.learned.take();
 
-        // Type check each expression in the body
-        for expr in &word.body {
-            current_stack = self.check_expr(expr, current_stack)?;
+        // Type check the body against the input stack from the declared effect
+        let current_stack = self.check_body(&word.body, word.effect.inputs.clone())?;
+
+        // Verify final stack matches declared output effect. The effect's row
+        // variables stand for stacks the caller chooses, so the body can't
+        // decide what they are.
+        let mismatch = || TypeError::EffectMismatch {
+            expected: word.effect.clone(),
+            actual: Effect::new(word.effect.inputs.clone(), current_stack.clone()),
+            word: word.name.clone(),
+        };
+        let (type_subst, stack_subst) =
+            unify_stack_types(&current_stack, &word.effect.outputs).map_err(|_| mismatch())?;
+        self.learn(&type_subst).map_err(|_| mismatch())?;
+        let declared_rows = [word.effect.inputs.base(), word.effect.outputs.base()];
+        if declared_rows.iter().any(|base| match base {
+            StackType::RowVar(name) => stack_subst.contains_key(name),
+            _ => false,
+        }) {
+            return Err(Box::new(mismatch()));
         }
 
-        // Verify final stack matches declared output effect
-        let (_, _) = unify_stack_types(&current_stack, &word.effect.outputs).map_err(|_| {
-            TypeError::EffectMismatch {
-                expected: word.effect.clone(),
-                actual: Effect::new(word.effect.inputs.clone(), current_stack),
-                word: word.name.clone(),
+        let mut declared = Vec::new();
+        Self::collect_type_vars(&word.effect.inputs, &mut declared);
+        Self::collect_type_vars(&word.effect.outputs, &mut declared);
+        let learned = self.learned.borrow();
+        let mut chosen: HashMap<String, &String> = HashMap::new();
+        for var in &declared {
+            let rigid = |actual: Type| {
+                Box::new(TypeError::RigidTypeVariable {
+                    word: word.name.clone(),
+                    var: var.clone(),
+                    actual,
+                })
+            };
+            match Self::apply_type_subst_to_type(&Type::Var(var.clone()), &learned) {
+                Type::Var(resolved) => {
+                    if let Some(other) = chosen.insert(resolved, var)
+                        && other != var
+                    {
+                        return Err(rigid(Type::Var(other.clone())));
+                    }
+                }
+                actual => return Err(rigid(actual)),
             }
-        })?;
+        }
+
+        Ok(())
+    }
+
+    /// Collect the type variables a stack mentions, including those inside
+    /// type arguments and quotation effects
+    fn collect_type_vars(stack: &StackType, vars: &mut Vec<String>) {
+        fn in_type(ty: &Type, vars: &mut Vec<String>) {
+            match ty {
+                Type::Var(name) if !vars.contains(name) => vars.push(name.clone()),
+                Type::Named { args, .. } => args.iter().for_each(|arg| in_type(arg, vars)),
+                Type::Quotation(effect) => {
+                    TypeChecker::collect_type_vars(&effect.inputs, vars);
+                    TypeChecker::collect_type_vars(&effect.outputs, vars);
+                }
+                _ => {}
+            }
+        }
 
-        // Add word to environment for future lookups
-        self.env.add_word(word.name.clone(), word.effect.clone());
+        if let StackType::Cons { rest, top } = stack {
+            Self::collect_type_vars(rest, vars);
+            in_type(top, vars);
+        }
+    }
 
+    /// Record what a unification learned about type variables
+    ///
+    /// `learned` is kept resolved: no variable it binds appears in the types
+    /// it binds them to. A variable learned twice must be bound consistently.
+    fn learn(&self, subst: &Substitution) -> TypeResult<()> {
+        let mut learned = self.learned.borrow_mut();
+        for (var, ty) in subst {
+            let known = Self::apply_type_subst_to_type(&Type::Var(var.clone()), &learned);
+            let ty = Self::apply_type_subst_to_type(ty, &learned);
+            for (var, ty) in unify_types(&known, &ty)? {
+                let step = Substitution::from([(var.clone(), ty.clone())]);
+                for bound in learned.values_mut() {
+                    *bound = Self::apply_type_subst_to_type(bound, &step);
+                }
+                learned.insert(var, ty);
+            }
+        }
         Ok(())
     }
 
@@ -72,6 +290,11 @@ impl TypeChecker {
                 Ok(stack.push(Type::Int))
             }
 
+            Expr::FloatLit(_, _) => {
+                // Push Float onto stack
+                Ok(stack.push(Type::Float))
+            }
+
             Expr::BoolLit(_, _) => {
                 // Push Bool onto stack
                 Ok(stack.push(Type::Bool))
@@ -82,37 +305,77 @@ impl TypeChecker {
                 Ok(stack.push(Type::String))
             }
 
-            Expr::WordCall(name, _) => {
+            Expr::WordCall(name, _) if name == "call" || name == "call_quotation" => {
+                // Calling applies whatever effect the quotation on top has
+                self.check_call(name, stack)
+            }
+
+            Expr::WordCall(name, _) if name == "dip" => {
+                // Like call, on the stack beneath the value that's set aside
+                let (rest, quotation) = stack.pop().ok_or_else(|| TypeError::StackUnderflow {
+                    word: name.clone(),
+                    required: 2,
+                    available: 0,
+                })?;
+                let (rest, hidden) = rest.pop().ok_or_else(|| TypeError::StackUnderflow {
+                    word: name.clone(),
+                    required: 2,
+                    available: 1,
+                })?;
+                let result = self.check_call(name, rest.push(quotation))?;
+                Ok(result.push(hidden))
+            }
+
+            Expr::WordCall(name, _) if self.is_counted_word(name) => {
+                // With a literal count, check_body types the pair
+                Err(Box::new(TypeError::CountNotLiteral { word: name.clone() }))
+            }
+
+            Expr::WordCall(name, _) if self.hidden_bindings.contains(name) => {
+                Err(Box::new(TypeError::BindingInQuotation {
+                    name: name.clone(),
+                }))
+            }
+
+            Expr::WordCall(name, loc) => {
                 // Look up word effect
                 let effect = self
                     .env
                     .lookup_word(name)
                     .ok_or_else(|| TypeError::UndefinedWord { name: name.clone() })?;
 
+                Self::check_duplication(name, &stack, loc)?;
+                self.check_binding_use(name)?;
+
+                // Each use of a polymorphic word gets its own type variables
+                let effect = if self.bindings.contains(name) {
+                    effect.clone()
+                } else {
+                    self.instantiate(effect)
+                };
+
                 // Apply effect to current stack
-                self.apply_effect(effect, stack, name)
-            }
-
-            Expr::Quotation(_exprs, _) => {
-                // TODO(#10): Implement quotation body type checking
-                //
-                // KNOWN LIMITATION: Currently all quotations have type [ -- ] regardless
-                // of their actual contents. This is a soundness hole in the type system.
-                //
-                // What needs to be implemented:
-                // 1. Type-check the quotation body expressions
-                // 2. Infer the actual input/output stack effects
-                // 3. Return Type::Quotation with the inferred effect
-                //
-                // Until this is fixed, invalid quotation usage will pass type checking
-                // and fail at runtime. For example, this incorrectly type-checks:
-                //   : broken ( Int -- String )
-                //     [ 1 + ]  # Type should be [Int -- Int], not [Int -- String]
-                //     call ;
-                //
-                // For now: push a generic quotation type with empty effect
-                let quotation_effect = Effect::new(StackType::empty(), StackType::empty());
-                Ok(stack.push(Type::Quotation(Box::new(quotation_effect))))
+                let stack = self.apply_effect(&effect, stack, name)?;
+
+                if name == "exit" {
+                    // exit never returns, so nothing constrains the stack after it
+                    return Ok(StackType::RowVar(self.fresh_name("r")));
+                }
+                Ok(stack)
+            }
+
+            Expr::Quotation(exprs, _) => {
+                // Push a quotation typed with the effect its body has
+                let effect = if self.bindings.is_empty() {
+                    self.infer_quotation(exprs)?
+                } else {
+                    let checker = self.without_bindings();
+                    let effect = checker.infer_quotation(exprs);
+                    self.fresh_counter.set(checker.fresh_counter.get());
+                    self.learned.replace(checker.learned.into_inner());
+                    effect?
+                };
+                Ok(stack.push(Type::Quotation(Box::new(effect))))
             }
 
             Expr::Match { branches, loc: _ } => {
@@ -134,24 +397,453 @@ impl TypeChecker {
                     })?;
 
                 // Verify condition is Bool
-                unify_types(&cond_type, &Type::Bool).map_err(|_| TypeError::TypeMismatch {
-                    expected: Type::Bool,
-                    actual: cond_type,
-                    context: "if condition".to_string(),
-                })?;
+                unify_types(&cond_type, &Type::Bool)
+                    .and_then(|subst| self.learn(&subst))
+                    .map_err(|_| TypeError::TypeMismatch {
+                        expected: Type::Bool,
+                        actual: cond_type,
+                        context: "if condition".to_string(),
+                    })?;
 
-                // Check both branches produce same stack
-                let then_stack = self.check_expr(then_branch, stack_after_cond.clone())?;
-                let else_stack = self.check_expr(else_branch, stack_after_cond)?;
+                // Check both branches produce same stack. Only one of them runs,
+                // so each starts from the same moved bindings.
+                let moved_before = self.moved.borrow().clone();
+                let then_stack = self.check_branch(then_branch, stack_after_cond.clone())?;
+                let moved_then = self.moved.replace(moved_before);
+                let else_stack = self.check_branch(else_branch, stack_after_cond)?;
+                self.moved.borrow_mut().extend(moved_then);
 
                 // Unify branch results
-                let (_, _) =
-                    unify_stack_types(&then_stack, &else_stack).map_err(|_| TypeError::Other {
-                        message: "if branches produce incompatible stack effects".to_string(),
+                self.join_branches(&then_stack, &else_stack).map_err(|_| {
+                    let show = |stack: &StackType| match stack {
+                        StackType::Empty => "( )".to_string(),
+                        stack => format!("( {} )", stack),
+                    };
+                    Box::new(TypeError::Other {
+                        message: format!(
+                            "if branches produce incompatible stack effects: then leaves {}, else leaves {}",
+                            show(&then_stack),
+                            show(&else_stack)
+                        ),
+                    })
+                })
+            }
+
+            Expr::While { cond, body, loc: _ } => {
+                let show = |stack: &StackType| match stack {
+                    StackType::Empty => "( )".to_string(),
+                    stack => format!("( {} )", stack),
+                };
+
+                // The condition runs before every iteration: ( R -- R Bool )
+                let moved_before = self.moved.borrow().clone();
+                let after_cond = self.check_branch(cond, stack.clone())?;
+                let (rest, cond_type) =
+                    after_cond
+                        .clone()
+                        .pop()
+                        .ok_or_else(|| TypeError::StackUnderflow {
+                            word: "while".to_string(),
+                            required: 1,
+                            available: 0,
+                        })?;
+                unify_types(&cond_type, &Type::Bool)
+                    .and_then(|subst| self.learn(&subst))
+                    .map_err(|_| TypeError::TypeMismatch {
+                        expected: Type::Bool,
+                        actual: cond_type,
+                        context: "while condition".to_string(),
                     })?;
+                let stack = self.join_branches(&stack, &rest).map_err(|_| {
+                    Box::new(TypeError::Other {
+                        message: format!(
+                            "while condition must leave {} with a Bool on top, but leaves {}",
+                            show(&stack),
+                            show(&after_cond)
+                        ),
+                    })
+                })?;
+
+                // The body runs any number of times: ( R -- R )
+                let after_body = self.check_branch(body, stack.clone())?;
+                let stack = self.join_branches(&stack, &after_body).map_err(|_| {
+                    Box::new(TypeError::Other {
+                        message: format!(
+                            "while body must leave the stack as it found it: takes {}, leaves {}",
+                            show(&stack),
+                            show(&after_body)
+                        ),
+                    })
+                })?;
+
+                // A binding moved inside the loop would be moved again on the
+                // next iteration
+                if let Some(var) = self.moved.borrow().difference(&moved_before).next() {
+                    return Err(Box::new(TypeError::UseAfterMove { var: var.clone() }));
+                }
+                Ok(stack)
+            }
+        }
+    }
+
+    /// Type check an `if` or `while` branch, which runs inline rather than being pushed
+    fn check_branch(&self, branch: &Expr, stack: StackType) -> TypeResult<StackType> {
+        match branch {
+            Expr::Quotation(exprs, _) => self.check_body(exprs, stack),
+            _ => self.check_expr(branch, stack),
+        }
+    }
+
+    /// Type check a sequence of expressions
+    ///
+    /// `pick`, `ndrop` and `ndup` take a count given at runtime, so they're
+    /// only typed with an integer literal count right before them: the values
+    /// they copy or drop are looked up on the stack, and copying a linear value
+    /// is rejected as it is for `dup`.
+    fn check_body(&self, body: &[Expr], mut stack: StackType) -> TypeResult<StackType> {
+        let mut i = 0;
+        while i < body.len() {
+            if let (Expr::IntLit(count, _), Some(Expr::WordCall(name, loc))) =
+                (&body[i], body.get(i + 1))
+                && self.is_counted_word(name)
+            {
+                stack = Self::check_counted_word(name, *count, loc, &stack)
+                    .map_err(|error| Self::located(error, loc))?;
+                i += 2;
+                continue;
+            }
+            stack = self
+                .check_expr(&body[i], stack)
+                .map_err(|error| Self::located(error, body[i].loc()))?;
+            i += 1;
+        }
+        Ok(stack)
+    }
+
+    /// Whether `name` is a built-in that takes a count rather than a match
+    /// binding of the same name
+    fn is_counted_word(&self, name: &str) -> bool {
+        Environment::is_counted_word(name) && !self.bindings.contains(name)
+    }
+
+    /// Reject a copy of a linear value by a word that takes a count
+    ///
+    /// The runtime deep-clones what it copies, like dup.
+    fn check_counted_copy(name: &str, ty: &Type, loc: &SourceLoc) -> TypeResult<()> {
+        if ty.is_linear() && !matches!(ty, Type::Var(_)) {
+            return Err(Box::new(TypeError::CannotDuplicate {
+                ty: ty.clone(),
+                operation: name.to_string(),
+                loc: loc.clone(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// The stack after `count name`, for the words that take a count
+    ///
+    /// The values the count covers must be on the stack's known part: beneath
+    /// a row variable, how deep the stack goes isn't known.
+    fn check_counted_word(
+        name: &str,
+        count: i64,
+        loc: &SourceLoc,
+        stack: &StackType,
+    ) -> TypeResult<StackType> {
+        let count = usize::try_from(count).map_err(|_| TypeError::Other {
+            message: format!("'{}' needs a count of at least 0, got {}", name, count),
+        })?;
+        // pick copies the value beneath the `count` above it
+        let required = if name == "pick" { count + 1 } else { count };
+        let (rest, top) =
+            Self::split_top(stack, required).ok_or_else(|| TypeError::StackUnderflow {
+                word: name.to_string(),
+                required,
+                available: stack.known_depth(),
+            })?;
+
+        match name {
+            "pick" => {
+                let picked = top[0].clone();
+                Self::check_counted_copy(name, &picked, loc)?;
+                Ok(stack.clone().push(picked))
+            }
+            "ndup" => {
+                for ty in &top {
+                    Self::check_counted_copy(name, ty, loc)?;
+                }
+                Ok(top
+                    .into_iter()
+                    .fold(stack.clone(), |stack, ty| stack.push(ty)))
+            }
+            _ => Ok(rest),
+        }
+    }
+
+    /// The stack below its top `count` types, and those types bottom first,
+    /// if it's known that deep
+    fn split_top(stack: &StackType, count: usize) -> Option<(StackType, Vec<Type>)> {
+        let mut rest = stack.clone();
+        let mut top = Vec::new();
+        for _ in 0..count {
+            let (below, ty) = rest.pop()?;
+            top.push(ty);
+            rest = below;
+        }
+        top.reverse();
+        Some((rest, top))
+    }
+
+    /// Combine the result stacks of two branches, only one of which runs
+    ///
+    /// A branch that never returns (it ends in `exit`) leaves an unconstrained
+    /// row variable, which takes on the other branch's stack.
+    fn join_branches(&self, first: &StackType, other: &StackType) -> TypeResult<StackType> {
+        let (type_subst, stack_subst) = unify_stack_types(first, other)?;
+        self.learn(&type_subst)?;
+        let joined = match first {
+            StackType::RowVar(name) => stack_subst.get(name).unwrap_or(first),
+            _ => first,
+        };
+        Ok(Self::apply_type_substitution(joined, &type_subst))
+    }
+
+    /// Locate an error at the expression it came from, unless an expression
+    /// nested inside that one already located it more precisely
+    fn located(error: Box<TypeError>, loc: &SourceLoc) -> Box<TypeError> {
+        match *error {
+            TypeError::At { .. } => error,
+            _ => Box::new(TypeError::At {
+                loc: loc.clone(),
+                error,
+            }),
+        }
+    }
+
+    /// Infer the stack effect of a quotation body
+    ///
+    /// The body is checked against a fresh row variable standing for whatever
+    /// the caller's stack holds. If it underflows, the quotation consumes more
+    /// than assumed, so the check is retried with one more fresh input type
+    /// beneath the ones already assumed.
+    fn infer_quotation(&self, body: &[Expr]) -> TypeResult<Effect> {
+        let mut inputs = StackType::RowVar(self.fresh_name("r"));
+        let moved_before = self.moved.borrow().clone();
+        let learned_before = self.learned.borrow().clone();
+
+        loop {
+            // A failed attempt must not leave its binding moves behind
+            self.moved.replace(moved_before.clone());
+            self.learned.replace(learned_before.clone());
+            let result = self.check_body(body, inputs.clone());
+
+            match result {
+                // The body may have learned what its inputs must be
+                Ok(outputs) => {
+                    let learned = self.learned.borrow();
+                    return Ok(Effect::new(
+                        Self::apply_type_substitution(&inputs, &learned),
+                        Self::apply_type_substitution(&outputs, &learned),
+                    ));
+                }
+                Err(e)
+                    if matches!(e.unlocated(), TypeError::StackUnderflow { .. })
+                        && inputs.known_depth() < MAX_QUOTATION_INPUTS =>
+                {
+                    let input = Type::Var(self.fresh_name("t"));
+                    inputs = Self::push_beneath(inputs, input);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Insert a type just above a stack's base, beneath all known types
+    fn push_beneath(stack: StackType, ty: Type) -> StackType {
+        match stack {
+            StackType::Cons { rest, top } => Self::push_beneath(*rest, ty).push(top),
+            base => base.push(ty),
+        }
+    }
 
-                Ok(then_stack)
+    /// Type check `call`, which pops a quotation and applies its effect
+    fn check_call(&self, word_name: &str, stack: StackType) -> TypeResult<StackType> {
+        let (rest, quotation) = stack.pop().ok_or_else(|| TypeError::StackUnderflow {
+            word: word_name.to_string(),
+            required: 1,
+            available: 0,
+        })?;
+
+        match quotation {
+            Type::Quotation(effect) => self.apply_effect(&effect, rest, word_name),
+            actual => Err(Box::new(TypeError::TypeMismatch {
+                expected: Type::Quotation(Box::new(Effect::new(
+                    StackType::RowVar("A".to_string()),
+                    StackType::RowVar("B".to_string()),
+                ))),
+                actual,
+                context: format!("'{}'", word_name),
+            })),
+        }
+    }
+
+    /// Record a reference to a linear match binding, rejecting a second one
+    fn check_binding_use(&self, name: &str) -> TypeResult<()> {
+        if self.linear_bindings.contains(name) && !self.moved.borrow_mut().insert(name.to_string())
+        {
+            return Err(Box::new(TypeError::UseAfterMove {
+                var: name.to_string(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Create a checker for a match branch that binds each of `bindings` to a
+    /// field of the given type, on top of everything visible here
+    fn with_bindings(&self, bindings: &[(String, Type)]) -> TypeChecker {
+        let mut env = self.env.clone();
+        let mut scoped_bindings = self.bindings.clone();
+        let mut hidden_bindings = self.hidden_bindings.clone();
+        let mut linear_bindings = self.linear_bindings.clone();
+        let mut moved = self.moved.borrow().clone();
+
+        for (binding, field_type) in bindings {
+            env.add_word(
+                binding.clone(),
+                Effect::from_vecs(vec![], vec![field_type.clone()]),
+            );
+            scoped_bindings.insert(binding.clone());
+            hidden_bindings.remove(binding);
+
+            // A new binding shadows any outer one of the same name
+            moved.remove(binding);
+            if field_type.is_linear() && !matches!(field_type, Type::Var(_)) {
+                linear_bindings.insert(binding.clone());
+            } else {
+                linear_bindings.remove(binding);
+            }
+        }
+
+        TypeChecker {
+            env,
+            fresh_counter: Cell::new(self.fresh_counter.get()),
+            bindings: scoped_bindings,
+            hidden_bindings,
+            linear_bindings,
+            moved: RefCell::new(moved),
+            learned: self.learned.clone(),
+        }
+    }
+
+    /// Create a checker for a quotation's body, in which the match bindings
+    /// visible here are hidden
+    fn without_bindings(&self) -> TypeChecker {
+        TypeChecker {
+            env: self.env.clone(),
+            fresh_counter: Cell::new(self.fresh_counter.get()),
+            bindings: HashSet::new(),
+            hidden_bindings: self
+                .hidden_bindings
+                .union(&self.bindings)
+                .cloned()
+                .collect(),
+            linear_bindings: HashSet::new(),
+            moved: RefCell::new(HashSet::new()),
+            learned: self.learned.clone(),
+        }
+    }
+
+    /// Reject implicit copies of linear values by `dup`, `over` and `tuck`
+    ///
+    /// The runtime deep-clones whatever these words copy, so a linear value
+    /// would silently get a second owner; `clone` makes that explicit. Type
+    /// variables are let through, since their instantiation isn't known here.
+    fn check_duplication(word_name: &str, stack: &StackType, loc: &SourceLoc) -> TypeResult<()> {
+        // Positions of the copied values, counted from the top of the stack
+        let depths: &[usize] = match word_name {
+            "dup" | "tuck" => &[0],
+            "over" => &[1],
+            "2dup" => &[0, 1],
+            _ => return Ok(()),
+        };
+
+        for &depth in depths {
+            let mut current = stack;
+            for _ in 0..depth {
+                match current {
+                    StackType::Cons { rest, .. } => current = rest,
+                    _ => return Ok(()), // Underflow is reported by apply_effect
+                }
+            }
+
+            if let StackType::Cons { top, .. } = current
+                && top.is_linear()
+                && !matches!(top, Type::Var(_))
+            {
+                return Err(Box::new(TypeError::CannotDuplicate {
+                    ty: top.clone(),
+                    operation: word_name.to_string(),
+                    loc: loc.clone(),
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy a word's effect with fresh names for its type and row variables
+    ///
+    /// Without this, `swap`'s `A` at one call would be the same variable as
+    /// at every other call, and as a caller's own `A`.
+    fn instantiate(&self, effect: &Effect) -> Effect {
+        let mut fresh = Substitution::new();
+        let mut fresh_rows = StackSubstitution::new();
+        let inputs = self.instantiate_stack(&effect.inputs, &mut fresh, &mut fresh_rows);
+        let outputs = self.instantiate_stack(&effect.outputs, &mut fresh, &mut fresh_rows);
+        Effect::new(inputs, outputs)
+    }
+
+    fn instantiate_stack(
+        &self,
+        stack: &StackType,
+        fresh: &mut Substitution,
+        fresh_rows: &mut StackSubstitution,
+    ) -> StackType {
+        match stack {
+            StackType::Cons { rest, top } => {
+                let rest = self.instantiate_stack(rest, fresh, fresh_rows);
+                rest.push(self.instantiate_type(top, fresh, fresh_rows))
             }
+            StackType::RowVar(name) => fresh_rows
+                .entry(name.clone())
+                .or_insert_with(|| StackType::RowVar(self.fresh_name("r")))
+                .clone(),
+            StackType::Empty => StackType::Empty,
+        }
+    }
+
+    fn instantiate_type(
+        &self,
+        ty: &Type,
+        fresh: &mut Substitution,
+        fresh_rows: &mut StackSubstitution,
+    ) -> Type {
+        match ty {
+            Type::Var(name) => fresh
+                .entry(name.clone())
+                .or_insert_with(|| Type::Var(self.fresh_name("t")))
+                .clone(),
+            Type::Named { name, args } => Type::Named {
+                name: name.clone(),
+                args: args
+                    .iter()
+                    .map(|arg| self.instantiate_type(arg, fresh, fresh_rows))
+                    .collect(),
+            },
+            Type::Quotation(effect) => Type::Quotation(Box::new(Effect::new(
+                self.instantiate_stack(&effect.inputs, fresh, fresh_rows),
+                self.instantiate_stack(&effect.outputs, fresh, fresh_rows),
+            ))),
+            _ => ty.clone(),
         }
     }
 
@@ -165,8 +857,10 @@ impl TypeChecker {
         // Try to unify the effect's input with the current stack
         // This handles polymorphic effects like dup: (A -- A A)
 
-        let input_depth = effect.inputs.depth().unwrap_or(0);
-        let stack_depth = stack.depth().unwrap_or(0);
+        // Only the concrete types above each stack's base take part; the rest of
+        // the stack passes through untouched
+        let input_depth = effect.inputs.known_depth();
+        let stack_depth = stack.known_depth();
 
         if stack_depth < input_depth {
             return Err(Box::new(TypeError::StackUnderflow {
@@ -176,6 +870,17 @@ impl TypeChecker {
             }));
         }
 
+        // A row variable ties the effect to the whole stack: its inputs' row
+        // stands for whatever lies beneath, which the outputs then sit on
+        if effect.inputs.base().is_row_var() {
+            let (type_subst, stack_subst) = unify_stack_types(&effect.inputs, &stack)
+                .and_then(|substs| self.learn(&substs.0).map(|_| substs))
+                .map_err(|e| TypeError::Other {
+                    message: format!("Cannot apply '{}': input type mismatch: {}", word_name, e),
+                })?;
+            return Ok(substitute_stack(&effect.outputs, &type_subst, &stack_subst));
+        }
+
         // For simple case: try unification
         // Split the stack into "will be consumed" and "will remain"
         let mut remaining_stack = stack.clone();
@@ -201,6 +906,7 @@ impl TypeChecker {
         // Now unify consumed types with effect.inputs
         let consumed_stack = StackType::from_vec(consumed);
         let (type_subst, _stack_subst) = unify_stack_types(&consumed_stack, &effect.inputs)
+            .and_then(|substs| self.learn(&substs.0).map(|_| substs))
             .map_err(|e| TypeError::Other {
                 message: format!("Cannot apply '{}': input type mismatch: {}", word_name, e),
             })?;
@@ -208,8 +914,9 @@ impl TypeChecker {
         // Apply substitution to outputs
         let output_stack = Self::apply_type_substitution(&effect.outputs, &type_subst);
 
-        // Rebuild stack: remaining + outputs
-        let mut result = remaining_stack;
+        // Rebuild stack: remaining + outputs. What the inputs learned about the
+        // caller's type variables holds for the rest of its stack too.
+        let mut result = Self::apply_type_substitution(&remaining_stack, &type_subst);
         let mut outputs_vec = Vec::new();
         let mut temp = output_stack;
         while let Some((rest, top)) = temp.pop() {
@@ -225,10 +932,7 @@ impl TypeChecker {
     }
 
     /// Apply type substitution to a stack type
-    fn apply_type_substitution(
-        stack: &StackType,
-        subst: &crate::typechecker::unification::Substitution,
-    ) -> StackType {
+    fn apply_type_substitution(stack: &StackType, subst: &Substitution) -> StackType {
         match stack {
             StackType::Empty => StackType::Empty,
             StackType::Cons { rest, top } => {
@@ -244,10 +948,7 @@ impl TypeChecker {
     }
 
     /// Apply type substitution to a type
-    fn apply_type_subst_to_type(
-        ty: &Type,
-        subst: &crate::typechecker::unification::Substitution,
-    ) -> Type {
+    fn apply_type_subst_to_type(ty: &Type, subst: &Substitution) -> Type {
         match ty {
             Type::Var(name) => subst.get(name).cloned().unwrap_or_else(|| ty.clone()),
             Type::Named { name, args } => Type::Named {
@@ -257,23 +958,10 @@ impl TypeChecker {
                     .map(|arg| Self::apply_type_subst_to_type(arg, subst))
                     .collect(),
             },
-            Type::Quotation(eff) => {
-                // TODO(#10): Implement recursive substitution into quotation effects
-                //
-                // KNOWN LIMITATION: Type substitution doesn't recurse into quotation
-                // effects. This will break when we have polymorphic quotations like:
-                //
-                //   : apply ( a [a -- b] -- b ) call ;
-                //
-                // When instantiating type variables 'a' and 'b', the quotation's effect
-                // won't be updated, causing incorrect type inference.
-                //
-                // To fix: implement apply_subst_to_effect that recursively substitutes
-                // in both input and output stack types of the effect.
-                //
-                // For now: just clone the effect without substitution
-                Type::Quotation(eff.clone())
-            }
+            Type::Quotation(eff) => Type::Quotation(Box::new(Effect::new(
+                Self::apply_type_substitution(&eff.inputs, subst),
+                Self::apply_type_substitution(&eff.outputs, subst),
+            ))),
             _ => ty.clone(),
         }
     }
@@ -294,99 +982,417 @@ impl TypeChecker {
                 available: 0,
             })?;
 
-        // Get the type name from scrutinee
-        let type_name = match &scrutinee_type {
-            Type::Named { name, .. } => name.clone(),
-            _ => {
-                return Err(Box::new(TypeError::Other {
-                    message: format!("Cannot pattern match on non-ADT type: {}", scrutinee_type),
-                }));
-            }
-        };
-
-        // Check exhaustiveness (all variants covered)
-        let variants =
-            self.env
-                .get_variants(&type_name)
-                .ok_or_else(|| TypeError::UndefinedType {
-                    name: type_name.clone(),
-                })?;
+        // A wildcard branch covers every variant (or integer) not named explicitly
+        let has_wildcard = branches.iter().any(|b| b.pattern == Pattern::Wildcard);
 
-        let covered_variants: Vec<_> = branches
+        let int_patterns = branches
             .iter()
-            .map(|b| match &b.pattern {
-                Pattern::Variant { name } => name.as_str(),
-            })
-            .collect();
+            .any(|b| matches!(b.pattern, Pattern::IntLit(_)));
 
-        let missing: Vec<_> = variants
+        let bool_patterns = branches
             .iter()
-            .filter(|v| !covered_variants.contains(&v.name.as_str()))
-            .map(|v| v.name.clone())
-            .collect();
+            .any(|b| matches!(b.pattern, Pattern::BoolLit(_)));
 
-        if !missing.is_empty() {
-            return Err(Box::new(TypeError::NonExhaustiveMatch {
-                type_name: type_name.clone(),
-                missing_variants: missing,
-            }));
-        }
+        let (type_name, variants): (String, &[Variant]) = if int_patterns
+            || (!bool_patterns && scrutinee_type == Type::Int)
+        {
+            self.check_int_patterns(branches, &scrutinee_type, has_wildcard)?;
+            ("Int".to_string(), &[])
+        } else if bool_patterns || scrutinee_type == Type::Bool {
+            self.check_bool_patterns(branches, &scrutinee_type, has_wildcard)?;
+            ("Bool".to_string(), &[])
+        } else {
+            // Get the type name from scrutinee
+            let type_name = match &scrutinee_type {
+                Type::Named { name, .. } => name.clone(),
+                _ => {
+                    return Err(Box::new(TypeError::Other {
+                        message: format!(
+                            "Cannot pattern match on non-ADT type: {}",
+                            scrutinee_type
+                        ),
+                    }));
+                }
+            };
 
-        // Type check each branch and verify they all produce same effect
-        let mut branch_results = Vec::new();
+            // Check exhaustiveness (all variants covered)
+            let variants =
+                self.env
+                    .get_variants(&type_name)
+                    .ok_or_else(|| TypeError::UndefinedType {
+                        name: type_name.clone(),
+                    })?;
 
-        for branch in branches {
-            // Get the variant definition
-            let variant = variants
+            let covered_variants: Vec<_> = branches
                 .iter()
-                .find(|v| match &branch.pattern {
-                    Pattern::Variant { name } => v.name == *name,
+                .filter_map(|b| match &b.pattern {
+                    Pattern::Variant { name, .. } => Some(name.as_str()),
+                    Pattern::IntLit(_)
+                    | Pattern::BoolLit(_)
+                    | Pattern::Binding(_)
+                    | Pattern::Wildcard => None,
                 })
-                .ok_or_else(|| TypeError::Other {
-                    message: "Unknown variant in pattern".to_string(),
-                })?;
+                .collect();
+
+            let mut missing: Vec<_> = variants
+                .iter()
+                .filter(|v| !has_wildcard && !covered_variants.contains(&v.name.as_str()))
+                .map(|v| v.name.clone())
+                .collect();
+
+            // Every variant has a branch, but patterns on fields may still
+            // leave some of its values out
+            if missing.is_empty() && !has_wildcard {
+                let rows: Vec<Vec<&Pattern>> = branches.iter().map(|b| vec![&b.pattern]).collect();
+                missing.extend(
+                    self.uncovered(&rows, std::slice::from_ref(&scrutinee_type))
+                        .into_iter()
+                        .flatten(),
+                );
+            }
+
+            if !missing.is_empty() {
+                return Err(Box::new(TypeError::NonExhaustiveMatch {
+                    type_name: type_name.clone(),
+                    missing_variants: missing,
+                }));
+            }
+
+            (type_name, variants)
+        };
+
+        // Field types mention the type's parameters; substitute the scrutinee's arguments
+        let field_subst: Substitution = match (&scrutinee_type, self.env.lookup_type(&type_name)) {
+            (Type::Named { args, .. }, Some(typedef)) => typedef
+                .type_params
+                .iter()
+                .cloned()
+                .zip(args.iter().cloned())
+                .collect(),
+            _ => Substitution::new(),
+        };
+
+        // Type check each branch and verify they all produce same effect
+        let mut branch_results = Vec::new();
+
+        // Only one branch runs, so each starts from the same moved bindings and
+        // anything moved on some branch counts as moved afterwards
+        let moved_before = self.moved.borrow().clone();
+        let mut moved_after = HashSet::new();
 
-            // Pattern destructures: push variant fields onto stack
+        for branch in branches {
             let mut branch_stack = stack_after_pop.clone();
-            for field_type in &variant.fields {
-                branch_stack = branch_stack.push(field_type.clone());
+            let mut scoped = None;
+            self.moved.replace(moved_before.clone());
+
+            if let Pattern::Variant { name, fields } = &branch.pattern {
+                // Get the variant definition
+                let variant =
+                    variants
+                        .iter()
+                        .find(|v| v.name == *name)
+                        .ok_or_else(|| TypeError::Other {
+                            message: "Unknown variant in pattern".to_string(),
+                        })?;
+
+                let field_types: Vec<_> = variant
+                    .fields
+                    .iter()
+                    .map(|field_type| Self::apply_type_subst_to_type(field_type, &field_subst))
+                    .collect();
+
+                if fields.is_empty() {
+                    // Pattern destructures: push variant fields onto stack
+                    for field_type in field_types {
+                        branch_stack = branch_stack.push(field_type);
+                    }
+                } else {
+                    if fields.len() != variant.fields.len() {
+                        return Err(Box::new(TypeError::PatternArityMismatch {
+                            variant: name.clone(),
+                            expected: variant.fields.len(),
+                            actual: fields.len(),
+                        }));
+                    }
+
+                    // Named fields stay off the stack; each binding behaves like a
+                    // word that pushes its field, visible only in this branch
+                    let mut bindings = Vec::new();
+                    self.bind_fields(fields, &field_types, &mut bindings)?;
+                    let names: Vec<String> =
+                        bindings.iter().map(|(name, _)| name.clone()).collect();
+                    scoped = Some((self.with_bindings(&bindings), names));
+                }
             }
+            // Wildcard and literal patterns: the scrutinee is consumed and nothing is pushed
 
             // Type check branch body
-            for expr in &branch.body {
-                branch_stack = self.check_expr(expr, branch_stack)?;
+            let checker = scoped.as_ref().map_or(self, |(checker, _)| checker);
+            branch_stack = checker.check_body(&branch.body, branch_stack)?;
+            self.fresh_counter.set(checker.fresh_counter.get());
+
+            if let Some((checker, bindings)) = scoped {
+                self.learned.replace(checker.learned.into_inner());
+
+                // The branch's own bindings go out of scope; shadowed ones come back
+                let mut moved = checker.moved.into_inner();
+                for binding in bindings {
+                    moved.remove(&binding);
+                    if moved_before.contains(&binding) {
+                        moved.insert(binding);
+                    }
+                }
+                self.moved.replace(moved);
             }
+            moved_after.extend(self.moved.borrow().iter().cloned());
 
             branch_results.push(branch_stack);
         }
+        self.moved.replace(moved_after);
 
         // All branches must produce the same stack effect
-        let first_result = &branch_results[0];
+        let mut joined = branch_results[0].clone();
         for (i, result) in branch_results.iter().enumerate().skip(1) {
-            let (_, _) = unify_stack_types(first_result, result).map_err(|_| {
+            joined = self.join_branches(&joined, result).map_err(|_| {
                 TypeError::InconsistentBranchEffects {
                     type_name: type_name.clone(),
-                    expected: Effect::new(stack_after_pop.clone(), first_result.clone()),
+                    expected: Effect::new(stack_after_pop.clone(), joined.clone()),
                     actual: Effect::new(stack_after_pop.clone(), result.clone()),
                     branch: format!("branch {}", i),
                 }
             })?;
         }
 
-        Ok(first_result.clone())
+        Ok(joined)
     }
-}
 
-impl Default for TypeChecker {
-    fn default() -> Self {
-        Self::new()
+    /// Collect the names a variant's field patterns bind, with their types
+    ///
+    /// A field pattern that names a variant must name one of the field's
+    /// type, with a pattern for each of its fields.
+    fn bind_fields(
+        &self,
+        fields: &[Pattern],
+        field_types: &[Type],
+        bindings: &mut Vec<(String, Type)>,
+    ) -> TypeResult<()> {
+        for (field, field_type) in fields.iter().zip(field_types) {
+            match field {
+                Pattern::Binding(name) => bindings.push((name.clone(), field_type.clone())),
+                Pattern::Variant { name, fields } => {
+                    let inner_types = self
+                        .variants_of(field_type)
+                        .and_then(|variants| {
+                            variants
+                                .into_iter()
+                                .find_map(|(variant, types)| (variant == *name).then_some(types))
+                        })
+                        .ok_or_else(|| TypeError::Other {
+                            message: format!(
+                                "Variant '{}' doesn't match a field of type {}",
+                                name, field_type
+                            ),
+                        })?;
+                    if fields.len() != inner_types.len() {
+                        return Err(Box::new(TypeError::PatternArityMismatch {
+                            variant: name.clone(),
+                            expected: inner_types.len(),
+                            actual: fields.len(),
+                        }));
+                    }
+                    self.bind_fields(fields, &inner_types, bindings)?;
+                }
+                Pattern::Wildcard => {}
+                Pattern::IntLit(_) | Pattern::BoolLit(_) => {
+                    return Err(Box::new(TypeError::Other {
+                        message: format!("Cannot match a field against literal '{}'", field),
+                    }));
+                }
+            }
+        }
+        Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    // Test imports (currently unused)
+
+    /// The variants of an ADT, each with its field types for `ty`'s arguments
+    fn variants_of(&self, ty: &Type) -> Option<Vec<(String, Vec<Type>)>> {
+        let Type::Named { name, args } = ty else {
+            return None;
+        };
+        let typedef = self.env.lookup_type(name)?;
+        let subst: Substitution = typedef
+            .type_params
+            .iter()
+            .cloned()
+            .zip(args.iter().cloned())
+            .collect();
+        Some(
+            typedef
+                .variants
+                .iter()
+                .map(|variant| {
+                    let fields = variant
+                        .fields
+                        .iter()
+                        .map(|field| Self::apply_type_subst_to_type(field, &subst))
+                        .collect();
+                    (variant.name.clone(), fields)
+                })
+                .collect(),
+        )
+    }
+
+    /// A value no row of patterns matches, if there is one, written as a
+    /// pattern for each of `types`
+    ///
+    /// Each row holds a pattern per type. A variant pattern without field
+    /// patterns unwraps its fields, so it matches any of them.
+    fn uncovered(&self, rows: &[Vec<&Pattern>], types: &[Type]) -> Option<Vec<String>> {
+        let Some((first, rest)) = types.split_first() else {
+            return rows.is_empty().then(Vec::new);
+        };
+
+        // Variants are only enumerated when some row tells them apart
+        let variants = self.variants_of(first).filter(|_| {
+            rows.iter()
+                .any(|row| matches!(row[0], Pattern::Variant { .. }))
+        });
+        let Some(variants) = variants else {
+            let remaining: Vec<Vec<&Pattern>> = rows
+                .iter()
+                .filter(|row| row[0].is_irrefutable())
+                .map(|row| row[1..].to_vec())
+                .collect();
+            let mut witness = self.uncovered(&remaining, rest)?;
+            witness.insert(0, "_".to_string());
+            return Some(witness);
+        };
+
+        variants.iter().find_map(|(name, field_types)| {
+            let arity = field_types.len();
+            let specialized: Vec<Vec<&Pattern>> = rows
+                .iter()
+                .filter_map(|row| {
+                    let fields: Vec<&Pattern> = match row[0] {
+                        Pattern::Variant { name: v, fields } if v == name && !fields.is_empty() => {
+                            fields.iter().collect()
+                        }
+                        Pattern::Variant { name: v, .. } if v == name => vec![&WILDCARD; arity],
+                        pattern if pattern.is_irrefutable() => vec![&WILDCARD; arity],
+                        _ => return None,
+                    };
+                    Some(fields.into_iter().chain(row[1..].iter().copied()).collect())
+                })
+                .collect();
+
+            let types: Vec<Type> = field_types.iter().chain(rest).cloned().collect();
+            let mut witness = self.uncovered(&specialized, &types)?;
+            let fields: Vec<String> = witness.drain(..arity).collect();
+            let head = if fields.is_empty() {
+                name.clone()
+            } else {
+                format!("{}({})", name, fields.join(", "))
+            };
+            witness.insert(0, head);
+            Some(witness)
+        })
+    }
+
+    /// Validate the patterns of a match on an `Int` scrutinee
+    ///
+    /// Integers can't be enumerated, so a wildcard branch is required.
+    fn check_int_patterns(
+        &self,
+        branches: &[MatchBranch],
+        scrutinee_type: &Type,
+        has_wildcard: bool,
+    ) -> TypeResult<()> {
+        unify_types(scrutinee_type, &Type::Int)
+            .and_then(|subst| self.learn(&subst))
+            .map_err(|_| TypeError::TypeMismatch {
+                expected: Type::Int,
+                actual: scrutinee_type.clone(),
+                context: "integer pattern match".to_string(),
+            })?;
+
+        if let Some(name) = branches.iter().find_map(|b| match &b.pattern {
+            Pattern::Variant { name, .. } => Some(name),
+            _ => None,
+        }) {
+            return Err(Box::new(TypeError::Other {
+                message: format!("Cannot match variant '{}' against an Int scrutinee", name),
+            }));
+        }
+
+        if !has_wildcard {
+            return Err(Box::new(TypeError::Other {
+                message: "Non-exhaustive pattern match on type 'Int': add a wildcard '_' branch"
+                    .to_string(),
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Validate the patterns of a match on a `Bool` scrutinee
+    ///
+    /// Without a wildcard, both `true` and `false` need a branch.
+    fn check_bool_patterns(
+        &self,
+        branches: &[MatchBranch],
+        scrutinee_type: &Type,
+        has_wildcard: bool,
+    ) -> TypeResult<()> {
+        unify_types(scrutinee_type, &Type::Bool)
+            .and_then(|subst| self.learn(&subst))
+            .map_err(|_| TypeError::TypeMismatch {
+                expected: Type::Bool,
+                actual: scrutinee_type.clone(),
+                context: "boolean pattern match".to_string(),
+            })?;
+
+        if let Some(name) = branches.iter().find_map(|b| match &b.pattern {
+            Pattern::Variant { name, .. } => Some(name),
+            _ => None,
+        }) {
+            return Err(Box::new(TypeError::Other {
+                message: format!("Cannot match variant '{}' against a Bool scrutinee", name),
+            }));
+        }
+
+        let missing: Vec<String> = [true, false]
+            .into_iter()
+            .filter(|value| {
+                !has_wildcard
+                    && !branches
+                        .iter()
+                        .any(|b| b.pattern == Pattern::BoolLit(*value))
+            })
+            .map(|value| value.to_string())
+            .collect();
+        if !missing.is_empty() {
+            return Err(Box::new(TypeError::NonExhaustiveMatch {
+                type_name: "Bool".to_string(),
+                missing_variants: missing,
+            }));
+        }
+
+        Ok(())
+    }
+}
+
+/// Stands in for the fields of a pattern that doesn't name them
+static WILDCARD: Pattern = Pattern::Wildcard;
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    // Test imports (currently unused)
 
     #[test]
     fn test_check_literals() {
@@ -402,6 +1408,11 @@ mod tests {
         // Bool literal
         let result = checker.check_expr(&Expr::BoolLit(true, SourceLoc::unknown()), stack.clone());
         assert!(result.is_ok());
+
+        // Float literal
+        let result = checker.check_expr(&Expr::FloatLit(2.5, SourceLoc::unknown()), stack.clone());
+        let (_, top) = result.unwrap().pop().unwrap();
+        assert_eq!(top, Type::Float);
     }
 
     #[test]
@@ -440,6 +1451,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_errors_are_located_at_the_expression() {
+        let source = ": main ( -- )\n  1 drop\n  [ 2 unknown ] drop ;\n";
+        let program = crate::parser::Parser::new_with_filename(source, "main.cem")
+            .parse()
+            .unwrap();
+        let error = TypeChecker::new().check_program(&program).unwrap_err();
+
+        // The call inside the quotation, not the quotation or the word
+        let loc = error.loc().expect("error should be located");
+        assert_eq!((loc.line, loc.column), (3, 7));
+        assert_eq!(
+            error.to_string(),
+            "main.cem:3:7: in word 'main': Undefined word: 'unknown'"
+        );
+    }
+
     #[test]
     fn test_stack_underflow() {
         let checker = TypeChecker::new();
@@ -456,4 +1484,878 @@ mod tests {
             e => panic!("Expected StackUnderflow, got {:?}", e),
         }
     }
+
+    #[test]
+    fn test_infer_quotation_effect() {
+        let checker = TypeChecker::new();
+        let quotation = Expr::Quotation(
+            vec![
+                Expr::IntLit(1, SourceLoc::unknown()),
+                Expr::WordCall("+".to_string(), SourceLoc::unknown()),
+            ],
+            SourceLoc::unknown(),
+        );
+
+        let stack = checker.check_expr(&quotation, StackType::empty()).unwrap();
+        let (_, top) = stack.pop().unwrap();
+        let Type::Quotation(effect) = top else {
+            panic!("Expected quotation type, got {}", top);
+        };
+
+        // [ 1 + ] needs an Int beneath the literal: ( R Int -- R Int )
+        let row = StackType::RowVar("_r0".to_string());
+        assert_eq!(effect.inputs, row.clone().push(Type::Int));
+        assert_eq!(effect.outputs, row.push(Type::Int));
+    }
+
+    /// Check a program, unwrapping errors from word bodies for easier matching
+    fn check_source(source: &str) -> TypeResult<()> {
+        let program = crate::parser::Parser::new(source)
+            .parse()
+            .expect("test program should parse");
+        TypeChecker::new()
+            .check_program(&program)
+            .map_err(|e| Box::new(e.unlocated().clone()))
+    }
+
+    #[test]
+    fn test_wildcard_makes_match_exhaustive() {
+        let result = check_source(
+            ": is-some ( Option(Int) -- Bool ) match Some => [ drop true ] _ => [ false ] end ;",
+        );
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn test_missing_variant_without_wildcard() {
+        let result =
+            check_source(": is-some ( Option(Int) -- Bool ) match Some => [ drop true ] end ;");
+        match *result.unwrap_err() {
+            TypeError::NonExhaustiveMatch {
+                missing_variants, ..
+            } => assert_eq!(missing_variants, vec!["None".to_string()]),
+            e => panic!("Expected NonExhaustiveMatch, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_pattern_bindings_push_fields() {
+        let result = check_source(
+            ": unwrap-or-zero ( Option(Int) -- Int ) match Some(x) => [ x ] None => [ 0 ] end ;",
+        );
+        assert!(result.is_ok(), "{:?}", result);
+
+        // Bindings are only visible inside their own branch
+        let result =
+            check_source(": f ( Option(Int) -- Int ) match Some(x) => [ x ] None => [ x ] end ;");
+        assert!(matches!(
+            *result.unwrap_err(),
+            TypeError::UndefinedWord { ref name } if name == "x"
+        ));
+    }
+
+    #[test]
+    fn test_binding_in_quotation_is_rejected() {
+        // A quotation is compiled apart from the match, so it can't reach `x`
+        let source = ": f ( -- Int )\n  5 Some match\n    Some(x) => [ [ x ] call ]\n    None => [ 0 ]\n  end ;";
+        let program = crate::parser::Parser::new_with_filename(source, "f.cem")
+            .parse()
+            .unwrap();
+        let error = TypeChecker::new().check_program(&program).unwrap_err();
+        assert_eq!(
+            *error.unlocated(),
+            TypeError::BindingInQuotation {
+                name: "x".to_string()
+            }
+        );
+        let loc = error.loc().expect("error should be located");
+        assert_eq!((loc.line, loc.column), (3, 20));
+
+        // Passing the value in on the stack works, as do bindings of a match
+        // inside the quotation, even one that shadows an outer binding
+        assert!(
+            check_source(
+                ": f ( -- Int ) 5 Some match Some(x) => [ x [ 1 + ] call ] None => [ 0 ] end ;"
+            )
+            .is_ok()
+        );
+        let source = ": f ( -- Int ) 5 Some match Some(x) => [ [ 7 Some match Some(x) => [ x ] None => [ 0 ] end ] call ] None => [ 0 ] end ;";
+        assert!(check_source(source).is_ok());
+    }
+
+    #[test]
+    fn test_pattern_binding_count_mismatch() {
+        let result = check_source(
+            ": f ( Option(Int) -- Int ) match Some(a, b) => [ a ] None => [ 0 ] end ;",
+        );
+        let err = result.unwrap_err();
+        assert_eq!(
+            *err,
+            TypeError::PatternArityMismatch {
+                variant: "Some".to_string(),
+                expected: 1,
+                actual: 2,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Pattern 'Some' binds 2 field(s), but the variant has 1"
+        );
+    }
+
+    #[test]
+    fn test_nested_patterns() {
+        let result = check_source(
+            ": second ( List(Int) -- Int ) match Cons(_, Cons(x, _)) => [ x ] Cons(_, Nil) => [ 0 ] Nil => [ 0 ] end ;",
+        );
+        assert!(result.is_ok(), "{:?}", result);
+
+        // Nested bindings get the inner variant's field types
+        let result = check_source(
+            ": f ( Option(Option(String)) -- String ) match Some(Some(s)) => [ s ] _ => [ \"\" ] end ;",
+        );
+        assert!(result.is_ok(), "{:?}", result);
+
+        // The nested variant must belong to the field's type
+        let err =
+            check_source(": f ( List(Int) -- Int ) match Cons(_, None) => [ 0 ] _ => [ 1 ] end ;")
+                .unwrap_err();
+        assert!(err.to_string().contains("Variant 'None'"), "{}", err);
+    }
+
+    #[test]
+    fn test_nested_pattern_exhaustiveness() {
+        let missing = |source: &str| match *check_source(source).unwrap_err() {
+            TypeError::NonExhaustiveMatch {
+                missing_variants, ..
+            } => missing_variants,
+            err => panic!("Expected NonExhaustiveMatch, got {}", err),
+        };
+
+        assert_eq!(
+            missing(
+                ": f ( List(Int) -- Int ) match Cons(_, Cons(x, _)) => [ x ] Nil => [ 0 ] end ;"
+            ),
+            vec!["Cons(_, Nil)"]
+        );
+        assert_eq!(
+            missing(
+                ": f ( Option(Option(Int)) -- Int ) match Some(None) => [ 0 ] None => [ 0 ] end ;"
+            ),
+            vec!["Some(Some(_))"]
+        );
+
+        // A binding covers whatever the nested patterns leave out
+        let result = check_source(
+            ": f ( List(Int) -- Int ) match Cons(_, Nil) => [ 1 ] Cons(_, rest) => [ rest drop 2 ] Nil => [ 0 ] end ;",
+        );
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn test_int_patterns() {
+        let result = check_source(
+            ": describe ( Int -- String ) match 0 => [ \"zero\" ] 1 => [ \"one\" ] _ => [ \"many\" ] end ;",
+        );
+        assert!(result.is_ok(), "{:?}", result);
+
+        // Integers can't be enumerated, so the wildcard is mandatory
+        let result = check_source(": f ( Int -- Int ) match 0 => [ 1 ] 1 => [ 0 ] end ;");
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("wildcard"), "{}", err);
+
+        // Integer patterns need an Int scrutinee
+        let result = check_source(": f ( Bool -- Int ) match 0 => [ 1 ] _ => [ 0 ] end ;");
+        assert!(matches!(
+            *result.unwrap_err(),
+            TypeError::TypeMismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn test_bool_patterns() {
+        for source in [
+            ": describe ( Bool -- String ) match true => [ \"yes\" ] false => [ \"no\" ] end ;",
+            ": describe ( Bool -- String ) match false => [ \"no\" ] _ => [ \"yes\" ] end ;",
+        ] {
+            let result = check_source(source);
+            assert!(result.is_ok(), "{}: {:?}", source, result);
+        }
+
+        // Without a wildcard, both values need a branch
+        let err = check_source(": f ( Bool -- Int ) match true => [ 1 ] end ;").unwrap_err();
+        assert!(
+            matches!(&*err, TypeError::NonExhaustiveMatch { missing_variants, .. } if missing_variants == &["false"]),
+            "{}",
+            err
+        );
+
+        // Boolean patterns need a Bool scrutinee, and a Bool can't match variants
+        let result = check_source(": f ( Int -- Int ) match true => [ 1 ] _ => [ 0 ] end ;");
+        assert!(matches!(
+            *result.unwrap_err(),
+            TypeError::TypeMismatch { .. }
+        ));
+        let err =
+            check_source(": f ( Bool -- Int ) match None => [ 1 ] _ => [ 0 ] end ;").unwrap_err();
+        assert!(err.to_string().contains("against a Bool"), "{}", err);
+    }
+
+    #[test]
+    fn test_wildcard_branch_consumes_scrutinee() {
+        // The wildcard body starts without the Option on the stack, so it must push a Bool
+        let result =
+            check_source(": f ( Option(Int) -- Bool ) match Some => [ drop true ] _ => [ ] end ;");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_call_applies_quotation_effect() {
+        assert!(check_source(": inc ( Int -- Int ) [ 1 + ] call ;").is_ok());
+        assert!(check_source(": three ( -- Int ) [ 1 2 + ] call ;").is_ok());
+        assert!(check_source(": three ( -- ) [ 1 2 + ] call ;").is_err());
+
+        // [ 1 + ] has effect ( Int -- Int ), so calling it cannot produce a String
+        let result = check_source(": broken ( Int -- String ) [ 1 + ] call ;");
+        assert!(matches!(
+            *result.unwrap_err(),
+            TypeError::EffectMismatch { ref word, .. } if word == "broken"
+        ));
+    }
+
+    #[test]
+    fn test_call_rejects_quotation_with_wrong_input_type() {
+        // The quotation's body decides that its input is an Int
+        assert!(check_source(": bad ( String -- Int ) [ 1 + ] call ;").is_err());
+        assert!(check_source(": main ( -- Int ) \"hello\" [ 1 + ] call ;").is_err());
+        assert!(check_source(": main ( -- Int ) 41 [ 1 + ] call ;").is_ok());
+    }
+
+    #[test]
+    fn test_spawn_takes_quotation_with_empty_effect() {
+        assert!(check_source(": go ( -- ) [ \"hi\" write_line ] spawn ;").is_ok());
+        assert!(check_source(": go ( Int -- Int ) [ 1 2 + drop ] spawn ;").is_ok());
+
+        // The strand starts with an empty stack and its result is discarded
+        assert!(check_source(": go ( Int -- ) [ drop ] spawn ;").is_err());
+        assert!(check_source(": go ( -- ) [ 1 ] spawn ;").is_err());
+        assert!(check_source(": go ( -- ) 1 spawn ;").is_err());
+    }
+
+    #[test]
+    fn test_join_returns_quotation_result_type() {
+        let source = ": go ( -- Int ) [ 6 7 * ] spawn-result join ;";
+        assert!(check_source(source).is_ok());
+        let source = ": go ( -- String ) [ 6 7 * ] spawn-result join ;";
+        assert!(check_source(source).is_err());
+
+        // A strand can only be joined once
+        let source = ": go ( -- Int Int ) [ 1 ] spawn-result dup join swap join ;";
+        assert!(check_source(source).is_err());
+    }
+
+    #[test]
+    fn test_equal_compares_values_of_one_type() {
+        assert!(check_source(": same ( String String -- Bool ) equal ;").is_ok());
+        assert!(check_source(": same ( Bool Bool -- Bool ) equal ;").is_ok());
+        assert!(check_source(": same ( Int String -- Bool ) equal ;").is_err());
+    }
+
+    #[test]
+    fn test_channel_words() {
+        let source = ": go ( -- Int ) channel dup [ 1 send drop ] spawn-with recv swap drop ;";
+        assert!(check_source(source).is_ok());
+
+        // Handles are Copy, and a channel carries one type of value
+        let source = ": go ( -- ) channel dup drop drop ;";
+        assert!(check_source(source).is_ok());
+        let source = ": go ( -- Int ) channel 1 send \"s\" send recv swap drop ;";
+        assert!(check_source(source).is_err());
+
+        // The spawned quotation must consume the value it's handed
+        let source = ": go ( -- ) channel [ drop 1 ] spawn-with ;";
+        assert!(check_source(source).is_err());
+    }
+
+    #[test]
+    fn test_dip_runs_quotation_beneath_top() {
+        // 5 10 [ 1 + ] dip leaves 6 10
+        let source = ": lower ( -- Int Int ) 5 10 [ 1 + ] dip ;";
+        assert!(check_source(source).is_ok());
+        let source = ": hidden ( -- Int String ) 5 \"s\" [ 1 + ] dip ;";
+        assert!(check_source(source).is_ok());
+
+        // The quotation can't reach the hidden String
+        let source = ": broken ( -- Int ) \"s\" [ 1 + ] dip ;";
+        assert!(check_source(source).is_err());
+    }
+
+    #[test]
+    fn test_quotation_effect_checked_when_passed() {
+        let source = r#"
+            : apply-int ( Int [Int -- Int] -- Int ) call ;
+            : good ( Int -- Int ) [ 2 * ] apply-int ;
+        "#;
+        assert!(check_source(source).is_ok());
+
+        let source = r#"
+            : apply-int ( Int [Int -- Int] -- Int ) call ;
+            : bad ( Int -- Int ) [ int-to-string ] apply-int ;
+        "#;
+        let err = check_source(source).unwrap_err();
+        assert!(err.to_string().contains("apply-int"), "{}", err);
+    }
+
+    #[test]
+    fn test_row_variable_ties_quotation_to_callers_stack() {
+        // The quotation runs on the caller's stack, whatever it holds
+        let apply = ": apply ( ..R [ ..R -- ..S ] -- ..S ) call ;\n";
+        let source = format!("{}: add ( Int Int -- Int ) [ + ] apply ;", apply);
+        assert!(check_source(&source).is_ok());
+        let source = format!("{}: short ( Int -- Int ) [ + ] apply ;", apply);
+        assert!(check_source(&source).is_err());
+
+        // The same row on both sides: the quotation may use the stack, but must
+        // leave it the same shape
+        let keep = ": keep ( ..R [ ..R -- ..R ] -- ..R ) call ;\n";
+        let source = format!("{}: swapped ( Int Int -- Int Int ) [ swap ] keep ;", keep);
+        assert!(check_source(&source).is_ok());
+        let source = format!("{}: sum ( Int Int -- Int ) [ + ] keep ;", keep);
+        assert!(check_source(&source).is_err());
+        let source = format!("{}: short ( Int -- Int ) [ swap ] keep ;", keep);
+        assert!(check_source(&source).is_err());
+    }
+
+    #[test]
+    fn test_row_variables_are_chosen_by_the_caller() {
+        assert!(check_source(": my-dup ( ..R Int -- ..R Int Int ) dup ;").is_ok());
+
+        // The body can't decide what a row variable stands for
+        let err = check_source(": f ( ..R Int -- ..S Int ) ;").unwrap_err();
+        assert!(matches!(*err, TypeError::EffectMismatch { .. }), "{}", err);
+        assert!(check_source(": f ( ..R Int -- Int ) ;").is_err());
+        assert!(check_source(": f ( Int -- ..R Int ) ;").is_err());
+    }
+
+    #[test]
+    fn test_type_variables_are_chosen_by_the_caller() {
+        assert!(check_source(": my-swap ( a b -- b a ) swap ;").is_ok());
+        assert!(check_source(": my-nip ( a b -- b ) swap drop ;").is_ok());
+
+        // The body can't decide what a type variable is
+        let err = check_source(": f ( a -- Int ) 1 + ;").unwrap_err();
+        assert!(
+            matches!(*err, TypeError::RigidTypeVariable { ref var, actual: Type::Int, .. } if var == "a"),
+            "{}",
+            err
+        );
+
+        // Nor that two of them are the same type
+        let err = check_source(": f ( a b -- a ) swap drop ;").unwrap_err();
+        assert!(
+            matches!(*err, TypeError::RigidTypeVariable { .. }),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_if_branches_run_inline() {
+        assert!(check_source(": pick-one ( Bool -- Int ) if [ 1 ] [ 2 ] ;").is_ok());
+        assert!(check_source(": f ( Bool -- Int ) if [ 1 ] [ true ] ;").is_err());
+    }
+
+    #[test]
+    fn test_dup_requires_copy_type() {
+        assert!(check_source(": square ( Int -- Int ) dup * ;").is_ok());
+        assert!(check_source(": twice ( Bool -- Bool Bool ) dup ;").is_ok());
+
+        let err = check_source(": twice ( String -- String String )\n  dup ;").unwrap_err();
+        match &*err {
+            TypeError::CannotDuplicate { ty, operation, loc } => {
+                assert_eq!(*ty, Type::String);
+                assert_eq!(operation, "dup");
+                assert_eq!((loc.line, loc.column), (2, 3));
+            }
+            e => panic!("Expected CannotDuplicate, got {:?}", e),
+        }
+        assert!(err.to_string().contains("clone"));
+
+        // clone is the explicit way to copy a linear value
+        assert!(check_source(": twice ( String -- String String ) clone ;").is_ok());
+    }
+
+    #[test]
+    fn test_over_and_tuck_copy_linear_values() {
+        // over copies the second value, tuck the top one
+        assert!(check_source(": f ( String Int -- String Int String ) over ;").is_err());
+        assert!(check_source(": f ( Int String -- Int String Int ) over ;").is_ok());
+        assert!(check_source(": f ( Int String -- String Int String ) tuck ;").is_err());
+        assert!(check_source(": f ( Option(Int) -- Option(Int) Option(Int) ) dup ;").is_err());
+
+        // 2dup copies both, so either one being linear is an error
+        assert!(check_source(": f ( Int Int -- Int Int Int Int ) 2dup ;").is_ok());
+        assert!(check_source(": f ( String Int -- String Int String Int ) 2dup ;").is_err());
+        assert!(check_source(": f ( Int String -- Int String Int String ) 2dup ;").is_err());
+
+        // -rot and 2drop move values without copying them
+        assert!(check_source(": f ( String Int Bool -- Bool String ) -rot drop ;").is_ok());
+        assert!(check_source(": f ( String Int Bool -- String ) 2drop ;").is_ok());
+    }
+
+    #[test]
+    fn test_linear_binding_used_twice() {
+        let source = r#"
+            type Wrapped | Wrap(String)
+            : twice ( Wrapped -- String String ) match Wrap(s) => [ s s ] end ;
+        "#;
+        assert_eq!(
+            *check_source(source).unwrap_err(),
+            TypeError::UseAfterMove {
+                var: "s".to_string()
+            }
+        );
+
+        // Copy fields can be referenced freely; Option(Int) fields are Int
+        let source =
+            ": double ( Option(Int) -- Int ) match Some(x) => [ x x + ] None => [ 0 ] end ;";
+        assert!(check_source(source).is_ok());
+    }
+
+    #[test]
+    fn test_moves_follow_branches() {
+        // Each branch of an if moves the binding once, which is fine
+        let source = r#"
+            type Wrapped | Wrap(String)
+            : f ( Bool Wrapped -- String )
+              match Wrap(s) => [ if [ s ] [ s string_length int-to-string ] ] end ;
+        "#;
+        assert!(check_source(source).is_ok(), "{:?}", check_source(source));
+
+        // ...but a value moved on one branch can't be used after the if
+        let source = r#"
+            type Wrapped | Wrap(String)
+            : f ( Bool Wrapped -- String )
+              match Wrap(s) => [ if [ s string_length drop ] [ ] s ] end ;
+        "#;
+        assert!(matches!(
+            *check_source(source).unwrap_err(),
+            TypeError::UseAfterMove { ref var } if var == "s"
+        ));
+    }
+
+    #[test]
+    fn test_self_recursive_word() {
+        let source = r#"
+            : factorial ( Int -- Int )
+              dup 1 <= if [ drop 1 ] [ dup 1 - factorial * ] ;
+        "#;
+        assert!(check_source(source).is_ok(), "{:?}", check_source(source));
+
+        // The body must still agree with the declared outputs
+        let source = ": countdown ( Int -- String ) dup 0 = if [ ] [ 1 - countdown ] ;";
+        assert!(check_source(source).is_err());
+    }
+
+    #[test]
+    fn test_mutually_recursive_words() {
+        let source = r#"
+            : even ( Int -- Bool ) dup 0 = if [ drop true ] [ 1 - odd ] ;
+            : odd ( Int -- Bool ) dup 0 = if [ drop false ] [ 1 - even ] ;
+        "#;
+        assert!(check_source(source).is_ok(), "{:?}", check_source(source));
+
+        let source = ": even ( Int -- Bool ) dup 0 = if [ drop true ] [ 1 - odd ] ;";
+        assert!(matches!(
+            *check_source(source).unwrap_err(),
+            TypeError::UndefinedWord { ref name } if name == "odd"
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_word_definition() {
+        let source = ": foo ( -- Int ) 1 ;\n: bar ( -- ) ;\n: foo ( -- Int ) 2 ;";
+        match *check_source(source).unwrap_err() {
+            TypeError::DuplicateWord {
+                name,
+                first_loc,
+                second_loc,
+            } => {
+                assert_eq!(name, "foo");
+                assert_eq!((first_loc.line, first_loc.column), (1, 1));
+                assert_eq!((second_loc.line, second_loc.column), (3, 1));
+            }
+            e => panic!("Expected DuplicateWord, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_builtin_redefinition_rejected() {
+        let err = check_source(": dup ( Int -- Int Int ) 1 ;").unwrap_err();
+        assert!(matches!(
+            *err,
+            TypeError::BuiltinRedefinition { ref name, .. } if name == "dup"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Cannot redefine built-in word 'dup' at <input>:1:1"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_variant_across_types() {
+        let source = "type Tree | Node(Int) | Leaf\ntype Graph | Node(Int, Int)";
+        let err = check_source(source).unwrap_err();
+        match &*err {
+            TypeError::DuplicateVariant {
+                name,
+                first_type,
+                first_loc,
+                second_type,
+                second_loc,
+            } => {
+                assert_eq!(name, "Node");
+                assert_eq!(
+                    (first_type.as_str(), second_type.as_str()),
+                    ("Tree", "Graph")
+                );
+                assert_eq!((first_loc.line, first_loc.column), (1, 13));
+                assert_eq!((second_loc.line, second_loc.column), (2, 14));
+            }
+            e => panic!("Expected DuplicateVariant, got {:?}", e),
+        }
+        assert_eq!(
+            err.to_string(),
+            "Variant 'Node' is declared by both 'Tree' at <input>:1:13 and 'Graph' at <input>:2:14"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_variant_within_type() {
+        let err = check_source("type Color | Red | Green | Red").unwrap_err();
+        assert!(matches!(
+            &*err,
+            TypeError::DuplicateVariant { first_type, second_type, .. }
+                if first_type == "Color" && second_type == "Color"
+        ));
+        assert!(err.to_string().contains("declared twice in type 'Color'"));
+    }
+
+    #[test]
+    fn test_duplicate_type_name() {
+        let err =
+            check_source("type Pair | MkPair(Int, Int)\ntype Pair | Both(Int, Int)").unwrap_err();
+        match &*err {
+            TypeError::DuplicateType {
+                name,
+                first_loc,
+                second_loc,
+            } => {
+                assert_eq!(name, "Pair");
+                assert_eq!((first_loc.line, second_loc.line), (1, 2));
+            }
+            e => panic!("Expected DuplicateType, got {:?}", e),
+        }
+
+        // Programs may provide their own definition of a built-in type
+        assert!(check_source("type Option(T) | Some(T) | None").is_ok());
+    }
+
+    #[test]
+    fn test_body_errors_name_the_word() {
+        let program = crate::parser::Parser::new(
+            ": ok ( -- ) ;
+: bad ( -- ) 1 ;",
+        )
+        .parse()
+        .unwrap();
+        let err = TypeChecker::new().check_program(&program).unwrap_err();
+        match &*err {
+            TypeError::InWord { word, loc, error } => {
+                assert_eq!(word, "bad");
+                assert_eq!(loc.line, 2);
+                assert!(matches!(**error, TypeError::EffectMismatch { .. }));
+            }
+            e => panic!("Expected InWord, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_main_effect() {
+        assert!(check_source(": main ( -- ) ;").is_ok());
+        assert!(check_source(": main ( -- Int ) 3 ;").is_ok());
+
+        let err = check_source(": main ( -- String ) \"done\" ;").unwrap_err();
+        assert!(matches!(*err, TypeError::InvalidMainEffect { .. }));
+        let err = check_source(": main ( Int -- ) drop ;").unwrap_err();
+        assert!(matches!(*err, TypeError::InvalidMainEffect { .. }));
+        assert!(
+            err.to_string()
+                .contains("has effect ( Int -- ), but must be ( -- ) or ( -- Int exit-code )"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_prelude_type_checks() {
+        assert!(check_source(include_str!("../../../stdlib/prelude.cem")).is_ok());
+    }
+
+    #[test]
+    fn test_string_to_int_returns_prelude_option() {
+        let source = format!(
+            "{}\n: parse-or-zero ( String -- Int ) string_to_int match Some => [ ] None => [ 0 ] end ;",
+            include_str!("../../../stdlib/prelude.cem")
+        );
+        assert!(check_source(&source).is_ok());
+    }
+
+    #[test]
+    fn test_string_split_returns_prelude_list() {
+        let source = format!(
+            "{}\n: first-field ( String -- String ) \",\" string_split match Cons(field, _) => [ field ] Nil => [ \"\" ] end ;",
+            include_str!("../../../stdlib/prelude.cem")
+        );
+        assert!(check_source(&source).is_ok());
+    }
+
+    #[test]
+    fn test_getenv_returns_prelude_option() {
+        let source = format!(
+            "{}\n: home ( -- String ) \"HOME\" getenv match Some => [ ] None => [ \"/\" ] end ;",
+            include_str!("../../../stdlib/prelude.cem")
+        );
+        assert!(check_source(&source).is_ok());
+    }
+
+    #[test]
+    fn test_read_file_returns_prelude_result() {
+        let source = format!(
+            "{}\n: cat ( String -- ) read-file match Ok => [ write ] Err => [ write_line ] end ;",
+            include_str!("../../../stdlib/prelude.cem")
+        );
+        assert!(check_source(&source).is_ok());
+    }
+
+    #[test]
+    fn test_polymorphic_word_used_at_two_types() {
+        let source = ": ident ( A -- A ) ;
+                      : both ( -- Int String ) 1 ident \"s\" ident ;";
+        assert!(check_source(source).is_ok());
+    }
+
+    #[test]
+    fn test_constructor_takes_last_field_on_top() {
+        let pair = "type Pair | Pair(Int, String)\n";
+        assert!(check_source(&format!("{}: make ( -- Pair ) 1 \"s\" Pair ;", pair)).is_ok());
+        assert!(check_source(&format!("{}: make ( -- Pair ) \"s\" 1 Pair ;", pair)).is_err());
+        assert!(check_source(": one ( -- List(Int) ) 1 Nil Cons ;").is_ok());
+        assert!(check_source(": one ( -- List(Int) ) Nil 1 Cons ;").is_err());
+    }
+
+    #[test]
+    fn test_if_without_else_must_be_neutral() {
+        // The missing else leaves the stack as it is, so the then-branch must too
+        let guard = ": check ( Int -- Int ) dup 0 < if [ \"negative\" write_line ] ;";
+        assert!(check_source(guard).is_ok());
+
+        // Changing values is fine, as long as their types stay the same
+        assert!(check_source(": bump ( Int Bool -- Int ) if [ 1 + ] ;").is_ok());
+
+        let err = check_source(": push ( Bool -- ) if [ 1 ] ;").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("then leaves ( Int ), else leaves ( )"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_while_effects() {
+        let countdown = ": countdown ( Int -- Int ) while [ dup 0 > ] [ 1 - ] ;";
+        assert!(
+            check_source(countdown).is_ok(),
+            "{:?}",
+            check_source(countdown)
+        );
+
+        // The condition must leave the stack as it found it, plus a Bool
+        let err = check_source(": f ( Int -- Int ) while [ 0 > ] [ 1 - ] ;").unwrap_err();
+        assert!(err.to_string().contains("while condition"), "{}", err);
+        let err = check_source(": f ( Int -- Int ) while [ dup ] [ 1 - ] ;").unwrap_err();
+        assert!(err.to_string().contains("while condition"), "{}", err);
+
+        // ...and the body must leave it as it found it
+        let err = check_source(": f ( Int -- Int ) while [ dup 0 > ] [ dup ] ;").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("takes ( Int ), leaves ( Int Int )"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_while_body_cannot_move_bindings() {
+        // The body may run more than once, so moving a binding in it would move it twice
+        let source = r#"
+            type Wrapped | Wrap(String)
+            : f ( Wrapped -- ) match Wrap(s) => [ while [ false ] [ s drop ] ] end ;
+        "#;
+        assert!(matches!(
+            *check_source(source).unwrap_err(),
+            TypeError::UseAfterMove { ref var } if var == "s"
+        ));
+    }
+
+    #[test]
+    fn test_not_negates_bools_only() {
+        assert!(check_source(": differ ( Int Int -- Bool ) = not ;").is_ok());
+        assert!(check_source(": flip ( Int -- Int ) not ;").is_err());
+        assert!(check_source(": flip ( Int -- Int ) invert ;").is_ok());
+    }
+
+    #[test]
+    fn test_and_or_are_boolean() {
+        assert!(check_source(": between ( Int -- Bool ) dup 0 > swap 10 < and ;").is_ok());
+        assert!(check_source(": either ( Bool Bool -- Bool ) or ;").is_ok());
+        assert!(check_source(": mask ( Int Int -- Int ) and ;").is_err());
+        assert!(check_source(": mask ( Int Int -- Int ) bitand ;").is_ok());
+    }
+
+    #[test]
+    fn test_exit_never_returns() {
+        // The branch that exits doesn't have to produce an Int
+        let source = ": check ( Bool -- Int ) if [ 1 ] [ 2 exit ] ;";
+        assert!(check_source(source).is_ok());
+        let source = ": check ( Bool -- Int ) if [ 2 exit ] [ 1 ] ;";
+        assert!(check_source(source).is_ok());
+    }
+
+    #[test]
+    fn test_pick_with_literal_depth() {
+        // `2 pick` copies the Bool, not the Int on top
+        let source = ": third ( Bool String Int -- Bool String Int Bool ) 2 pick ;";
+        assert!(check_source(source).is_ok());
+        let source = ": third ( Bool String Int -- Bool String Int Int ) 2 pick ;";
+        assert!(check_source(source).is_err());
+
+        // `0 pick` is dup, including for linear values
+        let source = ": copy ( Int -- Int Int ) 0 pick ;";
+        assert!(check_source(source).is_ok());
+        let source = ": copy ( List(Int) -- List(Int) List(Int) ) 0 pick ;";
+        assert!(matches!(
+            *check_source(source).unwrap_err(),
+            TypeError::CannotDuplicate { .. }
+        ));
+    }
+
+    #[test]
+    fn test_pick_needs_a_literal_depth() {
+        // A depth that isn't a literal would leave the copy's type open
+        let source = ": main ( -- ) 42 0 [ pick ] call write_line drop ;";
+        let err = check_source(source).unwrap_err();
+        assert!(
+            matches!(err.unlocated(), TypeError::CountNotLiteral { word } if word == "pick"),
+            "{}",
+            err
+        );
+        assert!(check_source(": pickn ( Int Int -- Int Int ) pick ;").is_err());
+
+        let err = check_source(": f ( ..R Int -- ..R Int Int ) 1 pick ;").unwrap_err();
+        assert!(
+            matches!(
+                err.unlocated(),
+                TypeError::StackUnderflow { required: 2, .. }
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_pick_in_quotation_copies_an_inferred_input() {
+        let checker = TypeChecker::new();
+        let quotation = Expr::Quotation(
+            vec![
+                Expr::IntLit(0, SourceLoc::unknown()),
+                Expr::WordCall("pick".to_string(), SourceLoc::unknown()),
+            ],
+            SourceLoc::unknown(),
+        );
+        let stack = checker.check_expr(&quotation, StackType::empty()).unwrap();
+        let (_, top) = stack.pop().unwrap();
+        let Type::Quotation(effect) = top else {
+            panic!("Expected quotation type, got {}", top);
+        };
+
+        // [ 0 pick ] is ( R a -- R a a ): the copy is the input, not a fresh type
+        let input = StackType::RowVar("_r0".to_string()).push(Type::Var("_t1".to_string()));
+        assert_eq!(effect.inputs, input);
+        assert_eq!(effect.outputs, input.push(Type::Var("_t1".to_string())));
+
+        // So calling it on an Int leaves Ints
+        let source = ": main ( -- ) 42 [ 0 pick ] call write_line drop ;";
+        assert!(check_source(source).is_err());
+        let source = ": main ( -- Int ) 42 [ 0 pick ] call + ;";
+        assert!(check_source(source).is_ok());
+    }
+
+    #[test]
+    fn test_ndrop_and_ndup_with_literal_counts() {
+        let source = ": f ( Bool String Int -- Bool ) 2 ndrop ;";
+        assert!(check_source(source).is_ok());
+        let source = ": f ( Bool Int Float -- Bool Int Float Int Float ) 2 ndup ;";
+        assert!(check_source(source).is_ok());
+        let source = ": f ( Bool Int Float -- Bool Int Float Float Int ) 2 ndup ;";
+        assert!(check_source(source).is_err());
+
+        // Copies are deep clones, so linear values can't be copied
+        let err = check_source(": f ( String Int -- String Int String Int ) 2 ndup ;").unwrap_err();
+        assert!(
+            matches!(*err, TypeError::CannotDuplicate { ref operation, .. } if operation == "ndup"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_ndrop_and_ndup_need_a_literal_count() {
+        // A count that isn't a literal would hide how the stack changes
+        let err = check_source(": dropn ( Int -- ) ndrop ;").unwrap_err();
+        assert!(
+            matches!(err.unlocated(), TypeError::CountNotLiteral { word } if word == "ndrop"),
+            "{}",
+            err
+        );
+        let err = check_source(": dupn ( Int Int -- ) ndup drop ;").unwrap_err();
+        assert!(
+            matches!(err.unlocated(), TypeError::CountNotLiteral { word } if word == "ndup"),
+            "{}",
+            err
+        );
+
+        // In a quotation the count is inferred as an input, not a literal
+        let source = ": main ( -- ) \"a\" \"b\" 2 [ ndrop ] call write_line write_line ;";
+        assert!(check_source(source).is_err());
+
+        // With the literal inside, the quotation takes the values it drops
+        let source = ": main ( -- ) \"a\" \"b\" [ 2 ndrop ] call write_line write_line ;";
+        assert!(check_source(source).is_err());
+        let source = ": main ( -- ) \"a\" \"b\" [ 2 ndrop ] call ;";
+        assert!(check_source(source).is_ok());
+
+        // Beneath a row variable, the stack may not be that deep
+        let err = check_source(": f ( ..R Int -- ..R ) 2 ndrop ;").unwrap_err();
+        assert!(
+            matches!(
+                err.unlocated(),
+                TypeError::StackUnderflow { required: 2, .. }
+            ),
+            "{}",
+            err
+        );
+    }
 }
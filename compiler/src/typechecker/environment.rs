@@ -4,8 +4,15 @@ Type checking environment for Cem
 Maintains symbol tables for words, types, and type variables during type checking.
 */
 use crate::ast::types::{Effect, StackType, Type};
-use crate::ast::{TypeDef, Variant};
-use std::collections::HashMap;
+use crate::ast::{SourceLoc, TypeDef, Variant};
+use std::collections::{HashMap, HashSet};
+
+/// Built-in words that take a count from the stack: `pick`, `ndrop` and `ndup`
+///
+/// Which value `pick` copies, and how many values the others drop or copy,
+/// is only known at runtime, so no fixed effect describes them. They have none here; the checker types them from
+/// a literal count right before them and rejects any other use.
+const COUNTED_WORDS: &[&str] = &["pick", "ndrop", "ndup"];
 
 /// Type checking environment
 ///
@@ -20,6 +27,9 @@ pub struct Environment {
 
     /// Type definitions: name -> TypeDef
     types: HashMap<String, TypeDef>,
+
+    /// Names of the built-in words
+    builtins: HashSet<String>,
 }
 
 impl Environment {
@@ -28,10 +38,14 @@ impl Environment {
         let mut env = Environment {
             words: HashMap::new(),
             types: HashMap::new(),
+            builtins: HashSet::new(),
         };
 
         // Add built-in stack operations
         env.add_builtin_words();
+        env.builtins = env.words.keys().cloned().collect();
+        env.builtins
+            .extend(COUNTED_WORDS.iter().map(|word| word.to_string()));
         env.add_builtin_types();
 
         env
@@ -42,6 +56,16 @@ impl Environment {
         self.words.insert(name, effect);
     }
 
+    /// Check if a word is one of the built-in primitives
+    pub fn is_builtin_word(&self, name: &str) -> bool {
+        self.builtins.contains(name)
+    }
+
+    /// Check if a word is a built-in that takes a count (see `COUNTED_WORDS`)
+    pub fn is_counted_word(name: &str) -> bool {
+        COUNTED_WORDS.contains(&name)
+    }
+
     /// Look up a word's effect signature
     pub fn lookup_word(&self, name: &str) -> Option<&Effect> {
         self.words.get(name)
@@ -79,17 +103,15 @@ impl Environment {
             };
 
             // Build the effect signature
-            // Input stack: variant fields (if any)
+            // Input stack: variant fields (if any), the last field on top
             // Output stack: the ADT type
             //
-            // Note: .rev() is used because stack types are built right-to-left
-            // For Some(T), we want: ( T -- Option(T) )
-            // Without .rev(), we'd get the fields in wrong order for multi-field variants
+            // For Cons(T, List(T)), we want: ( T List(T) -- List(T) ), the same
+            // order a match pushes the fields back in
             let effect = Effect {
                 inputs: variant
                     .fields
                     .iter()
-                    .rev()
                     .fold(StackType::Empty, |stack, field| stack.push(field.clone())),
                 outputs: StackType::Empty.push(result_type),
             };
@@ -178,6 +200,21 @@ impl Environment {
             },
         );
 
+        // -rot: ( A B C -- C A B )
+        self.add_word(
+            "-rot".to_string(),
+            Effect {
+                inputs: StackType::empty()
+                    .push(Type::Var("A".to_string()))
+                    .push(Type::Var("B".to_string()))
+                    .push(Type::Var("C".to_string())),
+                outputs: StackType::empty()
+                    .push(Type::Var("C".to_string()))
+                    .push(Type::Var("A".to_string()))
+                    .push(Type::Var("B".to_string())),
+            },
+        );
+
         // nip: ( A B -- B )
         self.add_word(
             "nip".to_string(),
@@ -203,35 +240,54 @@ impl Environment {
             },
         );
 
-        // pick: ( ... Int -- ... A )
-        // Type signature is polymorphic - copies nth element to top
-        // For simplicity, we type it conservatively as working on any depth
+        // 2dup: ( A B -- A B A B )
         self.add_word(
-            "pick".to_string(),
+            "2dup".to_string(),
             Effect {
                 inputs: StackType::empty()
                     .push(Type::Var("A".to_string()))
-                    .push(Type::Int),
+                    .push(Type::Var("B".to_string())),
                 outputs: StackType::empty()
                     .push(Type::Var("A".to_string()))
-                    .push(Type::Var("A".to_string())),
+                    .push(Type::Var("B".to_string()))
+                    .push(Type::Var("A".to_string()))
+                    .push(Type::Var("B".to_string())),
             },
         );
 
-        // dip: ( A [B -- C] -- C A )
-        // Calls quotation while hiding top value
+        // 2drop: ( A B -- )
         self.add_word(
-            "dip".to_string(),
+            "2drop".to_string(),
             Effect {
                 inputs: StackType::empty()
                     .push(Type::Var("A".to_string()))
-                    .push(Type::Quotation(Box::new(Effect {
-                        inputs: StackType::empty().push(Type::Var("B".to_string())),
-                        outputs: StackType::empty().push(Type::Var("C".to_string())),
-                    }))),
-                outputs: StackType::empty()
-                    .push(Type::Var("C".to_string()))
-                    .push(Type::Var("A".to_string())),
+                    .push(Type::Var("B".to_string())),
+                outputs: StackType::empty(),
+            },
+        );
+
+        // depth: ( -- Int )
+        // The number of values on the stack, not counting the one it pushes
+        self.add_word(
+            "depth".to_string(),
+            Effect::from_vecs(vec![], vec![Type::Int]),
+        );
+
+        // pick, ndrop and ndup have no effect of their own; see COUNTED_WORDS
+
+        // dip: ( R A [R -- S] -- S A )
+        // Calls quotation while hiding top value. Row-polymorphic like call; the
+        // checker types it from the quotation's actual effect.
+        self.add_word(
+            "dip".to_string(),
+            Effect {
+                inputs: StackType::RowVar("R".to_string())
+                    .push(Type::Var("A".to_string()))
+                    .push(Type::Quotation(Box::new(Effect::new(
+                        StackType::RowVar("R".to_string()),
+                        StackType::RowVar("S".to_string()),
+                    )))),
+                outputs: StackType::RowVar("S".to_string()).push(Type::Var("A".to_string())),
             },
         );
 
@@ -260,6 +316,58 @@ impl Environment {
             Effect::from_vecs(vec![Type::Int, Type::Int], vec![Type::Int]),
         );
 
+        // %: ( Int Int -- Int )
+        self.add_word(
+            "%".to_string(),
+            Effect::from_vecs(vec![Type::Int, Type::Int], vec![Type::Int]),
+        );
+
+        // neg, abs: ( Int -- Int )
+        for name in ["neg", "abs"] {
+            self.add_word(
+                name.to_string(),
+                Effect::from_vecs(vec![Type::Int], vec![Type::Int]),
+            );
+        }
+
+        // min, max: ( Int Int -- Int )
+        for name in ["min", "max"] {
+            self.add_word(
+                name.to_string(),
+                Effect::from_vecs(vec![Type::Int, Type::Int], vec![Type::Int]),
+            );
+        }
+
+        // Bitwise operations
+        // bitand, bitor, bitxor, shl, shr: ( Int Int -- Int )
+        for name in ["bitand", "bitor", "bitxor", "shl", "shr"] {
+            self.add_word(
+                name.to_string(),
+                Effect::from_vecs(vec![Type::Int, Type::Int], vec![Type::Int]),
+            );
+        }
+
+        // invert: ( Int -- Int )
+        self.add_word(
+            "invert".to_string(),
+            Effect::from_vecs(vec![Type::Int], vec![Type::Int]),
+        );
+
+        // Boolean operations
+        // not: ( Bool -- Bool )
+        self.add_word(
+            "not".to_string(),
+            Effect::from_vecs(vec![Type::Bool], vec![Type::Bool]),
+        );
+
+        // and, or: ( Bool Bool -- Bool ), without short-circuiting
+        for name in ["and", "or"] {
+            self.add_word(
+                name.to_string(),
+                Effect::from_vecs(vec![Type::Bool, Type::Bool], vec![Type::Bool]),
+            );
+        }
+
         // Comparison operations
         // =: ( Int Int -- Bool )
         self.add_word(
@@ -267,6 +375,15 @@ impl Environment {
             Effect::from_vecs(vec![Type::Int, Type::Int], vec![Type::Bool]),
         );
 
+        // equal: ( A A -- Bool ), structural equality of any two values
+        self.add_word(
+            "equal".to_string(),
+            Effect::from_vecs(
+                vec![Type::Var("A".to_string()), Type::Var("A".to_string())],
+                vec![Type::Bool],
+            ),
+        );
+
         // <: ( Int Int -- Bool )
         self.add_word(
             "<".to_string(),
@@ -322,11 +439,13 @@ impl Environment {
         );
 
         // String operations
-        // string_length: ( String -- Int )
-        self.add_word(
-            "string_length".to_string(),
-            Effect::from_vecs(vec![Type::String], vec![Type::Int]),
-        );
+        // string_length (bytes), string_char_length (characters): ( String -- Int )
+        for name in ["string_length", "string_char_length"] {
+            self.add_word(
+                name.to_string(),
+                Effect::from_vecs(vec![Type::String], vec![Type::Int]),
+            );
+        }
 
         // string_concat: ( String String -- String )
         self.add_word(
@@ -334,14 +453,285 @@ impl Environment {
             Effect::from_vecs(vec![Type::String, Type::String], vec![Type::String]),
         );
 
-        // string_equal: ( String String -- Bool )
+        // string_equal, string_lt, string_gt: ( String String -- Bool )
+        // string_contains, string_starts_with, string_ends_with take the
+        // string searched in first: ( haystack needle -- Bool )
+        for name in [
+            "string_equal",
+            "string_lt",
+            "string_gt",
+            "string_contains",
+            "string_starts_with",
+            "string_ends_with",
+        ] {
+            self.add_word(
+                name.to_string(),
+                Effect::from_vecs(vec![Type::String, Type::String], vec![Type::Bool]),
+            );
+        }
+
+        // string_index_of: ( haystack needle -- Int ), a character index or -1
+        self.add_word(
+            "string_index_of".to_string(),
+            Effect::from_vecs(vec![Type::String, Type::String], vec![Type::Int]),
+        );
+
+        // string_char_at: ( String Int -- Int ), the code point at a character index
+        self.add_word(
+            "string_char_at".to_string(),
+            Effect::from_vecs(vec![Type::String, Type::Int], vec![Type::Int]),
+        );
+
+        // string_repeat: ( String Int -- String )
+        self.add_word(
+            "string_repeat".to_string(),
+            Effect::from_vecs(vec![Type::String, Type::Int], vec![Type::String]),
+        );
+
+        // string_reverse (by characters), string_trim, string_to_upper,
+        // string_to_lower: ( String -- String )
+        for name in [
+            "string_reverse",
+            "string_trim",
+            "string_to_upper",
+            "string_to_lower",
+        ] {
+            self.add_word(
+                name.to_string(),
+                Effect::from_vecs(vec![Type::String], vec![Type::String]),
+            );
+        }
+
+        // string_replace: ( String String String -- String ), taking the string,
+        // the pattern and its replacement
+        self.add_word(
+            "string_replace".to_string(),
+            Effect::from_vecs(
+                vec![Type::String, Type::String, Type::String],
+                vec![Type::String],
+            ),
+        );
+
+        // string_split: ( String String -- List(String) ), separator on top;
+        // List comes from the prelude
+        self.add_word(
+            "string_split".to_string(),
+            Effect::from_vecs(
+                vec![Type::String, Type::String],
+                vec![Type::Named {
+                    name: "List".to_string(),
+                    args: vec![Type::String],
+                }],
+            ),
+        );
+
+        // string_to_int: ( String -- Option(Int) )
+        // None if the string isn't a number; Option comes from the prelude
+        self.add_word(
+            "string_to_int".to_string(),
+            Effect::from_vecs(
+                vec![Type::String],
+                vec![Type::Named {
+                    name: "Option".to_string(),
+                    args: vec![Type::Int],
+                }],
+            ),
+        );
+
+        // substring: ( String Int Int -- String )
+        // Takes a start character and a length in characters
+        self.add_word(
+            "substring".to_string(),
+            Effect::from_vecs(vec![Type::String, Type::Int, Type::Int], vec![Type::String]),
+        );
+
+        // string_compare: ( String String -- Int )
+        self.add_word(
+            "string_compare".to_string(),
+            Effect::from_vecs(vec![Type::String, Type::String], vec![Type::Int]),
+        );
+
+        // yield: ( -- )
+        // Lets other strands run before this one continues
+        self.add_word("yield".to_string(), Effect::from_vecs(vec![], vec![]));
+
+        // sleep: ( Int -- )
+        // Suspends this strand for N milliseconds while other strands run
         self.add_word(
-            "string_equal".to_string(),
+            "sleep".to_string(),
+            Effect::from_vecs(vec![Type::Int], vec![]),
+        );
+
+        // spawn: ( [ -- ] -- )
+        // Runs the quotation in a new strand, which starts with an empty stack
+        self.add_word(
+            "spawn".to_string(),
+            Effect::from_vecs(
+                vec![Type::Quotation(Box::new(Effect::from_vecs(vec![], vec![])))],
+                vec![],
+            ),
+        );
+
+        // spawn-with: ( A [ A -- ] -- )
+        // Like spawn, but moves one value to the new strand's stack
+        let a = Type::Var("A".to_string());
+        self.add_word(
+            "spawn-with".to_string(),
+            Effect::from_vecs(
+                vec![
+                    a.clone(),
+                    Type::Quotation(Box::new(Effect::from_vecs(vec![a], vec![]))),
+                ],
+                vec![],
+            ),
+        );
+
+        // spawn-result: ( [ -- A ] -- Strand(A) )
+        // The handle is linear, so each strand is joined at most once
+        let strand = Type::Named {
+            name: "Strand".to_string(),
+            args: vec![Type::Var("A".to_string())],
+        };
+        self.add_word(
+            "spawn-result".to_string(),
+            Effect::from_vecs(
+                vec![Type::Quotation(Box::new(Effect::from_vecs(
+                    vec![],
+                    vec![Type::Var("A".to_string())],
+                )))],
+                vec![strand.clone()],
+            ),
+        );
+
+        // join: ( Strand(A) -- A )
+        // Waits for the strand and takes the value it left on top
+        self.add_word(
+            "join".to_string(),
+            Effect::from_vecs(vec![strand], vec![Type::Var("A".to_string())]),
+        );
+
+        // Channels: Chan(T) is a handle, so copies share one queue
+        let chan = Type::Named {
+            name: "Chan".to_string(),
+            args: vec![Type::Var("T".to_string())],
+        };
+        let t = Type::Var("T".to_string());
+
+        // channel: ( -- Chan(T) )
+        self.add_word(
+            "channel".to_string(),
+            Effect::from_vecs(vec![], vec![chan.clone()]),
+        );
+
+        // send: ( Chan(T) T -- Chan(T) )
+        self.add_word(
+            "send".to_string(),
+            Effect::from_vecs(vec![chan.clone(), t.clone()], vec![chan.clone()]),
+        );
+
+        // recv: ( Chan(T) -- Chan(T) T )
+        // Waits for a value, suspending only this strand
+        self.add_word(
+            "recv".to_string(),
+            Effect::from_vecs(vec![chan.clone()], vec![chan, t]),
+        );
+
+        // call / call_quotation: ( R [R -- S] -- S )
+        // Row-polymorphic: the quotation's effect applies to the stack beneath it.
+        // The checker types these calls from the quotation's actual effect.
+        for name in ["call", "call_quotation"] {
+            self.add_word(
+                name.to_string(),
+                Effect {
+                    inputs: StackType::RowVar("R".to_string()).push(Type::Quotation(Box::new(
+                        Effect::new(
+                            StackType::RowVar("R".to_string()),
+                            StackType::RowVar("S".to_string()),
+                        ),
+                    ))),
+                    outputs: StackType::RowVar("S".to_string()),
+                },
+            );
+        }
+
+        // I/O
+        // write: ( String -- )
+        // Like write_line, without the newline
+        self.add_word(
+            "write".to_string(),
+            Effect::from_vecs(vec![Type::String], vec![]),
+        );
+
+        // write_line: ( String -- )
+        self.add_word(
+            "write_line".to_string(),
+            Effect::from_vecs(vec![Type::String], vec![]),
+        );
+
+        // read_line: ( -- String )
+        self.add_word(
+            "read_line".to_string(),
+            Effect::from_vecs(vec![], vec![Type::String]),
+        );
+
+        // read-file: ( String -- Result(String, String) )
+        // Err holds a message if the file can't be read; Result comes from the prelude
+        self.add_word(
+            "read-file".to_string(),
+            Effect::from_vecs(
+                vec![Type::String],
+                vec![Type::Named {
+                    name: "Result".to_string(),
+                    args: vec![Type::String, Type::String],
+                }],
+            ),
+        );
+
+        // write-file: ( String String -- Bool ), path beneath the contents
+        // True if the file was written
+        self.add_word(
+            "write-file".to_string(),
             Effect::from_vecs(vec![Type::String, Type::String], vec![Type::Bool]),
         );
 
+        // args_count: ( -- Int ), arg_at: ( Int -- String )
+        // Arguments are numbered like argv, the program itself first
+        self.add_word(
+            "args_count".to_string(),
+            Effect::from_vecs(vec![], vec![Type::Int]),
+        );
+        self.add_word(
+            "arg_at".to_string(),
+            Effect::from_vecs(vec![Type::Int], vec![Type::String]),
+        );
+
+        // getenv: ( String -- Option(String) )
+        // None if the variable isn't set; Option comes from the prelude
+        self.add_word(
+            "getenv".to_string(),
+            Effect::from_vecs(
+                vec![Type::String],
+                vec![Type::Named {
+                    name: "Option".to_string(),
+                    args: vec![Type::String],
+                }],
+            ),
+        );
+
+        // random: ( Int -- Int ), a value in [0, n)
+        self.add_word(
+            "random".to_string(),
+            Effect::from_vecs(vec![Type::Int], vec![Type::Int]),
+        );
+
+        // random-seed: ( Int -- )
+        self.add_word(
+            "random-seed".to_string(),
+            Effect::from_vecs(vec![Type::Int], vec![]),
+        );
+
         // exit: ( Int -- )
-        // Note: This function never returns, but we model it as consuming Int and producing empty stack
+        // Note: This function never returns; the checker leaves the stack after it unconstrained
         self.add_word(
             "exit".to_string(),
             Effect::from_vecs(vec![Type::Int], vec![]),
@@ -358,12 +748,15 @@ impl Environment {
                 Variant {
                     name: "Some".to_string(),
                     fields: vec![Type::Var("T".to_string())],
+                    loc: SourceLoc::unknown(),
                 },
                 Variant {
                     name: "None".to_string(),
                     fields: vec![],
+                    loc: SourceLoc::unknown(),
                 },
             ],
+            loc: SourceLoc::unknown(),
         });
 
         // Result<T, E>
@@ -374,12 +767,15 @@ impl Environment {
                 Variant {
                     name: "Ok".to_string(),
                     fields: vec![Type::Var("T".to_string())],
+                    loc: SourceLoc::unknown(),
                 },
                 Variant {
                     name: "Err".to_string(),
                     fields: vec![Type::Var("E".to_string())],
+                    loc: SourceLoc::unknown(),
                 },
             ],
+            loc: SourceLoc::unknown(),
         });
 
         // List<T>
@@ -396,12 +792,15 @@ impl Environment {
                             args: vec![Type::Var("T".to_string())],
                         },
                     ],
+                    loc: SourceLoc::unknown(),
                 },
                 Variant {
                     name: "Nil".to_string(),
                     fields: vec![],
+                    loc: SourceLoc::unknown(),
                 },
             ],
+            loc: SourceLoc::unknown(),
         });
     }
 }
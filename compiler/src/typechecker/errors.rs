@@ -1,6 +1,7 @@
 /**
 Type checking errors for Cem
 */
+use crate::ast::SourceLoc;
 use crate::ast::types::{Effect, StackType, Type};
 use std::fmt;
 
@@ -33,6 +34,32 @@ pub enum TypeError {
     /// Undefined word reference
     UndefinedWord { name: String },
 
+    /// Word defined more than once
+    DuplicateWord {
+        name: String,
+        first_loc: SourceLoc,
+        second_loc: SourceLoc,
+    },
+
+    /// User definition of a built-in word's name
+    BuiltinRedefinition { name: String, loc: SourceLoc },
+
+    /// Type defined more than once
+    DuplicateType {
+        name: String,
+        first_loc: SourceLoc,
+        second_loc: SourceLoc,
+    },
+
+    /// Variant name declared more than once, in the same type or in two types
+    DuplicateVariant {
+        name: String,
+        first_type: String,
+        first_loc: SourceLoc,
+        second_type: String,
+        second_loc: SourceLoc,
+    },
+
     /// Undefined type reference
     UndefinedType { name: String },
 
@@ -50,12 +77,36 @@ pub enum TypeError {
         branch: String,
     },
 
+    /// Pattern binds a different number of fields than the variant has
+    PatternArityMismatch {
+        variant: String,
+        expected: usize,
+        actual: usize,
+    },
+
     /// Attempt to duplicate non-Copy type
-    CannotDuplicate { ty: Type, operation: String },
+    CannotDuplicate {
+        ty: Type,
+        operation: String,
+        loc: SourceLoc,
+    },
 
     /// Use of value after move (linear type violation)
     UseAfterMove { var: String },
 
+    /// Word that takes a count used without a literal count right before it
+    CountNotLiteral { word: String },
+
+    /// Reference to an enclosing match's binding from inside a quotation
+    BindingInQuotation { name: String },
+
+    /// Word body that fixes a type variable its declared effect leaves open
+    RigidTypeVariable {
+        word: String,
+        var: String,
+        actual: Type,
+    },
+
     /// Cannot unify types (for polymorphism)
     UnificationError {
         ty1: Type,
@@ -70,6 +121,26 @@ pub enum TypeError {
         reason: String,
     },
 
+    /// The entry word must take nothing and leave nothing or an exit code
+    InvalidMainEffect {
+        word: String,
+        effect: Effect,
+        loc: SourceLoc,
+    },
+
+    /// Error at an expression in a word's body
+    At {
+        loc: SourceLoc,
+        error: Box<TypeError>,
+    },
+
+    /// Error inside the body of a word, located at the word's definition
+    InWord {
+        word: String,
+        loc: SourceLoc,
+        error: Box<TypeError>,
+    },
+
     /// Generic error
     Other { message: String },
 }
@@ -117,6 +188,64 @@ impl fmt::Display for TypeError {
                 write!(f, "Undefined word: '{}'", name)
             }
 
+            TypeError::DuplicateWord {
+                name,
+                first_loc,
+                second_loc,
+            } => {
+                write!(
+                    f,
+                    "Word '{}' is defined twice: first at {}, again at {}",
+                    name, first_loc, second_loc
+                )
+            }
+
+            TypeError::BuiltinRedefinition { name, loc } => {
+                write!(f, "Cannot redefine built-in word '{}' at {}", name, loc)
+            }
+
+            TypeError::InvalidMainEffect { word, effect, loc } => {
+                write!(
+                    f,
+                    "Entry word '{}' at {} has effect {}, but must be ( -- ) or ( -- Int exit-code )",
+                    word, loc, effect
+                )
+            }
+
+            TypeError::DuplicateType {
+                name,
+                first_loc,
+                second_loc,
+            } => {
+                write!(
+                    f,
+                    "Type '{}' is defined twice: first at {}, again at {}",
+                    name, first_loc, second_loc
+                )
+            }
+
+            TypeError::DuplicateVariant {
+                name,
+                first_type,
+                first_loc,
+                second_type,
+                second_loc,
+            } => {
+                if first_type == second_type {
+                    write!(
+                        f,
+                        "Variant '{}' is declared twice in type '{}': first at {}, again at {}",
+                        name, first_type, first_loc, second_loc
+                    )
+                } else {
+                    write!(
+                        f,
+                        "Variant '{}' is declared by both '{}' at {} and '{}' at {}",
+                        name, first_type, first_loc, second_type, second_loc
+                    )
+                }
+            }
+
             TypeError::UndefinedType { name } => {
                 write!(f, "Undefined type: '{}'", name)
             }
@@ -146,12 +275,24 @@ impl fmt::Display for TypeError {
                 )
             }
 
-            TypeError::CannotDuplicate { ty, operation } => {
+            TypeError::PatternArityMismatch {
+                variant,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Pattern '{}' binds {} field(s), but the variant has {}",
+                    variant, actual, expected
+                )
+            }
+
+            TypeError::CannotDuplicate { ty, operation, loc } => {
                 write!(
                     f,
-                    "Cannot duplicate non-Copy type {} in operation '{}'.\n\
+                    "Cannot duplicate non-Copy type {} in operation '{}' at {}.\n\
                      Hint: Use 'clone' to explicitly duplicate this value",
-                    ty, operation
+                    ty, operation, loc
                 )
             }
 
@@ -159,6 +300,30 @@ impl fmt::Display for TypeError {
                 write!(f, "Use of '{}' after move (linear type violation)", var)
             }
 
+            TypeError::CountNotLiteral { word } => {
+                write!(
+                    f,
+                    "'{}' needs a literal count right before it, as in '2 {}'",
+                    word, word
+                )
+            }
+
+            TypeError::BindingInQuotation { name } => {
+                write!(
+                    f,
+                    "Match binding '{}' can't be used inside a quotation; push it before the quotation instead",
+                    name
+                )
+            }
+
+            TypeError::RigidTypeVariable { word, var, actual } => {
+                write!(
+                    f,
+                    "Word '{}' declares type variable '{}' for any type, but its body needs it to be {}",
+                    word, var, actual
+                )
+            }
+
             TypeError::UnificationError { ty1, ty2, reason } => {
                 write!(f, "Cannot unify types {} and {}: {}", ty1, ty2, reason)
             }
@@ -175,6 +340,14 @@ impl fmt::Display for TypeError {
                 )
             }
 
+            TypeError::At { loc, error } => write!(f, "{}: {}", loc, error),
+
+            TypeError::InWord { word, loc, error } => match &**error {
+                // The expression's location is more precise than the word's
+                TypeError::At { loc, error } => write!(f, "{}: in word '{}': {}", loc, word, error),
+                error => write!(f, "{}: in word '{}': {}", loc, word, error),
+            },
+
             TypeError::Other { message } => {
                 write!(f, "{}", message)
             }
@@ -182,4 +355,29 @@ impl fmt::Display for TypeError {
     }
 }
 
+impl TypeError {
+    /// The error itself, without the word or expression it's located at
+    pub fn unlocated(&self) -> &TypeError {
+        match self {
+            TypeError::At { error, .. } | TypeError::InWord { error, .. } => error.unlocated(),
+            error => error,
+        }
+    }
+
+    /// Where the error is, as precisely as it's known
+    pub fn loc(&self) -> Option<&SourceLoc> {
+        match self {
+            TypeError::InWord { loc, error, .. } => error.loc().or(Some(loc)),
+            TypeError::At { loc, .. }
+            | TypeError::BuiltinRedefinition { loc, .. }
+            | TypeError::InvalidMainEffect { loc, .. }
+            | TypeError::CannotDuplicate { loc, .. } => Some(loc),
+            TypeError::DuplicateWord { second_loc, .. }
+            | TypeError::DuplicateType { second_loc, .. }
+            | TypeError::DuplicateVariant { second_loc, .. } => Some(second_loc),
+            _ => None,
+        }
+    }
+}
+
 impl std::error::Error for TypeError {}
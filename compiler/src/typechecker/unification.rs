@@ -3,7 +3,7 @@ Type unification for Cem
 
 Implements unification of types and stack types for polymorphic type checking.
 */
-use crate::ast::types::{StackType, Type};
+use crate::ast::types::{Effect, StackType, Type};
 use crate::typechecker::errors::{TypeError, TypeResult};
 use std::collections::HashMap;
 
@@ -16,27 +16,41 @@ pub type StackSubstitution = HashMap<String, StackType>;
 /// Unify two types, returning a substitution or error
 pub fn unify_types(ty1: &Type, ty2: &Type) -> TypeResult<Substitution> {
     let mut subst = HashMap::new();
-    unify_types_with_subst(ty1, ty2, &mut subst)?;
-    Ok(subst)
+    unify_types_with_subst(ty1, ty2, &mut subst, &mut HashMap::new())?;
+    Ok(normalize(&subst))
 }
 
-fn unify_types_with_subst(ty1: &Type, ty2: &Type, subst: &mut Substitution) -> TypeResult<()> {
+fn unify_types_with_subst(
+    ty1: &Type,
+    ty2: &Type,
+    subst: &mut Substitution,
+    stack_subst: &mut StackSubstitution,
+) -> TypeResult<()> {
+    // Bound variables stand for their bindings
+    let ty1 = &resolve(ty1, subst);
+    let ty2 = &resolve(ty2, subst);
+
     match (ty1, ty2) {
         // Same primitive types unify
         (Type::Int, Type::Int) => Ok(()),
         (Type::Bool, Type::Bool) => Ok(()),
+        (Type::Float, Type::Float) => Ok(()),
         (Type::String, Type::String) => Ok(()),
 
-        // Type variables
+        // A variable unifies with itself without binding anything
+        (Type::Var(n1), Type::Var(n2)) if n1 == n2 => Ok(()),
+
+        // Unbound type variables
         (Type::Var(name), ty) | (ty, Type::Var(name)) => {
-            if let Some(existing) = subst.get(name).cloned() {
-                // Variable already bound, check consistency
-                unify_types_with_subst(&existing, ty, subst)
-            } else {
-                // Bind variable
-                subst.insert(name.clone(), ty.clone());
-                Ok(())
+            if occurs(name, ty, subst, stack_subst) {
+                return Err(Box::new(TypeError::UnificationError {
+                    ty1: ty1.clone(),
+                    ty2: ty2.clone(),
+                    reason: format!("'{}' would contain itself", name),
+                }));
             }
+            subst.insert(name.clone(), ty.clone());
+            Ok(())
         }
 
         // Named types (ADTs) must have same name and compatible args
@@ -59,27 +73,19 @@ fn unify_types_with_subst(ty1: &Type, ty2: &Type, subst: &mut Substitution) -> T
 
             // Unify all type arguments
             for (arg1, arg2) in a1.iter().zip(a2.iter()) {
-                unify_types_with_subst(arg1, arg2, subst)?;
+                unify_types_with_subst(arg1, arg2, subst, stack_subst)?;
             }
 
             Ok(())
         }
 
-        // Quotations: unify their effects (would need effect unification)
-        (Type::Quotation(_eff1), Type::Quotation(_eff2)) => {
-            // TODO(#10): Implement effect unification
-            //
-            // KNOWN LIMITATION: Any two quotation types unify successfully, even if
-            // they have incompatible effects. This compounds the soundness hole from
-            // quotation type inference.
-            //
-            // Example that should fail but won't:
-            //   : takes-int-to-int ( [Int -- Int] -- ) drop ;
-            //   : main ( -- ) [ "hello" write_line ] takes-int-to-int ;
-            //
-            // To fix: recursively unify the input and output stack types of the effects
-            // For now, just succeed
-            Ok(())
+        // Quotations: unify their effects. Row variables share the substitution
+        // of the stacks around them, so `( ..R [ ..R -- ..R ] -- ..R )` ties the
+        // quotation to the rest of the caller's stack. Each use of a word gets
+        // fresh row variables, so unrelated ones never share a name.
+        (Type::Quotation(eff1), Type::Quotation(eff2)) => {
+            unify_stack_types_with_subst(&eff1.inputs, &eff2.inputs, subst, stack_subst)?;
+            unify_stack_types_with_subst(&eff1.outputs, &eff2.outputs, subst, stack_subst)
         }
 
         // Mismatched types
@@ -91,6 +97,122 @@ fn unify_types_with_subst(ty1: &Type, ty2: &Type, subst: &mut Substitution) -> T
     }
 }
 
+/// Follow a type variable's bindings to the type it stands for
+fn resolve(ty: &Type, subst: &Substitution) -> Type {
+    match ty {
+        Type::Var(name) => match subst.get(name) {
+            Some(bound) => resolve(bound, subst),
+            None => ty.clone(),
+        },
+        _ => ty.clone(),
+    }
+}
+
+/// Resolve every binding fully, so applying the substitution once is enough
+fn normalize(subst: &Substitution) -> Substitution {
+    subst
+        .iter()
+        .map(|(name, ty)| (name.clone(), resolve_deep(ty, subst)))
+        .collect()
+}
+
+fn resolve_deep(ty: &Type, subst: &Substitution) -> Type {
+    match resolve(ty, subst) {
+        Type::Named { name, args } => Type::Named {
+            name,
+            args: args.iter().map(|arg| resolve_deep(arg, subst)).collect(),
+        },
+        Type::Quotation(effect) => Type::Quotation(Box::new(Effect::new(
+            resolve_stack_deep(&effect.inputs, subst),
+            resolve_stack_deep(&effect.outputs, subst),
+        ))),
+        ty => ty,
+    }
+}
+
+fn resolve_stack_deep(stack: &StackType, subst: &Substitution) -> StackType {
+    match stack {
+        StackType::Cons { rest, top } => {
+            resolve_stack_deep(rest, subst).push(resolve_deep(top, subst))
+        }
+        base => base.clone(),
+    }
+}
+
+/// Whether the variable `name` appears in `ty`, looking through bindings
+fn occurs(name: &str, ty: &Type, subst: &Substitution, stack_subst: &StackSubstitution) -> bool {
+    match resolve(ty, subst) {
+        Type::Var(other) => other == name,
+        Type::Named { args, .. } => args.iter().any(|arg| occurs(name, arg, subst, stack_subst)),
+        Type::Quotation(effect) => {
+            stack_occurs(name, &effect.inputs, subst, stack_subst)
+                || stack_occurs(name, &effect.outputs, subst, stack_subst)
+        }
+        _ => false,
+    }
+}
+
+fn stack_occurs(
+    name: &str,
+    stack: &StackType,
+    subst: &Substitution,
+    stack_subst: &StackSubstitution,
+) -> bool {
+    match resolve_row(stack, stack_subst) {
+        StackType::Cons { rest, top } => {
+            occurs(name, top, subst, stack_subst) || stack_occurs(name, rest, subst, stack_subst)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the row variable `name` appears in `stack`, beneath its types or
+/// inside a quotation type on it, looking through bindings
+fn row_occurs(
+    name: &str,
+    stack: &StackType,
+    subst: &Substitution,
+    stack_subst: &StackSubstitution,
+) -> bool {
+    match resolve_row(stack, stack_subst) {
+        StackType::Cons { rest, top } => {
+            row_occurs_in_type(name, top, subst, stack_subst)
+                || row_occurs(name, rest, subst, stack_subst)
+        }
+        StackType::RowVar(other) => other == name,
+        StackType::Empty => false,
+    }
+}
+
+fn row_occurs_in_type(
+    name: &str,
+    ty: &Type,
+    subst: &Substitution,
+    stack_subst: &StackSubstitution,
+) -> bool {
+    match resolve(ty, subst) {
+        Type::Named { args, .. } => args
+            .iter()
+            .any(|arg| row_occurs_in_type(name, arg, subst, stack_subst)),
+        Type::Quotation(effect) => {
+            row_occurs(name, &effect.inputs, subst, stack_subst)
+                || row_occurs(name, &effect.outputs, subst, stack_subst)
+        }
+        _ => false,
+    }
+}
+
+/// Follow a row variable's bindings to the stack it stands for
+fn resolve_row<'a>(stack: &'a StackType, stack_subst: &'a StackSubstitution) -> &'a StackType {
+    match stack {
+        StackType::RowVar(name) => match stack_subst.get(name) {
+            Some(bound) => resolve_row(bound, stack_subst),
+            None => stack,
+        },
+        _ => stack,
+    }
+}
+
 /// Unify two stack types
 pub fn unify_stack_types(
     stack1: &StackType,
@@ -101,7 +223,7 @@ pub fn unify_stack_types(
 
     unify_stack_types_with_subst(stack1, stack2, &mut type_subst, &mut stack_subst)?;
 
-    Ok((type_subst, stack_subst))
+    Ok((normalize(&type_subst), stack_subst))
 }
 
 fn unify_stack_types_with_subst(
@@ -117,7 +239,7 @@ fn unify_stack_types_with_subst(
         // Cons cells: unify tops and rests
         (StackType::Cons { rest: r1, top: t1 }, StackType::Cons { rest: r2, top: t2 }) => {
             // Unify the top types
-            unify_types_with_subst(t1, t2, type_subst)?;
+            unify_types_with_subst(t1, t2, type_subst, stack_subst)?;
 
             // Unify the rest stacks
             unify_stack_types_with_subst(r1, r2, type_subst, stack_subst)?;
@@ -125,11 +247,25 @@ fn unify_stack_types_with_subst(
             Ok(())
         }
 
+        // A row variable unifies with itself without binding anything
+        (StackType::RowVar(n1), StackType::RowVar(n2)) if n1 == n2 => Ok(()),
+
         // Row variable can unify with anything
         (StackType::RowVar(name), stack) | (stack, StackType::RowVar(name)) => {
             if let Some(existing) = stack_subst.get(name).cloned() {
                 // Variable already bound, check consistency
                 unify_stack_types_with_subst(&existing, stack, type_subst, stack_subst)
+            } else if matches!(resolve_row(stack, stack_subst), StackType::RowVar(other) if other == name)
+            {
+                // Already the same row, through other bindings
+                Ok(())
+            } else if row_occurs(name, stack, type_subst, stack_subst) {
+                // The rest of a stack can't be that stack with more on top
+                Err(Box::new(TypeError::StackUnificationError {
+                    stack1: stack1.clone(),
+                    stack2: stack2.clone(),
+                    reason: format!("'..{}' would contain itself", name),
+                }))
             } else {
                 // Bind variable
                 stack_subst.insert(name.clone(), stack.clone());
@@ -146,6 +282,41 @@ fn unify_stack_types_with_subst(
     }
 }
 
+/// Apply both substitutions to a stack: row variables are replaced by the
+/// stacks they stand for, and type variables by their types
+pub fn substitute_stack(
+    stack: &StackType,
+    type_subst: &Substitution,
+    stack_subst: &StackSubstitution,
+) -> StackType {
+    match stack {
+        StackType::Empty => StackType::Empty,
+        StackType::Cons { rest, top } => substitute_stack(rest, type_subst, stack_subst)
+            .push(substitute_type(top, type_subst, stack_subst)),
+        StackType::RowVar(name) => match stack_subst.get(name) {
+            Some(bound) => substitute_stack(bound, type_subst, stack_subst),
+            None => stack.clone(),
+        },
+    }
+}
+
+fn substitute_type(ty: &Type, type_subst: &Substitution, stack_subst: &StackSubstitution) -> Type {
+    match resolve(ty, type_subst) {
+        Type::Named { name, args } => Type::Named {
+            name,
+            args: args
+                .iter()
+                .map(|arg| substitute_type(arg, type_subst, stack_subst))
+                .collect(),
+        },
+        Type::Quotation(effect) => Type::Quotation(Box::new(Effect::new(
+            substitute_stack(&effect.inputs, type_subst, stack_subst),
+            substitute_stack(&effect.outputs, type_subst, stack_subst),
+        ))),
+        ty => ty,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -166,6 +337,20 @@ mod tests {
         assert_eq!(subst.get("A"), Some(&Type::Int));
     }
 
+    #[test]
+    fn test_unify_variable_with_itself() {
+        let t = Type::Var("T".to_string());
+        let subst = unify_types(&t, &t).unwrap();
+        assert!(subst.is_empty());
+
+        // T can't stand for a list of itself
+        let list_t = Type::Named {
+            name: "List".to_string(),
+            args: vec![t.clone()],
+        };
+        assert!(unify_types(&t, &list_t).is_err());
+    }
+
     #[test]
     fn test_unify_named_types() {
         let opt_int1 = Type::Named {
@@ -198,4 +383,82 @@ mod tests {
         let stack3 = StackType::empty().push(Type::Bool);
         assert!(unify_stack_types(&stack1, &stack3).is_err());
     }
+
+    #[test]
+    fn test_unify_quotation_effects() {
+        use crate::ast::types::Effect;
+
+        let int_to_int = Type::Quotation(Box::new(Effect::from_vecs(
+            vec![Type::Int],
+            vec![Type::Int],
+        )));
+
+        // ( R a -- R Int ), as inferred for `[ 1 + ]`
+        let row = StackType::RowVar("R".to_string());
+        let inferred = Type::Quotation(Box::new(Effect::new(
+            row.clone().push(Type::Var("a".to_string())),
+            row.clone().push(Type::Int),
+        )));
+        let subst = unify_types(&inferred, &int_to_int).unwrap();
+        assert_eq!(subst.get("a"), Some(&Type::Int));
+
+        let int_to_string = Type::Quotation(Box::new(Effect::from_vecs(
+            vec![Type::Int],
+            vec![Type::String],
+        )));
+        assert!(unify_types(&inferred, &int_to_string).is_err());
+
+        // The row variable must stand for the same stack on both sides
+        let drops_two = Type::Quotation(Box::new(Effect::from_vecs(
+            vec![Type::Int, Type::Int],
+            vec![Type::Int],
+        )));
+        assert!(unify_types(&inferred, &drops_two).is_err());
+    }
+    #[test]
+    fn test_unify_row_variables() {
+        let row = |name: &str| StackType::RowVar(name.to_string());
+
+        // A row variable binds to the rest of the other stack
+        let declared = row("R").push(Type::Int);
+        let actual = StackType::from_vec(vec![Type::Bool, Type::Int]);
+        let (type_subst, stack_subst) = unify_stack_types(&declared, &actual).unwrap();
+        assert_eq!(
+            substitute_stack(&row("R").push(Type::String), &type_subst, &stack_subst),
+            StackType::from_vec(vec![Type::Bool, Type::String])
+        );
+
+        assert!(unify_stack_types(&row("R"), &row("R")).is_ok());
+
+        // `..R` can't be `..R Int`
+        assert!(unify_stack_types(&row("R"), &row("R").push(Type::Int)).is_err());
+    }
+    #[test]
+    fn test_occurs_check() {
+        let a = Type::Var("A".to_string());
+        let row = |name: &str| StackType::RowVar(name.to_string());
+
+        // A can't be a quotation that takes an A
+        let takes_a = Type::Quotation(Box::new(Effect::from_vecs(vec![a.clone()], vec![])));
+        let err = unify_types(&a, &takes_a).unwrap_err();
+        assert!(
+            matches!(*err, TypeError::UnificationError { .. }),
+            "{}",
+            err
+        );
+
+        // ..R can't be a stack holding a quotation that runs on ..R
+        let on_r = Type::Quotation(Box::new(Effect::new(row("R"), row("R"))));
+        let err = unify_stack_types(&row("R"), &row("S").push(on_r)).unwrap_err();
+        assert!(
+            matches!(*err, TypeError::StackUnificationError { .. }),
+            "{}",
+            err
+        );
+
+        // Binding ..R to ..S when ..S already stands for ..R is no cycle
+        let s_to_r = Type::Quotation(Box::new(Effect::new(row("S"), row("R"))));
+        let r_to_s = Type::Quotation(Box::new(Effect::new(row("R"), row("S"))));
+        assert!(unify_types(&s_to_r, &r_to_s).is_ok());
+    }
 }
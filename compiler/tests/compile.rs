@@ -0,0 +1,681 @@
+//! End-to-end tests of the `cem` binary
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Write `source` to `name.cem` in a fresh temporary directory
+fn write_source(name: &str, source: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("cem-test-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join(format!("{}.cem", name));
+    fs::write(&input, source).unwrap();
+    (dir, input)
+}
+
+/// Run `cem compile` on `source`
+fn compile(name: &str, source: &str, extra_args: &[&str]) -> (Output, PathBuf) {
+    let (dir, input) = write_source(name, source);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cem"))
+        .arg("compile")
+        .arg(&input)
+        .arg("-o")
+        .arg(dir.join(name))
+        .args(extra_args)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+
+    (output, dir)
+}
+
+#[test]
+fn test_compile_rejects_stack_effect_mismatch() {
+    // Declared to leave nothing, but leaves an Int
+    let (output, dir) = compile("mismatch", ": main ( -- )\n  1 2 + ;\n", &[]);
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Type error:"), "{}", stderr);
+    assert!(stderr.contains("in word 'main'"), "{}", stderr);
+    assert!(stderr.contains("Effect mismatch"), "{}", stderr);
+
+    // Nothing past the type check ran
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Generating LLVM IR"), "{}", stdout);
+}
+
+#[test]
+fn test_no_typecheck_skips_the_checker() {
+    let (output, dir) = compile(
+        "unchecked",
+        ": main ( -- )\n  1 2 + ;\n",
+        &["--no-typecheck"],
+    );
+    fs::remove_dir_all(&dir).ok();
+
+    // The build may still fail later (it needs `just` and clang), but not on types
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("Type error:"), "{}", stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Skipping type check"), "{}", stdout);
+}
+
+#[test]
+fn test_parse_errors_point_at_input_lines() {
+    let source = ": main ( -- )\n  1 ] drop ;\n: other ( -- ) ( ;\n";
+    let (output, dir) = compile("parse-error", source, &[]);
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(!output.status.success());
+    // Line numbers are the input file's own, not counting the prelude
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Parse error at 2:5:"), "{}", stderr);
+    assert!(stderr.contains("2 |   1 ] drop ;\n  |     ^"), "{}", stderr);
+
+    // Every broken word is reported
+    assert!(stderr.contains("Parse error at 3:16:"), "{}", stderr);
+}
+
+#[test]
+fn test_sole_entry_word_must_take_nothing() {
+    // Not called `main`, but the only word in the file, so it's the entry
+    let (output, dir) = compile("sole-entry", ": square ( Int -- Int )\n  dup * ;\n", &[]);
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Entry word 'square' at ") && stderr.contains("sole-entry.cem:1:1"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("must be ( -- ) or ( -- Int exit-code )"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_type_errors_point_at_input_lines() {
+    let (output, dir) = compile("undefined", ": main ( -- )\n  frobnicate ;\n", &[]);
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("undefined.cem:2:3: in word 'main'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_run_stops_on_compile_error() {
+    let (dir, input) = write_source("run-mismatch", ": main ( -- )\n  1 2 + ;\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cem"))
+        .arg("run")
+        .arg(&input)
+        .arg("--flag-for-the-program")
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Type error:"), "{}", stderr);
+
+    // Build progress is only printed by `compile`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Type checking"), "{}", stdout);
+}
+
+/// Build and run `source` with `cem run` from the workspace root, where the
+/// runtime library is built
+fn run(name: &str, source: &str) -> Output {
+    run_with_stdin(name, source, "")
+}
+
+/// Like `run`, with `stdin` piped to the program
+fn run_with_stdin(name: &str, source: &str, stdin: &str) -> Output {
+    run_with(name, source, stdin, &[])
+}
+
+/// Like `run`, with `stdin` piped to the program and `args` passed to it
+fn run_with(name: &str, source: &str, stdin: &str, args: &[&str]) -> Output {
+    let (dir, input) = write_source(name, source);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cem"))
+        .arg("run")
+        .arg(&input)
+        .args(args)
+        .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    fs::remove_dir_all(&dir).ok();
+
+    output
+}
+
+#[test]
+fn test_run_reports_division_by_zero() {
+    let output = run("divide-by-zero", ": main ( -- Int )\n  1 0 / ;\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Runtime error: divide: division by zero"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_read_line_reads_piped_stdin() {
+    let source = ": main ( -- )
+  read_line \"Hello, \" swap string_concat write_line
+  read_line write_line
+  read_line string_length int-to-string write_line ;
+";
+    let output = run_with_stdin("read-line", source, "world\r\nsecond\n");
+
+    assert!(output.status.success());
+    // Line endings are stripped, and end of input reads as an empty line
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Hello, world\nsecond\n0\n"
+    );
+}
+
+#[test]
+fn test_run_negates_conditions() {
+    let source = ": main ( -- )
+  1 2 = not if [ \"different\" write_line ] [ \"same\" write_line ]
+  true not if [ \"wrong\" write_line ] [ \"right\" write_line ] ;
+";
+    let output = run("not", source);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "different\nright\n"
+    );
+}
+
+#[test]
+fn test_run_counts_down_with_while() {
+    let source = ": main ( -- )
+  5 while [ dup 0 > ] [ dup int-to-string write_line 1 - ] drop ;
+";
+    let output = run("while", source);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5\n4\n3\n2\n1\n");
+}
+
+#[test]
+fn test_repl_keeps_definitions_and_survives_errors() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cem"))
+        .arg("repl")
+        .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b": double ( Int -- Int ) 2 * ;\n1 \"a\" +\n21 double true\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Defined double\n"), "{}", stdout);
+    assert!(stdout.contains("42 true\n"), "{}", stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Type error"), "{}", stderr);
+}
+
+#[test]
+fn test_run_hello_world() {
+    let source = ": main ( -- )\n  \"Hello, \" write \"World!\" write_line ;\n";
+    let output = run("hello-world", source);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello, World!\n");
+}
+
+#[test]
+fn test_run_echoes_first_argument() {
+    let source = ": main ( -- )
+  args_count int-to-string write_line
+  1 arg_at write_line ;
+";
+    let output = run_with("echo-arg", source, "", &["héllo world", "ignored"]);
+
+    assert!(output.status.success());
+    // The program itself is argument 0
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\nhéllo world\n");
+}
+
+#[test]
+fn test_run_reads_files() {
+    let (dir, input) = write_source("read-file-input", "line one\nline two\n");
+    let source = format!(
+        ": cat ( String -- )
+  read-file match
+    Ok => [ write ]
+    Err => [ \"error: \" swap string_concat write_line ]
+  end ;
+: main ( -- )
+  \"{}\" cat
+  \"{}\" cat ;
+",
+        input.display(),
+        dir.join("missing.txt").display()
+    );
+    let output = run("read-file", &source);
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("line one\nline two\nerror: ") && stdout.contains("missing.txt"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_run_waits_for_spawned_strands() {
+    let source = ": main ( -- )
+  [ \"strand 1\" write_line ] spawn
+  [ \"strand 2\" write_line ] spawn
+  [ \"strand 3\" write_line ] spawn ;
+";
+    let output = run("spawn", source);
+
+    assert!(output.status.success());
+    // Strands may run in any order, but the program exits only after all of them
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines: Vec<_> = stdout.lines().collect();
+    lines.sort();
+    assert_eq!(lines, ["strand 1", "strand 2", "strand 3"]);
+}
+
+#[test]
+fn test_run_channel_between_strands() {
+    let source = ": main ( -- )
+  channel dup [ 1 send 2 send 3 send drop ] spawn-with
+  recv int-to-string write_line
+  recv int-to-string write_line
+  recv int-to-string write_line
+  drop ;
+";
+    let output = run("channel", source);
+
+    assert!(output.status.success());
+    // Values arrive in the order they were sent
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n2\n3\n");
+}
+
+#[test]
+fn test_run_joins_strand_result() {
+    let source = ": main ( -- )
+  [ 6 7 * ] spawn-result
+  [ \"computing\" write_line 100 ] spawn-result
+  join swap join + int-to-string write_line ;
+";
+    let output = run("join", source);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "computing\n142\n");
+}
+
+#[test]
+fn test_run_exits_with_status() {
+    let source = ": main ( -- )\n  \"not printed\" drop 3 exit ;\n";
+    let output = run("exit", source);
+
+    assert_eq!(output.status.code(), Some(3));
+}
+
+/// Run `cem compile --emit <emit>` on `source` with the default output name
+///
+/// Asserts that the directory ends up with `files` files, the source included,
+/// and returns the contents of the artifact with `extension`, if one is expected.
+fn emit(
+    name: &str,
+    source: &str,
+    emit: &str,
+    files: usize,
+    extension: Option<&str>,
+) -> (Output, Option<Vec<u8>>) {
+    let (dir, input) = write_source(name, source);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cem"))
+        .arg("compile")
+        .arg(&input)
+        .arg("--emit")
+        .arg(emit)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let artifact = extension.and_then(|ext| fs::read(dir.join(format!("{}{}", name, ext))).ok());
+
+    let written = fs::read_dir(&dir).unwrap().count();
+    fs::remove_dir_all(&dir).ok();
+    assert_eq!(written, files, "unexpected files left by --emit {}", emit);
+
+    (output, artifact)
+}
+
+const EMIT_SOURCE: &str = ": main ( -- )\n  \"hi\" write_line ;\n";
+
+#[test]
+fn test_emit_ir_writes_ll_file() {
+    let (output, artifact) = emit("emit-ir", EMIT_SOURCE, "ir", 2, Some(".ll"));
+
+    assert!(output.status.success(), "{:?}", output);
+    let ir = String::from_utf8(artifact.unwrap()).unwrap();
+    assert!(ir.contains("; Cem Compiler - Generated LLVM IR"));
+    assert!(ir.contains("define i32 @main("));
+}
+
+#[test]
+fn test_emit_obj_writes_object_file() {
+    let (output, artifact) = emit("emit-obj", EMIT_SOURCE, "obj", 2, Some(".o"));
+
+    assert!(output.status.success(), "{:?}", output);
+    // ELF or 64-bit Mach-O
+    let object = artifact.unwrap();
+    assert!(
+        object.starts_with(b"\x7fELF") || object.starts_with(&[0xcf, 0xfa, 0xed, 0xfe]),
+        "{:?}",
+        &object[..4]
+    );
+}
+
+#[test]
+fn test_emit_bc_writes_bitcode() {
+    let (output, artifact) = emit("emit-bc", EMIT_SOURCE, "bc", 2, Some(".bc"));
+
+    assert!(output.status.success(), "{:?}", output);
+    let bitcode = artifact.unwrap();
+    assert!(bitcode.starts_with(b"BC\xc0\xde"), "{:?}", &bitcode[..4]);
+}
+
+#[test]
+fn test_emit_asm_writes_assembly() {
+    let (output, artifact) = emit("emit-asm", EMIT_SOURCE, "asm", 2, Some(".s"));
+
+    assert!(output.status.success(), "{:?}", output);
+    let asm = String::from_utf8(artifact.unwrap()).unwrap();
+    assert!(asm.contains("main:"), "{}", asm);
+}
+
+#[test]
+fn test_emit_tokens_prints_token_stream() {
+    // Dumps only print: nothing is written besides the source
+    let source = ": sq ( Int -- Int )\n  dup * ;\n";
+    let (output, _) = emit("emit-tokens", source, "tokens", 1, None);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines[0], "1:1\t:\t\":\"");
+    assert_eq!(lines[1], "1:3\tIDENT\t\"sq\"");
+    assert!(lines.contains(&"2:3\tIDENT\t\"dup\""), "{}", stdout);
+    assert_eq!(lines.last(), Some(&"3:1\tEOF\t\"\""));
+}
+
+#[test]
+fn test_emit_ast_prints_match_and_if_in_full() {
+    let source = ": pick-one ( Option(Int) Bool -- Int )
+  if [ match Some(n) => [ n ] None => [ 0 ] end ] [ drop 1 ] ;
+";
+    let (output, _) = emit("emit-ast", source, "ast", 1, None);
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("word pick-one ( Option<Int> Bool -- Int ) @ "));
+    for expected in [
+        "\n  If @ ",
+        "\n    then\n      Quotation @ ",
+        "\n        Match @ ",
+        "\n          Some(n) =>\n            Call n @ ",
+        "\n          None =>\n            Int 0 @ ",
+        "\n    else\n      Quotation @ ",
+    ] {
+        assert!(
+            stdout.contains(expected),
+            "missing {:?} in\n{}",
+            expected,
+            stdout
+        );
+    }
+
+    // Nothing from the prelude
+    assert!(!stdout.contains("type Option"), "{}", stdout);
+}
+
+/// Run `cem fmt` on `source` written to `name.cem`, returning the output and
+/// the file's contents afterwards
+fn fmt(name: &str, source: &str, extra_args: &[&str]) -> (Output, String) {
+    let (dir, input) = write_source(name, source);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cem"))
+        .arg("fmt")
+        .arg(&input)
+        .args(extra_args)
+        .output()
+        .unwrap();
+    let contents = fs::read_to_string(&input).unwrap();
+    fs::remove_dir_all(&dir).ok();
+
+    (output, contents)
+}
+
+#[test]
+fn test_fmt_prints_or_rewrites_canonical_source() {
+    let source = ": main   ( -- )\n\"hi\"   write_line ;";
+    let formatted = ": main ( -- )\n  \"hi\" write_line ;\n";
+
+    let (output, contents) = fmt("fmt-print", source, &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), formatted);
+    assert_eq!(contents, source);
+
+    let (output, contents) = fmt("fmt-write", source, &["--write"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stdout.is_empty());
+    assert_eq!(contents, formatted);
+}
+
+#[test]
+fn test_fmt_write_keeps_files_with_comments() {
+    let source = "# greeting\n: main ( -- ) \"hi\" write_line ;\n";
+
+    let (output, contents) = fmt("fmt-comments", source, &["--write"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("has comments"), "{}", stderr);
+    assert_eq!(contents, source);
+}
+
+/// Run `cem compile -` with `source` piped to stdin
+fn compile_stdin(source: &str, extra_args: &[&str]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cem"))
+        .arg("compile")
+        .arg("-")
+        .args(extra_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // cem may exit on a usage error before reading any of it
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .ok();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_compile_reads_source_from_stdin() {
+    let dir = std::env::temp_dir().join(format!("cem-test-stdin-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let ir_file = dir.join("piped.ll");
+
+    let source = ": main ( -- )\n  \"piped\" write_line ;\n";
+    let output = compile_stdin(source, &["--emit", "ir", "-o", ir_file.to_str().unwrap()]);
+    let ir = fs::read_to_string(&ir_file);
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(ir.unwrap().contains("define ptr @cem_main(ptr %stack)"));
+
+    // Errors point into <stdin>
+    let output = compile_stdin(
+        ": main ( -- )\n  1 ] ;\n",
+        &["--emit", "ir", "-o", "unused.ll"],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("<stdin>: Parse error at 2:5:"),
+        "{}",
+        stderr
+    );
+
+    // There's no input name to derive the output's from
+    let output = compile_stdin(source, &[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("give one with -o"), "{}", stderr);
+}
+
+#[test]
+fn test_compile_joins_several_input_files() {
+    let (dir, main) = write_source("several", ": main ( -- )\n  greet ;\n");
+    let lib = dir.join("lib.cem");
+    fs::write(&lib, ": greet ( -- )\n  \"hi\" write_line ;\n").unwrap();
+    let dup = dir.join("dup.cem");
+    fs::write(&dup, ": greet ( -- ) ;\n").unwrap();
+    let ir_file = dir.join("several.ll");
+
+    let compile = |inputs: &[&PathBuf]| {
+        Command::new(env!("CARGO_BIN_EXE_cem"))
+            .arg("compile")
+            .args(inputs)
+            .args(["--emit", "ir", "-o"])
+            .arg(&ir_file)
+            .current_dir(&dir)
+            .output()
+            .unwrap()
+    };
+
+    let output = compile(&[&main, &lib]);
+    let ir = fs::read_to_string(&ir_file);
+    // The same word in two files is an error that names both
+    let duplicate = compile(&[&main, &lib, &dup]);
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(output.status.success(), "{:?}", output);
+    let ir = ir.unwrap();
+    assert!(ir.contains("define ptr @greet(ptr %stack)"));
+    assert!(ir.contains("DIFile(filename: \"lib.cem\""));
+
+    assert!(!duplicate.status.success());
+    let stderr = String::from_utf8_lossy(&duplicate.stderr);
+    assert!(
+        stderr.contains("lib.cem:1:1") && stderr.contains("dup.cem:1:1"),
+        "{}",
+        stderr
+    );
+}
+
+/// Run `cem compile --emit ir` on `main.cem` in a directory of `files`
+fn compile_files(name: &str, files: &[(&str, &str)]) -> (Output, Option<String>) {
+    let dir = std::env::temp_dir().join(format!("cem-test-{}-{}", name, std::process::id()));
+    for (file, source) in files {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cem"))
+        .args(["compile", "main.cem", "--emit", "ir", "-o", "main.ll"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let ir = fs::read_to_string(dir.join("main.ll")).ok();
+    fs::remove_dir_all(&dir).ok();
+    (output, ir)
+}
+
+#[test]
+fn test_compile_loads_imports() {
+    let (output, ir) = compile_files(
+        "imports",
+        &[
+            (
+                "main.cem",
+                "import \"lib/greet.cem\"\nimport \"lib/greet.cem\"\n: main ( -- )\n  greet ;\n",
+            ),
+            (
+                "lib/greet.cem",
+                "import \"words.cem\"\n: greet ( -- )\n  hello ;\n",
+            ),
+            ("lib/words.cem", ": hello ( -- )\n  \"hi\" write_line ;\n"),
+        ],
+    );
+
+    assert!(output.status.success(), "{:?}", output);
+    // Imports are relative to the importing file, and loaded once each
+    let ir = ir.unwrap();
+    assert_eq!(ir.matches("define ptr @hello(ptr %stack)").count(), 1);
+    assert!(ir.contains("define ptr @greet(ptr %stack)"));
+}
+
+#[test]
+fn test_compile_rejects_import_cycles() {
+    let (output, _) = compile_files(
+        "import-cycle",
+        &[
+            ("main.cem", "import \"a.cem\"\n: main ( -- ) ;\n"),
+            ("a.cem", "import \"b.cem\"\n: a ( -- ) ;\n"),
+            ("b.cem", "import \"a.cem\"\n: b ( -- ) ;\n"),
+        ],
+    );
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("b.cem:1:1: Import cycle: a.cem -> b.cem -> a.cem"),
+        "{}",
+        stderr
+    );
+}
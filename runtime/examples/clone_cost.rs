@@ -0,0 +1,57 @@
+//! Measure what copying a list costs
+//!
+//! Run with `cargo run --release -p cem-runtime --example clone_cost`, and
+//! add `--features shared-cells` to measure copies that share the list's
+//! fields instead of deep-cloning them. docs/REFCOUNTED_CELLS.md compares
+//! the two.
+
+use cem_runtime::StackCell;
+use cem_runtime::pattern::{LIST_CONS_TAG, LIST_NIL_TAG, push_variant};
+use cem_runtime::scheduler::free_stack;
+use cem_runtime::stack::{copy_cell, push_int};
+use std::ptr;
+use std::time::{Duration, Instant};
+
+/// Build a list of `len` Ints laid out the way codegen builds `Cons`
+fn build_list(len: i64) -> *mut StackCell {
+    unsafe {
+        let mut list = push_variant(ptr::null_mut(), LIST_NIL_TAG, ptr::null_mut());
+        for i in 0..len {
+            (*list).next = push_int(ptr::null_mut(), i);
+            list = push_variant(ptr::null_mut(), LIST_CONS_TAG, list);
+        }
+        list
+    }
+}
+
+/// Average time of `copy_cell` (what `dup` and match bindings use) and of
+/// freeing the copy
+fn measure(list: *mut StackCell, rounds: u32) -> (Duration, Duration) {
+    let mut clone_time = Duration::ZERO;
+    let mut free_time = Duration::ZERO;
+    for _ in 0..rounds {
+        let start = Instant::now();
+        let copy = unsafe { copy_cell(list) };
+        clone_time += start.elapsed();
+
+        let start = Instant::now();
+        unsafe { free_stack(copy) };
+        free_time += start.elapsed();
+    }
+    (clone_time / rounds, free_time / rounds)
+}
+
+fn main() {
+    if cfg!(feature = "shared-cells") {
+        println!("copies share fields (shared-cells)");
+    } else {
+        println!("copies are deep clones");
+    }
+    println!("{:>10}  {:>12}  {:>12}", "length", "clone", "free");
+    for len in [1_000, 10_000, 100_000, 1_000_000] {
+        let list = build_list(len);
+        let (clone, free) = measure(list, 20);
+        println!("{:>10}  {:>12?}  {:>12?}", len, clone, free);
+        unsafe { free_stack(list) };
+    }
+}
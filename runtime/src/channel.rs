@@ -0,0 +1,253 @@
+/*!
+Channels - Message passing between strands, backed by May's mpsc
+*/
+
+use crate::stack::{CellDataUnion, CellType, StackCell};
+use may::sync::{Mutex, mpsc};
+use std::sync::Arc;
+
+/// A value in transit: a detached cell, owned by the channel until received
+struct Message(*mut StackCell);
+
+// SAFETY: a cell on its way through a channel belongs to no stack, so only
+// the strand that receives it can reach it
+unsafe impl Send for Message {}
+
+impl Message {
+    /// Take the cell out, so dropping the message doesn't free it
+    fn into_cell(self) -> *mut StackCell {
+        let cell = self.0;
+        std::mem::forget(self);
+        cell
+    }
+}
+
+impl Drop for Message {
+    /// Free values still queued when the channel itself is freed
+    fn drop(&mut self) {
+        unsafe { crate::stack::free_cell_chains(vec![self.0]) }
+    }
+}
+
+/// An unbounded channel, shared by every cell that holds a handle to it
+///
+/// Each handle is an `Arc` stored in a `CellType::Channel` cell, so copying
+/// the cell copies the handle and both copies reach the same queue. The
+/// channel holds its own sender, so it never disconnects while a handle to
+/// it exists.
+pub struct Channel {
+    sender: Mutex<mpsc::Sender<Message>>,
+    receiver: Mutex<mpsc::Receiver<Message>>,
+}
+
+/// Pop a channel handle, returning the rest of the stack and the handle cell
+///
+/// # Safety
+/// Stack must have a channel on top.
+unsafe fn pop_channel(stack: *mut StackCell, op: &str) -> (*mut StackCell, Box<StackCell>) {
+    assert!(!stack.is_null(), "{}: stack is empty", op);
+
+    let (rest, cell) = unsafe { StackCell::pop(stack) };
+    assert!(
+        cell.cell_type == CellType::Channel,
+        "{}: expected a channel",
+        op
+    );
+    (rest, cell)
+}
+
+/// Create a channel (the `channel` word)
+///
+/// Stack effect: ( -- Chan(T) )
+///
+/// # Safety
+/// Stack pointer must be a valid StackCell or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn channel_new(stack: *mut StackCell) -> *mut StackCell {
+    let (sender, receiver) = mpsc::channel();
+    let channel = Arc::new(Channel {
+        sender: Mutex::new(sender),
+        receiver: Mutex::new(receiver),
+    });
+
+    let cell = Box::new(StackCell {
+        cell_type: CellType::Channel,
+        _padding: 0,
+        data: CellDataUnion {
+            channel_ptr: Arc::into_raw(channel),
+        },
+        next: std::ptr::null_mut(),
+    });
+    unsafe { StackCell::push(stack, cell) }
+}
+
+/// Send a value on a channel (the `send` word)
+///
+/// Stack effect: ( Chan(T) T -- Chan(T) )
+///
+/// The value moves into the channel. Sending never blocks.
+///
+/// # Safety
+/// Stack must have a value on top and a channel beneath it.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn channel_send(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "send: stack is empty");
+
+    let (rest, mut value) = unsafe { StackCell::pop(stack) };
+    let (rest, handle) = unsafe { pop_channel(rest, "send") };
+
+    value.next = std::ptr::null_mut();
+    let channel = unsafe { &*handle.data.channel_ptr };
+    channel
+        .sender
+        .lock()
+        .unwrap()
+        .send(Message(Box::into_raw(value)))
+        .expect("send: the channel holds its own receiver");
+
+    unsafe { StackCell::push(rest, handle) }
+}
+
+/// Receive the next value from a channel (the `recv` word)
+///
+/// Stack effect: ( Chan(T) -- Chan(T) T )
+///
+/// Waits for a value if the channel is empty, suspending only the calling
+/// strand. If it's empty and this is the only handle left, nothing can ever
+/// be sent, so this is a runtime error rather than a strand that waits
+/// forever.
+///
+/// # Safety
+/// Stack must have a channel on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn channel_recv(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, handle) = unsafe { pop_channel(stack, "recv") };
+
+    let channel_ptr = unsafe { handle.data.channel_ptr };
+    let receiver = unsafe { &*channel_ptr }.receiver.lock().unwrap();
+    let message = match receiver.try_recv() {
+        Ok(message) => message,
+        Err(_) if unsafe { is_only_handle(channel_ptr) } => unsafe {
+            crate::runtime_error(c"recv: channel is empty and nothing else can send to it".as_ptr())
+        },
+        Err(_) => receiver
+            .recv()
+            .expect("recv: the channel holds its own sender"),
+    };
+    drop(receiver);
+
+    let rest = unsafe { StackCell::push(rest, handle) };
+    let value = unsafe { Box::from_raw(message.into_cell()) };
+    unsafe { StackCell::push(rest, value) }
+}
+
+/// Whether the handle being used is the last one to its channel
+///
+/// # Safety
+/// `channel_ptr` must come from a live channel cell.
+unsafe fn is_only_handle(channel_ptr: *const Channel) -> bool {
+    // Borrow the count without taking the cell's reference
+    let channel = std::mem::ManuallyDrop::new(unsafe { Arc::from_raw(channel_ptr) });
+    Arc::strong_count(&channel) == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime_error_from;
+    use crate::stack::{dup, push_int};
+    use std::ffi::CString;
+
+    #[test]
+    fn test_send_and_recv_in_order() {
+        unsafe {
+            let mut stack = channel_new(std::ptr::null_mut());
+            for value in [1, 2, 3] {
+                stack = channel_send(push_int(stack, value));
+            }
+
+            for expected in [1, 2, 3] {
+                stack = channel_recv(stack);
+                let (rest, value) = StackCell::pop(stack);
+                assert_eq!(value.as_int(), Some(expected));
+                stack = rest;
+            }
+
+            assert_eq!((*stack).cell_type, CellType::Channel);
+            assert!((*stack).next.is_null());
+            crate::scheduler::free_stack(stack);
+        }
+    }
+
+    #[test]
+    fn test_copies_share_the_queue() {
+        unsafe {
+            // ( chan chan ) - send on the lower copy, receive on the top one
+            let stack = dup(channel_new(std::ptr::null_mut()));
+            let (rest, top) = StackCell::pop(stack);
+            let text = CString::new("hello").unwrap();
+            let rest = channel_send(crate::stack::push_string(rest, text.as_ptr()));
+
+            let stack = channel_recv(StackCell::push(rest, top));
+            let (rest, value) = StackCell::pop(stack);
+            let received = std::ffi::CStr::from_ptr(value.as_string_ptr().unwrap());
+            assert_eq!(received.to_str(), Ok("hello"));
+
+            crate::scheduler::free_stack(rest);
+        }
+    }
+
+    #[test]
+    fn test_recv_between_strands() {
+        unsafe {
+            // The producer gets one copy of the channel, the test keeps the other
+            extern "C" fn producer(stack: *mut StackCell) -> *mut StackCell {
+                let mut stack = stack;
+                for value in 0..100 {
+                    stack = unsafe { channel_send(push_int(stack, value)) };
+                }
+                stack
+            }
+
+            // ( chan chan [ producer ] -- chan )
+            let stack = dup(channel_new(std::ptr::null_mut()));
+            let stack = crate::stack::push_quotation(stack, producer as *mut ());
+            let mut stack = crate::scheduler::spawn_with(stack);
+
+            for expected in 0..100 {
+                stack = channel_recv(stack);
+                let (rest, value) = StackCell::pop(stack);
+                assert_eq!(value.as_int(), Some(expected));
+                stack = rest;
+            }
+
+            crate::scheduler::wait_all_strands();
+            crate::scheduler::free_stack(stack);
+        }
+    }
+
+    #[test]
+    fn test_unreceived_values_are_freed_with_the_channel() {
+        unsafe {
+            let text = CString::new("never received").unwrap();
+            let stack = channel_new(std::ptr::null_mut());
+            let stack = channel_send(crate::stack::push_string(stack, text.as_ptr()));
+            crate::scheduler::free_stack(stack);
+        }
+    }
+
+    #[test]
+    fn test_recv_on_lone_empty_channel_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "channel::tests::test_recv_on_lone_empty_channel_is_a_runtime_error",
+            || unsafe {
+                channel_recv(channel_new(std::ptr::null_mut()));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: recv: channel is empty and nothing else can send"),
+            "{}",
+            stderr
+        );
+    }
+}
@@ -2,7 +2,7 @@
 Type Conversion Operations
 */
 
-use crate::stack::{StackCell, push_string};
+use crate::stack::{StackCell, push_owned_string};
 use std::ffi::CString;
 
 /// Convert integer to string
@@ -19,11 +19,10 @@ pub unsafe extern "C" fn int_to_string(stack: *mut StackCell) -> *mut StackCell
         .as_int()
         .expect("int_to_string: expected integer on stack");
 
-    // Convert to string
-    let string = int_val.to_string();
-    let c_string = CString::new(string).expect("int_to_string: conversion failed");
+    // Digits and a sign never contain a nul byte
+    let c_string = CString::new(int_val.to_string()).expect("int_to_string: conversion failed");
 
-    unsafe { push_string(rest, c_string.as_ptr()) }
+    unsafe { push_owned_string(rest, c_string) }
 }
 
 /// Convert boolean to string
@@ -44,7 +43,7 @@ pub unsafe extern "C" fn bool_to_string(stack: *mut StackCell) -> *mut StackCell
     let string = if bool_val { "true" } else { "false" };
     let c_string = CString::new(string).expect("bool_to_string: conversion failed");
 
-    unsafe { push_string(rest, c_string.as_ptr()) }
+    unsafe { push_owned_string(rest, c_string) }
 }
 
 #[cfg(test)]
@@ -122,4 +121,24 @@ mod tests {
             crate::scheduler::free_stack(std::ptr::null_mut());
         }
     }
+
+    #[test]
+    fn test_extreme_int_to_string() {
+        for (value, expected) in [
+            (0, "0"),
+            (i64::MAX, "9223372036854775807"),
+            (i64::MIN, "-9223372036854775808"),
+        ] {
+            unsafe {
+                let stack = int_to_string(push_int(std::ptr::null_mut(), value));
+
+                let (rest, cell) = StackCell::pop(stack);
+                let string_ptr = cell.as_string_ptr().expect("should be string");
+                let rust_str = std::ffi::CStr::from_ptr(string_ptr).to_str().unwrap();
+
+                assert_eq!(rust_str, expected);
+                assert!(rest.is_null());
+            }
+        }
+    }
 }
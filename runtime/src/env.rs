@@ -0,0 +1,183 @@
+/*!
+Process environment - Command-line arguments and environment variables
+*/
+
+use crate::pattern::{OPTION_NONE_TAG, OPTION_SOME_TAG, push_variant};
+use crate::stack::{CellDataUnion, CellType, StackCell};
+use std::ffi::{CStr, CString};
+use std::sync::OnceLock;
+
+/// The process arguments, read once on first use
+///
+/// Like C's `argv`, the first is the program itself. Arguments that aren't
+/// valid UTF-8 are converted lossily, since Cem strings are UTF-8.
+fn args() -> &'static [CString] {
+    static ARGS: OnceLock<Vec<CString>> = OnceLock::new();
+    ARGS.get_or_init(|| {
+        std::env::args_os()
+            .map(|arg| {
+                // The OS passes arguments as C strings, so they can't contain a null byte
+                CString::new(arg.to_string_lossy().into_owned())
+                    .expect("args: argument contains null byte")
+            })
+            .collect()
+    })
+}
+
+/// Push the number of command-line arguments, including the program itself
+///
+/// Stack effect: ( -- Int )
+///
+/// # Safety
+/// Stack pointer must be a valid StackCell or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn args_count(stack: *mut StackCell) -> *mut StackCell {
+    let count = i64::try_from(args().len()).expect("args_count: too many arguments");
+    unsafe { crate::stack::push_int(stack, count) }
+}
+
+/// Replace an index with the command-line argument at it
+///
+/// Stack effect: ( Int -- String )
+///
+/// Index 0 is the program itself. An index outside the arguments is a
+/// runtime error.
+///
+/// # Safety
+/// Stack must have an Int on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn arg_at(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "arg_at: stack is empty");
+
+    let (rest, index_cell) = unsafe { StackCell::pop(stack) };
+    let index = index_cell
+        .as_int()
+        .expect("arg_at: index must be an integer");
+
+    let Some(arg) = usize::try_from(index).ok().and_then(|i| args().get(i)) else {
+        unsafe { crate::runtime_error(c"arg_at: index is out of bounds".as_ptr()) }
+    };
+
+    let cell = Box::new(StackCell {
+        cell_type: CellType::String,
+        _padding: 0,
+        data: CellDataUnion {
+            string_ptr: arg.clone().into_raw(),
+        },
+        next: std::ptr::null_mut(),
+    });
+    unsafe { StackCell::push(rest, cell) }
+}
+
+/// Replace a variable name with the variable's value (the `getenv` word)
+///
+/// Named `getenv_op` so it doesn't clash with libc's `getenv`.
+///
+/// Stack effect: ( String -- Option(String) )
+///
+/// Pushes `Some(value)` if the variable is set and `None` if it isn't. A
+/// value that isn't valid UTF-8 can't be a Cem string, so it counts as unset.
+///
+/// # Safety
+/// Stack must have a string on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn getenv_op(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "getenv: stack is empty");
+
+    let (rest, cell) = unsafe { StackCell::pop(stack) };
+    let name_ptr = cell
+        .as_string_ptr()
+        .expect("getenv: expected string on stack");
+    assert!(!name_ptr.is_null(), "getenv: string is null");
+
+    let name = unsafe {
+        match CStr::from_ptr(name_ptr).to_str() {
+            Ok(name) => name,
+            Err(_) => crate::runtime_error(c"getenv: name contains invalid UTF-8".as_ptr()),
+        }
+    };
+
+    // The name is freed by cell Drop
+    match std::env::var(name) {
+        Ok(value) => unsafe {
+            // The environment can't hold a null byte, so this is always a C string
+            let value = CString::new(value).expect("getenv: value contains null byte");
+            let field = Box::new(StackCell {
+                cell_type: CellType::String,
+                _padding: 0,
+                data: CellDataUnion {
+                    string_ptr: value.into_raw(),
+                },
+                next: std::ptr::null_mut(),
+            });
+            push_variant(rest, OPTION_SOME_TAG, Box::into_raw(field))
+        },
+        Err(_) => unsafe { push_variant(rest, OPTION_NONE_TAG, std::ptr::null_mut()) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime_error_from;
+    use crate::stack::push_int;
+
+    #[test]
+    fn test_args_match_the_process() {
+        unsafe {
+            let stack = args_count(std::ptr::null_mut());
+            let expected = std::env::args().count() as i64;
+            assert_eq!((*stack).as_int(), Some(expected));
+            crate::scheduler::free_stack(stack);
+
+            // The test harness is argument 0
+            let stack = arg_at(push_int(std::ptr::null_mut(), 0));
+            let arg = CStr::from_ptr((*stack).as_string_ptr().unwrap());
+            assert_eq!(arg.to_str().ok(), std::env::args().next().as_deref());
+            crate::scheduler::free_stack(stack);
+        }
+    }
+
+    #[test]
+    fn test_getenv_op() {
+        unsafe {
+            // Unique to this test, so tests running in parallel don't race on it
+            std::env::set_var("CEM_TEST_GETENV", "some value");
+            let name = CString::new("CEM_TEST_GETENV").unwrap();
+            let stack = getenv_op(crate::stack::push_string(
+                std::ptr::null_mut(),
+                name.as_ptr(),
+            ));
+
+            let variant = (*stack).as_variant().expect("getenv should push a variant");
+            assert_eq!(variant.tag, OPTION_SOME_TAG);
+            let value = CStr::from_ptr((*variant.data).as_string_ptr().unwrap());
+            assert_eq!(value.to_str(), Ok("some value"));
+            crate::scheduler::free_stack(stack);
+
+            let name = CString::new("CEM_TEST_GETENV_UNSET").unwrap();
+            let stack = getenv_op(crate::stack::push_string(
+                std::ptr::null_mut(),
+                name.as_ptr(),
+            ));
+            let variant = (*stack).as_variant().expect("getenv should push a variant");
+            assert_eq!(variant.tag, OPTION_NONE_TAG);
+            crate::scheduler::free_stack(stack);
+        }
+    }
+
+    #[test]
+    fn test_arg_at_out_of_bounds_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "env::tests::test_arg_at_out_of_bounds_is_a_runtime_error",
+            || unsafe {
+                arg_at(push_int(std::ptr::null_mut(), -1));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: arg_at: index is out of bounds"),
+            "{}",
+            stderr
+        );
+    }
+}
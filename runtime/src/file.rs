@@ -0,0 +1,176 @@
+/*!
+Files - Reading and writing whole files as Cem strings
+*/
+
+use crate::pattern::{RESULT_ERR_TAG, RESULT_OK_TAG, push_variant};
+use crate::stack::{CellDataUnion, CellType, StackCell};
+use std::ffi::{CStr, CString};
+
+/// Replace a path with the whole contents of the file at it (the `read-file` word)
+///
+/// Stack effect: ( String -- Result(String, String) )
+///
+/// Pushes `Ok(contents)`, or `Err(message)` if the file can't be read. A
+/// file that isn't valid UTF-8 or contains a null byte can't be a Cem
+/// string, so it's an `Err` too.
+///
+/// # Safety
+/// Stack must have a string on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn read_file(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "read_file: stack is empty");
+
+    let (rest, path) = unsafe { pop_path(stack, "read_file") };
+
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| {
+            CString::new(contents).map_err(|_| "file contains a null byte".to_string())
+        });
+    let (tag, text) = match contents {
+        Ok(contents) => (RESULT_OK_TAG, contents),
+        Err(message) => {
+            // Neither the path nor an OS message contains a null byte
+            let message = CString::new(format!("{}: {}", path, message))
+                .expect("read_file: message contains null byte");
+            (RESULT_ERR_TAG, message)
+        }
+    };
+
+    let field = Box::new(StackCell {
+        cell_type: CellType::String,
+        _padding: 0,
+        data: CellDataUnion {
+            string_ptr: text.into_raw(),
+        },
+        next: std::ptr::null_mut(),
+    });
+    unsafe { push_variant(rest, tag, Box::into_raw(field)) }
+}
+
+/// Write a string to a file, replacing it (the `write-file` word)
+///
+/// Stack effect: ( String String -- Bool ), path beneath the contents
+///
+/// Pushes whether the write succeeded. Unlike `read-file` there's no error
+/// message: a program that needs to know why can read the file back, and a
+/// `Bool` can be tested with `if` directly. Cem strings are C strings, so
+/// neither can contain a null byte.
+///
+/// # Safety
+/// Stack must have two strings on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn write_file(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "write_file: stack is empty");
+
+    let (rest, contents) = unsafe { StackCell::pop(stack) };
+    let contents_ptr = contents
+        .as_string_ptr()
+        .expect("write_file: contents must be a string");
+    assert!(!contents_ptr.is_null(), "write_file: string is null");
+    assert!(!rest.is_null(), "write_file: need a path and contents");
+    let (rest, path) = unsafe { pop_path(rest, "write_file") };
+
+    // The bytes stop at the null terminator, so none is written
+    let bytes = unsafe { CStr::from_ptr(contents_ptr) }.to_bytes();
+    let written = std::fs::write(&path, bytes).is_ok();
+
+    // The contents are freed by cell Drop
+    unsafe { crate::stack::push_bool(rest, written) }
+}
+
+/// Pop a path string, returning the rest of the stack and the path
+///
+/// # Safety
+/// Stack must have a string on top.
+unsafe fn pop_path(stack: *mut StackCell, op: &str) -> (*mut StackCell, String) {
+    let (rest, cell) = unsafe { StackCell::pop(stack) };
+    let path_ptr = cell
+        .as_string_ptr()
+        .unwrap_or_else(|| panic!("{}: path must be a string", op));
+    assert!(!path_ptr.is_null(), "{}: string is null", op);
+
+    // The cell is freed on return, so the path is copied out of it
+    match unsafe { CStr::from_ptr(path_ptr) }.to_str() {
+        Ok(path) => (rest, path.to_string()),
+        Err(_) => {
+            let message =
+                CString::new(format!("{}: path contains invalid UTF-8", op)).unwrap_or_default();
+            unsafe { crate::runtime_error(message.as_ptr()) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stack::push_string;
+
+    /// Call `read_file` on `path`, returning the result's tag and string
+    unsafe fn read(path: &std::path::Path) -> (u32, String) {
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+        unsafe {
+            let stack = read_file(push_string(std::ptr::null_mut(), path.as_ptr()));
+            let variant = (*stack)
+                .as_variant()
+                .expect("read_file should push a variant");
+            let text = CStr::from_ptr((*variant.data).as_string_ptr().unwrap());
+            let result = (variant.tag, text.to_str().unwrap().to_string());
+            crate::scheduler::free_stack(stack);
+            result
+        }
+    }
+
+    #[test]
+    fn test_read_file() {
+        let dir = std::env::temp_dir().join(format!("cem-read-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("hello.txt");
+        std::fs::write(&path, "héllo\nworld\n").unwrap();
+        assert_eq!(
+            unsafe { read(&path) },
+            (RESULT_OK_TAG, "héllo\nworld\n".to_string())
+        );
+
+        let (tag, message) = unsafe { read(&dir.join("missing.txt")) };
+        assert_eq!(tag, RESULT_ERR_TAG);
+        assert!(message.contains("missing.txt: "), "{}", message);
+
+        // Not a Cem string, but not a crash either
+        let binary = dir.join("binary");
+        std::fs::write(&binary, [0xff, 0xfe, 0x00]).unwrap();
+        assert_eq!(unsafe { read(&binary) }.0, RESULT_ERR_TAG);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_write_file_round_trips() {
+        let dir = std::env::temp_dir().join(format!("cem-write-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let write = |path: &std::path::Path, contents: &str| unsafe {
+            let path = CString::new(path.to_str().unwrap()).unwrap();
+            let contents = CString::new(contents).unwrap();
+            let stack = push_string(std::ptr::null_mut(), path.as_ptr());
+            let stack = write_file(push_string(stack, contents.as_ptr()));
+            let (rest, written) = StackCell::pop(stack);
+            assert!(rest.is_null());
+            written.as_bool().unwrap()
+        };
+
+        let path = dir.join("out.txt");
+        assert!(write(&path, "first"));
+        assert!(write(&path, "héllo\nwritten\n"));
+        assert_eq!(
+            unsafe { read(&path) },
+            (RESULT_OK_TAG, "héllo\nwritten\n".to_string())
+        );
+
+        // A directory that doesn't exist can't hold the file
+        assert!(!write(&dir.join("missing").join("out.txt"), "lost"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -3,40 +3,77 @@ I/O Operations with May Coroutines - C-compatible layout
 */
 
 use crate::stack::{CellDataUnion, CellType, StackCell};
+use std::ffi::CString;
 use std::io::{self, Write};
 
+/// Write a string followed by a newline to stdout
+///
 /// # Safety
 /// Stack must have a string on top.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn write_line(stack: *mut StackCell) -> *mut StackCell {
-    assert!(!stack.is_null(), "write_line: stack is empty");
+    unsafe { write_string(stack, "\n", "write_line") }
+}
+
+/// Write a string to stdout, without a newline (the `write` word)
+///
+/// Named `write_op` so it doesn't clash with libc's `write`.
+///
+/// # Safety
+/// Stack must have a string on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn write_op(stack: *mut StackCell) -> *mut StackCell {
+    unsafe { write_string(stack, "", "write") }
+}
+
+/// Pop a string, write it and `ending` to stdout, and flush
+///
+/// # Safety
+/// Stack must have a string on top.
+unsafe fn write_string(stack: *mut StackCell, ending: &str, op: &str) -> *mut StackCell {
+    assert!(!stack.is_null(), "{}: stack is empty", op);
 
     let (rest, cell) = unsafe { StackCell::pop(stack) };
 
     // Get the C string using safe accessor
     let c_str_ptr = cell
         .as_string_ptr()
-        .expect("write_line: expected string on stack");
+        .unwrap_or_else(|| panic!("{}: expected string on stack", op));
 
     assert!(
         !c_str_ptr.is_null(),
-        "write_line: unexpected null string pointer"
+        "{}: unexpected null string pointer",
+        op
     );
 
-    let s = unsafe {
-        match std::ffi::CStr::from_ptr(c_str_ptr).to_str() {
-            Ok(s) => s.to_owned(),
-            Err(_) => crate::runtime_error(c"write_line: string contains invalid UTF-8".as_ptr()),
-        }
+    let fail = |problem: &str| -> ! {
+        let message = CString::new(format!("{}: {}", op, problem)).unwrap_or_default();
+        unsafe { crate::runtime_error(message.as_ptr()) }
+    };
+
+    let Ok(s) = (unsafe { std::ffi::CStr::from_ptr(c_str_ptr).to_str() }) else {
+        fail("string contains invalid UTF-8")
     };
 
-    println!("{}", s);
-    io::stdout().flush().unwrap();
+    let mut stdout = io::stdout().lock();
+    if write!(stdout, "{}{}", s, ending)
+        .and_then(|()| stdout.flush())
+        .is_err()
+    {
+        fail("failed to write to stdout")
+    }
 
     // String is automatically freed when cell is dropped
     rest
 }
 
+/// Read a line from stdin, without its line ending
+///
+/// At end of input this pushes an empty string.
+///
+/// Reading blocks the worker thread the strand runs on, not just the strand,
+/// so other strands scheduled on that thread wait until a line arrives.
+///
 /// # Safety
 /// Returns a new stack with string pushed.
 #[unsafe(no_mangle)]
@@ -46,7 +83,10 @@ pub unsafe extern "C" fn read_line(stack: *mut StackCell) -> *mut StackCell {
     let stdin = io::stdin();
     let mut line = String::new();
 
-    stdin.lock().read_line(&mut line).unwrap();
+    if stdin.lock().read_line(&mut line).is_err() {
+        // Includes input that isn't valid UTF-8
+        unsafe { crate::runtime_error(c"read_line: failed to read from stdin".as_ptr()) }
+    }
 
     if line.ends_with('\n') {
         line.pop();
@@ -75,7 +115,100 @@ pub unsafe extern "C" fn read_line(stack: *mut StackCell) -> *mut StackCell {
     unsafe { StackCell::push(stack, cell) }
 }
 
-/// Exit the program with a status code
+/// Print the whole stack to stdout on one line, bottom first
+///
+/// Ints print in decimal, bools as `true`/`false` and strings quoted.
+/// The runtime doesn't know constructor names, so a variant prints as its
+/// tag and fields: `Some(42)` prints as `#0(42)`. The stack isn't consumed.
+///
+/// # Safety
+/// Stack pointer must be a valid StackCell or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn print_stack(stack: *const StackCell) {
+    let mut stdout = io::stdout().lock();
+    if unsafe { write_stack(&mut stdout, stack) }
+        .and_then(|()| writeln!(stdout))
+        .and_then(|()| stdout.flush())
+        .is_err()
+    {
+        unsafe { crate::runtime_error(c"print_stack: failed to write to stdout".as_ptr()) }
+    }
+}
+
+/// Something left to print: a cell, or punctuation between cells
+enum Printing {
+    Cell(*const StackCell),
+    Text(&'static str),
+}
+
+/// Write the cells of a stack, bottom first, separated by spaces
+///
+/// Nested variants go on an explicit work list rather than recursing, so a
+/// deep value (such as a long `Cons` list) can't overflow the native stack.
+///
+/// # Safety
+/// Stack pointer must be a valid StackCell or null.
+unsafe fn write_stack(out: &mut impl Write, stack: *const StackCell) -> io::Result<()> {
+    // The work list is popped from the end, so push the top of the stack first
+    let mut pending = Vec::new();
+    unsafe { push_chain(&mut pending, stack, " ") };
+
+    while let Some(item) = pending.pop() {
+        let cell = match item {
+            Printing::Text(text) => {
+                write!(out, "{}", text)?;
+                continue;
+            }
+            Printing::Cell(cell) => unsafe { &*cell },
+        };
+
+        match cell.cell_type {
+            CellType::Int => write!(out, "{}", unsafe { cell.data.int_val })?,
+            CellType::Bool => write!(out, "{}", unsafe { cell.data.bool_val })?,
+            CellType::Float => write!(out, "{:?}", unsafe { cell.data.float_val })?,
+            CellType::String => {
+                let text = unsafe { std::ffi::CStr::from_ptr(cell.data.string_ptr) };
+                write!(out, "{:?}", text.to_string_lossy())?
+            }
+            CellType::Quotation => write!(out, "[quotation]")?,
+            CellType::Channel => write!(out, "<channel>")?,
+            CellType::Variant => {
+                let variant = unsafe { cell.data.variant };
+                write!(out, "#{}", variant.tag)?;
+                if !variant.data.is_null() {
+                    // The field chain holds the last field first, like a stack
+                    pending.push(Printing::Text(")"));
+                    unsafe { push_chain(&mut pending, variant.data, ", ") };
+                    pending.push(Printing::Text("("));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Queue a chain of cells so they pop from the work list last cell first
+///
+/// # Safety
+/// `chain` must be a valid StackCell or null.
+unsafe fn push_chain(
+    pending: &mut Vec<Printing>,
+    chain: *const StackCell,
+    separator: &'static str,
+) {
+    let mut current = chain;
+    while !current.is_null() {
+        if current != chain {
+            pending.push(Printing::Text(separator));
+        }
+        pending.push(Printing::Cell(current));
+        current = unsafe { (*current).next };
+    }
+}
+
+/// Exit the program with a status code (the `exit` word)
+///
+/// The rest of the stack is freed first. Other strands are not waited for.
 ///
 /// # Safety
 /// Stack must have an Int on top representing the exit code.
@@ -85,7 +218,7 @@ pub unsafe extern "C" fn read_line(stack: *mut StackCell) -> *mut StackCell {
 pub unsafe extern "C" fn exit_op(stack: *mut StackCell) -> ! {
     assert!(!stack.is_null(), "exit_op: stack is empty");
 
-    let (_rest, cell) = unsafe { StackCell::pop(stack) };
+    let (rest, cell) = unsafe { StackCell::pop(stack) };
 
     let exit_code = cell
         .as_int()
@@ -96,6 +229,7 @@ pub unsafe extern "C" fn exit_op(stack: *mut StackCell) -> ! {
         unsafe { crate::runtime_error(c"exit_op: exit code must be in range 0-255".as_ptr()) }
     }
 
+    unsafe { crate::scheduler::free_stack(rest) };
     std::process::exit(exit_code as i32);
 }
 
@@ -111,7 +245,73 @@ mod tests {
             let stack = std::ptr::null_mut();
             let test_str = CString::new("Hello, World!").unwrap();
             let stack = push_string(stack, test_str.as_ptr());
-            let _stack = write_line(stack);
+            let stack = write_line(stack);
+            assert!(stack.is_null());
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        unsafe {
+            let stack = crate::stack::push_int(std::ptr::null_mut(), 7);
+            let test_str = CString::new("no newline").unwrap();
+            let stack = push_string(stack, test_str.as_ptr());
+
+            // Only the string is consumed
+            let stack = write_op(stack);
+            let (rest, cell) = StackCell::pop(stack);
+            assert_eq!(cell.as_int(), Some(7));
+            assert!(rest.is_null());
+        }
+    }
+    #[test]
+    fn test_print_stack() {
+        let stdout = crate::stdout_from("io::tests::test_print_stack", || unsafe {
+            let text = CString::new("hi \"there\"").unwrap();
+            let stack = crate::stack::push_int(std::ptr::null_mut(), 1);
+            let stack = push_string(stack, text.as_ptr());
+            let some = crate::pattern::push_variant(
+                std::ptr::null_mut(),
+                crate::pattern::OPTION_SOME_TAG,
+                crate::stack::push_int(std::ptr::null_mut(), 42),
+            );
+            let stack = StackCell::push(stack, Box::from_raw(some));
+
+            print_stack(stack);
+            crate::scheduler::free_stack(stack);
+        });
+        assert_eq!(stdout, "1 \"hi \\\"there\\\"\" #0(42)\n");
+    }
+
+    #[test]
+    fn test_write_stack_nested_variants() {
+        unsafe {
+            // Pair(1, Some(true)), built with the last field first
+            let some = crate::pattern::push_variant(
+                std::ptr::null_mut(),
+                0,
+                crate::stack::push_bool(std::ptr::null_mut(), true),
+            );
+            let fields = StackCell::push(
+                crate::stack::push_int(std::ptr::null_mut(), 1),
+                Box::from_raw(some),
+            );
+            let stack = crate::pattern::push_variant(std::ptr::null_mut(), 3, fields);
+            let stack = crate::pattern::push_variant(stack, 1, std::ptr::null_mut());
+
+            let mut out = Vec::new();
+            write_stack(&mut out, stack).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), "#3(1, #0(true)) #1");
+            crate::scheduler::free_stack(stack);
         }
     }
+    #[test]
+    fn test_exit_op_exits_with_code() {
+        let output = crate::run_in_child("io::tests::test_exit_op_exits_with_code", || unsafe {
+            let text = CString::new("freed first").unwrap();
+            let stack = push_string(std::ptr::null_mut(), text.as_ptr());
+            exit_op(crate::stack::push_int(stack, 3));
+        });
+        assert_eq!(output.status.code(), Some(3));
+    }
 }
@@ -4,9 +4,13 @@ Cem Runtime - Memory-safe runtime using May coroutines
 Edition 2024 compliant with proper unsafe annotations.
 */
 
+pub mod channel;
 pub mod conversions;
+pub mod env;
+pub mod file;
 pub mod io;
 pub mod pattern;
+pub mod random;
 pub mod scheduler;
 pub mod stack;
 pub mod strings;
@@ -40,6 +44,48 @@ pub unsafe extern "C" fn runtime_error(msg: *const i8) -> ! {
     std::process::exit(1);
 }
 
+/// Run `op` in a child copy of this test binary and return its stderr
+///
+/// `runtime_error` exits the process, so it can't be observed in-process.
+/// The child re-runs the single test `test_name`, which calls `op`.
+#[cfg(test)]
+pub(crate) fn runtime_error_from(test_name: &str, op: impl FnOnce()) -> String {
+    let output = run_in_child(test_name, op);
+    assert_eq!(output.status.code(), Some(1));
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Run `op` in a child copy of this test binary and return its stdout
+///
+/// The test harness captures stdout only for `print!`, not for writes to the
+/// process's stdout, so output is observed from a child like runtime errors.
+#[cfg(test)]
+pub(crate) fn stdout_from(test_name: &str, op: impl FnOnce()) -> String {
+    let output = run_in_child(test_name, op);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // The child exits before the harness reports the test, so its output
+    // is everything after the harness's `test <name> ... `
+    let start = format!("test {} ... ", test_name);
+    let at = stdout.find(&start).expect("child ran the test") + start.len();
+    stdout[at..].to_string()
+}
+
+#[cfg(test)]
+fn run_in_child(test_name: &str, op: impl FnOnce()) -> std::process::Output {
+    if std::env::var_os("CEM_TEST_CHILD").is_some() {
+        op();
+        std::process::exit(0);
+    }
+
+    std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", test_name, "--nocapture"])
+        .env("CEM_TEST_CHILD", "1")
+        .output()
+        .unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
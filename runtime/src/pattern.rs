@@ -7,16 +7,30 @@ use crate::stack::{CellDataUnion, CellType, StackCell, VariantData};
 /// Maximum allowed variant tag value
 ///
 /// This limit serves as a sanity check to catch obviously invalid variant tags
-/// from malicious or buggy codegen. A well-formed Cem program is unlikely to
-/// define more than 1000 variants in a single type.
+/// from malicious or buggy codegen. Tags are a variant's index in its type, so
+/// a legitimate tag is small, but generated code (a lexer's token type, say)
+/// can define thousands of variants; the limit sits far above that.
 ///
-/// If you hit this limit legitimately (e.g., code-generated enums), the limit
-/// can be increased or removed. The limit exists primarily to detect corruption:
-/// if a type tag is accidentally set to a garbage value (e.g., 0xFFFFFFFF),
-/// we want to fail fast rather than allocate invalid memory or exhibit UB.
+/// The limit exists to detect corruption: if a type tag is accidentally set
+/// to a garbage value (e.g., 0xFFFFFFFF), we want to fail fast rather than
+/// allocate invalid memory or exhibit UB. The check is one comparison per
+/// variant built, so it stays in release builds.
+const MAX_VARIANT_TAG: u32 = 1 << 16;
+
+/// Tags of the prelude's `Option(T)`, for runtime words that return one
 ///
-/// For most programs, the number of variants per type will be < 100.
-const MAX_VARIANT_TAG: u32 = 1000;
+/// Codegen numbers variants by their position in the type definition, so
+/// these must follow the order of `Some` and `None` in stdlib/prelude.cem.
+pub const OPTION_SOME_TAG: u32 = 0;
+pub const OPTION_NONE_TAG: u32 = 1;
+
+/// Tags of the prelude's `Result(T, E)`, in the order of `Ok` and `Err` there
+pub const RESULT_OK_TAG: u32 = 0;
+pub const RESULT_ERR_TAG: u32 = 1;
+
+/// Tags of the prelude's `List(T)`, in the order of `Cons` and `Nil` there
+pub const LIST_CONS_TAG: u32 = 0;
+pub const LIST_NIL_TAG: u32 = 1;
 
 /// Push a variant onto the stack
 ///
@@ -41,6 +55,11 @@ pub unsafe extern "C" fn push_variant(
         MAX_VARIANT_TAG
     );
 
+    #[cfg(feature = "shared-cells")]
+    if !field_data.is_null() {
+        unsafe { crate::stack::shared_fields::init(field_data) };
+    }
+
     let cell = Box::new(StackCell {
         cell_type: CellType::Variant,
         _padding: 0,
@@ -57,6 +76,24 @@ pub unsafe extern "C" fn push_variant(
     unsafe { StackCell::push(stack, cell) }
 }
 
+/// Push a prelude `List(T)` holding `elements`, the first at its head
+///
+/// Each `Cons` chains its fields as codegen builds them: from the last field,
+/// the tail, to the first, the head.
+///
+/// # Safety
+/// `stack` must be a valid StackCell pointer or null. The list takes
+/// ownership of the elements.
+pub unsafe fn push_list(stack: *mut StackCell, elements: Vec<Box<StackCell>>) -> *mut StackCell {
+    let (mut tag, mut data) = (LIST_NIL_TAG, std::ptr::null_mut());
+    for element in elements.into_iter().rev() {
+        let head = unsafe { StackCell::push(std::ptr::null_mut(), element) };
+        data = unsafe { push_variant(head, tag, data) };
+        tag = LIST_CONS_TAG;
+    }
+    unsafe { push_variant(stack, tag, data) }
+}
+
 /// Allocate a new empty StackCell
 ///
 /// # Safety
@@ -215,6 +252,16 @@ mod tests {
     // because push_variant is extern "C" and cannot unwind.
     // The assertion exists to catch bugs in codegen, not for runtime testing.
 
+    #[test]
+    fn test_variant_tags_beyond_a_thousand() {
+        unsafe {
+            // Generated types can have more variants than a person would write
+            let variant = push_variant(std::ptr::null_mut(), 1500, std::ptr::null_mut());
+            assert_eq!(variant_get_tag(variant), 1500);
+            crate::scheduler::free_stack(variant);
+        }
+    }
+
     #[test]
     fn test_multiple_variant_types() {
         unsafe {
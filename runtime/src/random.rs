@@ -0,0 +1,134 @@
+/*!
+Pseudo-random numbers - `random` and `random-seed`
+
+A SplitMix64 generator with a fixed default seed, so a program draws the same
+numbers on every run unless it seeds with something that varies.
+
+The state is one atomic shared by every strand. May moves strands between
+worker threads, so thread-local state would hand a strand a different
+sequence depending on where it happened to run. Each draw advances the state
+with a single `fetch_add`, which makes `random` safe to call from any strand
+and gives concurrent draws distinct values. Which strand gets which value
+still depends on scheduling: the sequence is only reproducible when one
+strand does the drawing.
+*/
+
+use crate::stack::{StackCell, push_int};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Seed used until `random-seed` is called
+const DEFAULT_SEED: u64 = 0;
+
+/// SplitMix64's increment, the golden ratio as a 64-bit fraction
+const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+static STATE: AtomicU64 = AtomicU64::new(DEFAULT_SEED);
+
+/// Advance the generator and return its next 64 bits
+fn next_u64() -> u64 {
+    let mut z = STATE
+        .fetch_add(GAMMA, Ordering::Relaxed)
+        .wrapping_add(GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Replace a bound `n` with a pseudo-random integer in `[0, n)`
+///
+/// Draws that would make some results more likely than others are rejected,
+/// so every value is equally likely. A bound that isn't positive is a
+/// runtime error.
+///
+/// Stack effect: ( Int -- Int )
+///
+/// # Safety
+/// Stack must have an integer on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn random_op(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "random: stack is empty");
+
+    let (rest, cell) = unsafe { StackCell::pop(stack) };
+    let bound = cell.as_int().expect("random: bound must be an integer");
+    let Some(bound) = u64::try_from(bound).ok().filter(|&n| n > 0) else {
+        unsafe { crate::runtime_error(c"random: bound must be positive".as_ptr()) }
+    };
+
+    // The largest multiple of `bound` that fits, so `% bound` is unbiased below it
+    let limit = u64::MAX - u64::MAX % bound;
+    let value = loop {
+        let x = next_u64();
+        if x < limit {
+            break x % bound;
+        }
+    };
+
+    // Below a positive i64, so it fits
+    unsafe { push_int(rest, value as i64) }
+}
+
+/// Seed the generator, restarting its sequence
+///
+/// Stack effect: ( Int -- )
+///
+/// # Safety
+/// Stack must have an integer on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn random_seed(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "random_seed: stack is empty");
+
+    let (rest, cell) = unsafe { StackCell::pop(stack) };
+    let seed = cell.as_int().expect("random_seed: seed must be an integer");
+    STATE.store(seed as u64, Ordering::Relaxed);
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime_error_from;
+
+    /// Draw `count` numbers below `bound` after seeding with `seed`
+    unsafe fn draw(seed: i64, bound: i64, count: usize) -> Vec<i64> {
+        unsafe {
+            random_seed(push_int(std::ptr::null_mut(), seed));
+            (0..count)
+                .map(|_| {
+                    let stack = random_op(push_int(std::ptr::null_mut(), bound));
+                    let (rest, cell) = StackCell::pop(stack);
+                    assert!(rest.is_null());
+                    cell.as_int().unwrap()
+                })
+                .collect()
+        }
+    }
+
+    // The only in-process test that touches the shared state, since tests
+    // run in parallel
+    #[test]
+    fn test_same_seed_gives_same_sequence() {
+        unsafe {
+            let first = draw(42, 1000, 20);
+            assert_eq!(draw(42, 1000, 20), first);
+            assert_ne!(draw(43, 1000, 20), first);
+            assert!(first.iter().all(|n| (0..1000).contains(n)));
+
+            // Not stuck on one value
+            assert!(first.iter().any(|&n| n != first[0]));
+
+            assert!(draw(-7, 1, 10).iter().all(|&n| n == 0));
+            assert!(draw(-7, i64::MAX, 10).iter().all(|&n| n >= 0));
+        }
+    }
+
+    #[test]
+    fn test_non_positive_bound_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "random::tests::test_non_positive_bound_is_a_runtime_error",
+            || unsafe {
+                random_op(push_int(std::ptr::null_mut(), 0));
+            },
+        );
+        assert!(stderr.contains("bound must be positive"), "{}", stderr);
+    }
+}
@@ -2,10 +2,12 @@
 Scheduler - Green Thread Management with May - Edition 2024 compliant
 */
 
-use crate::stack::StackCell;
+use crate::stack::{CellType, StackCell};
 use may::coroutine;
-use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::sync::{Condvar, Mutex, Once};
+use may::sync::mpsc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Condvar, LazyLock, Mutex, Once};
 
 static SCHEDULER_INIT: Once = Once::new();
 
@@ -40,6 +42,17 @@ static SHUTDOWN_MUTEX: Mutex<()> = Mutex::new(());
 // Unique strand ID generation
 static NEXT_STRAND_ID: AtomicU64 = AtomicU64::new(1);
 
+// Final stack of the strand started by strand_spawn_main, handed to main() by
+// scheduler_run instead of being freed when the strand completes
+static MAIN_FINAL_STACK: AtomicPtr<StackCell> = AtomicPtr::new(std::ptr::null_mut());
+
+// Strands that can be joined, by id. Each receives the strand's final stack
+// (as an address, since pointers aren't Send) when it completes. An entry is
+// removed by the join, so a strand that's never joined keeps its final stack
+// until the program exits.
+static JOINABLE_STRANDS: LazyLock<Mutex<HashMap<i64, mpsc::Receiver<usize>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
 /// Initialize the scheduler
 ///
 /// # Safety
@@ -55,7 +68,8 @@ pub unsafe extern "C" fn scheduler_init() {
 /// Run the scheduler and wait for all coroutines to complete
 ///
 /// # Safety
-/// Returns the final stack (always null for now since May handles all scheduling).
+/// Returns the final stack of the strand started by `strand_spawn_main`, or null
+/// if there was none; the caller owns it and must free it with `free_stack`.
 /// This function blocks until all spawned strands have completed.
 ///
 /// Uses a condition variable for event-driven shutdown synchronization rather than
@@ -72,7 +86,7 @@ pub unsafe extern "C" fn scheduler_run() -> *mut StackCell {
     }
 
     // All strands have completed
-    std::ptr::null_mut()
+    MAIN_FINAL_STACK.swap(std::ptr::null_mut(), Ordering::Acquire)
 }
 
 /// Shutdown the scheduler
@@ -103,9 +117,199 @@ pub unsafe extern "C" fn scheduler_shutdown() {
 pub unsafe extern "C" fn strand_spawn(
     entry: extern "C" fn(*mut StackCell) -> *mut StackCell,
     initial_stack: *mut StackCell,
+) -> i64 {
+    unsafe { spawn(entry, initial_stack, FinalStack::Free) }
+}
+
+/// Spawn the program's entry strand
+///
+/// # Safety
+/// Same requirements as `strand_spawn`. Only one entry strand may be spawned.
+///
+/// # Memory Management
+/// Unlike other strands, the final stack returned by `entry` is kept rather than
+/// freed, and `scheduler_run` returns it so `main()` can read the exit code.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn strand_spawn_main(
+    entry: extern "C" fn(*mut StackCell) -> *mut StackCell,
+    initial_stack: *mut StackCell,
+) -> i64 {
+    unsafe { spawn(entry, initial_stack, FinalStack::Main) }
+}
+
+/// Spawn a strand that runs the quotation on top of the stack (the `spawn` word)
+///
+/// Stack effect: ( [ -- ] -- )
+///
+/// The strand starts with an empty stack, and its final stack is freed when it
+/// completes. `scheduler_run` waits for it like any other strand.
+///
+/// # Safety
+/// Top of stack must be a quotation created by codegen.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spawn_quotation(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, entry) = unsafe { pop_quotation(stack, "spawn") };
+    unsafe { spawn(entry, std::ptr::null_mut(), FinalStack::Free) };
+    rest
+}
+
+/// Spawn a strand that runs a quotation on one value (the `spawn-with` word)
+///
+/// Stack effect: ( A [ A -- ] -- )
+///
+/// The value moves to the new strand's stack, which is otherwise empty. This
+/// is how a strand is handed a channel to talk to its parent.
+///
+/// # Safety
+/// Top of stack must be a quotation created by codegen, with a value beneath it.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spawn_with(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, entry) = unsafe { pop_quotation(stack, "spawn-with") };
+    assert!(
+        !rest.is_null(),
+        "spawn-with: no value to hand to the strand"
+    );
+
+    let (rest, mut value) = unsafe { StackCell::pop(rest) };
+    value.next = std::ptr::null_mut();
+    unsafe { spawn(entry, Box::into_raw(value), FinalStack::Free) };
+    rest
+}
+
+/// Spawn a strand that can be joined for its final stack
+///
+/// # Safety
+/// Same requirements as `strand_spawn`. The returned id must be passed to
+/// `strand_join` to reclaim the final stack.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn strand_spawn_joinable(
+    entry: extern "C" fn(*mut StackCell) -> *mut StackCell,
+    initial_stack: *mut StackCell,
+) -> i64 {
+    unsafe { spawn(entry, initial_stack, FinalStack::Join) }
+}
+
+/// Wait for a joinable strand to finish and take its final stack
+///
+/// Suspends only the calling strand. Each strand can be joined once; joining
+/// an id that isn't a joinable strand, or was already joined, is a runtime
+/// error.
+///
+/// # Safety
+/// The caller owns the returned stack and must free it with `free_stack`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn strand_join(strand_id: i64) -> *mut StackCell {
+    let receiver = JOINABLE_STRANDS.lock().unwrap().remove(&strand_id);
+    let Some(receiver) = receiver else {
+        unsafe { crate::runtime_error(c"join: no strand left to join with that id".as_ptr()) }
+    };
+
+    let stack_addr = receiver
+        .recv()
+        .expect("join: the strand sends its final stack before finishing");
+    stack_addr as *mut StackCell
+}
+
+/// Spawn a strand whose result can be joined (the `spawn-result` word)
+///
+/// Stack effect: ( [ -- A ] -- Strand(A) )
+///
+/// The strand starts with an empty stack. The handle pushed is the strand's
+/// id as an Int.
+///
+/// # Safety
+/// Top of stack must be a quotation created by codegen.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spawn_result(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, entry) = unsafe { pop_quotation(stack, "spawn-result") };
+    let strand_id = unsafe { spawn(entry, std::ptr::null_mut(), FinalStack::Join) };
+    unsafe { crate::stack::push_int(rest, strand_id) }
+}
+
+/// Wait for a strand and push the value it left on top (the `join` word)
+///
+/// Stack effect: ( Strand(A) -- A )
+///
+/// Anything beneath the strand's top value is freed.
+///
+/// # Safety
+/// Top of stack must be a strand handle from `spawn_result`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn join_strand(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "join: stack is empty");
+
+    let (rest, handle) = unsafe { StackCell::pop(stack) };
+    let strand_id = handle.as_int().expect("join: expected a strand handle");
+
+    let final_stack = unsafe { strand_join(strand_id) };
+    if final_stack.is_null() {
+        unsafe { crate::runtime_error(c"join: the strand left nothing on its stack".as_ptr()) }
+    }
+
+    let (beneath, result) = unsafe { StackCell::pop(final_stack) };
+    unsafe { free_stack(beneath) };
+    unsafe { StackCell::push(rest, result) }
+}
+
+/// Pop a quotation, returning the rest of the stack and its function
+///
+/// # Safety
+/// Top of stack must be a quotation created by codegen.
+unsafe fn pop_quotation(
+    stack: *mut StackCell,
+    op: &str,
+) -> (
+    *mut StackCell,
+    extern "C" fn(*mut StackCell) -> *mut StackCell,
+) {
+    assert!(!stack.is_null(), "{}: stack is empty", op);
+
+    let (rest, cell) = unsafe { StackCell::pop(stack) };
+    assert!(
+        cell.cell_type == CellType::Quotation,
+        "{}: top of stack is not a quotation",
+        op
+    );
+
+    // SAFETY: quotation cells only hold pointers to functions generated by
+    // codegen, which all have this signature (see call_quotation)
+    let entry: extern "C" fn(*mut StackCell) -> *mut StackCell =
+        unsafe { std::mem::transmute(cell.data.quotation_ptr) };
+    (rest, entry)
+}
+
+/// What happens to a strand's final stack when it completes
+enum FinalStack {
+    /// Freed right away
+    Free,
+    /// Kept for `scheduler_run` to hand to `main()`
+    Main,
+    /// Handed to whoever joins the strand
+    Join,
+}
+
+/// Spawn a strand, disposing of its final stack as `final_stack` says
+///
+/// # Safety
+/// See `strand_spawn`.
+unsafe fn spawn(
+    entry: extern "C" fn(*mut StackCell) -> *mut StackCell,
+    initial_stack: *mut StackCell,
+    final_stack: FinalStack,
 ) -> i64 {
     // Generate unique strand ID
-    let strand_id = NEXT_STRAND_ID.fetch_add(1, Ordering::Relaxed);
+    let strand_id = NEXT_STRAND_ID.fetch_add(1, Ordering::Relaxed) as i64;
+
+    // Registered before the strand starts, so a join can't miss its result
+    let result_sender = match final_stack {
+        FinalStack::Join => {
+            let (sender, receiver) = mpsc::channel();
+            JOINABLE_STRANDS.lock().unwrap().insert(strand_id, receiver);
+            Some(sender)
+        }
+        FinalStack::Free | FinalStack::Main => None,
+    };
+    let keep_for_main = matches!(final_stack, FinalStack::Main);
 
     // Increment active strand counter
     ACTIVE_STRANDS.fetch_add(1, Ordering::Release);
@@ -125,8 +329,18 @@ pub unsafe extern "C" fn strand_spawn(
             // Execute the entry function
             let final_stack = entry_fn(stack_ptr);
 
-            // Clean up the final stack to prevent memory leak
-            free_stack(final_stack);
+            if let Some(sender) = result_sender {
+                // The joiner owns the final stack now
+                sender
+                    .send(final_stack as usize)
+                    .expect("strand result receiver is held until joined");
+            } else if keep_for_main {
+                // Published before the strand counts as done, so scheduler_run sees it
+                MAIN_FINAL_STACK.store(final_stack, Ordering::Release);
+            } else {
+                // Clean up the final stack to prevent memory leak
+                free_stack(final_stack);
+            }
 
             // Decrement active strand counter
             // If this was the last strand, notify anyone waiting for shutdown
@@ -140,11 +354,14 @@ pub unsafe extern "C" fn strand_spawn(
         });
     }
 
-    strand_id as i64
+    strand_id
 }
 
 /// Free a stack allocated by the runtime
 ///
+/// Frees every cell down to the bottom of the stack, along with any values
+/// the cells own.
+///
 /// # Safety
 /// - `stack` must be either:
 ///   - A null pointer (safe, will be a no-op)
@@ -159,12 +376,8 @@ pub unsafe extern "C" fn strand_spawn(
 /// Passing a stack pointer that was not Box-allocated will cause undefined behavior.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn free_stack(stack: *mut StackCell) {
-    if !stack.is_null() {
-        unsafe {
-            // SAFETY: Caller guarantees this was Box-allocated
-            let _ = Box::from_raw(stack);
-        }
-    }
+    // SAFETY: Caller guarantees this was Box-allocated
+    unsafe { crate::stack::free_cell_chains(vec![stack]) }
 }
 
 /// Legacy spawn_strand function (kept for compatibility)
@@ -178,13 +391,43 @@ pub unsafe extern "C" fn spawn_strand(entry: extern "C" fn(*mut StackCell) -> *m
     }
 }
 
-/// Yield execution to allow other coroutines to run
+/// Yield execution to allow other coroutines to run (the `yield` word)
+///
+/// Stack effect: ( -- )
+///
+/// With no other strand ready to run, the calling strand simply continues.
 ///
 /// # Safety
-/// Always safe to call from within a May coroutine.
+/// Always safe to call from within a May coroutine. The stack is passed
+/// through untouched.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn yield_strand() {
+pub unsafe extern "C" fn yield_strand(stack: *mut StackCell) -> *mut StackCell {
     coroutine::yield_now();
+    stack
+}
+
+/// Suspend the calling strand for a number of milliseconds (the `sleep` word)
+///
+/// Stack effect: ( Int -- )
+///
+/// This is a coroutine sleep: the strand yields and other strands keep
+/// running on the same OS thread until the time is up. `0 sleep` behaves like
+/// `yield`. A negative duration is a runtime error.
+///
+/// # Safety
+/// Stack must have an integer on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sleep_strand(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "sleep: stack is empty");
+
+    let (rest, millis) = unsafe { StackCell::pop(stack) };
+    let millis = millis.as_int().expect("sleep: duration must be an integer");
+    let Ok(millis) = u64::try_from(millis) else {
+        unsafe { crate::runtime_error(c"sleep: duration is negative".as_ptr()) }
+    };
+
+    coroutine::sleep(std::time::Duration::from_millis(millis));
+    rest
 }
 
 /// Wait for all strands to complete
@@ -207,7 +450,9 @@ pub unsafe extern "C" fn wait_all_strands() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runtime_error_from;
     use crate::stack::push_int;
+    use std::sync::Mutex;
     use std::sync::atomic::{AtomicU32, Ordering};
 
     #[test]
@@ -276,6 +521,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_scheduler_run_returns_main_stack() {
+        unsafe {
+            extern "C" fn test_main(stack: *mut StackCell) -> *mut StackCell {
+                unsafe { push_int(stack, 3) }
+            }
+
+            strand_spawn_main(test_main, std::ptr::null_mut());
+            let stack = scheduler_run();
+
+            assert!(!stack.is_null());
+            assert_eq!((*stack).as_int(), Some(3));
+            free_stack(stack);
+        }
+    }
+
     #[test]
     fn test_scheduler_shutdown() {
         unsafe {
@@ -311,6 +572,198 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_spawn_quotation() {
+        unsafe {
+            static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+            extern "C" fn quotation(stack: *mut StackCell) -> *mut StackCell {
+                assert!(stack.is_null(), "spawned strands start with an empty stack");
+                COUNTER.fetch_add(1, Ordering::SeqCst);
+                unsafe { push_int(stack, 1) }
+            }
+
+            // ( 7 [ ... ] -- 7 ), three times
+            let mut stack = push_int(std::ptr::null_mut(), 7);
+            for _ in 0..3 {
+                stack = crate::stack::push_quotation(stack, quotation as *mut ());
+                stack = spawn_quotation(stack);
+            }
+
+            wait_all_strands();
+
+            assert_eq!(COUNTER.load(Ordering::SeqCst), 3);
+            assert_eq!((*stack).as_int(), Some(7));
+            free_stack(stack);
+        }
+    }
+
+    #[test]
+    fn test_spawn_with_moves_value_to_strand() {
+        unsafe {
+            static SEEN: AtomicU32 = AtomicU32::new(0);
+
+            extern "C" fn quotation(stack: *mut StackCell) -> *mut StackCell {
+                let (rest, value) = unsafe { StackCell::pop(stack) };
+                assert!(rest.is_null(), "the strand's stack holds just the value");
+                SEEN.store(value.as_int().unwrap() as u32, Ordering::SeqCst);
+                rest
+            }
+
+            // ( 1 42 [ ... ] -- 1 )
+            let stack = push_int(push_int(std::ptr::null_mut(), 1), 42);
+            let stack = crate::stack::push_quotation(stack, quotation as *mut ());
+            let stack = spawn_with(stack);
+
+            wait_all_strands();
+
+            assert_eq!(SEEN.load(Ordering::SeqCst), 42);
+            assert_eq!((*stack).as_int(), Some(1));
+            free_stack(stack);
+        }
+    }
+
+    #[test]
+    fn test_join_returns_strand_result() {
+        unsafe {
+            // Sums 1..=100, leaving a scratch value beneath the result
+            extern "C" fn compute(stack: *mut StackCell) -> *mut StackCell {
+                let stack = unsafe { push_int(stack, -1) };
+                unsafe { push_int(stack, (1..=100).sum()) }
+            }
+
+            // ( 7 [ compute ] -- 7 Strand ) then ( 7 Strand -- 7 5050 )
+            let stack = push_int(std::ptr::null_mut(), 7);
+            let stack = spawn_result(crate::stack::push_quotation(stack, compute as *mut ()));
+            let stack = join_strand(stack);
+
+            let (rest, result) = StackCell::pop(stack);
+            assert_eq!(result.as_int(), Some(5050));
+            assert_eq!((*rest).as_int(), Some(7));
+            assert!((*rest).next.is_null());
+            free_stack(rest);
+        }
+    }
+
+    #[test]
+    fn test_join_waits_for_a_slow_strand() {
+        unsafe {
+            extern "C" fn slow(stack: *mut StackCell) -> *mut StackCell {
+                coroutine::sleep(std::time::Duration::from_millis(50));
+                unsafe { push_int(stack, 42) }
+            }
+
+            let strand_id = strand_spawn_joinable(slow, std::ptr::null_mut());
+            let final_stack = strand_join(strand_id);
+            assert_eq!((*final_stack).as_int(), Some(42));
+            free_stack(final_stack);
+        }
+    }
+
+    #[test]
+    fn test_join_twice_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "scheduler::tests::test_join_twice_is_a_runtime_error",
+            || unsafe {
+                extern "C" fn nothing(stack: *mut StackCell) -> *mut StackCell {
+                    stack
+                }
+                let strand_id = strand_spawn_joinable(nothing, std::ptr::null_mut());
+                strand_join(strand_id);
+                strand_join(strand_id);
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: join: no strand left to join with that id"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_yield_strand() {
+        // On a single worker, strands run in turn: each yield hands control
+        // to the other strand. That only holds in a process whose scheduler
+        // starts with one worker, so the strands run in a child.
+        let stdout = crate::stdout_from("scheduler::tests::test_yield_strand", || unsafe {
+            static STEPS: Mutex<Vec<(u8, u8)>> = Mutex::new(Vec::new());
+
+            // Each strand records three steps, yielding between them
+            extern "C" fn steps(stack: *mut StackCell) -> *mut StackCell {
+                let strand = unsafe { (*stack).as_int().unwrap() } as u8;
+                let mut stack = stack;
+                for step in 0..3 {
+                    STEPS.lock().unwrap().push((strand, step));
+                    stack = unsafe { yield_strand(stack) };
+                }
+                stack
+            }
+
+            // Spawned from a strand, both are queued before either starts
+            extern "C" fn spawn_both(stack: *mut StackCell) -> *mut StackCell {
+                for strand in [1, 2] {
+                    unsafe { strand_spawn(steps, push_int(std::ptr::null_mut(), strand)) };
+                }
+                stack
+            }
+
+            may::config().set_workers(1);
+
+            // Yielding with no other strand running just continues
+            let stack = yield_strand(push_int(std::ptr::null_mut(), 5));
+            assert_eq!((*stack).as_int(), Some(5));
+            free_stack(stack);
+
+            strand_spawn(spawn_both, std::ptr::null_mut());
+            wait_all_strands();
+            println!("{:?}", STEPS.lock().unwrap());
+        });
+
+        assert_eq!(
+            stdout.trim(),
+            "[(1, 0), (2, 0), (1, 1), (2, 1), (1, 2), (2, 2)]"
+        );
+    }
+
+    #[test]
+    fn test_sleep_orders_strands_by_duration() {
+        unsafe {
+            static WOKEN: Mutex<Vec<i64>> = Mutex::new(Vec::new());
+
+            // Sleeps for the number of milliseconds it's handed, then records it
+            extern "C" fn sleeper(stack: *mut StackCell) -> *mut StackCell {
+                let millis = unsafe { (*stack).as_int().unwrap() };
+                let stack = unsafe { sleep_strand(stack) };
+                WOKEN.lock().unwrap().push(millis);
+                stack
+            }
+
+            // The longer sleep starts first
+            for millis in [120, 20] {
+                let stack = push_int(std::ptr::null_mut(), millis);
+                strand_spawn(sleeper, stack);
+            }
+            wait_all_strands();
+
+            assert_eq!(*WOKEN.lock().unwrap(), [20, 120]);
+        }
+    }
+
+    #[test]
+    fn test_sleep_negative_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "scheduler::tests::test_sleep_negative_is_a_runtime_error",
+            || unsafe {
+                sleep_strand(push_int(std::ptr::null_mut(), -1));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: sleep: duration is negative"),
+            "{}",
+            stderr
+        );
+    }
+
     #[test]
     fn test_strand_ids_are_unique() {
         unsafe {
@@ -9,12 +9,18 @@ Memory Layout (64-bit):
 - _padding: 4 bytes at offset 4
 - data union: 16 bytes at offset 8
   - int_val: 8 bytes (i64)
+  - float_val: 8 bytes (f64)
   - bool_val: 1 byte (bool) + 7 bytes padding
   - string_ptr: 8 bytes (*mut i8)
   - quotation_ptr: 8 bytes (*mut ())
   - variant: 16 bytes (u32 tag + u32 padding + *mut StackCell data)
+  - channel_ptr: 8 bytes (*const Channel, one Arc reference)
 - next: 8 bytes (*mut StackCell) at offset 24
   TOTAL: 32 bytes
+
+With the `shared-cells` feature, the padding of the first cell of a variant's
+field chain holds the number of variants sharing that chain (see
+`shared_fields`). Nothing in the generated code reads it.
 */
 
 use std::ptr;
@@ -27,6 +33,8 @@ pub enum CellType {
     String = 2,
     Variant = 3,
     Quotation = 4,
+    Float = 5,
+    Channel = 6,
 }
 
 /// Variant data - matches C layout: { uint32_t tag; uint32_t padding; void* data; }
@@ -49,10 +57,12 @@ impl std::fmt::Debug for VariantData {
 #[derive(Copy, Clone)]
 pub union CellDataUnion {
     pub int_val: i64,
+    pub float_val: f64,
     pub bool_val: bool,
     pub string_ptr: *mut i8,
     pub quotation_ptr: *mut (),
     pub variant: VariantData,
+    pub channel_ptr: *const crate::channel::Channel,
 }
 
 impl std::fmt::Debug for CellDataUnion {
@@ -76,26 +86,121 @@ impl std::fmt::Debug for StackCell {
     }
 }
 
+/// Dropping a cell frees what it owns, but not the rest of the stack below it
 impl Drop for StackCell {
     fn drop(&mut self) {
         unsafe {
             match self.cell_type {
-                CellType::String => {
-                    if !self.data.string_ptr.is_null() {
-                        let _ = std::ffi::CString::from_raw(self.data.string_ptr);
-                    }
+                CellType::String if !self.data.string_ptr.is_null() => {
+                    let _ = std::ffi::CString::from_raw(self.data.string_ptr);
                 }
-                CellType::Variant => {
-                    if !self.data.variant.data.is_null() {
-                        let _ = Box::from_raw(self.data.variant.data);
+                CellType::Variant if !self.data.variant.data.is_null() => {
+                    let fields = std::mem::replace(&mut self.data.variant.data, ptr::null_mut());
+                    if release_fields(fields) {
+                        free_cell_chains(vec![fields]);
                     }
                 }
+                CellType::Channel => {
+                    // Release this cell's reference to the channel
+                    std::mem::drop(std::sync::Arc::from_raw(self.data.channel_ptr));
+                }
                 _ => {}
             }
         }
     }
 }
 
+/// Free chains of cells linked through `next`, with every variant's fields
+///
+/// Nested variants (such as a long `Cons` list) are queued on an explicit
+/// work list instead of being freed recursively, so depth can't overflow the
+/// native stack.
+///
+/// # Safety
+/// Each pointer must be null or the head of a Box-allocated chain that
+/// nothing else owns.
+pub(crate) unsafe fn free_cell_chains(mut pending: Vec<*mut StackCell>) {
+    while let Some(mut current) = pending.pop() {
+        while !current.is_null() {
+            let mut cell = unsafe { Box::from_raw(current) };
+            current = cell.next;
+            if cell.cell_type == CellType::Variant {
+                // Take the fields so the cell's own drop doesn't free them
+                let fields =
+                    unsafe { std::mem::replace(&mut cell.data.variant.data, ptr::null_mut()) };
+                if !fields.is_null() && unsafe { release_fields(fields) } {
+                    pending.push(fields);
+                }
+            }
+        }
+    }
+}
+
+/// Give up a variant's hold on its field chain, returning whether the chain
+/// is now unowned and must be freed
+///
+/// Without `shared-cells` every variant owns its fields outright.
+#[cfg(not(feature = "shared-cells"))]
+unsafe fn release_fields(_fields: *mut StackCell) -> bool {
+    true
+}
+
+#[cfg(feature = "shared-cells")]
+use shared_fields::release as release_fields;
+
+/// Variant field chains shared between copies (the `shared-cells` feature)
+///
+/// Copying a variant shares its fields instead of copying them, which makes
+/// the copy O(1) whatever the size of the value. Fields are never modified
+/// once the variant is built: a match pushes copies of them.
+///
+/// The count is kept in the `_padding` word of the chain's first cell, so
+/// the cell layout codegen relies on is unchanged. It's atomic because
+/// strands share values over channels and move between worker threads.
+#[cfg(feature = "shared-cells")]
+pub(crate) mod shared_fields {
+    use super::StackCell;
+    use std::sync::atomic::{AtomicU32, Ordering, fence};
+
+    /// # Safety
+    /// `fields` must be the non-null head of a variant's field chain.
+    unsafe fn count<'a>(fields: *mut StackCell) -> &'a AtomicU32 {
+        // The padding sits at offset 4 of an 8-byte-aligned cell
+        unsafe { AtomicU32::from_ptr(&raw mut (*fields)._padding) }
+    }
+
+    /// Give a new variant the only reference to its fields
+    ///
+    /// # Safety
+    /// `fields` must be the non-null head of a field chain nothing else holds.
+    pub(crate) unsafe fn init(fields: *mut StackCell) {
+        unsafe { count(fields) }.store(1, Ordering::Relaxed);
+    }
+
+    /// Add a reference for a copy of the variant
+    ///
+    /// # Safety
+    /// `fields` must be the non-null head of a variant's field chain.
+    pub(crate) unsafe fn retain(fields: *mut StackCell) {
+        if unsafe { count(fields) }.fetch_add(1, Ordering::Relaxed) == u32::MAX {
+            unsafe { crate::runtime_error(c"copy: too many copies of one value".as_ptr()) }
+        }
+    }
+
+    /// Drop a reference, returning whether it was the last one
+    ///
+    /// # Safety
+    /// `fields` must be the non-null head of a variant's field chain.
+    pub(crate) unsafe fn release(fields: *mut StackCell) -> bool {
+        if unsafe { count(fields) }.fetch_sub(1, Ordering::Release) != 1 {
+            return false;
+        }
+        // Other holders' reads of the fields happen before they're freed
+        fence(Ordering::Acquire);
+        true
+    }
+}
+
 impl StackCell {
     /// Safe accessor for integer value
     ///
@@ -108,6 +213,17 @@ impl StackCell {
         }
     }
 
+    /// Safe accessor for floating-point value
+    ///
+    /// # Returns
+    /// `Some(value)` if cell contains a float, `None` otherwise
+    pub fn as_float(&self) -> Option<f64> {
+        match self.cell_type {
+            CellType::Float => Some(unsafe { self.data.float_val }),
+            _ => None,
+        }
+    }
+
     /// Safe accessor for boolean value
     ///
     /// # Returns
@@ -159,12 +275,55 @@ impl StackCell {
         Box::into_raw(cell)
     }
 
-    /// Deep clone a cell (recursively clones heap-allocated data)
+    /// Deep clone a cell (clones heap-allocated data, including variant fields)
+    ///
+    /// Variant field chains are copied from an explicit work list rather than
+    /// by recursion, so deeply nested variants can't overflow the native stack.
     ///
     /// # Safety
     /// Cell pointer must be valid. This properly deep-copies all heap allocations
     /// to prevent double-free issues.
     pub unsafe fn deep_clone(cell: &StackCell) -> StackCell {
+        let mut clone = unsafe { Self::clone_shallow(cell) };
+        let Some(variant) = cell.as_variant() else {
+            return clone;
+        };
+
+        #[cfg(feature = "shared-cells")]
+        if !variant.data.is_null() {
+            unsafe {
+                shared_fields::retain(variant.data);
+                clone.data.variant.data = variant.data;
+            }
+            return clone;
+        }
+
+        // Each entry is a field chain to copy and where to link the copy
+        let mut pending: Vec<(*mut StackCell, *mut *mut StackCell)> =
+            vec![(variant.data, unsafe { &raw mut clone.data.variant.data })];
+        while let Some((mut source, mut slot)) = pending.pop() {
+            while !source.is_null() {
+                unsafe {
+                    let field = &*source;
+                    let copy = Box::into_raw(Box::new(Self::clone_shallow(field)));
+                    *slot = copy;
+                    if let Some(nested) = field.as_variant() {
+                        pending.push((nested.data, &raw mut (*copy).data.variant.data));
+                    }
+                    slot = &raw mut (*copy).next;
+                    source = field.next;
+                }
+            }
+        }
+
+        clone
+    }
+
+    /// Copy a single cell, with `next` and any variant fields left null
+    ///
+    /// # Safety
+    /// Cell pointer must be valid.
+    unsafe fn clone_shallow(cell: &StackCell) -> StackCell {
         match cell.cell_type {
             CellType::Int => {
                 let int_val = cell.as_int().expect("deep_clone: invalid Int cell");
@@ -175,6 +334,15 @@ impl StackCell {
                     next: ptr::null_mut(),
                 }
             }
+            CellType::Float => {
+                let float_val = cell.as_float().expect("deep_clone: invalid Float cell");
+                StackCell {
+                    cell_type: CellType::Float,
+                    _padding: 0,
+                    data: CellDataUnion { float_val },
+                    next: ptr::null_mut(),
+                }
+            }
             CellType::Bool => {
                 let bool_val = cell.as_bool().expect("deep_clone: invalid Bool cell");
                 StackCell {
@@ -216,40 +384,20 @@ impl StackCell {
                     next: ptr::null_mut(),
                 }
             }
+            CellType::Channel => {
+                // Channels are shared - the copy is another reference to the same one
+                let channel_ptr = unsafe { cell.data.channel_ptr };
+                unsafe { std::sync::Arc::increment_strong_count(channel_ptr) };
+                StackCell {
+                    cell_type: CellType::Channel,
+                    _padding: 0,
+                    data: CellDataUnion { channel_ptr },
+                    next: ptr::null_mut(),
+                }
+            }
             CellType::Variant => {
-                // Deep copy the variant and its field data (recursively)
-                // For multi-field variants, data points to a chain of field cells
-                // We need to clone the ENTIRE chain, not just the first field
+                // The fields are copied by deep_clone
                 let variant = cell.as_variant().expect("deep_clone: invalid Variant cell");
-                let cloned_data = if variant.data.is_null() {
-                    ptr::null_mut()
-                } else {
-                    // Recursively deep-clone the entire field chain
-                    unsafe {
-                        let mut cloned_fields: Vec<*mut StackCell> = Vec::new();
-                        let mut current = variant.data;
-
-                        // Walk the field chain and clone each field
-                        while !current.is_null() {
-                            let field = &*current;
-                            let cloned_field = Box::into_raw(Box::new(Self::deep_clone(field)));
-                            cloned_fields.push(cloned_field);
-                            current = field.next;
-                        }
-
-                        // Link the cloned fields together
-                        for i in 0..cloned_fields.len() {
-                            if i + 1 < cloned_fields.len() {
-                                (*cloned_fields[i]).next = cloned_fields[i + 1];
-                            } else {
-                                (*cloned_fields[i]).next = ptr::null_mut();
-                            }
-                        }
-
-                        // Return first field as data pointer (or null if no fields)
-                        cloned_fields.first().copied().unwrap_or(ptr::null_mut())
-                    }
-                };
                 StackCell {
                     cell_type: CellType::Variant,
                     _padding: 0,
@@ -257,7 +405,7 @@ impl StackCell {
                         variant: VariantData {
                             tag: variant.tag,
                             _padding: 0,
-                            data: cloned_data,
+                            data: ptr::null_mut(),
                         },
                     },
                     next: ptr::null_mut(),
@@ -284,6 +432,19 @@ pub unsafe extern "C" fn push_int(stack: *mut StackCell, value: i64) -> *mut Sta
     unsafe { StackCell::push(stack, cell) }
 }
 
+/// # Safety
+/// Caller must ensure stack pointer is valid or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn push_float(stack: *mut StackCell, value: f64) -> *mut StackCell {
+    let cell = Box::new(StackCell {
+        cell_type: CellType::Float,
+        _padding: 0,
+        data: CellDataUnion { float_val: value },
+        next: ptr::null_mut(),
+    });
+    unsafe { StackCell::push(stack, cell) }
+}
+
 /// # Safety
 /// Caller must ensure stack pointer is valid or null.
 #[unsafe(no_mangle)]
@@ -328,6 +489,24 @@ pub unsafe extern "C" fn push_string(stack: *mut StackCell, s: *const i8) -> *mu
     unsafe { StackCell::push(stack, cell) }
 }
 
+/// Push a string the runtime has just built, taking ownership of it
+///
+/// Unlike `push_string`, this doesn't copy the string.
+///
+/// # Safety
+/// Stack pointer must be a valid StackCell or null.
+pub unsafe fn push_owned_string(stack: *mut StackCell, s: std::ffi::CString) -> *mut StackCell {
+    let cell = Box::new(StackCell {
+        cell_type: CellType::String,
+        _padding: 0,
+        data: CellDataUnion {
+            string_ptr: s.into_raw(),
+        },
+        next: ptr::null_mut(),
+    });
+    unsafe { StackCell::push(stack, cell) }
+}
+
 /// Push a quotation (function pointer) onto the stack
 ///
 /// # Safety
@@ -471,6 +650,24 @@ pub unsafe extern "C" fn rot(stack: *mut StackCell) -> *mut StackCell {
     unsafe { StackCell::push(rest, a) }
 }
 
+/// # Safety
+/// Stack must have at least 3 elements.
+/// -rot: ( A B C -- C A B ), the inverse of rot
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn unrot(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "-rot: stack too small");
+    let (rest, c) = unsafe { StackCell::pop(stack) };
+    assert!(!rest.is_null(), "-rot: stack too small");
+    let (rest, b) = unsafe { StackCell::pop(rest) };
+    assert!(!rest.is_null(), "-rot: stack too small");
+    let (rest, a) = unsafe { StackCell::pop(rest) };
+
+    // ( A B C -- C A B )
+    let rest = unsafe { StackCell::push(rest, c) };
+    let rest = unsafe { StackCell::push(rest, a) };
+    unsafe { StackCell::push(rest, b) }
+}
+
 /// # Safety
 /// Stack must have at least 2 elements.
 /// nip: ( A B -- B )
@@ -503,6 +700,55 @@ pub unsafe extern "C" fn tuck(stack: *mut StackCell) -> *mut StackCell {
     unsafe { StackCell::push(rest, b_clone) }
 }
 
+/// # Safety
+/// Stack must have at least 2 elements.
+/// Deep-copies both elements to prevent double-free.
+/// 2dup: ( A B -- A B A B )
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn two_dup(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "2dup: stack too small");
+
+    unsafe {
+        let b = &*stack;
+        assert!(!b.next.is_null(), "2dup: stack too small");
+        let a = &*b.next;
+
+        let a_clone = Box::new(StackCell::deep_clone(a));
+        let b_clone = Box::new(StackCell::deep_clone(b));
+        let stack = StackCell::push(stack, a_clone);
+        StackCell::push(stack, b_clone)
+    }
+}
+
+/// # Safety
+/// Stack must have at least 2 elements.
+/// 2drop: ( A B -- )
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn two_drop(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "2drop: stack too small");
+    let (rest, _b) = unsafe { StackCell::pop(stack) };
+    assert!(!rest.is_null(), "2drop: stack too small");
+    let (rest, _a) = unsafe { StackCell::pop(rest) };
+    rest
+}
+
+/// Walk `depth` cells down from the top of the stack
+///
+/// Returns `None` if the stack has no element at that depth.
+///
+/// # Safety
+/// Stack must be null or a valid stack pointer
+unsafe fn cell_at_depth(stack: *mut StackCell, depth: usize) -> Option<*mut StackCell> {
+    let mut current = stack;
+    for _ in 0..depth {
+        if current.is_null() {
+            return None;
+        }
+        current = unsafe { (*current).next };
+    }
+    (!current.is_null()).then_some(current)
+}
+
 /// Pick: Copy the nth element from the stack to the top
 /// Stack effect: ( ... n -- ... val )
 /// where n=0 is equivalent to dup, n=1 is equivalent to over, etc.
@@ -527,22 +773,10 @@ pub unsafe extern "C" fn pick(stack: *mut StackCell) -> *mut StackCell {
 
         assert!(depth >= 0, "pick: depth must be non-negative");
 
-        // Walk down the stack to find the element at depth n
-        let mut current = rest_stack;
-        for _ in 0..depth {
-            assert!(
-                !current.is_null(),
-                "pick: stack depth {} is too small",
-                depth
-            );
-            current = (*current).next;
-        }
-
-        assert!(
-            !current.is_null(),
-            "pick: stack depth {} is too small",
-            depth
-        );
+        // Find the element at depth n
+        let Some(current) = cell_at_depth(rest_stack, depth as usize) else {
+            panic!("pick: stack depth {} is too small", depth);
+        };
 
         // Deep clone the element at depth n
         let picked = Box::new(StackCell::deep_clone(&*current));
@@ -552,6 +786,93 @@ pub unsafe extern "C" fn pick(stack: *mut StackCell) -> *mut StackCell {
     }
 }
 
+/// Depth: Push the number of values on the stack
+/// Stack effect: ( -- Int )
+///
+/// Counts the cells already there, then pushes the count on top of them:
+/// ( A B C depth ) -> ( A B C 3 )
+///
+/// # Safety
+/// Stack must be null or a valid stack pointer
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn depth(stack: *mut StackCell) -> *mut StackCell {
+    let mut count: i64 = 0;
+    let mut current = stack;
+    while !current.is_null() {
+        count += 1;
+        current = unsafe { (*current).next };
+    }
+    unsafe { push_int(stack, count) }
+}
+
+/// Pop the count for `ndrop` or `ndup` and check the stack below it holds
+/// that many values
+///
+/// # Safety
+/// Stack must be a valid, non-empty stack pointer with an Int on top
+unsafe fn pop_count(stack: *mut StackCell, op: &str) -> (*mut StackCell, usize) {
+    assert!(!stack.is_null(), "{}: stack is empty", op);
+    let (rest, count_cell) = unsafe { StackCell::pop(stack) };
+    let count = count_cell
+        .as_int()
+        .unwrap_or_else(|| panic!("{}: count must be an integer", op));
+
+    let problem = match usize::try_from(count) {
+        Err(_) => format!("count {} is negative", count),
+        Ok(n) if n > 0 && unsafe { cell_at_depth(rest, n - 1) }.is_none() => {
+            format!("stack has fewer than {} values", n)
+        }
+        Ok(n) => return (rest, n),
+    };
+    let message = std::ffi::CString::new(format!("{}: {}", op, problem)).unwrap_or_default();
+    unsafe { crate::runtime_error(message.as_ptr()) }
+}
+
+/// Ndrop: Drop a number of values given at runtime
+/// Stack effect: ( ... n -- ... ) with n values fewer
+///
+/// ( A B C 2 ndrop ) -> ( A )
+///
+/// # Safety
+/// Stack must be a valid stack pointer with an Int on top. Too few values
+/// below the count is a runtime error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ndrop(stack: *mut StackCell) -> *mut StackCell {
+    let (mut stack, count) = unsafe { pop_count(stack, "ndrop") };
+    for _ in 0..count {
+        stack = unsafe { StackCell::pop(stack) }.0;
+    }
+    stack
+}
+
+/// Ndup: Duplicate a number of values given at runtime, keeping their order
+/// Stack effect: ( ... n -- ... ) with the top n values copied
+///
+/// ( A B C 2 ndup ) -> ( A B C B C ); `2 ndup` is `2dup`
+///
+/// # Safety
+/// Stack must be a valid stack pointer with an Int on top. Too few values
+/// below the count is a runtime error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ndup(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, count) = unsafe { pop_count(stack, "ndup") };
+
+    // Deepest first, so the copies come out in the same order
+    let mut originals = Vec::with_capacity(count);
+    let mut current = rest;
+    for _ in 0..count {
+        originals.push(current);
+        current = unsafe { (*current).next };
+    }
+
+    let mut stack = rest;
+    for &original in originals.iter().rev() {
+        let copy = Box::new(unsafe { StackCell::deep_clone(&*original) });
+        stack = unsafe { StackCell::push(stack, copy) };
+    }
+    stack
+}
+
 /// Dip: Call a quotation while temporarily hiding the top stack value
 /// Stack effect: ( x y [x -- x'] -- x' y )
 ///
@@ -573,28 +894,22 @@ pub unsafe extern "C" fn dip(stack: *mut StackCell) -> *mut StackCell {
     unsafe {
         // Pop the quotation
         let (rest_stack, quot_cell) = StackCell::pop(stack);
-        assert!(
-            quot_cell.cell_type == CellType::Quotation,
-            "dip: top of stack must be a quotation"
-        );
 
         // Pop the value to hide
         assert!(!rest_stack.is_null(), "dip: stack too small");
         let (hidden_stack, hidden_value) = StackCell::pop(rest_stack);
 
-        // Extract and call the quotation on the stack without the hidden value
-        let func_ptr = quot_cell.data.quotation_ptr;
-
-        // SAFETY: Same safety rationale as call_quotation - the compiler
-        // guarantees this is a valid function pointer with the correct signature
-        let func: fn(*mut StackCell) -> *mut StackCell = std::mem::transmute(func_ptr);
-        let result_stack = func(hidden_stack);
+        // Call the quotation on the stack without the hidden value
+        let result_stack = call_quotation(StackCell::push(hidden_stack, quot_cell));
 
         // Push the hidden value back on top
         StackCell::push(result_stack, hidden_value)
     }
 }
 
+// Integer arithmetic is checked: overflow is a runtime error in every build
+// profile, rather than a panic in debug builds and wraparound in release.
+
 /// # Safety
 /// Stack must have 2 integers.
 #[unsafe(no_mangle)]
@@ -605,7 +920,9 @@ pub unsafe extern "C" fn add(stack: *mut StackCell) -> *mut StackCell {
     let a_val = a.as_int().expect("add: first operand must be an integer");
     let b_val = b.as_int().expect("add: second operand must be an integer");
 
-    let result = a_val + b_val;
+    let Some(result) = a_val.checked_add(b_val) else {
+        unsafe { crate::runtime_error(c"add: integer overflow".as_ptr()) }
+    };
     unsafe { push_int(rest, result) }
 }
 
@@ -623,7 +940,9 @@ pub unsafe extern "C" fn multiply(stack: *mut StackCell) -> *mut StackCell {
         .as_int()
         .expect("multiply: second operand must be an integer");
 
-    let result = a_val * b_val;
+    let Some(result) = a_val.checked_mul(b_val) else {
+        unsafe { crate::runtime_error(c"multiply: integer overflow".as_ptr()) }
+    };
     unsafe { push_int(rest, result) }
 }
 
@@ -641,7 +960,9 @@ pub unsafe extern "C" fn subtract(stack: *mut StackCell) -> *mut StackCell {
         .as_int()
         .expect("subtract: second operand must be an integer");
 
-    let result = a_val - b_val;
+    let Some(result) = a_val.checked_sub(b_val) else {
+        unsafe { crate::runtime_error(c"subtract: integer overflow".as_ptr()) }
+    };
     unsafe { push_int(rest, result) }
 }
 
@@ -659,107 +980,398 @@ pub unsafe extern "C" fn divide(stack: *mut StackCell) -> *mut StackCell {
         .as_int()
         .expect("divide: second operand must be an integer");
 
-    assert!(b_val != 0, "divide: division by zero");
+    if b_val == 0 {
+        unsafe { crate::runtime_error(c"divide: division by zero".as_ptr()) }
+    }
 
-    let result = a_val / b_val;
+    let Some(result) = a_val.checked_div(b_val) else {
+        unsafe { crate::runtime_error(c"divide: integer overflow".as_ptr()) }
+    };
     unsafe { push_int(rest, result) }
 }
 
-// ============================================================================
-// Comparison operations
-// ============================================================================
-
+/// Remainder of truncated division, as in C and Rust
+///
+/// The result takes the sign of the dividend: `-7 % 3` is `-1` and
+/// `7 % -3` is `1`.
+///
 /// # Safety
 /// Stack must have 2 integers.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn eq(stack: *mut StackCell) -> *mut StackCell {
+pub unsafe extern "C" fn modulo(stack: *mut StackCell) -> *mut StackCell {
     let (rest, b) = unsafe { StackCell::pop(stack) };
     let (rest, a) = unsafe { StackCell::pop(rest) };
 
-    let a_val = a.as_int().expect("eq: first operand must be an integer");
-    let b_val = b.as_int().expect("eq: second operand must be an integer");
+    let a_val = a
+        .as_int()
+        .expect("modulo: first operand must be an integer");
+    let b_val = b
+        .as_int()
+        .expect("modulo: second operand must be an integer");
 
-    let result = a_val == b_val;
-    unsafe { push_bool(rest, result) }
+    if b_val == 0 {
+        unsafe { crate::runtime_error(c"modulo: division by zero".as_ptr()) }
+    }
+
+    let Some(result) = a_val.checked_rem(b_val) else {
+        unsafe { crate::runtime_error(c"modulo: integer overflow".as_ptr()) }
+    };
+    unsafe { push_int(rest, result) }
 }
 
 /// # Safety
-/// Stack must have 2 integers.
+/// Stack must have an integer on top.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn lt(stack: *mut StackCell) -> *mut StackCell {
-    let (rest, b) = unsafe { StackCell::pop(stack) };
-    let (rest, a) = unsafe { StackCell::pop(rest) };
+pub unsafe extern "C" fn neg(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, a) = unsafe { StackCell::pop(stack) };
+    let a_val = a.as_int().expect("neg: operand must be an integer");
 
-    let a_val = a.as_int().expect("lt: first operand must be an integer");
-    let b_val = b.as_int().expect("lt: second operand must be an integer");
+    let Some(result) = a_val.checked_neg() else {
+        unsafe { crate::runtime_error(c"neg: integer overflow".as_ptr()) }
+    };
+    unsafe { push_int(rest, result) }
+}
 
-    let result = a_val < b_val;
-    unsafe { push_bool(rest, result) }
+/// # Safety
+/// Stack must have an integer on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn abs(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, a) = unsafe { StackCell::pop(stack) };
+    let a_val = a.as_int().expect("abs: operand must be an integer");
+
+    let Some(result) = a_val.checked_abs() else {
+        unsafe { crate::runtime_error(c"abs: integer overflow".as_ptr()) }
+    };
+    unsafe { push_int(rest, result) }
 }
 
 /// # Safety
 /// Stack must have 2 integers.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn gt(stack: *mut StackCell) -> *mut StackCell {
+pub unsafe extern "C" fn min(stack: *mut StackCell) -> *mut StackCell {
     let (rest, b) = unsafe { StackCell::pop(stack) };
     let (rest, a) = unsafe { StackCell::pop(rest) };
 
-    let a_val = a.as_int().expect("gt: first operand must be an integer");
-    let b_val = b.as_int().expect("gt: second operand must be an integer");
+    let a_val = a.as_int().expect("min: first operand must be an integer");
+    let b_val = b.as_int().expect("min: second operand must be an integer");
 
-    let result = a_val > b_val;
-    unsafe { push_bool(rest, result) }
+    unsafe { push_int(rest, a_val.min(b_val)) }
 }
 
 /// # Safety
 /// Stack must have 2 integers.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn le(stack: *mut StackCell) -> *mut StackCell {
+pub unsafe extern "C" fn max(stack: *mut StackCell) -> *mut StackCell {
     let (rest, b) = unsafe { StackCell::pop(stack) };
     let (rest, a) = unsafe { StackCell::pop(rest) };
 
-    let a_val = a.as_int().expect("le: first operand must be an integer");
-    let b_val = b.as_int().expect("le: second operand must be an integer");
+    let a_val = a.as_int().expect("max: first operand must be an integer");
+    let b_val = b.as_int().expect("max: second operand must be an integer");
 
-    let result = a_val <= b_val;
-    unsafe { push_bool(rest, result) }
+    unsafe { push_int(rest, a_val.max(b_val)) }
 }
 
+// ============================================================================
+// Bitwise operations
+// ============================================================================
+
+/// Pop two integers for the bitwise operation `op`, returning (rest, a, b)
+///
 /// # Safety
 /// Stack must have 2 integers.
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn ge(stack: *mut StackCell) -> *mut StackCell {
+unsafe fn pop_two_ints(stack: *mut StackCell, op: &str) -> (*mut StackCell, i64, i64) {
     let (rest, b) = unsafe { StackCell::pop(stack) };
     let (rest, a) = unsafe { StackCell::pop(rest) };
 
-    let a_val = a.as_int().expect("ge: first operand must be an integer");
-    let b_val = b.as_int().expect("ge: second operand must be an integer");
+    let a_val = a
+        .as_int()
+        .unwrap_or_else(|| panic!("{}: first operand must be an integer", op));
+    let b_val = b
+        .as_int()
+        .unwrap_or_else(|| panic!("{}: second operand must be an integer", op));
 
-    let result = a_val >= b_val;
-    unsafe { push_bool(rest, result) }
+    (rest, a_val, b_val)
 }
 
 /// # Safety
 /// Stack must have 2 integers.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn ne(stack: *mut StackCell) -> *mut StackCell {
-    let (rest, b) = unsafe { StackCell::pop(stack) };
-    let (rest, a) = unsafe { StackCell::pop(rest) };
+pub unsafe extern "C" fn bitand(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, a, b) = unsafe { pop_two_ints(stack, "bitand") };
+    unsafe { push_int(rest, a & b) }
+}
 
-    let a_val = a.as_int().expect("ne: first operand must be an integer");
-    let b_val = b.as_int().expect("ne: second operand must be an integer");
+/// # Safety
+/// Stack must have 2 integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bitor(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, a, b) = unsafe { pop_two_ints(stack, "bitor") };
+    unsafe { push_int(rest, a | b) }
+}
 
-    let result = a_val != b_val;
-    unsafe { push_bool(rest, result) }
+/// # Safety
+/// Stack must have 2 integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bitxor(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, a, b) = unsafe { pop_two_ints(stack, "bitxor") };
+    unsafe { push_int(rest, a ^ b) }
 }
 
-/// Deep copy a cell (for variant field extraction)
+/// Shift left by the amount on top, which must be in 0..64
 ///
 /// # Safety
-/// Cell pointer must be valid
+/// Stack must have 2 integers.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn copy_cell(cell: *mut StackCell) -> *mut StackCell {
-    assert!(!cell.is_null(), "copy_cell: null cell");
+pub unsafe extern "C" fn shl(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, a, b) = unsafe { pop_two_ints(stack, "shl") };
+    if !(0..64).contains(&b) {
+        unsafe { crate::runtime_error(c"shl: shift amount out of range".as_ptr()) }
+    }
+    unsafe { push_int(rest, a << b) }
+}
+
+/// Arithmetic shift right (the sign bit is copied in) by the amount on top,
+/// which must be in 0..64
+///
+/// # Safety
+/// Stack must have 2 integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn shr(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, a, b) = unsafe { pop_two_ints(stack, "shr") };
+    if !(0..64).contains(&b) {
+        unsafe { crate::runtime_error(c"shr: shift amount out of range".as_ptr()) }
+    }
+    unsafe { push_int(rest, a >> b) }
+}
+
+/// Bitwise complement (the `invert` word)
+///
+/// # Safety
+/// Stack must have an integer on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn invert(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, a) = unsafe { StackCell::pop(stack) };
+    let a_val = a.as_int().expect("invert: operand must be an integer");
+    unsafe { push_int(rest, !a_val) }
+}
+
+// ============================================================================
+// Boolean operations
+// ============================================================================
+
+/// Logical negation
+///
+/// # Safety
+/// Stack must have a bool on top.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn not(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, a) = unsafe { StackCell::pop(stack) };
+    let a_val = a.as_bool().expect("not: operand must be a bool");
+    unsafe { push_bool(rest, !a_val) }
+}
+
+/// Pop two bools for the boolean operation `op`, returning (rest, a, b)
+///
+/// # Safety
+/// Stack must have 2 bools.
+unsafe fn pop_two_bools(stack: *mut StackCell, op: &str) -> (*mut StackCell, bool, bool) {
+    let (rest, b) = unsafe { StackCell::pop(stack) };
+    let (rest, a) = unsafe { StackCell::pop(rest) };
+
+    let a_val = a
+        .as_bool()
+        .unwrap_or_else(|| panic!("{}: first operand must be a bool", op));
+    let b_val = b
+        .as_bool()
+        .unwrap_or_else(|| panic!("{}: second operand must be a bool", op));
+
+    (rest, a_val, b_val)
+}
+
+/// Logical and (the `and` word)
+///
+/// Both operands are already on the stack, so there's no short-circuiting:
+/// whatever computed the second one has run either way.
+///
+/// # Safety
+/// Stack must have 2 bools.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bool_and(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, a, b) = unsafe { pop_two_bools(stack, "and") };
+    unsafe { push_bool(rest, a && b) }
+}
+
+/// Logical or (the `or` word)
+///
+/// Like `and`, this doesn't short-circuit.
+///
+/// # Safety
+/// Stack must have 2 bools.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bool_or(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, a, b) = unsafe { pop_two_bools(stack, "or") };
+    unsafe { push_bool(rest, a || b) }
+}
+
+// ============================================================================
+// Comparison operations
+// ============================================================================
+
+/// # Safety
+/// Stack must have 2 integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn eq(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, b) = unsafe { StackCell::pop(stack) };
+    let (rest, a) = unsafe { StackCell::pop(rest) };
+
+    let a_val = a.as_int().expect("eq: first operand must be an integer");
+    let b_val = b.as_int().expect("eq: second operand must be an integer");
+
+    let result = a_val == b_val;
+    unsafe { push_bool(rest, result) }
+}
+
+/// # Safety
+/// Stack must have 2 integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lt(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, b) = unsafe { StackCell::pop(stack) };
+    let (rest, a) = unsafe { StackCell::pop(rest) };
+
+    let a_val = a.as_int().expect("lt: first operand must be an integer");
+    let b_val = b.as_int().expect("lt: second operand must be an integer");
+
+    let result = a_val < b_val;
+    unsafe { push_bool(rest, result) }
+}
+
+/// # Safety
+/// Stack must have 2 integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gt(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, b) = unsafe { StackCell::pop(stack) };
+    let (rest, a) = unsafe { StackCell::pop(rest) };
+
+    let a_val = a.as_int().expect("gt: first operand must be an integer");
+    let b_val = b.as_int().expect("gt: second operand must be an integer");
+
+    let result = a_val > b_val;
+    unsafe { push_bool(rest, result) }
+}
+
+/// # Safety
+/// Stack must have 2 integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn le(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, b) = unsafe { StackCell::pop(stack) };
+    let (rest, a) = unsafe { StackCell::pop(rest) };
+
+    let a_val = a.as_int().expect("le: first operand must be an integer");
+    let b_val = b.as_int().expect("le: second operand must be an integer");
+
+    let result = a_val <= b_val;
+    unsafe { push_bool(rest, result) }
+}
+
+/// # Safety
+/// Stack must have 2 integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ge(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, b) = unsafe { StackCell::pop(stack) };
+    let (rest, a) = unsafe { StackCell::pop(rest) };
+
+    let a_val = a.as_int().expect("ge: first operand must be an integer");
+    let b_val = b.as_int().expect("ge: second operand must be an integer");
+
+    let result = a_val >= b_val;
+    unsafe { push_bool(rest, result) }
+}
+
+/// # Safety
+/// Stack must have 2 integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ne(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, b) = unsafe { StackCell::pop(stack) };
+    let (rest, a) = unsafe { StackCell::pop(rest) };
+
+    let a_val = a.as_int().expect("ne: first operand must be an integer");
+    let b_val = b.as_int().expect("ne: second operand must be an integer");
+
+    let result = a_val != b_val;
+    unsafe { push_bool(rest, result) }
+}
+
+/// Structural equality of any two values (the `equal` word)
+///
+/// Stack effect: ( A A -- Bool )
+///
+/// # Safety
+/// Stack must have 2 values.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cell_equal(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "equal: stack is empty");
+    let (rest, b) = unsafe { StackCell::pop(stack) };
+    assert!(!rest.is_null(), "equal: need two values");
+    let (rest, a) = unsafe { StackCell::pop(rest) };
+
+    let result = unsafe { cells_equal(&a, &b) };
+    unsafe { push_bool(rest, result) }
+}
+
+/// Compare two cells by type and contents, including every variant field
+///
+/// Only the cells themselves are compared, not the stacks below them.
+/// Variant fields are compared through an explicit work list of pairs, so
+/// deep values can't overflow the native stack.
+///
+/// # Safety
+/// Both cells (and their variant fields) must be valid.
+unsafe fn cells_equal(a: &StackCell, b: &StackCell) -> bool {
+    let mut pending: Vec<(*const StackCell, *const StackCell)> = vec![(a, b)];
+
+    while let Some((a, b)) = pending.pop() {
+        let (a, b) = unsafe { (&*a, &*b) };
+        if a.cell_type != b.cell_type {
+            return false;
+        }
+
+        let same = unsafe {
+            match a.cell_type {
+                CellType::Int => a.data.int_val == b.data.int_val,
+                CellType::Bool => a.data.bool_val == b.data.bool_val,
+                CellType::Float => a.data.float_val == b.data.float_val,
+                CellType::String => {
+                    std::ffi::CStr::from_ptr(a.data.string_ptr)
+                        == std::ffi::CStr::from_ptr(b.data.string_ptr)
+                }
+                CellType::Quotation => a.data.quotation_ptr == b.data.quotation_ptr,
+                CellType::Channel => ptr::eq(a.data.channel_ptr, b.data.channel_ptr),
+                CellType::Variant => {
+                    let (a, b) = (a.data.variant, b.data.variant);
+                    let (mut field_a, mut field_b) = (a.data, b.data);
+                    while !field_a.is_null() && !field_b.is_null() {
+                        pending.push((field_a, field_b));
+                        field_a = (*field_a).next;
+                        field_b = (*field_b).next;
+                    }
+                    a.tag == b.tag && field_a.is_null() && field_b.is_null()
+                }
+            }
+        };
+        if !same {
+            return false;
+        }
+    }
+    true
+}
+
+/// Deep copy a cell (for variant field extraction)
+///
+/// # Safety
+/// Cell pointer must be valid
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn copy_cell(cell: *mut StackCell) -> *mut StackCell {
+    assert!(!cell.is_null(), "copy_cell: null cell");
 
     unsafe {
         let original = &*cell;
@@ -786,9 +1398,32 @@ pub unsafe extern "C" fn skip_n(stack: *mut StackCell, n: i64) -> *mut StackCell
     }
 }
 
+/// Read the Int on top of the stack as a process exit code
+///
+/// Returns `default` if the stack is empty or its top isn't an Int. Like `exit`,
+/// an Int outside 0-255 is a runtime error.
+///
+/// # Safety
+/// Stack must be null or a valid stack pointer
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn stack_top_int_or(stack: *const StackCell, default: i32) -> i32 {
+    if stack.is_null() {
+        return default;
+    }
+
+    match unsafe { (*stack).as_int() } {
+        Some(code) if (0..=255).contains(&code) => code as i32,
+        Some(_) => unsafe {
+            crate::runtime_error(c"main: exit code must be in range 0-255".as_ptr())
+        },
+        None => default,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runtime_error_from;
 
     #[test]
     fn test_push_pop() {
@@ -803,6 +1438,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_stack_top_int_or() {
+        unsafe {
+            assert_eq!(stack_top_int_or(ptr::null(), 0), 0);
+
+            let stack = push_int(ptr::null_mut(), 3);
+            assert_eq!(stack_top_int_or(stack, 0), 3);
+
+            let stack = push_bool(stack, true);
+            assert_eq!(stack_top_int_or(stack, 7), 7);
+            crate::scheduler::free_stack(stack);
+        }
+    }
+
+    #[test]
+    fn test_push_float() {
+        unsafe {
+            let stack = ptr::null_mut();
+            let stack = push_float(stack, 3.5);
+            let stack = dup(stack);
+
+            let (rest, top) = StackCell::pop(stack);
+            let (rest, second) = StackCell::pop(rest);
+
+            assert!(rest.is_null());
+            assert_eq!(top.cell_type, CellType::Float);
+            assert_eq!(top.as_float(), Some(3.5));
+            assert_eq!(second.as_float(), Some(3.5));
+            assert_eq!(top.as_int(), None);
+        }
+    }
+
     #[test]
     fn test_dup() {
         unsafe {
@@ -946,6 +1613,166 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_unrot() {
+        unsafe {
+            // Test -rot: ( A B C -- C A B )
+            let stack = ptr::null_mut();
+            let stack = push_int(stack, 1); // A
+            let stack = push_int(stack, 2); // B
+            let stack = push_int(stack, 3); // C
+            let stack = unrot(stack);
+
+            let (rest, b) = StackCell::pop(stack); // Should be 2
+            let (rest, a) = StackCell::pop(rest); // Should be 1
+            let (rest, c) = StackCell::pop(rest); // Should be 3
+
+            assert!(rest.is_null());
+            assert_eq!(b.as_int().unwrap(), 2);
+            assert_eq!(a.as_int().unwrap(), 1);
+            assert_eq!(c.as_int().unwrap(), 3);
+        }
+    }
+
+    #[test]
+    fn test_two_dup() {
+        unsafe {
+            // Test 2dup: ( A B -- A B A B ), with a linear value to copy
+            let text = std::ffi::CString::new("b").unwrap();
+            let stack = ptr::null_mut();
+            let stack = push_int(stack, 1); // A
+            let stack = push_string(stack, text.as_ptr()); // B
+            let stack = two_dup(stack);
+
+            let (rest, b2) = StackCell::pop(stack);
+            let (rest, a2) = StackCell::pop(rest);
+            let (rest, b1) = StackCell::pop(rest);
+            let (rest, a1) = StackCell::pop(rest);
+
+            assert!(rest.is_null());
+            assert_eq!(a1.as_int().unwrap(), 1);
+            assert_eq!(a2.as_int().unwrap(), 1);
+            // Each copy owns its own string, so both can be freed
+            let (p1, p2) = (b1.as_string_ptr().unwrap(), b2.as_string_ptr().unwrap());
+            assert_ne!(p1, p2);
+            assert_eq!(std::ffi::CStr::from_ptr(p1).to_str(), Ok("b"));
+            assert_eq!(std::ffi::CStr::from_ptr(p2).to_str(), Ok("b"));
+        }
+    }
+
+    #[test]
+    fn test_two_drop() {
+        unsafe {
+            // Test 2drop: ( A B -- )
+            let stack = ptr::null_mut();
+            let stack = push_int(stack, 1);
+            let stack = push_int(stack, 2);
+            let stack = push_int(stack, 3);
+            let stack = two_drop(stack);
+
+            let (rest, a) = StackCell::pop(stack); // Should be 1
+            assert!(rest.is_null());
+            assert_eq!(a.as_int().unwrap(), 1);
+        }
+    }
+
+    #[test]
+    fn test_depth() {
+        unsafe {
+            let empty = depth(ptr::null_mut());
+            assert_eq!((*empty).as_int(), Some(0));
+            crate::scheduler::free_stack(empty);
+
+            // 1 2 3 depth: the count doesn't include itself
+            let stack = ptr::null_mut();
+            let stack = push_int(stack, 1);
+            let stack = push_int(stack, 2);
+            let stack = push_int(stack, 3);
+            let stack = depth(stack);
+
+            let (rest, count) = StackCell::pop(stack);
+            assert_eq!(count.as_int(), Some(3));
+            let (rest, top) = StackCell::pop(rest);
+            assert_eq!(top.as_int(), Some(3));
+            let (rest, _) = StackCell::pop(rest);
+            let (rest, _) = StackCell::pop(rest);
+            assert!(rest.is_null());
+        }
+    }
+
+    #[test]
+    fn test_ndrop() {
+        unsafe {
+            // 1 2 3 2 ndrop leaves 1
+            let stack = ptr::null_mut();
+            let stack = push_int(stack, 1);
+            let stack = push_int(stack, 2);
+            let stack = push_int(stack, 3);
+            let stack = ndrop(push_int(stack, 2));
+
+            let (rest, top) = StackCell::pop(stack);
+            assert_eq!(top.as_int(), Some(1));
+            assert!(rest.is_null());
+
+            // 0 ndrop only drops the count
+            let stack = ndrop(push_int(push_int(ptr::null_mut(), 5), 0));
+            let (rest, top) = StackCell::pop(stack);
+            assert_eq!(top.as_int(), Some(5));
+            assert!(rest.is_null());
+        }
+    }
+
+    #[test]
+    fn test_ndup() {
+        unsafe {
+            // "a" 2 3 2 ndup leaves "a" 2 3 2 3, with the copies in order
+            let stack = ptr::null_mut();
+            let stack = push_string(stack, c"a".as_ptr());
+            let stack = push_int(stack, 2);
+            let stack = push_int(stack, 3);
+            let stack = ndup(push_int(stack, 2));
+
+            let mut values = Vec::new();
+            let mut rest = stack;
+            while !rest.is_null() {
+                let (next, cell) = StackCell::pop(rest);
+                values.push(cell.as_int());
+                rest = next;
+            }
+            assert_eq!(values, vec![Some(3), Some(2), Some(3), Some(2), None]);
+        }
+    }
+
+    #[test]
+    fn test_ndrop_too_deep_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_ndrop_too_deep_is_a_runtime_error",
+            || unsafe {
+                ndrop(push_int(push_int(ptr::null_mut(), 1), 2));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: ndrop: stack has fewer than 2 values"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_ndup_negative_count_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_ndup_negative_count_is_a_runtime_error",
+            || unsafe {
+                ndup(push_int(ptr::null_mut(), -1));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: ndup: count -1 is negative"),
+            "{}",
+            stderr
+        );
+    }
+
     #[test]
     fn test_subtract() {
         unsafe {
@@ -960,6 +1787,70 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_add_overflow_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_add_overflow_is_a_runtime_error",
+            || unsafe {
+                let stack = push_int(ptr::null_mut(), i64::MAX);
+                add(push_int(stack, 1));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: add: integer overflow"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_subtract_overflow_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_subtract_overflow_is_a_runtime_error",
+            || unsafe {
+                let stack = push_int(ptr::null_mut(), i64::MIN);
+                subtract(push_int(stack, 1));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: subtract: integer overflow"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_multiply_overflow_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_multiply_overflow_is_a_runtime_error",
+            || unsafe {
+                let stack = push_int(ptr::null_mut(), i64::MAX);
+                multiply(push_int(stack, 2));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: multiply: integer overflow"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_divide_overflow_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_divide_overflow_is_a_runtime_error",
+            || unsafe {
+                let stack = push_int(ptr::null_mut(), i64::MIN);
+                divide(push_int(stack, -1));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: divide: integer overflow"),
+            "{}",
+            stderr
+        );
+    }
+
     #[test]
     fn test_divide() {
         unsafe {
@@ -974,6 +1865,233 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_divide_by_zero_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_divide_by_zero_is_a_runtime_error",
+            || unsafe {
+                let stack = push_int(ptr::null_mut(), 1);
+                divide(push_int(stack, 0));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: divide: division by zero"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_modulo_by_zero_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_modulo_by_zero_is_a_runtime_error",
+            || unsafe {
+                let stack = push_int(ptr::null_mut(), 1);
+                modulo(push_int(stack, 0));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: modulo: division by zero"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_modulo() {
+        let cases = [(20, 6, 2), (-7, 3, -1), (7, -3, 1), (-7, -3, -1), (6, 3, 0)];
+        for (a, b, expected) in cases {
+            unsafe {
+                let stack = push_int(ptr::null_mut(), a);
+                let stack = push_int(stack, b);
+                let stack = modulo(stack);
+
+                let (rest, result) = StackCell::pop(stack);
+                assert!(rest.is_null());
+                assert_eq!(result.as_int().unwrap(), expected, "{} % {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_modulo_overflow_is_a_runtime_error() {
+        // The remainder is 0, but computing it overflows the quotient
+        let stderr = runtime_error_from(
+            "stack::tests::test_modulo_overflow_is_a_runtime_error",
+            || unsafe {
+                let stack = push_int(ptr::null_mut(), i64::MIN);
+                modulo(push_int(stack, -1));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: modulo: integer overflow"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_neg_abs_min_max() {
+        unsafe {
+            let stack = neg(push_int(ptr::null_mut(), 5));
+            assert_eq!((*stack).as_int(), Some(-5));
+            let stack = neg(stack);
+            assert_eq!((*stack).as_int(), Some(5));
+
+            let stack = abs(neg(stack));
+            assert_eq!((*stack).as_int(), Some(5));
+            let stack = abs(push_int(stack, i64::MAX));
+            assert_eq!((*stack).as_int(), Some(i64::MAX));
+
+            // ( 5 MAX -- 5 ), then ( 5 -3 -- 5 )
+            let stack = min(stack);
+            assert_eq!((*stack).as_int(), Some(5));
+            let stack = max(push_int(stack, -3));
+
+            let (rest, result) = StackCell::pop(stack);
+            assert!(rest.is_null());
+            assert_eq!(result.as_int(), Some(5));
+        }
+    }
+
+    #[test]
+    fn test_neg_overflow_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_neg_overflow_is_a_runtime_error",
+            || unsafe {
+                neg(push_int(ptr::null_mut(), i64::MIN));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: neg: integer overflow"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_abs_overflow_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_abs_overflow_is_a_runtime_error",
+            || unsafe {
+                abs(push_int(ptr::null_mut(), i64::MIN));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: abs: integer overflow"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let cases: [(
+            unsafe extern "C" fn(*mut StackCell) -> *mut StackCell,
+            i64,
+            i64,
+            i64,
+        ); 7] = [
+            (bitand, 0b1100, 0b1010, 0b1000),
+            (bitor, 0b1100, 0b1010, 0b1110),
+            (bitxor, 0b1100, 0b1010, 0b0110),
+            (shl, 5, 0, 5),
+            (shr, -5, 0, -5),
+            (shl, 1, 63, i64::MIN),
+            (shr, -16, 2, -4),
+        ];
+        for (op, a, b, expected) in cases {
+            unsafe {
+                let stack = push_int(ptr::null_mut(), a);
+                let stack = push_int(stack, b);
+                let stack = op(stack);
+
+                let (rest, result) = StackCell::pop(stack);
+                assert!(rest.is_null());
+                assert_eq!(result.as_int().unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shl_out_of_range_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_shl_out_of_range_is_a_runtime_error",
+            || unsafe {
+                let stack = push_int(ptr::null_mut(), 1);
+                shl(push_int(stack, 64));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: shl: shift amount out of range"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_shr_out_of_range_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "stack::tests::test_shr_out_of_range_is_a_runtime_error",
+            || unsafe {
+                let stack = push_int(ptr::null_mut(), 1);
+                shr(push_int(stack, -1));
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: shr: shift amount out of range"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_invert_flips_all_bits() {
+        for (value, expected) in [(0, -1), (-1, 0), (i64::MAX, i64::MIN), (0b1010, -11)] {
+            unsafe {
+                let stack = invert(push_int(ptr::null_mut(), value));
+
+                let (rest, result) = StackCell::pop(stack);
+                assert!(rest.is_null());
+                assert_eq!(result.as_int().unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_not_negates_bools() {
+        for (value, expected) in [(true, false), (false, true)] {
+            unsafe {
+                let stack = not(push_bool(ptr::null_mut(), value));
+
+                let (rest, result) = StackCell::pop(stack);
+                assert!(rest.is_null());
+                assert_eq!(result.as_bool(), Some(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn test_and_or_truth_tables() {
+        for a in [false, true] {
+            for b in [false, true] {
+                for (op, expected) in [
+                    (bool_and as unsafe extern "C" fn(_) -> _, a && b),
+                    (bool_or, a || b),
+                ] {
+                    unsafe {
+                        let stack = push_bool(ptr::null_mut(), a);
+                        let stack = op(push_bool(stack, b));
+
+                        let (rest, result) = StackCell::pop(stack);
+                        assert!(rest.is_null());
+                        assert_eq!(result.as_bool(), Some(expected), "{} {}", a, b);
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_comparison_eq() {
         unsafe {
@@ -999,6 +2117,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cell_equal_compares_structure() {
+        unsafe {
+            use crate::pattern::{OPTION_NONE_TAG, OPTION_SOME_TAG, push_variant};
+
+            let some = |stack, value| {
+                push_variant(stack, OPTION_SOME_TAG, push_int(ptr::null_mut(), value))
+            };
+            let equal = |stack| {
+                let (rest, result) = StackCell::pop(cell_equal(stack));
+                assert!(rest.is_null());
+                result.as_bool().unwrap()
+            };
+
+            assert!(equal(some(some(ptr::null_mut(), 1), 1)));
+            assert!(!equal(some(some(ptr::null_mut(), 1), 2)));
+            let stack = push_variant(some(ptr::null_mut(), 1), OPTION_NONE_TAG, ptr::null_mut());
+            assert!(!equal(stack));
+
+            // Nested fields are compared too, and values of different types differ
+            let nested = |stack, value| {
+                let inner = some(ptr::null_mut(), value);
+                push_variant(stack, OPTION_SOME_TAG, inner)
+            };
+            assert!(equal(nested(nested(ptr::null_mut(), 7), 7)));
+            assert!(!equal(nested(nested(ptr::null_mut(), 7), 8)));
+            assert!(!equal(push_bool(push_int(ptr::null_mut(), 1), true)));
+
+            let hello = std::ffi::CString::new("hello").unwrap();
+            let stack = push_string(push_string(ptr::null_mut(), hello.as_ptr()), hello.as_ptr());
+            assert!(equal(stack));
+        }
+    }
+
     #[test]
     fn test_comparison_lt() {
         unsafe {
@@ -1213,6 +2365,106 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pick_depth_out_of_range() {
+        unsafe {
+            let stack = push_int(ptr::null_mut(), 10);
+            let stack = push_int(stack, 20);
+
+            assert_eq!(cell_at_depth(stack, 0), Some(stack));
+            assert_eq!(cell_at_depth(stack, 1), Some((*stack).next));
+            assert_eq!(cell_at_depth(stack, 2), None);
+            assert_eq!(cell_at_depth(stack, 5), None);
+            assert_eq!(cell_at_depth(ptr::null_mut(), 0), None);
+
+            crate::scheduler::free_stack(stack);
+        }
+    }
+
+    /// A list of `len` Ints, built the way codegen builds `Cons`: the
+    /// variant's data points at the tail, followed by the head
+    unsafe fn long_list(len: i64) -> *mut StackCell {
+        unsafe {
+            use crate::pattern::{LIST_CONS_TAG, LIST_NIL_TAG, push_variant};
+            let mut list = push_variant(ptr::null_mut(), LIST_NIL_TAG, ptr::null_mut());
+            for i in 0..len {
+                (*list).next = push_int(ptr::null_mut(), i);
+                list = push_variant(ptr::null_mut(), LIST_CONS_TAG, list);
+            }
+            list
+        }
+    }
+
+    #[test]
+    fn test_free_long_stack() {
+        unsafe {
+            let mut stack = ptr::null_mut();
+            for i in 0..100_000 {
+                stack = push_int(stack, i);
+            }
+            crate::scheduler::free_stack(stack);
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "shared-cells"))]
+    fn test_clone_and_free_long_list() {
+        unsafe {
+            let list = long_list(100_000);
+            let copy = Box::into_raw(Box::new(StackCell::deep_clone(&*list)));
+
+            // The copy has its own cells, with the same values
+            let mut original = (*list).as_variant().unwrap().data;
+            let mut cloned = (*copy).as_variant().unwrap().data;
+            while !original.is_null() {
+                assert_ne!(original, cloned);
+                assert_eq!((*(*original).next).as_int(), (*(*cloned).next).as_int());
+                original = (*original).as_variant().unwrap().data;
+                cloned = (*cloned).as_variant().unwrap().data;
+            }
+            assert!(cloned.is_null());
+
+            crate::scheduler::free_stack(list);
+            crate::scheduler::free_stack(copy);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "shared-cells")]
+    fn test_copies_share_a_long_list() {
+        unsafe {
+            let list = long_list(100_000);
+            let fields = (*list).as_variant().unwrap().data;
+            let copy = copy_cell(list);
+            assert_eq!((*copy).as_variant().unwrap().data, fields);
+            assert_eq!((*fields)._padding, 2);
+
+            // Freeing one copy leaves the fields to the other
+            crate::scheduler::free_stack(copy);
+            assert_eq!((*fields)._padding, 1);
+            assert_eq!((*(*fields).next).as_int(), Some(99_999));
+
+            // A copy of a field shares the rest of the list
+            let tail = copy_cell(fields);
+            assert_eq!((*fields)._padding, 1);
+            assert_eq!((*(*fields).as_variant().unwrap().data)._padding, 2);
+
+            crate::scheduler::free_stack(list);
+            crate::scheduler::free_stack(tail);
+        }
+    }
+
+    #[test]
+    fn test_dup_and_drop_deep_list() {
+        unsafe {
+            let stack = dup(long_list(50_000));
+            assert_ne!(stack, (*stack).next);
+
+            let stack = drop(drop(stack));
+            assert!(stack.is_null());
+        }
+    }
+
     #[test]
     fn test_dip() {
         unsafe {
@@ -2,12 +2,17 @@
 String Operations - C-compatible string manipulation
 */
 
+use crate::pattern::{OPTION_NONE_TAG, OPTION_SOME_TAG, push_list, push_variant};
 #[cfg(test)]
 use crate::stack::push_string;
 use crate::stack::{CellDataUnion, CellType, StackCell, push_bool, push_int};
-use std::ffi::CString;
+use std::cmp::Ordering;
+use std::ffi::{CStr, CString};
 
-/// Get the length of a string
+/// Get the length of a string in bytes
+///
+/// Strings are UTF-8, so this counts bytes, not characters: "café" is 5
+/// bytes long. Use `string_char_length` to count characters.
 ///
 /// # Safety
 /// Stack must have a string on top.
@@ -34,6 +39,39 @@ pub unsafe extern "C" fn string_length(stack: *mut StackCell) -> *mut StackCell
     unsafe { push_int(rest, length) }
 }
 
+/// Get the length of a string in characters (Unicode scalar values)
+///
+/// # Safety
+/// Stack must have a string on top.
+/// Returns stack with integer length pushed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_char_length(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "string_char_length: stack is empty");
+
+    let (rest, cell) = unsafe { StackCell::pop(stack) };
+
+    let string_ptr = cell
+        .as_string_ptr()
+        .expect("string_char_length: expected string on stack");
+    assert!(
+        !string_ptr.is_null(),
+        "string_char_length: unexpected null string pointer"
+    );
+
+    let s = unsafe {
+        match CStr::from_ptr(string_ptr).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                crate::runtime_error(c"string_char_length: string contains invalid UTF-8".as_ptr())
+            }
+        }
+    };
+    let length = s.chars().count() as i64;
+
+    // String is freed by cell Drop
+    unsafe { push_int(rest, length) }
+}
+
 /// Concatenate two strings
 ///
 /// # Safety
@@ -82,21 +120,21 @@ pub unsafe extern "C" fn string_concat(stack: *mut StackCell) -> *mut StackCell
         crate::runtime_error(c"string_concat: result contains null byte".as_ptr())
     });
 
-    // Transfer ownership to avoid double allocation
-    let result_ptr = c_result.into_raw();
+    // Strings are freed by cell Drop
+    unsafe { StackCell::push(rest, string_cell(c_result)) }
+}
 
-    // Create cell directly instead of using push_string to avoid extra copy
-    let cell = Box::new(StackCell {
+/// A cell owning `s`, created directly instead of using push_string to
+/// avoid an extra copy
+fn string_cell(s: CString) -> Box<StackCell> {
+    Box::new(StackCell {
         cell_type: CellType::String,
         _padding: 0,
         data: CellDataUnion {
-            string_ptr: result_ptr,
+            string_ptr: s.into_raw(),
         },
         next: std::ptr::null_mut(),
-    });
-
-    // Strings are freed by cell Drop
-    unsafe { StackCell::push(rest, cell) }
+    })
 }
 
 /// Compare two strings for equality
@@ -146,9 +184,497 @@ pub unsafe extern "C" fn string_equal(stack: *mut StackCell) -> *mut StackCell {
     unsafe { push_bool(rest, result) }
 }
 
+/// Take `length` characters of a string, starting at character `start`
+///
+/// Positions count characters, not bytes, so multibyte UTF-8 characters are
+/// never split. The range must lie within the string.
+///
+/// # Safety
+/// Stack must have a string and two integers: ( str start length -- substr )
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn substring(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "substring: stack is empty");
+
+    let (rest, length_cell) = unsafe { StackCell::pop(stack) };
+    let (rest, start_cell) = unsafe { StackCell::pop(rest) };
+    let (rest, string_cell) = unsafe { StackCell::pop(rest) };
+
+    let length = length_cell
+        .as_int()
+        .expect("substring: length must be an integer");
+    let start = start_cell
+        .as_int()
+        .expect("substring: start must be an integer");
+    let string_ptr = string_cell
+        .as_string_ptr()
+        .expect("substring: first argument must be string");
+    assert!(!string_ptr.is_null(), "substring: string is null");
+
+    let s = unsafe {
+        match CStr::from_ptr(string_ptr).to_str() {
+            Ok(s) => s,
+            Err(_) => crate::runtime_error(c"substring: string contains invalid UTF-8".as_ptr()),
+        }
+    };
+
+    // Byte offset of the character at `n`, where `n` may be one past the end
+    let byte_offset = |n: i64| {
+        let n = usize::try_from(n).ok()?;
+        s.char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(s.len()))
+            .nth(n)
+    };
+    let range = start
+        .checked_add(length)
+        .filter(|_| length >= 0)
+        .and_then(|end| Some(byte_offset(start)?..byte_offset(end)?));
+    let Some(range) = range else {
+        unsafe { crate::runtime_error(c"substring: range is out of bounds".as_ptr()) }
+    };
+
+    // A slice of a C string can't contain a null byte
+    let result = CString::new(&s[range]).expect("substring: result contains null byte");
+    let cell = Box::new(StackCell {
+        cell_type: CellType::String,
+        _padding: 0,
+        data: CellDataUnion {
+            string_ptr: result.into_raw(),
+        },
+        next: std::ptr::null_mut(),
+    });
+
+    // The original string is freed by cell Drop
+    unsafe { StackCell::push(rest, cell) }
+}
+
+/// Get the character at position `index` as its Unicode scalar value
+///
+/// Positions count characters, not bytes, as in `substring`: in "café" the
+/// `é` is at position 3. An index outside the string is a runtime error.
+///
+/// # Safety
+/// Stack must have a string and an integer: ( str index -- code_point )
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_char_at(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "string_char_at: stack is empty");
+
+    let (rest, index_cell) = unsafe { StackCell::pop(stack) };
+    assert!(
+        !rest.is_null(),
+        "string_char_at: need a string and an index"
+    );
+    let (rest, string_cell) = unsafe { StackCell::pop(rest) };
+
+    let index = index_cell
+        .as_int()
+        .expect("string_char_at: index must be an integer");
+    let string_ptr = string_cell
+        .as_string_ptr()
+        .expect("string_char_at: first argument must be string");
+    assert!(!string_ptr.is_null(), "string_char_at: string is null");
+
+    let s = unsafe {
+        match CStr::from_ptr(string_ptr).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                crate::runtime_error(c"string_char_at: string contains invalid UTF-8".as_ptr())
+            }
+        }
+    };
+
+    let found = usize::try_from(index).ok().and_then(|n| s.chars().nth(n));
+    let Some(c) = found else {
+        unsafe { crate::runtime_error(c"string_char_at: index is out of bounds".as_ptr()) }
+    };
+
+    // String is freed by cell Drop
+    unsafe { push_int(rest, i64::from(u32::from(c))) }
+}
+
+/// Concatenate `count` copies of a string
+///
+/// A count of 0 gives the empty string. A negative count, or a result too
+/// long to allocate, is a runtime error.
+///
+/// # Safety
+/// Stack must have a string and an integer: ( str count -- repeated )
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_repeat(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "string_repeat: stack is empty");
+
+    let (rest, count_cell) = unsafe { StackCell::pop(stack) };
+    assert!(!rest.is_null(), "string_repeat: need a string and a count");
+    let (rest, cell) = unsafe { StackCell::pop(rest) };
+
+    let count = count_cell
+        .as_int()
+        .expect("string_repeat: count must be an integer");
+    let string_ptr = cell
+        .as_string_ptr()
+        .expect("string_repeat: first argument must be string");
+    assert!(!string_ptr.is_null(), "string_repeat: string is null");
+
+    let Ok(count) = usize::try_from(count) else {
+        unsafe { crate::runtime_error(c"string_repeat: count is negative".as_ptr()) }
+    };
+    let s = unsafe { CStr::from_ptr(string_ptr) }.to_bytes();
+    if s.len()
+        .checked_mul(count)
+        .is_none_or(|len| len > isize::MAX as usize)
+    {
+        unsafe { crate::runtime_error(c"string_repeat: result is too long".as_ptr()) }
+    }
+
+    // Copies of a C string can't contain a null byte
+    let result = CString::new(s.repeat(count)).expect("string_repeat: result contains null byte");
+
+    // String is freed by cell Drop
+    unsafe { StackCell::push(rest, string_cell(result)) }
+}
+
+/// Pop a string and push `f` of it, freshly allocated
+///
+/// # Safety
+/// Stack must have a string on top: ( str -- result )
+unsafe fn map_string(
+    stack: *mut StackCell,
+    op: &str,
+    f: impl FnOnce(&str) -> String,
+) -> *mut StackCell {
+    assert!(!stack.is_null(), "{}: stack is empty", op);
+
+    let (rest, cell) = unsafe { StackCell::pop(stack) };
+
+    let string_ptr = cell
+        .as_string_ptr()
+        .unwrap_or_else(|| panic!("{}: expected string on stack", op));
+    assert!(!string_ptr.is_null(), "{}: string is null", op);
+
+    let s = match unsafe { CStr::from_ptr(string_ptr) }.to_str() {
+        Ok(s) => s,
+        Err(_) => {
+            let message = CString::new(format!("{}: string contains invalid UTF-8", op)).unwrap();
+            unsafe { crate::runtime_error(message.as_ptr()) }
+        }
+    };
+
+    // Only characters of the string go into results, so there's no null byte
+    let result = CString::new(f(s)).unwrap_or_else(|_| panic!("{}: result contains null byte", op));
+
+    // String is freed by cell Drop
+    unsafe { StackCell::push(rest, string_cell(result)) }
+}
+
+/// Reverse the characters of a string
+///
+/// Characters, not bytes, are reversed, so multibyte UTF-8 characters stay
+/// intact: "café" becomes "éfac".
+///
+/// # Safety
+/// Stack must have a string on top: ( str -- reversed )
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_reverse(stack: *mut StackCell) -> *mut StackCell {
+    unsafe { map_string(stack, "string_reverse", |s| s.chars().rev().collect()) }
+}
+
+/// Remove whitespace from both ends of a string
+///
+/// Whitespace is as Unicode defines it, so this includes newlines and tabs.
+///
+/// # Safety
+/// Stack must have a string on top: ( str -- trimmed )
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_trim(stack: *mut StackCell) -> *mut StackCell {
+    unsafe { map_string(stack, "string_trim", |s| s.trim().to_string()) }
+}
+
+/// Convert a string to uppercase
+///
+/// Case mapping is Unicode's, which can change a string's length: "ß"
+/// becomes "SS".
+///
+/// # Safety
+/// Stack must have a string on top: ( str -- upper )
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_to_upper(stack: *mut StackCell) -> *mut StackCell {
+    unsafe { map_string(stack, "string_to_upper", str::to_uppercase) }
+}
+
+/// Convert a string to lowercase
+///
+/// Case mapping is Unicode's, as for `string_to_upper`.
+///
+/// # Safety
+/// Stack must have a string on top: ( str -- lower )
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_to_lower(stack: *mut StackCell) -> *mut StackCell {
+    unsafe { map_string(stack, "string_to_lower", str::to_lowercase) }
+}
+
+/// Parse a string as a decimal integer
+///
+/// Surrounding whitespace is ignored. Pushes `Some(n)` on success and `None`
+/// if the string isn't a number that fits in an Int.
+///
+/// # Safety
+/// Stack must have a string on top: ( str -- Option(Int) )
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_to_int(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "string_to_int: stack is empty");
+
+    let (rest, cell) = unsafe { StackCell::pop(stack) };
+
+    let string_ptr = cell
+        .as_string_ptr()
+        .expect("string_to_int: expected string on stack");
+    assert!(!string_ptr.is_null(), "string_to_int: string is null");
+
+    let s = unsafe {
+        match CStr::from_ptr(string_ptr).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                crate::runtime_error(c"string_to_int: string contains invalid UTF-8".as_ptr())
+            }
+        }
+    };
+
+    // String is freed by cell Drop
+    match s.trim().parse::<i64>() {
+        Ok(value) => unsafe {
+            let field = push_int(std::ptr::null_mut(), value);
+            push_variant(rest, OPTION_SOME_TAG, field)
+        },
+        Err(_) => unsafe { push_variant(rest, OPTION_NONE_TAG, std::ptr::null_mut()) },
+    }
+}
+
+/// Pop two strings and apply `f` to the bytes of the first and second
+///
+/// # Safety
+/// Stack must have two strings: ( str1 str2 -- )
+unsafe fn with_two_strings<T>(
+    stack: *mut StackCell,
+    op: &str,
+    f: impl FnOnce(&[u8], &[u8]) -> T,
+) -> (*mut StackCell, T) {
+    assert!(!stack.is_null(), "{}: stack is empty", op);
+
+    let (rest, cell2) = unsafe { StackCell::pop(stack) };
+    assert!(!rest.is_null(), "{}: need two strings", op);
+    let (rest, cell1) = unsafe { StackCell::pop(rest) };
+
+    let str1_ptr = cell1
+        .as_string_ptr()
+        .unwrap_or_else(|| panic!("{}: first argument must be string", op));
+    let str2_ptr = cell2
+        .as_string_ptr()
+        .unwrap_or_else(|| panic!("{}: second argument must be string", op));
+
+    assert!(!str1_ptr.is_null(), "{}: first string is null", op);
+    assert!(!str2_ptr.is_null(), "{}: second string is null", op);
+
+    let (s1, s2) = unsafe { (CStr::from_ptr(str1_ptr), CStr::from_ptr(str2_ptr)) };
+
+    // Strings are freed by cell Drop
+    (rest, f(s1.to_bytes(), s2.to_bytes()))
+}
+
+/// Pop two strings and order the first against the second
+///
+/// Strings compare byte by byte, which for UTF-8 is the same as comparing
+/// code points. A string that is a prefix of another sorts first.
+///
+/// # Safety
+/// Stack must have two strings: ( str1 str2 -- )
+unsafe fn compare_strings(stack: *mut StackCell, op: &str) -> (*mut StackCell, Ordering) {
+    unsafe { with_two_strings(stack, op, |s1, s2| s1.cmp(s2)) }
+}
+
+/// Test whether one string sorts before another
+///
+/// # Safety
+/// Stack must have two strings: ( str1 str2 -- bool )
+/// Returns true if str1 sorts before str2.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_lt(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, ordering) = unsafe { compare_strings(stack, "string_lt") };
+    unsafe { push_bool(rest, ordering == Ordering::Less) }
+}
+
+/// Test whether one string sorts after another
+///
+/// # Safety
+/// Stack must have two strings: ( str1 str2 -- bool )
+/// Returns true if str1 sorts after str2.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_gt(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, ordering) = unsafe { compare_strings(stack, "string_gt") };
+    unsafe { push_bool(rest, ordering == Ordering::Greater) }
+}
+
+/// Three-way string comparison
+///
+/// # Safety
+/// Stack must have two strings: ( str1 str2 -- int )
+/// Returns -1 if str1 sorts before str2, 0 if they are equal and 1 if after.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_compare(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, ordering) = unsafe { compare_strings(stack, "string_compare") };
+    unsafe { push_int(rest, ordering as i64) }
+}
+
+/// Test whether a string contains another
+///
+/// Every string contains the empty string.
+///
+/// # Safety
+/// Stack must have two strings: ( haystack needle -- bool )
+/// The needle is on top: `"banana" "nan" string_contains` is true.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_contains(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, found) = unsafe {
+        with_two_strings(stack, "string_contains", |haystack, needle| {
+            needle.is_empty() || haystack.windows(needle.len()).any(|w| w == needle)
+        })
+    };
+    unsafe { push_bool(rest, found) }
+}
+
+/// Test whether a string starts with another
+///
+/// Every string starts with the empty string.
+///
+/// # Safety
+/// Stack must have two strings: ( str prefix -- bool )
+/// The prefix is on top: `"banana" "ban" string_starts_with` is true.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_starts_with(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, found) = unsafe {
+        with_two_strings(stack, "string_starts_with", |s, prefix| {
+            s.starts_with(prefix)
+        })
+    };
+    unsafe { push_bool(rest, found) }
+}
+
+/// Test whether a string ends with another
+///
+/// Every string ends with the empty string.
+///
+/// # Safety
+/// Stack must have two strings: ( str suffix -- bool )
+/// The suffix is on top: `"banana" "ana" string_ends_with` is true.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_ends_with(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, found) =
+        unsafe { with_two_strings(stack, "string_ends_with", |s, suffix| s.ends_with(suffix)) };
+    unsafe { push_bool(rest, found) }
+}
+
+/// Find the first occurrence of one string in another
+///
+/// Returns its position counted in characters, not bytes, so it can be
+/// passed to `substring`, or -1 if it doesn't occur. The empty string occurs
+/// at position 0 of every string.
+///
+/// # Safety
+/// Stack must have two strings: ( haystack needle -- index )
+/// The needle is on top: `"café!" "!" string_index_of` is 4.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_index_of(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, index) = unsafe {
+        with_two_strings(stack, "string_index_of", |haystack, needle| {
+            let haystack = String::from_utf8_lossy(haystack);
+            let found = haystack.find(&*String::from_utf8_lossy(needle));
+            found.map_or(-1, |byte_index| {
+                haystack[..byte_index].chars().count() as i64
+            })
+        })
+    };
+    unsafe { push_int(rest, index) }
+}
+
+/// Replace every occurrence of a pattern in a string
+///
+/// Occurrences are found from the start and don't overlap: replacing "aa"
+/// in "aaa" replaces only the first two. The pattern can't be empty.
+///
+/// # Safety
+/// Stack must have three strings: ( str pattern replacement -- replaced )
+/// `"banana" "an" "AN" string_replace` is "bANANa".
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_replace(stack: *mut StackCell) -> *mut StackCell {
+    assert!(!stack.is_null(), "string_replace: stack is empty");
+
+    let (rest, replacement_cell) = unsafe { StackCell::pop(stack) };
+    assert!(!rest.is_null(), "string_replace: need three strings");
+    let (rest, pattern_cell) = unsafe { StackCell::pop(rest) };
+    assert!(!rest.is_null(), "string_replace: need three strings");
+    let (rest, cell) = unsafe { StackCell::pop(rest) };
+
+    let [s, pattern, replacement] = [
+        (&cell, "string"),
+        (&pattern_cell, "pattern"),
+        (&replacement_cell, "replacement"),
+    ]
+    .map(|(cell, what)| {
+        let ptr = cell
+            .as_string_ptr()
+            .unwrap_or_else(|| panic!("string_replace: {} must be a string", what));
+        assert!(!ptr.is_null(), "string_replace: {} is null", what);
+        match unsafe { CStr::from_ptr(ptr) }.to_str() {
+            Ok(s) => s,
+            Err(_) => unsafe {
+                crate::runtime_error(c"string_replace: string contains invalid UTF-8".as_ptr())
+            },
+        }
+    });
+    if pattern.is_empty() {
+        unsafe { crate::runtime_error(c"string_replace: pattern is empty".as_ptr()) }
+    }
+
+    let result = CString::new(s.replace(pattern, replacement)).unwrap_or_else(|_| unsafe {
+        crate::runtime_error(c"string_replace: result contains null byte".as_ptr())
+    });
+
+    // Strings are freed by cell Drop
+    unsafe { StackCell::push(rest, string_cell(result)) }
+}
+
+/// Split a string into the pieces between occurrences of a separator
+///
+/// A string without the separator is a list of itself alone, and separators
+/// at either end leave empty pieces there. The separator can't be empty.
+///
+/// # Safety
+/// Stack must have two strings: ( str separator -- List(String) )
+/// The separator is on top: `"a,b" "," string_split` is `[ "a" "b" ]`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn string_split(stack: *mut StackCell) -> *mut StackCell {
+    let (rest, pieces) = unsafe {
+        with_two_strings(stack, "string_split", |s, separator| {
+            (!separator.is_empty()).then(|| {
+                let (s, separator) = (
+                    String::from_utf8_lossy(s),
+                    String::from_utf8_lossy(separator),
+                );
+                s.split(&*separator)
+                    .map(|piece| string_cell(CString::new(piece).unwrap()))
+                    .collect()
+            })
+        })
+    };
+    match pieces {
+        Some(pieces) => unsafe { push_list(rest, pieces) },
+        None => unsafe { crate::runtime_error(c"string_split: separator is empty".as_ptr()) },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runtime_error_from;
     use std::ffi::CString;
 
     #[test]
@@ -183,6 +709,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_string_length_counts_bytes_not_chars() {
+        for (s, bytes, chars) in [("hello", 5, 5), ("café", 5, 4), ("👋 hi", 7, 4), ("", 0, 0)] {
+            let c_string = CString::new(s).unwrap();
+            unsafe {
+                let stack = push_string(std::ptr::null_mut(), c_string.as_ptr());
+                let (_, byte_length) = StackCell::pop(string_length(stack));
+                assert_eq!(byte_length.as_int(), Some(bytes), "{:?}", s);
+
+                let stack = push_string(std::ptr::null_mut(), c_string.as_ptr());
+                let (rest, char_length) = StackCell::pop(string_char_length(stack));
+                assert_eq!(char_length.as_int(), Some(chars), "{:?}", s);
+                assert!(rest.is_null());
+            }
+        }
+    }
+
     #[test]
     fn test_string_concat() {
         unsafe {
@@ -279,4 +822,424 @@ mod tests {
             assert!(rest.is_null());
         }
     }
+
+    /// Apply `string_to_int` to `s`, returning the parsed value if it's `Some`
+    unsafe fn parse_int(s: &str) -> Option<i64> {
+        let s = CString::new(s).unwrap();
+        unsafe {
+            let stack = string_to_int(push_string(std::ptr::null_mut(), s.as_ptr()));
+            let (rest, cell) = StackCell::pop(stack);
+            assert!(rest.is_null());
+
+            let variant = cell.as_variant().expect("should be variant");
+            if variant.tag == OPTION_NONE_TAG {
+                assert!(variant.data.is_null());
+                return None;
+            }
+            assert_eq!(variant.tag, OPTION_SOME_TAG);
+            Some((*variant.data).as_int().expect("should be int"))
+        }
+    }
+
+    #[test]
+    fn test_string_to_int() {
+        unsafe {
+            assert_eq!(parse_int("42"), Some(42));
+            assert_eq!(parse_int("-17"), Some(-17));
+            assert_eq!(parse_int("  7\n"), Some(7));
+            assert_eq!(parse_int("9223372036854775807"), Some(i64::MAX));
+
+            assert_eq!(parse_int(""), None);
+            assert_eq!(parse_int("   "), None);
+            assert_eq!(parse_int("forty-two"), None);
+            assert_eq!(parse_int("12abc"), None);
+            assert_eq!(parse_int("1.5"), None);
+            assert_eq!(parse_int("9223372036854775808"), None);
+        }
+    }
+
+    /// Apply `substring` to `s`, returning the resulting string
+    unsafe fn substring_of(s: &str, start: i64, length: i64) -> String {
+        let s = CString::new(s).unwrap();
+        unsafe {
+            let stack = push_string(std::ptr::null_mut(), s.as_ptr());
+            let stack = push_int(push_int(stack, start), length);
+            let (rest, cell) = StackCell::pop(substring(stack));
+            assert!(rest.is_null());
+
+            let result_ptr = cell.as_string_ptr().expect("should be string");
+            CStr::from_ptr(result_ptr).to_str().unwrap().to_owned()
+        }
+    }
+
+    #[test]
+    fn test_substring() {
+        unsafe {
+            assert_eq!(substring_of("hello world", 6, 5), "world");
+            assert_eq!(substring_of("hello", 0, 5), "hello");
+            assert_eq!(substring_of("hello", 1, 0), "");
+            assert_eq!(substring_of("hello", 5, 0), "");
+            assert_eq!(substring_of("", 0, 0), "");
+        }
+    }
+
+    #[test]
+    fn test_substring_counts_characters() {
+        unsafe {
+            // 'é' and '€' are 2 and 3 bytes long
+            assert_eq!(substring_of("café €5", 3, 1), "é");
+            assert_eq!(substring_of("café €5", 5, 2), "€5");
+            assert_eq!(substring_of("日本語", 1, 2), "本語");
+        }
+    }
+
+    #[test]
+    fn test_substring_past_the_end_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "strings::tests::test_substring_past_the_end_is_a_runtime_error",
+            || unsafe {
+                // Three characters, but six bytes
+                substring_of("éàü", 2, 2);
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: substring: range is out of bounds"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn test_substring_negative_start_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "strings::tests::test_substring_negative_start_is_a_runtime_error",
+            || unsafe {
+                substring_of("hello", -1, 1);
+            },
+        );
+        assert!(
+            stderr.contains("Runtime error: substring: range is out of bounds"),
+            "{}",
+            stderr
+        );
+    }
+
+    /// Apply a two-string word to `a` and `b`, returning the result cell
+    unsafe fn compare(
+        op: unsafe extern "C" fn(*mut StackCell) -> *mut StackCell,
+        a: &str,
+        b: &str,
+    ) -> Box<StackCell> {
+        let a = CString::new(a).unwrap();
+        let b = CString::new(b).unwrap();
+        unsafe {
+            let stack = push_string(std::ptr::null_mut(), a.as_ptr());
+            let stack = op(push_string(stack, b.as_ptr()));
+
+            let (rest, cell) = StackCell::pop(stack);
+            assert!(rest.is_null());
+            cell
+        }
+    }
+
+    #[test]
+    fn test_string_ordering() {
+        // (first, second, expected string_compare)
+        let cases = [
+            ("apple", "apple", 0),
+            ("", "", 0),
+            ("apple", "banana", -1),
+            ("banana", "apple", 1),
+            // A prefix sorts first
+            ("app", "apple", -1),
+            ("apple", "app", 1),
+            ("", "a", -1),
+            // Differing lengths compare by content first
+            ("b", "apple", 1),
+            ("Zebra", "apple", -1),
+            ("é", "z", 1),
+        ];
+
+        for (a, b, expected) in cases {
+            unsafe {
+                let compared = compare(string_compare, a, b).as_int();
+                assert_eq!(compared, Some(expected), "{:?} vs {:?}", a, b);
+                let lt = compare(string_lt, a, b).as_bool();
+                assert_eq!(lt, Some(expected < 0), "{:?} < {:?}", a, b);
+                let gt = compare(string_gt, a, b).as_bool();
+                assert_eq!(gt, Some(expected > 0), "{:?} > {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_substring_predicates() {
+        // (string, other, contains, starts with, ends with)
+        let cases = [
+            ("banana", "nan", true, false, false),
+            ("banana", "ban", true, true, false),
+            ("banana", "ana", true, false, true),
+            ("banana", "banana", true, true, true),
+            ("banana", "bananas", false, false, false),
+            ("banana", "x", false, false, false),
+            // The empty string is in every string, at both ends
+            ("banana", "", true, true, true),
+            ("", "", true, true, true),
+            ("", "a", false, false, false),
+            ("café", "é", true, false, true),
+        ];
+
+        for (s, other, contains, starts, ends) in cases {
+            unsafe {
+                let found = compare(string_contains, s, other).as_bool();
+                assert_eq!(found, Some(contains), "{:?} contains {:?}", s, other);
+                let found = compare(string_starts_with, s, other).as_bool();
+                assert_eq!(found, Some(starts), "{:?} starts with {:?}", s, other);
+                let found = compare(string_ends_with, s, other).as_bool();
+                assert_eq!(found, Some(ends), "{:?} ends with {:?}", s, other);
+            }
+        }
+    }
+
+    /// Apply `string_split` to `s`, returning the pieces in the list it builds
+    unsafe fn split(s: &str, separator: &str) -> Vec<String> {
+        use crate::pattern::{LIST_CONS_TAG, LIST_NIL_TAG};
+
+        let list = unsafe { compare(string_split, s, separator) };
+        let mut pieces = Vec::new();
+        let mut variant = list.as_variant().expect("should be a list");
+        while variant.tag == LIST_CONS_TAG {
+            // The Cons's data chain runs from the tail to the head
+            let tail = unsafe { &*variant.data };
+            let head = unsafe { &*tail.next };
+            let piece = unsafe { CStr::from_ptr(head.as_string_ptr().unwrap()) };
+            pieces.push(piece.to_str().unwrap().to_owned());
+            variant = tail.as_variant().expect("tail should be a list");
+        }
+        assert_eq!(variant.tag, LIST_NIL_TAG);
+        pieces
+    }
+
+    #[test]
+    fn test_string_split() {
+        unsafe {
+            assert_eq!(split("a,b", ","), vec!["a", "b"]);
+            assert_eq!(split("one, two, three", ", "), vec!["one", "two", "three"]);
+            // Without the separator, the string is the only piece
+            assert_eq!(split("abc", ","), vec!["abc"]);
+            assert_eq!(split("", ","), vec![""]);
+            // Separators at the ends and next to each other leave empty pieces
+            assert_eq!(split(",a,,b,", ","), vec!["", "a", "", "b", ""]);
+        }
+    }
+
+    #[test]
+    fn test_string_split_empty_separator_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "strings::tests::test_string_split_empty_separator_is_a_runtime_error",
+            || unsafe {
+                split("abc", "");
+            },
+        );
+        assert!(stderr.contains("separator is empty"), "{}", stderr);
+    }
+
+    /// Apply `string_char_at` to `s`, returning the code point it pushes
+    unsafe fn char_at(s: &str, index: i64) -> i64 {
+        let s = CString::new(s).unwrap();
+        unsafe {
+            let stack = push_int(push_string(std::ptr::null_mut(), s.as_ptr()), index);
+            let (rest, cell) = StackCell::pop(string_char_at(stack));
+            assert!(rest.is_null());
+            cell.as_int().expect("should be an integer")
+        }
+    }
+
+    #[test]
+    fn test_string_char_at() {
+        unsafe {
+            assert_eq!(char_at("abc", 0), 'a' as i64);
+            assert_eq!(char_at("abc", 2), 'c' as i64);
+            // Positions count characters: 'é' is two bytes, so 'x' is byte 5
+            assert_eq!(char_at("caféx", 3), 'é' as i64);
+            assert_eq!(char_at("caféx", 4), 'x' as i64);
+            assert_eq!(char_at("🦀!", 1), '!' as i64);
+        }
+    }
+
+    #[test]
+    fn test_string_char_at_past_the_end_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "strings::tests::test_string_char_at_past_the_end_is_a_runtime_error",
+            || unsafe {
+                // Five characters, but six bytes
+                char_at("caféx", 5);
+            },
+        );
+        assert!(stderr.contains("index is out of bounds"), "{}", stderr);
+    }
+
+    #[test]
+    fn test_string_char_at_negative_index_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "strings::tests::test_string_char_at_negative_index_is_a_runtime_error",
+            || unsafe {
+                char_at("abc", -1);
+            },
+        );
+        assert!(stderr.contains("index is out of bounds"), "{}", stderr);
+    }
+
+    /// Pop the string on top of `stack`, which must be all that's on it
+    unsafe fn only_string(stack: *mut StackCell) -> String {
+        unsafe {
+            let (rest, cell) = StackCell::pop(stack);
+            assert!(rest.is_null());
+            let result_ptr = cell.as_string_ptr().expect("should be string");
+            CStr::from_ptr(result_ptr).to_str().unwrap().to_owned()
+        }
+    }
+
+    unsafe fn repeat(s: &str, count: i64) -> String {
+        let s = CString::new(s).unwrap();
+        unsafe {
+            let stack = push_int(push_string(std::ptr::null_mut(), s.as_ptr()), count);
+            only_string(string_repeat(stack))
+        }
+    }
+
+    #[test]
+    fn test_string_repeat() {
+        unsafe {
+            assert_eq!(repeat("ab", 3), "ababab");
+            assert_eq!(repeat("é", 2), "éé");
+            assert_eq!(repeat("ab", 1), "ab");
+            assert_eq!(repeat("ab", 0), "");
+            assert_eq!(repeat("", 5), "");
+        }
+    }
+
+    #[test]
+    fn test_string_repeat_negative_count_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "strings::tests::test_string_repeat_negative_count_is_a_runtime_error",
+            || unsafe {
+                repeat("ab", -1);
+            },
+        );
+        assert!(stderr.contains("count is negative"), "{}", stderr);
+    }
+
+    #[test]
+    fn test_string_repeat_overflow_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "strings::tests::test_string_repeat_overflow_is_a_runtime_error",
+            || unsafe {
+                repeat("ab", i64::MAX);
+            },
+        );
+        assert!(stderr.contains("result is too long"), "{}", stderr);
+    }
+
+    /// Apply a one-string word to `s`, returning the string it pushes
+    unsafe fn map(op: unsafe extern "C" fn(*mut StackCell) -> *mut StackCell, s: &str) -> String {
+        let s = CString::new(s).unwrap();
+        unsafe { only_string(op(push_string(std::ptr::null_mut(), s.as_ptr()))) }
+    }
+
+    #[test]
+    fn test_string_reverse() {
+        for (s, expected) in [("abc", "cba"), ("", ""), ("café", "éfac"), ("a🦀b", "b🦀a")] {
+            assert_eq!(unsafe { map(string_reverse, s) }, expected);
+        }
+    }
+
+    #[test]
+    fn test_string_trim() {
+        for (s, expected) in [
+            ("  hello  ", "hello"),
+            ("\t line\n", "line"),
+            ("inner  space", "inner  space"),
+            ("   ", ""),
+            ("", ""),
+        ] {
+            assert_eq!(unsafe { map(string_trim, s) }, expected);
+        }
+    }
+
+    #[test]
+    fn test_string_case() {
+        // (string, upper, lower)
+        let cases = [
+            ("Hello, World!", "HELLO, WORLD!", "hello, world!"),
+            ("123", "123", "123"),
+            ("", "", ""),
+            ("Café", "CAFÉ", "café"),
+            // Uppercase 'ß' is two letters, so the string grows
+            ("straße", "STRASSE", "straße"),
+        ];
+
+        for (s, upper, lower) in cases {
+            assert_eq!(unsafe { map(string_to_upper, s) }, upper);
+            assert_eq!(unsafe { map(string_to_lower, s) }, lower);
+        }
+    }
+
+    #[test]
+    fn test_string_index_of() {
+        let cases = [
+            ("banana", "nan", 2),
+            ("banana", "a", 1),
+            ("banana", "banana", 0),
+            ("banana", "x", -1),
+            ("banana", "bananas", -1),
+            ("", "a", -1),
+            // Positions count characters: '!' is at byte 5
+            ("café!", "!", 4),
+            // The empty string is found at the start
+            ("banana", "", 0),
+            ("", "", 0),
+        ];
+
+        for (haystack, needle, expected) in cases {
+            let index = unsafe { compare(string_index_of, haystack, needle).as_int() };
+            assert_eq!(index, Some(expected), "{:?} in {:?}", needle, haystack);
+        }
+    }
+
+    unsafe fn replace(s: &str, pattern: &str, replacement: &str) -> String {
+        let [s, pattern, replacement] = [s, pattern, replacement].map(|s| CString::new(s).unwrap());
+        unsafe {
+            let stack = push_string(std::ptr::null_mut(), s.as_ptr());
+            let stack = push_string(stack, pattern.as_ptr());
+            only_string(string_replace(push_string(stack, replacement.as_ptr())))
+        }
+    }
+
+    #[test]
+    fn test_string_replace() {
+        unsafe {
+            assert_eq!(replace("banana", "an", "AN"), "bANANa");
+            assert_eq!(replace("a-b-c", "-", " - "), "a - b - c");
+            // Occurrences don't overlap
+            assert_eq!(replace("aaa", "aa", "b"), "ba");
+            // Replacing with nothing deletes
+            assert_eq!(replace("banana", "a", ""), "bnn");
+            // Without a match, the string comes back unchanged
+            assert_eq!(replace("banana", "x", "y"), "banana");
+            assert_eq!(replace("", "x", "y"), "");
+            assert_eq!(replace("café", "é", "e"), "cafe");
+        }
+    }
+
+    #[test]
+    fn test_string_replace_empty_pattern_is_a_runtime_error() {
+        let stderr = runtime_error_from(
+            "strings::tests::test_string_replace_empty_pattern_is_a_runtime_error",
+            || unsafe {
+                replace("abc", "", "x");
+            },
+        );
+        assert!(stderr.contains("pattern is empty"), "{}", stderr);
+    }
 }
@@ -0,0 +1,38 @@
+//! Shutdown latency of the scheduler
+//!
+//! Kept out of the unit tests: `scheduler_run` waits for every strand in the
+//! process, so strands from other tests running in parallel would skew it.
+
+use cem_runtime::scheduler::{free_stack, scheduler_run, strand_spawn_main};
+use cem_runtime::stack::{StackCell, push_int};
+use std::time::{Duration, Instant};
+
+extern "C" fn trivial_main(stack: *mut StackCell) -> *mut StackCell {
+    unsafe { push_int(stack, 0) }
+}
+
+#[test]
+fn test_trivial_program_exits_without_polling_delay() {
+    // A polling wait would sleep a full interval whenever the strand hasn't
+    // finished by the first check; the median of several runs shows that
+    let mut times: Vec<Duration> = (0..11)
+        .map(|_| unsafe {
+            let start = Instant::now();
+            strand_spawn_main(trivial_main, std::ptr::null_mut());
+            let final_stack = scheduler_run();
+            let elapsed = start.elapsed();
+
+            assert_eq!((*final_stack).as_int(), Some(0));
+            free_stack(final_stack);
+            elapsed
+        })
+        .collect();
+    times.sort();
+
+    let median = times[times.len() / 2];
+    assert!(
+        median < Duration::from_millis(2),
+        "median exit time {:?}",
+        median
+    );
+}